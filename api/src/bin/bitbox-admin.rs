@@ -0,0 +1,238 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use dotenvy::dotenv;
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+use application::bootstrap::{create_account, create_admin, get_or_create_main_config, migrate_database, reset_admin_password, NewAccount};
+use application::database::export::{export_data, import_data, DataExport};
+use application::mail::init::init_smtp_client;
+use application::marks::handler::handle_project_rating;
+use application::scheduler::handler::get_scheduled_reminders;
+use application::scheduler::init::run_scheduled_checks;
+use domain::models::users::OrganizationRole;
+use domain::repositories::projects::ProjectsRepository;
+use infrastructure::repositories::PgRepositories;
+use infrastructure::{init_pool_with_settings, DBPool};
+use shared::app_config::{Config, DeploymentConfig};
+use shared::app_state_model::AppState;
+
+/// Administration of a BitBox instance, using the same configuration as the API
+#[derive(Parser)]
+#[command(name = "bitbox-admin", version)]
+struct Cli {
+    /// Configuration file, `bitbox.toml` being read when it exists
+    #[arg(long, global = true, env = "BITBOX_CONFIG")]
+    config: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Apply the pending migrations of the database
+    Migrate,
+    /// Manage the admin account
+    #[command(subcommand)]
+    Admin(AdminCommand),
+    /// Manage the users
+    #[command(subcommand)]
+    User(UserCommand),
+    /// Run the tasks of the scheduler on the projects
+    #[command(subcommand)]
+    Projects(ProjectsCommand),
+    /// Export or import the data of the database
+    #[command(subcommand)]
+    Data(DataCommand),
+    /// Inspect the reminders sent during the notation periods
+    #[command(subcommand)]
+    Reminders(RemindersCommand),
+}
+
+#[derive(Subcommand)]
+enum AdminCommand {
+    /// Create the admin account in the default organization
+    Create {
+        #[arg(long, default_value = "")]
+        email: String,
+        #[arg(long, env = "BITBOX_PASSWORD", hide_env_values = true)]
+        password: String,
+    },
+    /// Replace the password of the admin account and revoke its sessions
+    ResetPassword {
+        #[arg(long, env = "BITBOX_PASSWORD", hide_env_values = true)]
+        password: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum UserCommand {
+    /// Create a user with a password, its email being considered validated
+    Create {
+        #[arg(long)]
+        username: String,
+        #[arg(long)]
+        email: String,
+        #[arg(long, env = "BITBOX_PASSWORD", hide_env_values = true)]
+        password: String,
+        /// Slug of the organization of the user
+        #[arg(long, default_value = "default")]
+        organization: String,
+        #[arg(long, value_enum, default_value_t = Role::Member)]
+        role: Role,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Role {
+    Member,
+    Admin,
+}
+
+impl From<Role> for OrganizationRole {
+    fn from(role: Role) -> Self {
+        match role {
+            Role::Member => OrganizationRole::Member,
+            Role::Admin => OrganizationRole::Admin,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum ProjectsCommand {
+    /// Update the state of the projects and send their alerts once, as the scheduler does every day
+    Check,
+    /// Compute again the marks of the students of a project
+    RecomputeMarks {
+        project_id: Uuid,
+    },
+}
+
+#[derive(Subcommand)]
+enum DataCommand {
+    /// Write the data as JSON, to the standard output by default
+    Export {
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Replace all the data by the content of an export
+    Import {
+        file: PathBuf,
+        /// Confirm that the current data will be deleted
+        #[arg(long)]
+        replace: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum RemindersCommand {
+    /// List the reminders which will be sent to the students and teachers
+    List,
+}
+
+#[actix_web::main]
+async fn main() -> ExitCode {
+    dotenv().ok();
+    let cli = Cli::parse();
+
+    // The logs go to the standard error, to keep the standard output for the results
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn")))
+        .with_writer(std::io::stderr)
+        .init();
+
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), String> {
+    let deployment_config = DeploymentConfig::load(cli.config.as_deref()).map_err(|e| e.to_string())?;
+    let conn = init_pool_with_settings(&deployment_config.database_url, deployment_config.database_pool);
+
+    match cli.command {
+        Command::Migrate => {
+            let versions = migrate_database(&deployment_config.database_url).await?;
+            match versions.is_empty() {
+                true => println!("The database is up to date"),
+                false => versions.iter().for_each(|version| println!("Applied migration {}", version)),
+            }
+        }
+        Command::Admin(AdminCommand::Create { email, password }) => {
+            let user_id = create_admin(&conn, &email, &password).await.map_err(|e| e.to_string())?;
+            println!("Created the admin account {}", user_id);
+        }
+        Command::Admin(AdminCommand::ResetPassword { password }) => {
+            reset_admin_password(&conn, &password).await.map_err(|e| e.to_string())?;
+            println!("Reset the password of the admin account");
+        }
+        Command::User(UserCommand::Create { username, email, password, organization, role }) => {
+            let user_id = create_account(&conn, NewAccount {
+                username: &username,
+                email: &email,
+                password: &password,
+                organization_slug: &organization,
+                organization_role: role.into(),
+            }).await.map_err(|e| e.to_string())?;
+            println!("Created the user {}", user_id);
+        }
+        Command::Projects(ProjectsCommand::Check) => {
+            let app_state = init_app_state(conn, deployment_config).await?;
+            run_scheduled_checks(&app_state).await;
+            println!("Checked the projects");
+        }
+        Command::Projects(ProjectsCommand::RecomputeMarks { project_id }) => {
+            // The administration tool isn't scoped to an organization
+            let repos = PgRepositories::new(conn);
+            let project = repos.get_project_by_id(project_id).await.map_err(|e| e.to_string())?;
+            handle_project_rating(&repos, project.id).await.map_err(|e| e.to_string())?;
+            println!("Computed the marks of the project {}", project.name);
+        }
+        Command::Data(DataCommand::Export { output }) => {
+            let data = export_data(&conn).await.map_err(|e| e.to_string())?;
+            let writer: Box<dyn Write> = match output {
+                Some(path) => Box::new(File::create(path).map_err(|e| e.to_string())?),
+                None => Box::new(std::io::stdout()),
+            };
+            let mut writer = BufWriter::new(writer);
+            serde_json::to_writer_pretty(&mut writer, &data).map_err(|e| e.to_string())?;
+            writer.flush().map_err(|e| e.to_string())?;
+        }
+        Command::Data(DataCommand::Import { file, replace }) => {
+            if !replace {
+                return Err("The import deletes the current data, confirm it with --replace".to_string());
+            }
+            let file = File::open(file).map_err(|e| e.to_string())?;
+            let data: DataExport = serde_json::from_reader(BufReader::new(file)).map_err(|e| e.to_string())?;
+            import_data(&conn, &data).await?;
+            println!("Imported the data");
+        }
+        Command::Reminders(RemindersCommand::List) => {
+            let reminders = get_scheduled_reminders(&PgRepositories::new(conn)).await.map_err(|e| e.to_string())?;
+            if reminders.is_empty() {
+                println!("No reminder is scheduled");
+            }
+            for reminder in reminders {
+                println!("{}\t{}\t{}", reminder.send_at.to_rfc3339(), reminder.project_id, reminder.project_name);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Builds the state the scheduler needs, from the same configuration as the API.
+async fn init_app_state(conn: DBPool, deployment_config: DeploymentConfig) -> Result<AppState, String> {
+    let main_config = get_or_create_main_config(&conn).await.map_err(|e| e.to_string())?;
+    let config = Config::new(deployment_config, main_config);
+    let smtp_transport = init_smtp_client(&config);
+    Ok(AppState::init(conn, smtp_transport, config))
+}
@@ -1,55 +1,100 @@
 use actix_cors::Cors;
 use actix_settings::{ApplySettings, Mode, Settings};
-use actix_web::middleware::{Compress, Condition, Logger};
+use actix_web::middleware::{from_fn, Compress, Condition};
 use actix_web::web::Data;
 use actix_web::{web, App, HttpServer};
+use clap::Parser;
 use dotenvy::dotenv;
+use futures_util::future::try_join;
 use parking_lot::lock_api::Mutex;
 use std::env;
+use std::path::PathBuf;
+use std::process::exit;
 use std::sync::Arc;
+use std::time::Duration;
+use tracing_subscriber::EnvFilter;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
 use api::handlers::basic_routes_handler::*;
+use api::middlewares::request_id::{AssignRequestId, REQUEST_ID_HEADER};
+use api::middlewares::startup::require_startup;
 use api::{docs::swagger_config::ApiDoc, handlers};
-use application::database::config::{create_main_config, get_main_config};
-use application::database::user_passwords::create_user_password;
-use application::database::users::{create_user, get_user_by_username, update_user};
+use application::bootstrap::{ensure_admin, get_or_create_main_config, migrate_database};
 use application::mail::init::init_smtp_client;
 use application::scheduler::init::init_projects_check;
-use domain::models::config::NewMainConfig;
-use domain::models::user_passwords::NewUserPassword;
-use domain::models::users::{NewUser, UpdatedUser, UserRole};
-use infrastructure::{init_pool, DBPool};
-use shared::app_state_model::AppState;
-use shared::error_models::DBError;
+use domain::models::config::MainConfig;
+use infrastructure::{init_pool_with_settings, DBPool};
+use shared::app_config::{Config, DeploymentConfig};
+use shared::app_state_model::{AppState, StartupStatus};
+use shared::error_models::{BadRequestError, UserError};
+
+/// Delay between two attempts to connect to the database at startup
+const DATABASE_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Attempts to connect to the database at startup before giving up, about 5 minutes
+const DATABASE_MAX_ATTEMPTS: u32 = 60;
+
+/// Actix settings used when no settings file is configured
+const SERVER_SETTINGS: &str = include_str!("../Server.toml");
+
+/// API server of BitBox
+#[derive(Parser)]
+#[command(name = "main", version)]
+struct Cli {
+    /// Configuration file, `bitbox.toml` being read when it exists
+    #[arg(long, env = "BITBOX_CONFIG")]
+    config: Option<PathBuf>,
+    /// Print the configuration with its secrets redacted, then exit
+    #[arg(long)]
+    print_config: bool,
+}
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv().ok();
+    let cli = Cli::parse();
+
+    let deployment_config = match DeploymentConfig::load(cli.config.as_deref()) {
+        Ok(deployment_config) => deployment_config,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(1);
+        }
+    };
+    if cli.print_config {
+        print!("{}", deployment_config.to_redacted_toml());
+        return Ok(());
+    }
 
-    // Init the environment
-    let app_state = init_app_state();
-    check_admin(&app_state.database_pool);
-    let mut settings = Settings::parse_toml("api/src/Server.toml")
-        .expect("Failed to parse `Settings` from Server.toml");
+    let mut settings = match &deployment_config.server_config.settings_file {
+        Some(settings_file) => Settings::parse_toml(settings_file),
+        None => Settings::from_template(SERVER_SETTINGS),
+    }.expect("Failed to parse the Actix settings");
 
     // If the environment variable `$APPLICATION__HOSTS` is set,
     // have its value override the `settings.actix.hosts` setting:
     Settings::override_field_with_env_var(&mut settings.actix.hosts, "APPLICATION__HOSTS")?;
 
-    init_logger(&settings);
-    let projects_checker = init_projects_check(&app_state);
-    // Modify `projects_checker` in `runtime_values` to store the interval ID in the `AppState`
-    app_state.runtime_values.write().projects_checker = Arc::new(Mutex::new(Some(projects_checker)));
+    init_tracing(&settings);
+
+    // Init the environment, the database being prepared once the server answers the health probes
+    let migrate_on_startup = deployment_config.server_config.migrate_on_startup;
+    let app_state = init_app_state(deployment_config);
+    actix_web::rt::spawn(start_application(app_state.clone(), migrate_on_startup));
 
     // Create the OpenAPI documentation0
     let openapi = ApiDoc::openapi();
     let content = serde_json::to_string_pretty(&openapi)?;
     std::fs::write("docs/openapi.json", content).expect("Unable to write documentation use api::handlers::marks_handler::get_evaluation_token_route;file");
 
+    // The metrics are served apart when an address is configured, else they require the token
+    let metrics_config = app_state.config.read().metrics_config.clone();
+    let expose_metrics = metrics_config.bind_address.is_none() && metrics_config.token.is_some();
+    let metrics_app_state = app_state.clone();
+
     // Start the server
-    HttpServer::new({
+    let server = HttpServer::new({
         let settings = settings.clone();
         move || {
             let cors = Cors::default()
@@ -57,19 +102,23 @@ async fn main() -> std::io::Result<()> {
                 .allow_any_method()
                 .allow_any_header()
                 .supports_credentials()
+                .expose_headers([REQUEST_ID_HEADER])
                 .max_age(3600);
 
             App::new()
                 .wrap(cors)
+                // identify and trace the requests, around the routes to trace the errors of their middlewares too
+                .wrap(AssignRequestId)
                 .wrap(Condition::new(
                     settings.actix.enable_compression,
                     Compress::default(),
                 ))
                 // make `Settings` available to handlers
                 .app_data(Data::new(settings.clone()))
-                // enable logger
-                .wrap(Logger::default())
-                .wrap(Logger::new("%a %{User-Agent}i"))
+                // reject malformed requests with problem details
+                .app_data(web::JsonConfig::default().error_handler(|err, _| bad_request(err)))
+                .app_data(web::QueryConfig::default().error_handler(|err, _| bad_request(err)))
+                .app_data(web::PathConfig::default().error_handler(|err, _| bad_request(err)))
                 // make `AppState` available to handlers
                 .app_data(app_state.clone())
                 // set up the on-demand documentation
@@ -78,17 +127,27 @@ async fn main() -> std::io::Result<()> {
                 )
                 // provision the API routes
                 .service(healthcheck)
+                .service(liveness)
+                .service(readiness)
+                .configure(|cfg| if expose_metrics { handlers::metrics_handler::metrics_config(cfg) })
                 .service(
                     web::scope("/api")
+                        .wrap(from_fn(require_startup))
                         .configure(handlers::authentication_handler::auth_config)
+                        .configure(handlers::authentication_handler::password_config)
                         .configure(handlers::promotions_handler::promotions_config)
                         .configure(handlers::configurations_handler::configurations_config)
                         .configure(handlers::admin_handler::admin_config)
+                        .configure(handlers::organizations_handler::organizations_config)
+                        .configure(handlers::api_keys_handler::api_keys_config)
+                        .configure(handlers::me_handler::me_config)
+                        .configure(handlers::me_handler::email_config)
                         .configure(handlers::projects_handler::projects_config)
                         .configure(handlers::students_handler::students_config)
                         .configure(handlers::groups_handler::groups_config)
                         .configure(handlers::marks_handler::marks_config)
                         .configure(handlers::tokens_handler::token_config)
+                        .configure(handlers::search_handler::search_config)
                 )
                 .default_service(web::route().to(not_found))
         }
@@ -96,100 +155,109 @@ async fn main() -> std::io::Result<()> {
         // apply the `Settings` to Actix Web's `HttpServer`
         .try_apply_settings(&settings)?
         // .bind_openssl(("127.0.0.1", 8443), ())?
-        .run()
-        .await
+        .run();
+
+    match metrics_config.bind_address {
+        Some(address) => {
+            let metrics_server = HttpServer::new(move || {
+                App::new()
+                    .app_data(metrics_app_state.clone())
+                    .configure(handlers::metrics_handler::metrics_config)
+            })
+                .workers(1)
+                .bind(address)?
+                .run();
+            try_join(server, metrics_server).await.map(|_| ())
+        }
+        None => server.await,
+    }
+}
+
+/// Error of the requests the extractors can't read.
+fn bad_request(err: impl std::fmt::Display) -> actix_web::Error {
+    UserError::BadRequest(BadRequestError {
+        request: err.to_string(),
+    }).into()
 }
 
-/// Initialize the logging infrastructure.
-fn init_logger(settings: &Settings) {
+/// Initialize the tracing infrastructure.
+///
+/// The events are written as JSON in production, and the level can be changed with `RUST_LOG`.
+fn init_tracing(settings: &Settings) {
     if !settings.actix.enable_log {
         return;
     }
 
-    env::set_var(
-        "RUST_LOG",
-        match settings.actix.mode {
-            Mode::Development => "actix_web=debug",
-            Mode::Production => "actix_web=info",
-        },
-    );
-
     env::set_var("RUST_BACKTRACE", "1");
 
-    env_logger::init();
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(match settings.actix.mode {
+        Mode::Development => "debug,h2=info,hyper=info,rustls=info",
+        Mode::Production => "info",
+    }));
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter);
+    match settings.actix.mode {
+        Mode::Development => subscriber.init(),
+        Mode::Production => subscriber.json().flatten_event(true).with_current_span(true).with_span_list(false).init(),
+    }
 }
 
-// Initialize the application state
-fn init_app_state() -> Data<AppState> {
-    let database_url = dotenvy::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let database_pool = init_pool(&database_url);
-    let main_config = match get_main_config(&database_pool) {
-        Ok(config) => config,
-        Err(err) => match err {
-            DBError::NotFound => {
-                create_main_config(&database_pool, NewMainConfig {
-                    register: None,
-                    authorized_domains: None,
-                }).unwrap();
-                get_main_config(&database_pool).unwrap()
-            }
-            _ => panic!("Error when creating main config")
+/// Connect to the database, waiting for it to be reachable instead of failing at startup.
+async fn wait_for_database(database_pool: &DBPool) -> Result<(), String> {
+    let mut attempts = 1;
+    while let Err(e) = database_pool.get().await {
+        if attempts == DATABASE_MAX_ATTEMPTS {
+            return Err(format!("The database is unreachable: {}", e));
         }
-    };
-    let app_config = shared::app_config::Config::init(main_config);
-    let smtp_transport = init_smtp_client(&app_config.clone());
-    Data::new(AppState::init(database_pool, smtp_transport, app_config))
+        tracing::warn!(error = %e, attempts, "waiting for the database");
+        tokio::time::sleep(DATABASE_RETRY_DELAY).await;
+        attempts += 1;
+    }
+    Ok(())
+}
+
+/// Prepares the database, then starts the scheduler and opens the API.
+async fn start_application(app_state: Data<AppState>, migrate_on_startup: bool) {
+    match prepare_database(&app_state, migrate_on_startup).await {
+        Ok(()) => {
+            let projects_checker = init_projects_check(&app_state).await;
+            // Keep the task running the scheduled checks in the `AppState`
+            app_state.runtime_values.write().projects_checker = Arc::new(Mutex::new(Some(projects_checker)));
+            app_state.set_startup_status(StartupStatus::Started);
+            tracing::info!("application started");
+        }
+        Err(e) => {
+            tracing::error!(error = %e, "failed to start the application");
+            app_state.set_startup_status(StartupStatus::Failed(e));
+        }
+    }
 }
 
-// Check if the admin account exists, if not create it
-fn check_admin(conn: &DBPool) {
-    match get_user_by_username(conn, "admin") {
-        Ok(_) => return,
-        Err(err) => match err {
-            DBError::NotFound => {
-                // Create admin account
-                let email = match env::var("ADMIN_EMAIL") {
-                    Ok(email) => {
-                        if email.is_empty() {
-                            "".to_string()
-                        } else { email }
-                    }
-                    Err(_) => "".to_string()
-                };
-                let user_id = create_user(conn, NewUser {
-                    username: "admin".to_string(),
-                    email: email.clone(),
-                }).unwrap();
-                let updated_user = UpdatedUser {
-                    username: None,
-                    email: None,
-                    // If the email is provided, the email is automatically validated
-                    has_validated_email: if Some(email) == None { None } else { Some(true) },
-                    role: Some(UserRole::Admin),
-                    token_version: None,
-                };
-                update_user(conn, user_id.clone(), updated_user).unwrap();
-
-                // Create password
-                let password = match env::var("ADMIN_PASSWORD") {
-                    Ok(password) => {
-                        if password.is_empty() {
-                            env::var("DEFAULT_ADMIN_PASSWORD").unwrap()
-                        } else { password }
-                    }
-                    Err(_) => env::var("DEFAULT_ADMIN_PASSWORD").unwrap()
-                };
-                let hashed_password = match bcrypt::hash(&password, bcrypt::DEFAULT_COST) {
-                    Ok(h) => h,
-                    Err(_) => panic!("Error when hashing admin password")
-                };
-
-                create_user_password(conn, NewUserPassword {
-                    user_id: user_id.clone(),
-                    password: hashed_password,
-                }).unwrap();
-            }
-            _ => panic!("Error when checking admin account")
+async fn prepare_database(app_state: &AppState, migrate_on_startup: bool) -> Result<(), String> {
+    let database_pool = app_state.database_pool.as_ref();
+    wait_for_database(database_pool).await?;
+
+    // The migrations can be left to `bitbox-admin migrate`
+    if migrate_on_startup {
+        let database_url = app_state.config.read().database_url.clone();
+        match migrate_database(&database_url).await {
+            Ok(versions) if versions.is_empty() => {}
+            Ok(versions) => tracing::info!(migrations = %versions.join(", "), "database migrated"),
+            Err(e) => return Err(format!("Error when migrating the database: {}", e)),
         }
     }
-}
\ No newline at end of file
+
+    let main_config = get_or_create_main_config(database_pool).await
+        .map_err(|e| format!("Error when creating main config: {}", e))?;
+    app_state.config.write().main_config = main_config;
+    ensure_admin(database_pool).await
+        .map_err(|e| format!("Error when creating the admin account: {}", e))?;
+    Ok(())
+}
+
+// Initialize the application state, with the default main config until it's read from the database
+fn init_app_state(deployment_config: DeploymentConfig) -> Data<AppState> {
+    let database_pool = init_pool_with_settings(&deployment_config.database_url, deployment_config.database_pool);
+    let app_config = Config::new(deployment_config, MainConfig::default());
+    let smtp_transport = init_smtp_client(&app_config);
+    Data::new(AppState::init(database_pool, smtp_transport, app_config))
+}
@@ -1,3 +1,8 @@
+// Arguments of the generic schemas, which utoipauto registers by their short name
+use application::database::groups::ProjectGroup;
+use domain::models::projects::Project;
+use domain::models::promotions::Promotion;
+use domain::models::students::Student;
 use serde::Serialize;
 use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
 use utoipa::{openapi, Modify, OpenApi};
@@ -1,9 +1,21 @@
-use actix_web::{get, put, web, HttpResponse, ResponseError};
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, ResponseError};
 
+use application::audit::log::{record_audit_log, AuditEvent};
+use application::database::audit_logs::{get_audit_logs, AuditLogSearchParams};
 use application::database::config::{get_main_config, update_main_config};
-use domain::models::config::UpdatedMainConfig;
+use application::database::organizations::{create_organization, delete_organization, get_all_organizations, get_organization_by_id, DEFAULT_ORGANIZATION_SLUG};
+use application::scheduler::init::{run_project_check, run_scheduled_checks};
+use domain::models::audit_logs::AuditAction;
+use domain::models::config::{MainConfig, UpdatedMainConfig};
+use domain::models::organizations::NewOrganization;
+use garde::Validate;
 use shared::app_state_model::AppState;
-use shared::error_models::{APIError, InternalError, ServerError};
+use shared::error_models::{APIError, BadRequestError, InternalError, ServerError, UserError};
+use uuid::Uuid;
+
+use crate::models::get_models::SchedulerGetModel;
+use crate::models::post_models::NewOrganizationPostModel;
+use crate::utils::audit::get_audit_context;
 
 use crate::middlewares::admin::RequireAdminRole;
 use crate::middlewares::auth::{RequireAuth, UserTokenValidator};
@@ -18,27 +30,21 @@ use crate::middlewares::auth::{RequireAuth, UserTokenValidator};
     context_path = "/admin",
     responses(
         (status = 200, description = "The main config of the application", body = MainConfig),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError, examples(
-        ("NoToken" = (value = json!("Token not provided"))),
-        ("InvalidToken" = (value = json!("Error")))
-        )),
-        (status = 403, description = "Forbidden", body = String, example = json!("Forbidden")),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[get("/config")]
 pub async fn get_main_config_route(data: web::Data<AppState>) -> HttpResponse {
-    let result = web::block(move || {
+    let result = async move {
         let conn = data.database_pool.clone().as_ref().clone();
-        get_main_config(&conn)
-    }).await;
+        get_main_config(&conn).await
+    }.await;
 
     match result {
-        Ok(response) => match response {
-            Ok(config) => HttpResponse::Ok().json(config),
-            Err(err) => APIError::from(err).error_response()
-        },
-        Err(_) => ServerError::InternalError(InternalError).error_response(),
+        Ok(config) => HttpResponse::Ok().json(config),
+        Err(err) => APIError::from(err).error_response()
     }
 }
 
@@ -57,41 +63,311 @@ pub async fn get_main_config_route(data: web::Data<AppState>) -> HttpResponse {
     ),
     responses(
         (status = 200, description = "Main configuration updated successfully"),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError, examples(
-        ("NoToken" = (value = json!("Token not provided"))),
-        ("InvalidToken" = (value = json!("Error")))
-        )),
-        (status = 403, description = "Forbidden", body = String, example = json!("Forbidden")),
-        (status = 404, description = "Not Found", body = NotFoundError, example = json!("Database record")),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 400, description = "Bad Request", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Not Found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[put("/config")]
-pub async fn update_main_config_route(data: web::Data<AppState>, updated_config: web::Json<UpdatedMainConfig>) -> HttpResponse {
+pub async fn update_main_config_route(data: web::Data<AppState>, req: HttpRequest, updated_config: web::Json<UpdatedMainConfig>) -> HttpResponse {
     let conn = data.database_pool.clone().as_ref().clone();
-    let result = web::block(move || {
-        update_main_config(&conn, updated_config.into_inner())
-    }).await;
+    let audit_context = get_audit_context(&req);
+    let previous_config = data.config.read().main_config.clone();
+    let current_config = previous_config.clone();
+    let result: Result<(), APIError> = async move {
+        let updated_config = updated_config.into_inner();
+        if updated_config.audit_log_retention_days.is_some_and(|days| days < 1) {
+            return Err(APIError::UserError(UserError::BadRequest(BadRequestError {
+                request: "The audit log retention must be at least one day".to_string(),
+            })));
+        }
+        check_password_policy_update(&current_config, &updated_config)?;
+
+        update_main_config(&conn, updated_config).await?;
+
+        Ok(())
+    }.await;
 
     match result {
-        Ok(response) => match response {
-            Ok(_) => {
-                let config_update = get_main_config(&data.database_pool.clone().as_ref().clone());
-                match config_update {
-                    Ok(main_config) => {
-                        let mut config = data.config.write();
-                        config.main_config = main_config.clone();
-                        HttpResponse::Ok().finish()
-                    },
-                    Err(_) => ServerError::InternalError(InternalError).error_response(),
-                }
-            },
-            Err(err) => APIError::from(err).error_response(),
+        Ok(_) => {
+            let conn = data.database_pool.clone().as_ref().clone();
+            let config_update = get_main_config(&conn).await;
+            match config_update {
+                Ok(main_config) => {
+                    record_audit_log(&conn, &audit_context, AuditEvent {
+                        action: AuditAction::MainConfigUpdated,
+                        resource_type: "main_config",
+                        resource_id: Some(main_config.id.to_string()),
+                        before: Some(&previous_config),
+                        after: Some(&main_config),
+                    }).await;
+
+                    let mut config = data.config.write();
+                    config.main_config = main_config.clone();
+                    HttpResponse::Ok().finish()
+                },
+                Err(_) => ServerError::InternalError(InternalError).error_response(),
+            }
         },
-        Err(_) => ServerError::InternalError(InternalError).error_response(),
+        Err(err) => err.error_response(),
+    }
+}
+
+/// Get the audit log
+///
+/// This endpoint returns the sensitive actions performed on the application, most recent first.
+#[utoipa::path(
+    get,
+    path = "/audit-logs",
+    tag = "Admin",
+    context_path = "/admin",
+    params(AuditLogSearchParams),
+    responses(
+        (status = 200, description = "The matching audit log entries", body = [AuditLog]),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[get("/audit-logs")]
+pub async fn get_audit_logs_route(data: web::Data<AppState>, params: web::Query<AuditLogSearchParams>) -> HttpResponse {
+    let result = async move {
+        let conn = data.database_pool.clone().as_ref().clone();
+        get_audit_logs(&conn, &params.into_inner()).await
+    }.await;
+
+    match result {
+        Ok(audit_logs) => HttpResponse::Ok().json(audit_logs),
+        Err(err) => APIError::from(err).error_response()
+    }
+}
+
+/// Get all organizations
+///
+/// This endpoint returns all the organizations hosted by the application.
+#[utoipa::path(
+    get,
+    path = "/organizations",
+    tag = "Admin",
+    context_path = "/admin",
+    responses(
+        (status = 200, description = "All the organizations", body = [Organization]),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[get("/organizations")]
+pub async fn get_all_organizations_route(data: web::Data<AppState>) -> HttpResponse {
+    let result = async move {
+        let conn = data.database_pool.clone().as_ref().clone();
+        get_all_organizations(&conn).await
+    }.await;
+
+    match result {
+        Ok(organizations) => HttpResponse::Ok().json(organizations),
+        Err(err) => APIError::from(err).error_response()
+    }
+}
+
+/// Create an organization
+///
+/// This endpoint creates a new organization with its own configuration.
+#[utoipa::path(
+    post,
+    path = "/organizations",
+    tag = "Admin",
+    context_path = "/admin",
+    request_body(
+        content = NewOrganizationPostModel,
+        description = "The new organization object to create",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 201, description = "Organization created successfully", body = Uuid),
+        (status = 400, description = "Bad Request", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[post("/organizations")]
+pub async fn create_organization_route(data: web::Data<AppState>, organization: web::Json<NewOrganizationPostModel>) -> HttpResponse {
+    let result: Result<Uuid, APIError> = async move {
+        let conn = data.database_pool.clone().as_ref().clone();
+        let organization = organization.into_inner();
+        organization.validate()?;
+
+        let organization_id = create_organization(&conn, NewOrganization {
+            name: organization.name,
+            slug: organization.slug,
+            register: organization.register,
+            authorized_domains: organization.authorized_domains,
+            logo_url: None,
+            primary_color: None,
+            mail_sender: organization.mail_sender,
+        }).await?;
+
+        Ok(organization_id)
+    }.await;
+
+    match result {
+        Ok(id) => HttpResponse::Created().json(id),
+        Err(err) => err.error_response()
+    }
+}
+
+/// Delete an organization
+///
+/// This endpoint deletes an organization with all its users and promotions. The default organization can't be deleted.
+#[utoipa::path(
+    delete,
+    path = "/organizations/{id}",
+    tag = "Admin",
+    context_path = "/admin",
+    params(
+        ("id" = Uuid, description = "The organization id to delete")
+    ),
+    responses(
+        (status = 200, description = "Organization deleted successfully"),
+        (status = 400, description = "Bad Request", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Not Found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[delete("/organizations/{id}")]
+pub async fn delete_organization_route(data: web::Data<AppState>, id: web::Path<Uuid>) -> HttpResponse {
+    let result: Result<(), APIError> = async move {
+        let conn = data.database_pool.clone().as_ref().clone();
+        let organization = get_organization_by_id(&conn, id.into_inner()).await?;
+
+        if organization.slug == DEFAULT_ORGANIZATION_SLUG {
+            return Err(APIError::UserError(UserError::BadRequest(BadRequestError {
+                request: "The default organization can't be deleted".to_string(),
+            })));
+        }
+
+        delete_organization(&conn, organization.id).await?;
+
+        Ok(())
+    }.await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(err) => err.error_response()
+    }
+}
+
+fn get_scheduler_status(data: &AppState) -> SchedulerGetModel {
+    SchedulerGetModel {
+        schedule: data.config.read().scheduler_config.schedule.to_string(),
+        last_run: data.get_scheduler_heartbeat(),
+        next_run: data.get_scheduler_next_run(),
     }
 }
 
+/// Get the scheduler status
+///
+/// This endpoint returns the schedule of the checks of the projects, with the times of the last and next runs.
+#[utoipa::path(
+    get,
+    path = "/scheduler",
+    tag = "Admin",
+    context_path = "/admin",
+    responses(
+        (status = 200, description = "The status of the scheduler", body = SchedulerGetModel),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[get("/scheduler")]
+pub async fn get_scheduler_route(data: web::Data<AppState>) -> HttpResponse {
+    HttpResponse::Ok().json(get_scheduler_status(&data))
+}
+
+/// Run the scheduler
+///
+/// This endpoint checks every current project immediately, without waiting for the next scheduled run. It
+/// returns once the check is done, after the run in progress if any.
+#[utoipa::path(
+    post,
+    path = "/scheduler/run",
+    tag = "Admin",
+    context_path = "/admin",
+    responses(
+        (status = 200, description = "The projects were checked", body = SchedulerGetModel),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[post("/scheduler/run")]
+pub async fn run_scheduler_route(data: web::Data<AppState>) -> HttpResponse {
+    run_scheduled_checks(&data).await;
+
+    HttpResponse::Ok().json(get_scheduler_status(&data))
+}
+
+/// Run the scheduler on a project
+///
+/// This endpoint checks a single project immediately: its state is advanced according to its dates, and the
+/// emails which are due are sent.
+#[utoipa::path(
+    post,
+    path = "/scheduler/run/{project_id}",
+    tag = "Admin",
+    context_path = "/admin",
+    params(
+        ("project_id" = Uuid, description = "The project id to check")
+    ),
+    responses(
+        (status = 200, description = "The project was checked"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Not Found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[post("/scheduler/run/{project_id}")]
+pub async fn run_project_check_route(data: web::Data<AppState>, project_id: web::Path<Uuid>) -> HttpResponse {
+    match run_project_check(&data, project_id.into_inner()).await {
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(err) => APIError::from(err).error_response()
+    }
+}
+
+/// Bcrypt ignores everything after 72 bytes.
+const MAX_PASSWORD_LENGTH: i32 = 72;
+
+fn check_password_policy_update(previous_config: &MainConfig, updated_config: &UpdatedMainConfig) -> Result<(), APIError> {
+    let bad_request = |request: &str| APIError::UserError(UserError::BadRequest(BadRequestError {
+        request: request.to_string(),
+    }));
+
+    let min_length = updated_config.password_min_length.unwrap_or(previous_config.password_min_length);
+    let max_length = updated_config.password_max_length.unwrap_or(previous_config.password_max_length);
+    if min_length < 1 || max_length > MAX_PASSWORD_LENGTH || min_length > max_length {
+        return Err(bad_request("The password length must be between 1 and 72 characters"));
+    }
+
+    let minimums = [
+        updated_config.password_min_lower_case,
+        updated_config.password_min_upper_case,
+        updated_config.password_min_digits,
+        updated_config.password_min_special_chars,
+        updated_config.password_history_size,
+        updated_config.password_expiry_days,
+    ];
+    if minimums.iter().flatten().any(|value| *value < 0) {
+        return Err(bad_request("The password policy values can't be negative"));
+    }
+
+    Ok(())
+}
+
 pub fn admin_config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/admin")
@@ -99,5 +375,12 @@ pub fn admin_config(cfg: &mut web::ServiceConfig) {
             .wrap(RequireAuth::new(UserTokenValidator))
             .service(get_main_config_route)
             .service(update_main_config_route)
+            .service(get_audit_logs_route)
+            .service(get_all_organizations_route)
+            .service(create_organization_route)
+            .service(delete_organization_route)
+            .service(get_scheduler_route)
+            .service(run_scheduler_route)
+            .service(run_project_check_route)
     );
 }
\ No newline at end of file
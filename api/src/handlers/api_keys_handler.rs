@@ -0,0 +1,138 @@
+use crate::middlewares::auth::{RequireAuth, UserTokenValidator};
+use crate::models::get_models::{ApiKeyGetModel, CreatedApiKeyGetModel};
+use crate::models::post_models::ApiKeyPostModel;
+use actix_web::{delete, get, post, web, HttpMessage, HttpRequest, HttpResponse, ResponseError};
+use application::authentication::api_keys::generate_api_key;
+use application::database::api_keys::{create_api_key, get_api_keys_from_user_id, revoke_api_key};
+use domain::models::api_keys::NewApiKey;
+use domain::models::users::User;
+use garde::Validate;
+use shared::app_state_model::AppState;
+use shared::error_models::APIError;
+use uuid::Uuid;
+
+/// Get the API keys of the current user
+///
+/// This endpoint returns the API keys of the current user, including the revoked ones. The keys themselves are never returned.
+#[utoipa::path(
+    get,
+    path = "",
+    tag = "API keys",
+    context_path = "/api-keys",
+    responses(
+        (status = 200, description = "The API keys of the user", body = [ApiKeyGetModel]),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[get("")]
+pub async fn get_api_keys_route(data: web::Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let user = req.extensions().get::<User>().cloned().unwrap();
+    let result: Result<Vec<ApiKeyGetModel>, APIError> = async move {
+        let conn = data.database_pool.clone().as_ref().clone();
+
+        let api_keys = get_api_keys_from_user_id(&conn, user.id).await?;
+        Ok(api_keys.into_iter().map(ApiKeyGetModel::from).collect())
+    }.await;
+
+    match result {
+        Ok(api_keys) => HttpResponse::Ok().json(api_keys),
+        Err(err) => err.error_response(),
+    }
+}
+
+/// Create an API key
+///
+/// This endpoint creates a personal API key for the current user. The key is only returned once.
+/// It can be sent in an `Authorization: Bearer` or `X-API-Key` header.
+#[utoipa::path(
+    post,
+    path = "",
+    tag = "API keys",
+    context_path = "/api-keys",
+    request_body(
+        content = ApiKeyPostModel,
+        description = "The name, scopes and expiration date of the key",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 201, description = "API key created", body = CreatedApiKeyGetModel),
+        (status = 400, description = "Bad Request", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[post("")]
+pub async fn create_api_key_route(data: web::Data<AppState>, req: HttpRequest, api_key: web::Json<ApiKeyPostModel>) -> HttpResponse {
+    let user = req.extensions().get::<User>().cloned().unwrap();
+    let result: Result<CreatedApiKeyGetModel, APIError> = async move {
+        let conn = data.database_pool.clone().as_ref().clone();
+        let api_key = api_key.into_inner();
+        api_key.validate()?;
+
+        let generated = generate_api_key();
+        let id = create_api_key(&conn, NewApiKey {
+            user_id: user.id,
+            name: api_key.name,
+            prefix: generated.prefix,
+            key_hash: generated.key_hash,
+            scopes: api_key.scopes,
+            expires_at: api_key.expires_at,
+        }).await?;
+
+        Ok(CreatedApiKeyGetModel {
+            id,
+            key: generated.key,
+        })
+    }.await;
+
+    match result {
+        Ok(api_key) => HttpResponse::Created().json(api_key),
+        Err(err) => err.error_response(),
+    }
+}
+
+/// Revoke an API key
+///
+/// This endpoint revokes one of the API keys of the current user. Revoked keys are rejected immediately.
+#[utoipa::path(
+    delete,
+    path = "/{id}",
+    tag = "API keys",
+    context_path = "/api-keys",
+    params(
+        ("id" = Uuid, description = "The API key id to revoke")
+    ),
+    responses(
+        (status = 200, description = "API key revoked"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Not Found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[delete("/{id}")]
+pub async fn revoke_api_key_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>) -> HttpResponse {
+    let user = req.extensions().get::<User>().cloned().unwrap();
+    let result: Result<(), APIError> = async move {
+        let conn = data.database_pool.clone().as_ref().clone();
+
+        revoke_api_key(&conn, user.id, id.into_inner()).await?;
+        Ok(())
+    }.await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(err) => err.error_response(),
+    }
+}
+
+/// API keys can only be managed with a user session, so a leaked key can't create other keys.
+pub fn api_keys_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api-keys")
+            .wrap(RequireAuth::new(UserTokenValidator))
+            .service(get_api_keys_route)
+            .service(create_api_key_route)
+            .service(revoke_api_key_route)
+    );
+}
@@ -1,29 +1,52 @@
 use crate::middlewares::auth::{RequireAuth, SpecificTokenValidator};
-use crate::models::post_models::{LoginUserPostModel, RegisterUserPostModel, ResetPasswordPostModel, ResetPasswordRequestPostModel, ValidateMFACodePostModel};
+use crate::models::post_models::{LoginUserPostModel, PasswordContext, RegisterUserPostModel, ResetPasswordPostModel, ResetPasswordRequestPostModel, ValidateMFACodePostModel, validate_unique_user};
+use crate::utils::audit::get_audit_context;
 use actix_web::cookie::{CookieBuilder, SameSite};
-use actix_web::{post, web, HttpMessage, HttpRequest, HttpResponse, ResponseError};
+use actix_web::http::header;
+use actix_web::{get, post, web, HttpMessage, HttpRequest, HttpResponse, ResponseError};
+use application::audit::log::{record_audit_log, AuditEvent};
 use application::authentication::codes::generate_random_code;
-use application::authentication::password_reset::request_password_reset;
+use application::authentication::oidc::{build_authorization_url, discover_provider, exchange_code, generate_pkce_challenge, generate_random_string, resolve_oidc_user};
+use application::authentication::password_reset::{request_password_reset, reset_password};
+use application::authentication::passwords::is_password_expired;
 use application::authentication::tokens::encode_token;
+use application::authorization::organizations::check_email_domain;
 use application::database::config::create_user_config;
 use application::database::mfa_codes::{create_mfa_code, get_mfa_code_by_id};
-use application::database::tokens::update_token;
-use application::database::user_passwords::{create_user_password, get_user_password_by_user_id, update_user_password};
-use application::database::users::{create_user, get_user_by_email, get_user_by_id, get_user_by_username};
-use application::mail::send::{build_mail, send_mail, MailProps};
+use application::database::oidc_login_states::{create_oidc_login_state, take_oidc_login_state};
+use application::database::organizations::{get_organization_by_slug, DEFAULT_ORGANIZATION_SLUG};
+use application::database::user_passwords::{create_user_password, get_user_password_by_user_id};
+use application::database::transaction::{transaction, ScopedFutureExt};
+use application::database::users::{create_user, get_user_by_email_in_any_organization, get_user_by_id, get_user_by_username};
+use application::mail::send::{build_mail, get_organization_mail_sender, send_mail, MailKind, MailProps};
 use chrono::{Duration, Utc};
+use domain::models::audit_logs::AuditAction;
 use domain::models::config::{Alert, NewUserConfig};
 use domain::models::mfa_codes::NewMfaCode;
-use domain::models::tokens::UpdatedToken;
-use domain::models::user_passwords::{NewUserPassword, UpdatedUserPassword};
+use domain::models::oidc_login_states::NewOidcLoginState;
+use domain::models::user_passwords::NewUserPassword;
 use domain::models::users::{NewUser, User};
 use garde::Validate;
 use infrastructure::DBPool;
+use serde::Deserialize;
+use shared::app_config::OidcConfig;
 use shared::app_state_model::AppState;
-use shared::error_models::{APIError, DBError, InternalError, ServerError, UnauthorizedError, UserError};
+use shared::error_models::{APIError, DBError, ForbiddenError, InternalError, NotFoundError, ServerError, UnauthorizedError, UserError};
 use shared::token_models::UserClaims;
+use tracing::Instrument;
 use uuid::Uuid;
 
+#[derive(Deserialize)]
+struct OidcAuthorizeQuery {
+    organization: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OidcCallbackQuery {
+    code: String,
+    state: String,
+}
+
 /// Register a new user
 ///
 /// This endpoint allows users to register a new account.
@@ -39,8 +62,10 @@ use uuid::Uuid;
     ),
     responses(
         (status = 201, description = "Account created", body = String),
-        (status = 400, description = "Bad Request", body = ValidationError),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 400, description = "Bad Request", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Organization Not Found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[post("/register")]
@@ -48,27 +73,33 @@ async fn register_route(
     data: web::Data<AppState>,
     info: web::Json<RegisterUserPostModel>,
 ) -> HttpResponse {
-    let result = web::block(move || {
+    let result = async move {
         let config = {
             let config_guard = data.config.read();
             config_guard.clone()
         };
 
-        if config.clone().main_config.register == false {
-            return Err(APIError::UserError(UserError::Unauthorized(UnauthorizedError)));
-        }
+        let conn = data.database_pool.clone().as_ref().clone();
+        let credentials = info.into_inner();
 
-        // Check if email domain in credentials is part of the domains' whitelist
-        let email_domain = info.email.split('@').collect::<Vec<&str>>()[1];
-        if config.clone().main_config.authorized_domains.len() != 0 &&
-            !config.clone().main_config.authorized_domains.contains(&Some(email_domain.to_string())) {
+        let validation = credentials.validate_with(&PasswordContext::new(&config.main_config, Some(credentials.username.clone())));
+        validate_unique_user(&conn, Some(&credentials.email), Some(&credentials.username), validation).await?;
+
+        // The registration rules are defined by the organization the user joins
+        let organization_slug = credentials.organization.clone().unwrap_or(DEFAULT_ORGANIZATION_SLUG.to_string());
+        let organization = match get_organization_by_slug(&conn, &organization_slug).await {
+            Ok(organization) => organization,
+            Err(DBError::NotFound) => return Err(APIError::UserError(UserError::NotFound(NotFoundError {
+                resource: "Organization".to_string(),
+            }))),
+            Err(err) => return Err(APIError::from(err)),
+        };
+        if !organization.register {
             return Err(APIError::UserError(UserError::Unauthorized(UnauthorizedError)));
         }
 
-        let conn = data.database_pool.clone().as_ref().clone();
-        let credentials = info.into_inner();
-
-        credentials.validate()?;
+        // Check if email domain in credentials is part of the domains' whitelist
+        check_email_domain(&organization, &credentials.email)?;
 
         // Hash password
         let hashed_password = match bcrypt::hash(&credentials.password, bcrypt::DEFAULT_COST) {
@@ -76,35 +107,41 @@ async fn register_route(
             Err(_) => return Err(APIError::ServerError(ServerError::InternalError(InternalError))),
         };
 
-        // Insert user into database
-        let user_id = create_user(&conn, NewUser {
-            email: credentials.email.to_string(),
-            username: credentials.username.to_string(),
-        })?;
-
-        // Insert password into database
-        create_user_password(&conn, NewUserPassword {
-            user_id,
-            password: hashed_password,
-        })?;
-
-        // Init user config
-        create_user_config(&conn, NewUserConfig{
-            user_id,
-            alerts: Some(vec![
-                Alert {
-                    before_event: false,
-                    hours: 24,
-                },
-                Alert {
-                    before_event: true,
-                    hours: 24,
-                },
-            ]),
-        })?;
+        // The user is only created along with its password and its config
+        let user_id = transaction(&conn, |conn| async move {
+            // Insert user into database
+            let user_id = create_user(&mut *conn, NewUser {
+                email: credentials.email.to_string(),
+                username: credentials.username.to_string(),
+                organization_id: organization.id,
+            }).await?;
+
+            // Insert password into database
+            create_user_password(&mut *conn, NewUserPassword {
+                user_id,
+                password: hashed_password,
+            }).await?;
+
+            // Init user config
+            create_user_config(&mut *conn, NewUserConfig{
+                user_id,
+                alerts: Some(vec![
+                    Alert {
+                        before_event: false,
+                        hours: 24,
+                    },
+                    Alert {
+                        before_event: true,
+                        hours: 24,
+                    },
+                ]),
+            }).await?;
+
+            Ok::<_, DBError>(user_id)
+        }.scope_boxed()).await?;
 
         let now = Utc::now();
-        let expiration = now + Duration::hours(config.jwt_config.expires_in.parse::<i64>().unwrap());
+        let expiration = now + config.jwt_config.expires_in;
         let claim = UserClaims{
             sub: user_id,
             iat: now.timestamp() as usize,
@@ -113,22 +150,19 @@ async fn register_route(
         };
         let token = encode_token::<UserClaims>(&claim, &config)?;
         Ok(token)
-    }).await;
+    }.await;
 
     match result {
-        Ok(user) => match user {
-            Ok(token) => {
-                let cookie = CookieBuilder::new("token", token)
-                    .http_only(false)
-                    .secure(false)
-                    .same_site(SameSite::Strict)
-                    .path("/")
-                    .finish();
-                HttpResponse::Created().cookie(cookie).finish()
-            },
-            Err(err) => err.error_response(),
+        Ok(token) => {
+            let cookie = CookieBuilder::new("token", token)
+                .http_only(false)
+                .secure(false)
+                .same_site(SameSite::Strict)
+                .path("/")
+                .finish();
+            HttpResponse::Created().cookie(cookie).finish()
         },
-        Err(_) => ServerError::InternalError(InternalError).error_response()
+        Err(err) => err.error_response(),
     }
 }
 
@@ -147,8 +181,9 @@ async fn register_route(
     ),
     responses(
         (status = 200, description = "1st step of login successful", body = Uuid),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError, example = json!("Unauthorized")),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Password expired, it has to be reset", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[post("/login")]
@@ -156,12 +191,12 @@ async fn login_route(
     data: web::Data<AppState>,
     info: web::Json<LoginUserPostModel>,
 ) -> HttpResponse {
-    let result = web::block(move || {
+    let result = async move {
         let conn: DBPool = data.database_pool.clone().as_ref().clone();
         let credentials = info.into_inner();
 
         // Check user
-        let user = match get_user_from_body(&conn, &credentials.login) {
+        let user = match get_user_from_body(&conn, &credentials.login).await {
             Ok(user) => user,
             Err(err) => return match err {
                 DBError::NotFound => Err(APIError::UserError(UserError::Unauthorized(UnauthorizedError))),
@@ -169,7 +204,7 @@ async fn login_route(
             },
         };
 
-        let user_password = match get_user_password_by_user_id(&conn, user.id) {
+        let user_password = match get_user_password_by_user_id(&conn, user.id).await {
             Ok(user_password) => user_password,
             Err(err) => return match err {
                 DBError::NotFound => Err(APIError::UserError(UserError::Unauthorized(UnauthorizedError))),
@@ -177,6 +212,16 @@ async fn login_route(
             },
         };
 
+        // The code is only sent once the password is checked
+        if !bcrypt::verify(&credentials.password, &user_password.password)? {
+            return Err(APIError::UserError(UserError::Unauthorized(UnauthorizedError)));
+        }
+
+        // Expired passwords have to be reset before logging in
+        if is_password_expired(&data.config.read().main_config, &user_password, Utc::now().naive_utc()) {
+            return Err(APIError::UserError(UserError::Forbidden(ForbiddenError)));
+        }
+
         // Generate MFA Code
         let code = generate_random_code(6);
         let new_mfa_code = NewMfaCode {
@@ -184,11 +229,11 @@ async fn login_route(
             code: code.clone(),
             exp: None,
         };
-        let mfa_code_id = create_mfa_code(&conn, new_mfa_code)?;
+        let mfa_code_id = create_mfa_code(&conn, new_mfa_code).await?;
 
         // Send email
         let mail = build_mail(MailProps {
-            from: "Bitbox <no-reply@sigma-bot.fr>".to_string(),
+            from: get_organization_mail_sender(&conn, user.organization_id).await,
             to: user.email.clone(),
             subject: "Authentication Code".to_string(),
             body: format!(
@@ -196,14 +241,10 @@ async fn login_route(
                 code
             ),
         });
-        send_mail(&data.smtp_transport.as_ref(), mail)?;
+        send_mail(&data.smtp_transport.as_ref(), mail, MailKind::MfaCode).await?;
 
-        if bcrypt::verify(&credentials.password, &user_password.password)? {
-            Ok(mfa_code_id)
-        } else {
-            Err(APIError::UserError(UserError::Unauthorized(UnauthorizedError)))
-        }
-    }).await;
+        Ok(mfa_code_id)
+    }.await;
 
     // match result {
     //     Ok(user) => match user {
@@ -221,11 +262,8 @@ async fn login_route(
     //     Err(_) => ServerError::InternalError(InternalError).error_response()
     // }
     match result {
-        Ok(id) => match id {
-            Ok(id) => HttpResponse::Ok().json(id),
-            Err(err) => err.error_response(),
-        },
-        Err(_) => ServerError::InternalError(InternalError).error_response()
+        Ok(id) => HttpResponse::Ok().json(id),
+        Err(err) => err.error_response(),
     }
 }
 
@@ -247,9 +285,9 @@ async fn login_route(
     ),
     responses(
         (status = 200, description = "Login successful", body = String),
-        (status = 400, description = "Bad Request", body = ValidationError),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 400, description = "Bad Request", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[post("/login/code/{id}")]
@@ -258,7 +296,7 @@ async fn validate_mfa_code_route(
     mfa_code_id: web::Path<Uuid>,
     given_code: web::Json<ValidateMFACodePostModel>,
 ) -> HttpResponse {
-    let result = web::block(move || {
+    let result = async move {
         let conn: DBPool = data.database_pool.clone().as_ref().clone();
         let config = {
             let config_guard = data.config.read();
@@ -267,60 +305,201 @@ async fn validate_mfa_code_route(
 
         let mfa_code_id = mfa_code_id.into_inner();
 
-        let mfa_code = get_mfa_code_by_id(&conn, mfa_code_id)?;
+        let mfa_code = get_mfa_code_by_id(&conn, mfa_code_id).await?;
 
         // Check if code is correct
         if mfa_code.code != given_code.code {
-            println!("2");
+            tracing::warn!(%mfa_code_id, "wrong MFA code");
             return Err(APIError::UserError(UserError::Unauthorized(UnauthorizedError)));
         }
 
         // Check if code is expired
         if mfa_code.exp < Utc::now().naive_utc() - Duration::hours(2) {
-            println!("Exp : {}", mfa_code.exp);
-            println!("{}", Utc::now().naive_utc() - Duration::hours(2));
-            println!("1");
+            tracing::warn!(%mfa_code_id, "expired MFA code");
             return Err(APIError::UserError(UserError::Unauthorized(UnauthorizedError)));
         }
 
         // Get user
-        let user = get_user_by_id(&conn, mfa_code.user_id)?;
-        println!("3");
+        let user = get_user_by_id(&conn, mfa_code.user_id).await?;
 
         // Generate token
         let now = Utc::now();
-        let expiration = now + Duration::hours(config.jwt_config.expires_in.parse::<i64>().unwrap());
+        let expiration = now + config.jwt_config.expires_in;
         let claim = UserClaims{
             sub: user.id,
             iat: now.timestamp() as usize,
             exp: expiration.timestamp() as usize,
             token_version: user.token_version,
         };
-        println!("4");
         let token = encode_token::<UserClaims>(&claim, &config)?;
 
         Ok(token)
-    }).await;
+    }.await;
     match result {
-        Ok(user) => match user {
-            Ok(token) => {
-                let cookie = CookieBuilder::new("token", token)
-                    .http_only(false)
-                    .secure(false)
-                    .same_site(SameSite::Strict)
-                    .path("/")
-                    .finish();
-                HttpResponse::Ok().cookie(cookie).finish()
-            },
-            Err(err) => err.error_response(),
+        Ok(token) => {
+            let cookie = CookieBuilder::new("token", token)
+                .http_only(false)
+                .secure(false)
+                .same_site(SameSite::Strict)
+                .path("/")
+                .finish();
+            HttpResponse::Ok().cookie(cookie).finish()
         },
-        Err(_) => ServerError::InternalError(InternalError).error_response()
+        Err(err) => err.error_response(),
+    }
+}
+
+/// Start an OIDC login
+///
+/// This endpoint redirects users to the configured identity provider to log in with the authorization code flow and PKCE.
+#[utoipa::path(
+    get,
+    path = "/oidc/authorize",
+    tag = "Authentication",
+    context_path = "/auth",
+    params(
+        ("organization" = Option<String>, Query, description = "The slug of the organization to log in, the default organization if omitted")
+    ),
+    responses(
+        (status = 302, description = "Redirection to the identity provider"),
+        (status = 404, description = "Not Found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[get("/oidc/authorize")]
+async fn oidc_authorize_route(
+    data: web::Data<AppState>,
+    query: web::Query<OidcAuthorizeQuery>,
+) -> HttpResponse {
+    let oidc_config = match get_oidc_config(&data) {
+        Ok(oidc_config) => oidc_config,
+        Err(err) => return err.error_response(),
+    };
+    let metadata = match discover_provider(&oidc_config).await {
+        Ok(metadata) => metadata,
+        Err(err) => return err.error_response(),
+    };
+
+    let result = async move {
+        let conn: DBPool = data.database_pool.clone().as_ref().clone();
+
+        let organization_slug = query.into_inner().organization.unwrap_or(DEFAULT_ORGANIZATION_SLUG.to_string());
+        let organization = match get_organization_by_slug(&conn, &organization_slug).await {
+            Ok(organization) => organization,
+            Err(DBError::NotFound) => return Err(APIError::UserError(UserError::NotFound(NotFoundError {
+                resource: "Organization".to_string(),
+            }))),
+            Err(err) => return Err(APIError::from(err)),
+        };
+
+        // Keep the verifier until the identity provider calls back
+        let state = generate_random_string(32);
+        let pkce_challenge = generate_pkce_challenge();
+        create_oidc_login_state(&conn, NewOidcLoginState {
+            state: state.clone(),
+            code_verifier: pkce_challenge.code_verifier.clone(),
+            organization_id: organization.id,
+        }).await?;
+
+        build_authorization_url(&metadata, &oidc_config, &state, &pkce_challenge)
+    }.await;
+
+    match result {
+        Ok(url) => HttpResponse::Found().insert_header((header::LOCATION, url)).finish(),
+        Err(err) => err.error_response(),
+    }
+}
+
+/// Finish an OIDC login
+///
+/// This endpoint is called back by the identity provider. The user is linked by verified email or provisioned if needed,
+/// then redirected with the authentication cookie.
+#[utoipa::path(
+    get,
+    path = "/oidc/callback",
+    tag = "Authentication",
+    context_path = "/auth",
+    params(
+        ("code" = String, Query, description = "The authorization code given by the identity provider"),
+        ("state" = String, Query, description = "The state given when the login started")
+    ),
+    responses(
+        (status = 302, description = "Login successful, redirection to the application"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "OIDC Not Configured", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[get("/oidc/callback")]
+async fn oidc_callback_route(
+    data: web::Data<AppState>,
+    query: web::Query<OidcCallbackQuery>,
+) -> HttpResponse {
+    let oidc_config = match get_oidc_config(&data) {
+        Ok(oidc_config) => oidc_config,
+        Err(err) => return err.error_response(),
+    };
+    let query = query.into_inner();
+
+    // The state can only be used once
+    let conn: DBPool = data.database_pool.clone().as_ref().clone();
+    let login_state = match take_oidc_login_state(&conn, &query.state).await {
+        Ok(login_state) => login_state,
+        Err(DBError::NotFound) => return UserError::Unauthorized(UnauthorizedError).error_response(),
+        Err(err) => return APIError::from(err).error_response(),
+    };
+
+    let metadata = match discover_provider(&oidc_config).await {
+        Ok(metadata) => metadata,
+        Err(err) => return err.error_response(),
+    };
+    let user_info = match exchange_code(&metadata, &oidc_config, &query.code, &login_state.code_verifier).await {
+        Ok(user_info) => user_info,
+        Err(err) => return err.error_response(),
+    };
+
+    let result = async move {
+        let conn: DBPool = data.database_pool.clone().as_ref().clone();
+        let config = {
+            let config_guard = data.config.read();
+            config_guard.clone()
+        };
+
+        let user = resolve_oidc_user(&conn, login_state.organization_id, &metadata.issuer, user_info).await?;
+
+        // Generate token
+        let now = Utc::now();
+        let expiration = now + config.jwt_config.expires_in;
+        let claim = UserClaims{
+            sub: user.id,
+            iat: now.timestamp() as usize,
+            exp: expiration.timestamp() as usize,
+            token_version: user.token_version,
+        };
+        encode_token::<UserClaims>(&claim, &config)
+    }.await;
+
+    match result {
+        Ok(token) => {
+            let cookie = CookieBuilder::new("token", token)
+                .http_only(false)
+                .secure(false)
+                .same_site(SameSite::Strict)
+                .path("/")
+                .finish();
+            HttpResponse::Found()
+                .insert_header((header::LOCATION, oidc_config.post_login_redirect_url))
+                .cookie(cookie)
+                .finish()
+        },
+        Err(err) => err.error_response(),
     }
 }
 
 /// Request a password reset
 ///
-/// This endpoint allows users to request a password reset.
+/// This endpoint allows users to request a password reset. A link to reset the password is sent by email if an account
+/// exists with this address, the response is the same either way.
 #[utoipa::path(
     post,
     path = "/reset-request",
@@ -333,50 +512,65 @@ async fn validate_mfa_code_route(
     ),
     responses(
         (status = 200, description = "Password reset request sent", body = String),
-        (status = 400, description = "Bad Request", body = ValidationError),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 400, description = "Bad Request", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[post("/reset-request")]
 async fn request_reset_password_route(
+    req: HttpRequest,
     data: web::Data<AppState>,
     info: web::Json<ResetPasswordRequestPostModel>,
 ) -> HttpResponse {
-    let result = web::block(move || {
+    let audit_context = get_audit_context(&req);
+    let app_state = data.clone();
+    let result = async move {
         let conn: DBPool = data.database_pool.clone().as_ref().clone();
         let value = info.into_inner();
         value.validate()?;
         let email = value.email;
 
         // Check if email exists
-        let user = match get_user_by_email(&conn, &email) {
+        let user = match get_user_by_email_in_any_organization(&conn, &email).await {
             Ok(user) => Some(user),
             Err(err) => match err {
                 DBError::NotFound => None,
                 _ => return Err(APIError::from(err)),
             },
         };
-        if let Some(user) = user {
-            tokio::spawn( async move {
-                request_password_reset(&data, user);
-            });
+        if let Some(user) = &user {
+            record_audit_log::<()>(&conn, &audit_context, AuditEvent {
+                action: AuditAction::PasswordResetRequested,
+                resource_type: "user",
+                resource_id: Some(user.id.to_string()),
+                before: None,
+                after: None,
+            }).await;
         }
 
-        Ok(())
-    }).await;
+        Ok(user)
+    }.await;
 
     match result {
-        Ok(response) => match response {
-            Ok(_) => HttpResponse::Ok().finish(),
-            Err(err) => err.error_response(),
+        Ok(user) => {
+            // The mail is sent in the background so the response doesn't reveal whether the account exists
+            if let Some(user) = user {
+                actix_web::rt::spawn(async move {
+                    if let Err(err) = request_password_reset(&app_state, &user).await {
+                        tracing::error!(error = %err, "failed to send the password reset email");
+                    }
+                }.instrument(tracing::Span::current()));
+            }
+            HttpResponse::Ok().finish()
         },
-        Err(_) => ServerError::InternalError(InternalError).error_response()
+        Err(err) => err.error_response(),
     }
 }
 
 /// Reset user's password
 ///
-/// This endpoint allows users to reset their password with the given token.
+/// This endpoint allows users to reset their password with the token returned by `/token/reset`.
+/// The token can only be used once and all the sessions of the user are invalidated.
 #[utoipa::path(
     post,
     path = "/password",
@@ -389,76 +583,62 @@ async fn request_reset_password_route(
     ),
     responses(
         (status = 200, description = "Password reset", body = String),
-        (status = 400, description = "Bad Request", body = ValidationError),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 400, description = "Bad Request", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
-#[post("/reset/password")]
+#[post("/password")]
 async fn reset_password_route(
     req: HttpRequest,
     data: web::Data<AppState>,
     info: web::Json<ResetPasswordPostModel>,
 ) -> HttpResponse {
-    let user = req.extensions().get::<User>().cloned();
-    let token_id = req.extensions().get::<Uuid>().cloned();
-    let result = web::block(move || {
+    let user = req.extensions().get::<User>().cloned().unwrap();
+    let token_id = req.extensions().get::<Uuid>().cloned().unwrap();
+    let audit_context = get_audit_context(&req);
+    let result: Result<(), APIError> = async move {
         let conn: DBPool = data.database_pool.clone().as_ref().clone();
+        let main_config = data.config.read().main_config.clone();
         let credentials = info.into_inner();
 
-        credentials.validate()?;
+        credentials.validate_with(&PasswordContext::new(&main_config, Some(user.username.clone())))?;
+        reset_password(&conn, &user, token_id, &credentials.password, main_config.password_history_size).await?;
 
-        println!("{:?}", credentials);
-
-        // Hash password
-        let hashed_password = match bcrypt::hash(&credentials.password, bcrypt::DEFAULT_COST) {
-            Ok(h) => h,
-            Err(_) => return Err(APIError::ServerError(ServerError::InternalError(InternalError))),
-        };
-
-        println!("{:?}", hashed_password);
-
-        // Update password
-        if let Some(user) = user {
-            update_user_password(&conn, user.id, UpdatedUserPassword {
-                password: Some(hashed_password),
-            })?;
-        } else {
-            return Err(APIError::UserError(UserError::Unauthorized(UnauthorizedError)));
-        }
-
-        println!("{:?}", token_id);
-
-        // Update token
-        update_token(&conn, token_id.unwrap(), UpdatedToken{
-            type_: None,
-            used: Some(true),
-        })?;
-
-        println!("{:?}", token_id);
+        record_audit_log::<()>(&conn, &audit_context, AuditEvent {
+            action: AuditAction::PasswordReset,
+            resource_type: "user",
+            resource_id: Some(user.id.to_string()),
+            before: None,
+            after: None,
+        }).await;
 
         Ok(())
-    }).await;
+    }.await;
 
     match result {
-        Ok(response) => match response {
-            Ok(_) => HttpResponse::Ok().finish(),
-            Err(err) => err.error_response(),
-        },
-        Err(_) => ServerError::InternalError(InternalError).error_response()
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(err) => err.error_response(),
     }
 }
 
-fn get_user_from_body(conn: &DBPool, login: &str) -> Result<User, DBError> {
+fn get_oidc_config(data: &AppState) -> Result<OidcConfig, APIError> {
+    data.config.read().oidc_config.clone()
+        .ok_or(APIError::UserError(UserError::NotFound(NotFoundError {
+            resource: "OIDC provider".to_string(),
+        })))
+}
+
+async fn get_user_from_body(conn: &DBPool, login: &str) -> Result<User, DBError> {
     let user = if login.contains('@') {
         // Check if email exists
-        match get_user_by_email(&conn, login) {
+        match get_user_by_email_in_any_organization(conn, login).await {
             Ok(user) => Ok(user),
             Err(err) => Err(err),
         }
     } else {
         // Check if username exists
-        match get_user_by_username(&conn, login) {
+        match get_user_by_username(conn, login).await {
             Ok(user) => Ok(user),
             Err(err) => Err(err),
         }
@@ -472,6 +652,8 @@ pub fn auth_config(cfg: &mut web::ServiceConfig) {
             .service(register_route)
             .service(login_route)
             .service(validate_mfa_code_route)
+            .service(oidc_authorize_route)
+            .service(oidc_callback_route)
             .service(request_reset_password_route)
     );
 }
@@ -1,4 +1,7 @@
-use actix_web::{get, HttpResponse, Responder};
+use actix_web::{get, web, HttpResponse, Responder, ResponseError};
+use application::health::{check_readiness, ReadinessStatus};
+use shared::app_state_model::AppState;
+use shared::error_models::{NotFoundError, UserError};
 
 use crate::models::get_models::GenericResponse;
 
@@ -21,9 +24,49 @@ pub async fn healthcheck() -> impl Responder {
     HttpResponse::Ok().json(response)
 }
 
-pub async fn not_found() -> actix_web::Result<HttpResponse> {
+/// Check if the API process is alive
+///
+/// This liveness probe doesn't check the dependencies of the API, so that the process isn't restarted when one of them is down.
+#[utoipa::path(
+    get,
+    path = "/health/live",
+    tag = "Health",
+    responses(
+        (status = 200, description = "API is alive", body = GenericResponse),
+    )
+)]
+#[get("/api/health/live")]
+pub async fn liveness() -> impl Responder {
     let response = GenericResponse {
-        message: "Resource not found".to_string(),
+        message: "The API is alive".to_string(),
     };
-    Ok(HttpResponse::NotFound().json(response))
+    HttpResponse::Ok().json(response)
+}
+
+/// Check if the API is ready to handle requests
+///
+/// This readiness probe checks that the database was prepared at startup, the database connection and its migrations, the SMTP server when `SMTP_READINESS_CHECK` is enabled, and the last run of the scheduler.
+/// The result of each check is returned, with a 503 status when one of them failed.
+#[utoipa::path(
+    get,
+    path = "/health/ready",
+    tag = "Health",
+    responses(
+        (status = 200, description = "API is ready", body = ReadinessReport),
+        (status = 503, description = "API is degraded", body = ReadinessReport),
+    )
+)]
+#[get("/api/health/ready")]
+pub async fn readiness(data: web::Data<AppState>) -> HttpResponse {
+    let report = check_readiness(&data).await;
+    match report.status {
+        ReadinessStatus::Ready => HttpResponse::Ok().json(report),
+        ReadinessStatus::Degraded => HttpResponse::ServiceUnavailable().json(report),
+    }
+}
+
+pub async fn not_found() -> actix_web::Result<HttpResponse> {
+    Ok(UserError::NotFound(NotFoundError {
+        resource: "Route".to_string(),
+    }).error_response())
 }
\ No newline at end of file
@@ -1,12 +1,13 @@
 use actix_web::{get, put, web, HttpMessage, HttpRequest, HttpResponse, ResponseError};
 
 use application::database::config::{get_config_by_user_id, update_user_config};
+use application::timezone::parse_timezone;
 use domain::models::config::UpdatedUserConfig;
 use domain::models::users::User;
 use shared::app_state_model::AppState;
-use shared::error_models::{APIError, DBError, InternalError, NotFoundError, ServerError, UnauthorizedError, UserError};
+use shared::error_models::{APIError, BadRequestError, DBError, NotFoundError, UnauthorizedError, UserError};
 
-use crate::middlewares::auth::{RequireAuth, UserTokenValidator};
+use crate::middlewares::auth::{ApiKeyTokenValidator, RequireAuth};
 
 /// Get current user config
 ///
@@ -18,25 +19,22 @@ use crate::middlewares::auth::{RequireAuth, UserTokenValidator};
     context_path = "/config",
     responses(
         (status = 200, description = "The current user configuration", body = UserConfig),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError, examples(
-            ("NoToken" = (value = json!("Token not provided"))),
-            ("InvalidToken" = (value = json!("Error")))
-        )),
-        (status = 404, description = "Not Found", body = NotFoundError, example = json!("Database record")),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Not Found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[get("/user")]
 pub async fn get_config_by_user_id_route(req: HttpRequest, data: web::Data<AppState>) -> HttpResponse {
     let user = req.extensions().get::<User>().cloned();
-    let result = web::block(move || {
+    let result = async move {
         let conn = data.database_pool.clone().as_ref().clone();
         if let Some(user) = user {
-            match get_config_by_user_id(&conn, user.id) {
+            match get_config_by_user_id(&conn, user.id).await {
                 Ok(user_config) => Ok(user_config),
                 Err(err) => match err {
                     DBError::NotFound => Err(APIError::UserError(UserError::NotFound(NotFoundError {
-                        resource: "User configuration not found".to_string(),
+                        resource: "User configuration".to_string(),
                     }))),
                     _ => Err(APIError::from(err)),
                 },
@@ -44,14 +42,11 @@ pub async fn get_config_by_user_id_route(req: HttpRequest, data: web::Data<AppSt
         } else {
             Err(APIError::UserError(UserError::Unauthorized(UnauthorizedError)))
         }
-    }).await;
+    }.await;
 
     match result {
-        Ok(response) => match response {
-            Ok(user_config) => HttpResponse::Ok().json(user_config),
-            Err(err) => APIError::from(err).error_response(),
-        },
-        Err(_) => ServerError::InternalError(InternalError).error_response(),
+        Ok(user_config) => HttpResponse::Ok().json(user_config),
+        Err(err) => err.error_response(),
     }
 }
 
@@ -70,39 +65,39 @@ pub async fn get_config_by_user_id_route(req: HttpRequest, data: web::Data<AppSt
     ),
     responses(
         (status = 200, description = "The user configuration updated successfully"),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError, examples(
-            ("NoToken" = (value = json!("Token not provided"))),
-            ("InvalidToken" = (value = json!("Error")))
-        )),
-        (status = 404, description = "Not Found", body = NotFoundError, example = json!("Database record")),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 400, description = "Bad Request", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Not Found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[put("/user")]
 pub async fn update_user_config_route(req: HttpRequest, data: web::Data<AppState>, updated_config: web::Json<UpdatedUserConfig>) -> HttpResponse {
     let user = req.extensions().get::<User>().cloned();
-    let result = web::block(move || {
+    let result = async move {
         let conn = data.database_pool.clone().as_ref().clone();
+        if updated_config.timezone.as_deref().is_some_and(|timezone| parse_timezone(timezone).is_none()) {
+            return Err(APIError::UserError(UserError::BadRequest(BadRequestError {
+                request: "Unknown timezone".to_string(),
+            })));
+        }
         if let Some(user) = user {
-            update_user_config(&conn, user.id, updated_config.into_inner()).map_err(|err | APIError::from(err))
+            update_user_config(&conn, user.id, updated_config.into_inner()).await.map_err(|err | APIError::from(err))
         } else {
             Err(APIError::UserError(UserError::Unauthorized(UnauthorizedError)))
         }
-    }).await;
+    }.await;
 
     match result {
-        Ok(response) => match response {
-            Ok(_) => HttpResponse::Ok().finish(),
-            Err(err) => APIError::from(err).error_response(),
-        },
-        Err(_) => ServerError::InternalError(InternalError).error_response(),
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(err) => err.error_response(),
     }
 }
 
 pub fn configurations_config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/config")
-            .wrap(RequireAuth::new(UserTokenValidator))
+            .wrap(RequireAuth::new(ApiKeyTokenValidator))
             .service(get_config_by_user_id_route)
             .service(update_user_config_route)
     );
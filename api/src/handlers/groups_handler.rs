@@ -1,52 +1,56 @@
-use crate::middlewares::auth::{RequireAuth, UserTokenValidator};
-use crate::models::get_models::ProjectGroupsGetModel;
+use crate::middlewares::auth::{ApiKeyTokenValidator, RequireAuth};
 use crate::models::post_models::NewGroupPostModel;
 use crate::models::put_models::UpdatedGroupPutModel;
-use actix_web::{delete, get, post, put, web, HttpResponse, ResponseError};
-use application::database::groups::{create_group, create_group_students, delete_group, get_group_student_mark_details, get_groups_and_students_from_project_id, get_students_without_group, remove_all_students_from_a_group, update_group};
+use crate::utils::audit::get_audit_context;
+use actix_web::{delete, get, post, put, web, HttpMessage, HttpRequest, HttpResponse, ResponseError};
+use application::audit::log::{record_audit_log, AuditEvent};
+use application::authorization::promotions::{check_group_permission, check_project_permission};
+use application::database::groups::{create_group, create_group_students, delete_group, get_group_by_id, get_group_student_mark_details, get_groups_page_and_students_from_project_id, get_students_without_group, remove_all_students_from_a_group, update_group, ProjectGroup};
+use application::database::pagination::{GroupSortField, ListParams, Page};
+use application::database::transaction::{transaction, ScopedFutureExt};
+use domain::models::audit_logs::AuditAction;
 use domain::models::groups::{NewGroup, NewGroupStudent, UpdatedGroup};
+use domain::models::promotions_members::PromotionPermission;
+use domain::models::users::User;
 use garde::Validate;
 use shared::app_state_model::AppState;
-use shared::error_models::{APIError, DBError, InternalError, ServerError};
+use shared::error_models::APIError;
 use uuid::Uuid;
 
 /// Get all the groups and the students from a project
 ///
-/// This endpoint allows you to get all the groups and the corresponding students from a project in the database.
+/// This endpoint returns a page of the groups of a project with their students, sorted by name by default.
+/// The text filter applies to the name of the groups.
 #[utoipa::path(
     get,
     path = "/project/{id}",
     tag = "Groups",
     context_path = "/groups",
     params(
-        ("id" = Uuid, description = "The project id to get the groups from")
+        ("id" = Uuid, description = "The project id to get the groups from"),
+        ListParams<GroupSortField>
     ),
     responses(
-        (status = 200, description = "All the returned groups", body = ProjectGroupsGetModel),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError, examples(
-            ("NoToken" = (value = json!("Token not provided"))),
-            ("InvalidToken" = (value = json!("Error")))
-        )),
-        (status = 404, description = "Not Found", body = NotFoundError, example = json!("NotFoundError")),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 200, description = "A page of the groups", body = ProjectGroupsPage),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Not Found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[get("/project/{id}")]
-pub async fn get_groups_and_students_from_project_id_route(data: web::Data<AppState>, id: web::Path<Uuid>) -> HttpResponse {
-    let result = web::block(move || -> Result<ProjectGroupsGetModel, DBError> {
+pub async fn get_groups_and_students_from_project_id_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>, params: web::Query<ListParams<GroupSortField>>) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
+    let result: Result<Page<ProjectGroup>, APIError> = async move {
         let conn = data.database_pool.clone().as_ref().clone();
-        let groups = get_groups_and_students_from_project_id(&conn, id.into_inner())?;
-        Ok(ProjectGroupsGetModel {
-            groups
-        })
-    }).await;
+        let project_id = id.into_inner();
+        check_project_permission(&conn, project_id, &teacher, PromotionPermission::Read).await?;
+        Ok(get_groups_page_and_students_from_project_id(&conn, teacher.organization_id, project_id, &params).await?)
+    }.await;
 
     match result {
-        Ok(response) => match response {
-            Ok(groups) => HttpResponse::Ok().json(groups),
-            Err(err) => APIError::from(err).error_response(),
-        },
-        Err(_) => ServerError::InternalError(InternalError).error_response(),
+        Ok(groups) => HttpResponse::Ok().json(groups),
+        Err(err) => err.error_response(),
     }
 }
 
@@ -63,28 +67,25 @@ pub async fn get_groups_and_students_from_project_id_route(data: web::Data<AppSt
     ),
     responses(
         (status = 200, description = "All the returned students", body = [Student]),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError, examples(
-            ("NoToken" = (value = json!("Token not provided"))),
-            ("InvalidToken" = (value = json!("Error")))
-        )),
-        (status = 404, description = "Not Found", body = NotFoundError, example = json!("NotFoundError")),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Not Found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[get("/project/{id}/students")]
-pub async fn get_students_without_group_route(data: web::Data<AppState>, id: web::Path<Uuid>) -> HttpResponse {
-    let result = web::block(move || {
+pub async fn get_students_without_group_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
+    let result = async move {
         let conn = data.database_pool.clone().as_ref().clone();
         let project_id = id.into_inner();
-        get_students_without_group(&conn, project_id)
-    }).await;
+        check_project_permission(&conn, project_id, &teacher, PromotionPermission::Read).await?;
+        get_students_without_group(&conn, teacher.organization_id, project_id).await.map_err(APIError::from)
+    }.await;
 
     match result {
-        Ok(response) => match response {
-            Ok(students) => HttpResponse::Ok().json(students),
-            Err(err) => APIError::from(err).error_response(),
-        },
-        Err(_) => ServerError::InternalError(InternalError).error_response(),
+        Ok(students) => HttpResponse::Ok().json(students),
+        Err(err) => err.error_response(),
     }
 }
 
@@ -102,28 +103,25 @@ pub async fn get_students_without_group_route(data: web::Data<AppState>, id: web
     ),
     responses(
         (status = 200, description = "The student details", body = StudentGroupMarkDetails),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError, examples(
-            ("NoToken" = (value = json!("Token not provided"))),
-            ("InvalidToken" = (value = json!("Error")))
-        )),
-        (status = 404, description = "Group or Student Not Found", body = NotFoundError, example = json!("NotFoundError")),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Group or Student Not Found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[get("/{group_id}/student/{student_id}")]
-pub async fn get_group_student_mark_details_route(data: web::Data<AppState>, path: web::Path<(Uuid, Uuid)>) -> HttpResponse {
+pub async fn get_group_student_mark_details_route(data: web::Data<AppState>, req: HttpRequest, path: web::Path<(Uuid, Uuid)>) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
     let (group_id, student_id) = path.into_inner();
-    let result = web::block(move || {
+    let result = async move {
         let conn = data.database_pool.clone().as_ref().clone();
-        get_group_student_mark_details(&conn, group_id, student_id)
-    }).await;
+        check_group_permission(&conn, group_id, &teacher, PromotionPermission::Read).await?;
+        get_group_student_mark_details(&conn, teacher.organization_id, group_id, student_id).await.map_err(APIError::from)
+    }.await;
 
     match result {
-        Ok(response) => match response {
-            Ok(group_student) => HttpResponse::Ok().json(group_student),
-            Err(err) => APIError::from(err).error_response(),
-        },
-        Err(_) => ServerError::InternalError(InternalError).error_response(),
+        Ok(group_student) => HttpResponse::Ok().json(group_student),
+        Err(err) => err.error_response(),
     }
 }
 
@@ -144,36 +142,32 @@ pub async fn get_group_student_mark_details_route(data: web::Data<AppState>, pat
     ),
     responses(
         (status = 201, description = "The group has been created", body = Uuid),
-        (status = 400, description = "Bad Request", body = BadRequestError, examples(
-            ("InvalidName" = (value = json!("Invalid name"))),
-        )),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError, examples(
-            ("NoToken" = (value = json!("Token not provided"))),
-            ("InvalidToken" = (value = json!("Error")))
-        )),
-        (status = 404, description = "Project Not Found", body = NotFoundError, example = json!("NotFoundError")),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 400, description = "Bad Request", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project Not Found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[post("/project/{id}")]
-pub async fn create_group_route(data: web::Data<AppState>, id: web::Path<Uuid>, group: web::Json<NewGroupPostModel>) -> HttpResponse {
-    let result = web::block(move || {
+pub async fn create_group_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>, group: web::Json<NewGroupPostModel>) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
+    let result = async move {
         let conn = data.database_pool.clone().as_ref().clone();
         group.validate().map_err(APIError::from)?;
+        let project_id = id.into_inner();
+        check_project_permission(&conn, project_id, &teacher, PromotionPermission::Edit).await?;
         let new_group = NewGroup {
             name: group.name.clone(),
-            project_id: id.into_inner(),
+            project_id,
             max_mark: None,
         };
-        create_group(&conn, new_group).map_err(APIError::from)
-    }).await;
+        create_group(&conn, teacher.organization_id, new_group).await.map_err(APIError::from)
+    }.await;
 
     match result {
-        Ok(response) => match response {
-            Ok(group_id) => HttpResponse::Created().json(group_id),
-            Err(err) => APIError::from(err).error_response(),
-        },
-        Err(_) => ServerError::InternalError(InternalError).error_response(),
+        Ok(group_id) => HttpResponse::Created().json(group_id),
+        Err(err) => err.error_response(),
     }
 }
 
@@ -195,20 +189,19 @@ pub async fn create_group_route(data: web::Data<AppState>, id: web::Path<Uuid>,
     ),
     responses(
         (status = 200, description = "The students have been assigned to the group"),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError, examples(
-            ("NoToken" = (value = json!("Token not provided"))),
-            ("InvalidToken" = (value = json!("Error")))
-        )),
-        (status = 404, description = "Group Not Found", body = NotFoundError, example = json!("NotFoundError")),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Group Not Found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[post("/{group_id}/students")]
-pub async fn assign_students_to_group_route(data: web::Data<AppState>, group_id: web::Path<Uuid>, students: web::Json<Vec<Uuid>>) -> HttpResponse {
-    let result = web::block(move || {
+pub async fn assign_students_to_group_route(data: web::Data<AppState>, req: HttpRequest, group_id: web::Path<Uuid>, students: web::Json<Vec<Uuid>>) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
+    let result: Result<(), APIError> = async move {
         let conn = data.database_pool.clone().as_ref().clone();
         let group_id = group_id.into_inner();
-        remove_all_students_from_a_group(&conn, group_id)?;
+        check_group_permission(&conn, group_id, &teacher, PromotionPermission::Edit).await?;
 
         let mut new_group_students: Vec<NewGroupStudent> = Vec::new();
         for student_id in students.into_inner() {
@@ -217,15 +210,19 @@ pub async fn assign_students_to_group_route(data: web::Data<AppState>, group_id:
                 student_id,
             });
         }
-        create_group_students(&conn, new_group_students)
-    }).await;
+
+        // The previous students are kept if the new ones can't be assigned
+        transaction(&conn, |conn| async move {
+            remove_all_students_from_a_group(&mut *conn, teacher.organization_id, group_id).await?;
+            create_group_students(&mut *conn, new_group_students).await
+        }.scope_boxed()).await?;
+
+        Ok(())
+    }.await;
 
     match result {
-        Ok(response) => match response {
-            Ok(_) => HttpResponse::Ok().finish(),
-            Err(err) => APIError::from(err).error_response(),
-        },
-        Err(_) => ServerError::InternalError(InternalError).error_response(),
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(err) => err.error_response(),
     }
 }
 
@@ -247,37 +244,48 @@ pub async fn assign_students_to_group_route(data: web::Data<AppState>, group_id:
     ),
     responses(
         (status = 200, description = "The group has been updated"),
-        (status = 400, description = "Bad Request", body = BadRequestError, examples(
-            ("InvalidName" = (value = json!("Invalid name"))),
-        )),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError, examples(
-            ("NoToken" = (value = json!("Token not provided"))),
-            ("InvalidToken" = (value = json!("Error")))
-        )),
-        (status = 404, description = "Group Not Found", body = NotFoundError, example = json!("NotFoundError")),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 400, description = "Bad Request", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Group Not Found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[put("/{id}")]
-pub async fn update_group_route(data: web::Data<AppState>, id: web::Path<Uuid>, group: web::Json<UpdatedGroupPutModel>) -> HttpResponse {
-    let result = web::block(move || {
+pub async fn update_group_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>, group: web::Json<UpdatedGroupPutModel>) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
+    let audit_context = get_audit_context(&req);
+    let result: Result<(), APIError> = async move {
         let conn = data.database_pool.clone().as_ref().clone();
         let group_id = id.into_inner();
-        group.validate().map_err(APIError::from).map_err(APIError::from)?;
+        group.validate().map_err(APIError::from)?;
+
+        // Assistants can only enter the group mark
+        let permission = if group.name.is_some() { PromotionPermission::Edit } else { PromotionPermission::Grade };
+        check_group_permission(&conn, group_id, &teacher, permission).await?;
+        let previous_group = get_group_by_id(&conn, teacher.organization_id, group_id).await?;
         let updated_group = UpdatedGroup {
             name: group.name.clone(),
             mark: group.mark,
             max_mark: None,
         };
-        update_group(&conn, group_id, updated_group).map_err(APIError::from)
-    }).await;
+        update_group(&conn, teacher.organization_id, group_id, updated_group).await?;
+
+        let group = get_group_by_id(&conn, teacher.organization_id, group_id).await?;
+        record_audit_log(&conn, &audit_context, AuditEvent {
+            action: AuditAction::GroupUpdated,
+            resource_type: "group",
+            resource_id: Some(group_id.to_string()),
+            before: Some(&previous_group),
+            after: Some(&group),
+        }).await;
+
+        Ok(())
+    }.await;
 
     match result {
-        Ok(response) => match response {
-            Ok(_) => HttpResponse::Ok().finish(),
-            Err(err) => APIError::from(err).error_response(),
-        },
-        Err(_) => ServerError::InternalError(InternalError).error_response(),
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(err) => err.error_response(),
     }
 }
 
@@ -294,36 +302,37 @@ pub async fn update_group_route(data: web::Data<AppState>, id: web::Path<Uuid>,
     ),
     responses(
         (status = 200, description = "The group has been deleted"),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError, examples(
-            ("NoToken" = (value = json!("Token not provided"))),
-            ("InvalidToken" = (value = json!("Error")))
-        )),
-        (status = 404, description = "Group Not Found", body = NotFoundError, example = json!("NotFoundError")),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Group Not Found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[delete("/{id}")]
-pub async fn delete_group_route(data: web::Data<AppState>, id: web::Path<Uuid>) -> HttpResponse {
-    let result = web::block(move || {
+pub async fn delete_group_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
+    let result: Result<(), APIError> = async move {
         let conn = data.database_pool.clone().as_ref().clone();
         let group_id = id.into_inner();
-        remove_all_students_from_a_group(&conn, group_id)?;
-        delete_group(&conn, group_id)
-    }).await;
+        check_group_permission(&conn, group_id, &teacher, PromotionPermission::Delete).await?;
+        transaction(&conn, |conn| async move {
+            remove_all_students_from_a_group(&mut *conn, teacher.organization_id, group_id).await?;
+            delete_group(&mut *conn, teacher.organization_id, group_id).await
+        }.scope_boxed()).await?;
+
+        Ok(())
+    }.await;
 
     match result {
-        Ok(response) => match response {
-            Ok(_) => HttpResponse::Ok().finish(),
-            Err(err) => APIError::from(err).error_response(),
-        },
-        Err(_) => ServerError::InternalError(InternalError).error_response(),
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(err) => err.error_response(),
     }
 }
 
 pub fn groups_config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/groups")
-            .wrap(RequireAuth::new(UserTokenValidator))
+            .wrap(RequireAuth::new(ApiKeyTokenValidator))
             .service(get_groups_and_students_from_project_id_route)
             .service(get_students_without_group_route)
             .service(get_group_student_mark_details_route)
@@ -3,14 +3,17 @@ use crate::models::post_models::GradedStudentPostModel;
 use actix_web::{get, post, web, HttpMessage, HttpRequest, HttpResponse, ResponseError};
 use application::database::groups::{get_group_by_id, get_group_id_of_student, get_students_from_group_for_evaluation};
 use application::database::marks::create_mark;
+use application::database::projects::get_project_by_id;
 use application::database::students_tokens::update_student_token;
+use application::database::transaction::{transaction, Executor, ScopedFutureExt};
+use application::extensions::check_student_submission;
 use domain::models::groups::Group;
 use domain::models::marks::NewMark;
 use domain::models::students::Student;
 use domain::models::students_tokens::UpdatedStudentToken;
 use infrastructure::DBPool;
 use shared::app_state_model::AppState;
-use shared::error_models::{APIError, DBError, InternalError, NotFoundError, ServerError, UnauthorizedError, UserError};
+use shared::error_models::{APIError, DBError, NotFoundError, UnauthorizedError, UserError};
 use uuid::Uuid;
 
 /// Request group of current student to evaluate
@@ -23,12 +26,9 @@ use uuid::Uuid;
     context_path = "/marks",
     responses(
         (status = 200, description = "Respond with group students to evaluate", body = MinimalGroupStudents),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError, examples(
-            ("NoToken" = (value = json!("Token not provided"))),
-            ("InvalidToken" = (value = json!("Error")))
-        )),
-        (status = 404, description = "Group not found", body = NotFoundError, example = json!("NotFoundError")),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Group not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[get("/group-to-evaluate")]
@@ -36,28 +36,26 @@ async fn get_group_to_evaluate_route(
     data: web::Data<AppState>,
     req: HttpRequest
 ) -> HttpResponse {
-    // Get group from request
+    // Get student and group from request
+    let student = req.extensions().get::<Student>().cloned().unwrap();
     let group = req.extensions().get::<Group>().cloned().unwrap();
 
-    println!("Group: {:?}", group);
-
-    let result = web::block(move || {
+    let result = async move {
         let conn: DBPool = data.database_pool.clone().as_ref().clone();
-        get_students_from_group_for_evaluation(&conn, group.id).map_err(APIError::from)
-    }).await;
+        get_students_from_group_for_evaluation(&conn, student.organization_id, group.id).await.map_err(APIError::from)
+    }.await;
 
     match result {
-        Ok(group) => match group {
-            Ok(group) => HttpResponse::Ok().json(group),
-            Err(err) => err.error_response(),
-        },
-        Err(_) => ServerError::InternalError(InternalError).error_response()
+        Ok(group) => HttpResponse::Ok().json(group),
+        Err(err) => err.error_response(),
     }
 }
 
 /// Evaluate group
 ///
-/// This endpoint allows student to evaluate his group.
+/// This endpoint allows student to evaluate his group. Once the evaluation is closed, the students granted an
+/// extension can still evaluate theirs, and the late submissions are accepted if the project allows them, the
+/// evaluation being flagged as late.
 #[utoipa::path(
     post,
     path = "/evaluate/group/{group_id}",
@@ -73,12 +71,10 @@ async fn get_group_to_evaluate_route(
     ),
     responses(
         (status = 200, description = "The group was evaluated successfully", body = ()),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError, examples(
-            ("NoToken" = (value = json!("Token not provided"))),
-            ("InvalidToken" = (value = json!("Error")))
-        )),
-        (status = 404, description = "Not found", body = NotFoundError, example = json!("NotFoundError")),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "The evaluation of the project is not open or the deadline of the student has passed", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[post("/evaluate/group/{group_id}")]
@@ -93,14 +89,18 @@ pub async fn evaluate_group_route(
     let token_id = req.extensions().get::<Uuid>().cloned().unwrap();
     let group_id = group_id.into_inner();
 
-    let result = web::block(move || {
+    let result = async move {
         let conn: DBPool = data.database_pool.clone().as_ref().clone();
 
         // Get group
-        let group = get_group_by_id(&conn, group_id.clone())?;
+        let group = get_group_by_id(&conn, student.organization_id, group_id).await?;
+
+        // The evaluation may have been closed before the token expires, unless the student has an extension
+        let project = get_project_by_id(&conn, student.organization_id, group.project_id).await?;
+        let is_late = check_student_submission(&data.repositories(), &project, student.id).await?;
 
         // Get student's group
-        let student_group_id = get_group_id_of_student(&conn, student.id, group.project_id)?;
+        let student_group_id = get_group_id_of_student(&conn, student.organization_id, student.id, group.project_id).await?;
         if student_group_id.is_none() {
             return Err(APIError::UserError(UserError::NotFound(NotFoundError { resource: format!("Group for student {}", student.id) })));
         }
@@ -111,47 +111,52 @@ pub async fn evaluate_group_route(
             return Err(APIError::UserError(UserError::Unauthorized(UnauthorizedError)));
         }
 
-        // Evaluate group
-        register_group_grades(&conn, group.project_id, group_id, student.id, body.0).map_err(APIError::from)?;
+        // The token can't be used again once the grades are saved
+        transaction(&conn, |conn| async move {
+            // Evaluate group
+            register_group_grades(&mut *conn, student.organization_id, group.project_id, group_id, student.id, body.0).await?;
 
-        // Define token as used
-        update_student_token(&conn, token_id, UpdatedStudentToken {
-            used: Some(true),
-        }).map_err(APIError::from)
-    }).await;
+            // Define token as used
+            update_student_token(&mut *conn, student.organization_id, token_id, UpdatedStudentToken {
+                token: None,
+                used: Some(true),
+                submitted_late: Some(is_late),
+            }).await
+        }.scope_boxed()).await.map_err(APIError::from)
+    }.await;
 
     match result {
-        Ok(_) => match result {
-            Ok(_) => HttpResponse::Ok().finish(),
-            Err(err) => err.error_response(),
-        },
-        Err(_) => ServerError::InternalError(InternalError).error_response()
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(err) => err.error_response(),
     }
 }
 
-fn register_group_grades(conn: &DBPool, project_id: Uuid, group_id: Uuid, student_id: Uuid, grades: Vec<GradedStudentPostModel>) -> Result<(), DBError> {
-    for graded_student in grades {
-        // Check if student is in the group
-        let student_group_id = get_group_id_of_student(conn, graded_student.student_id, project_id)?;
-        if student_group_id.is_none() || student_group_id.unwrap() != group_id {
-            return Err(DBError::NotFound);
+/// Saves the grades given by a student to the members of their group, all of them or none.
+async fn register_group_grades(conn: impl Executor, organization_id: Uuid, project_id: Uuid, group_id: Uuid, student_id: Uuid, grades: Vec<GradedStudentPostModel>) -> Result<(), DBError> {
+    transaction(conn, |conn| async move {
+        for graded_student in grades {
+            // Check if student is in the group
+            let student_group_id = get_group_id_of_student(&mut *conn, organization_id, graded_student.student_id, project_id).await?;
+            if student_group_id.is_none() || student_group_id.unwrap() != group_id {
+                return Err(DBError::NotFound);
+            }
+
+            let new_mark = NewMark {
+                project_id,
+                group_id,
+                noted_student_id: graded_student.student_id,
+                grader_student_id: student_id,
+                mark: graded_student.mark,
+                max_mark: None,
+                comment: graded_student.comment.clone(),
+            };
+
+            // Save mark
+            create_mark(&mut *conn, organization_id, new_mark).await?;
         }
 
-        let new_mark = NewMark {
-            project_id,
-            group_id,
-            noted_student_id: graded_student.student_id,
-            grader_student_id: student_id,
-            mark: graded_student.mark,
-            max_mark: None,
-            comment: graded_student.comment.clone(),
-        };
-
-        // Save mark
-        create_mark(conn, new_mark)?;
-    }
-
-    Ok(())
+        Ok(())
+    }.scope_boxed()).await
 }
 
 pub fn marks_config(cfg: &mut web::ServiceConfig) {
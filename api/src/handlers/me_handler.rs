@@ -0,0 +1,319 @@
+use crate::middlewares::auth::{RequireAuth, UserTokenValidator};
+use crate::models::get_models::MeGetModel;
+use crate::models::post_models::{DeleteAccountPostModel, PasswordContext, validate_unique_user};
+use crate::models::put_models::{UpdatedMePutModel, UpdatedPasswordPutModel};
+use crate::utils::audit::get_audit_context;
+use actix_web::cookie::{CookieBuilder, SameSite};
+use actix_web::{delete, get, put, web, HttpMessage, HttpRequest, HttpResponse, ResponseError};
+use application::audit::log::{record_audit_log, AuditContext, AuditEvent};
+use application::authentication::email_verification::{confirm_email_verification, request_email_verification};
+use application::authentication::passwords::set_user_password;
+use application::authentication::tokens::encode_token;
+use application::authorization::organizations::check_email_domain;
+use application::database::organizations::get_organization_by_id;
+use application::database::transaction::{transaction, ScopedFutureExt};
+use application::database::user_passwords::get_user_password_by_user_id;
+use application::database::users::{delete_user_account, get_user_by_id, update_user, update_user_info};
+use chrono::Utc;
+use domain::models::audit_logs::AuditAction;
+use domain::models::users::{UpdatedUser, UpdatedUserInfo, User, UserRole};
+use garde::Validate;
+use infrastructure::DBPool;
+use serde::Deserialize;
+use shared::app_state_model::AppState;
+use shared::error_models::{APIError, DBError, ForbiddenError, UnauthorizedError, UserError};
+use shared::token_models::UserClaims;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+struct EmailVerificationQuery {
+    id: Uuid,
+}
+
+/// Get the current user
+///
+/// This endpoint returns the profile of the current user.
+#[utoipa::path(
+    get,
+    path = "",
+    tag = "Me",
+    context_path = "/me",
+    responses(
+        (status = 200, description = "The profile of the user", body = MeGetModel),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[get("")]
+pub async fn get_me_route(req: HttpRequest) -> HttpResponse {
+    let user = req.extensions().get::<User>().cloned().unwrap();
+
+    HttpResponse::Ok().json(MeGetModel::from(user))
+}
+
+/// Update the current user
+///
+/// This endpoint updates the username of the current user, and sends a verification link to the new email address if one is given.
+/// The email address is only changed once the link has been followed.
+#[utoipa::path(
+    put,
+    path = "",
+    tag = "Me",
+    context_path = "/me",
+    request_body(
+        content = UpdatedMePutModel,
+        description = "The new username and email of the user",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Profile updated", body = MeGetModel),
+        (status = 400, description = "Bad Request", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[put("")]
+pub async fn update_me_route(data: web::Data<AppState>, req: HttpRequest, updated_me: web::Json<UpdatedMePutModel>) -> HttpResponse {
+    let user = req.extensions().get::<User>().cloned().unwrap();
+    let result: Result<MeGetModel, APIError> = async move {
+        let conn = data.database_pool.clone().as_ref().clone();
+        let updated_me = updated_me.into_inner();
+        validate_unique_user(&conn, updated_me.email.as_deref(), updated_me.username.as_deref(), updated_me.validate()).await?;
+
+        if updated_me.username.is_some() {
+            update_user_info(&conn, user.id, UpdatedUserInfo {
+                username: updated_me.username,
+                email: None,
+            }).await?;
+        }
+        if let Some(email) = updated_me.email.filter(|email| *email != user.email) {
+            // The new address has to follow the rules of the organization, as when registering
+            check_email_domain(&get_organization_by_id(&conn, user.organization_id).await?, &email)?;
+            request_email_verification(&data, &user, email).await?;
+        }
+
+        Ok(MeGetModel::from(get_user_by_id(&conn, user.id).await?))
+    }.await;
+
+    match result {
+        Ok(me) => HttpResponse::Ok().json(me),
+        Err(err) => err.error_response(),
+    }
+}
+
+/// Change the password of the current user
+///
+/// This endpoint changes the password of the current user after checking the current one.
+/// All the other sessions are invalidated and a new token is returned in a cookie.
+#[utoipa::path(
+    put,
+    path = "/password",
+    tag = "Me",
+    context_path = "/me",
+    request_body(
+        content = UpdatedPasswordPutModel,
+        description = "The current and the new password of the user",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Password changed"),
+        (status = 400, description = "Bad Request", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[put("/password")]
+pub async fn update_password_route(data: web::Data<AppState>, req: HttpRequest, passwords: web::Json<UpdatedPasswordPutModel>) -> HttpResponse {
+    let user = req.extensions().get::<User>().cloned().unwrap();
+    let audit_context = get_audit_context(&req);
+    let result: Result<String, APIError> = async move {
+        let conn = data.database_pool.clone().as_ref().clone();
+        let config = {
+            let config_guard = data.config.read();
+            config_guard.clone()
+        };
+        let passwords = passwords.into_inner();
+
+        check_current_password(&conn, &user, Some(&passwords.current_password)).await?;
+        passwords.validate_with(&PasswordContext::new(&config.main_config, Some(user.username.clone())))?;
+        // Increment the token version to invalidate all existing tokens
+        let token_version = user.token_version + 1;
+        let password_history_size = config.main_config.password_history_size;
+        transaction(&conn, |conn| async move {
+            set_user_password(&mut *conn, user.id, &passwords.new_password, password_history_size).await?;
+            update_user(&mut *conn, user.id, UpdatedUser {
+                username: None,
+                email: None,
+                has_validated_email: None,
+                role: None,
+                token_version: Some(token_version),
+                organization_role: None,
+            }).await?;
+            Ok::<_, APIError>(())
+        }.scope_boxed()).await?;
+
+        record_audit_log::<()>(&conn, &audit_context, AuditEvent {
+            action: AuditAction::PasswordChanged,
+            resource_type: "user",
+            resource_id: Some(user.id.to_string()),
+            before: None,
+            after: None,
+        }).await;
+
+        // Generate a token for the current session
+        let now = Utc::now();
+        let expiration = now + config.jwt_config.expires_in;
+        let claim = UserClaims {
+            sub: user.id,
+            iat: now.timestamp() as usize,
+            exp: expiration.timestamp() as usize,
+            token_version,
+        };
+        encode_token::<UserClaims>(&claim, &config)
+    }.await;
+
+    match result {
+        Ok(token) => {
+            let cookie = CookieBuilder::new("token", token)
+                .http_only(false)
+                .secure(false)
+                .same_site(SameSite::Strict)
+                .path("/")
+                .finish();
+            HttpResponse::Ok().cookie(cookie).finish()
+        },
+        Err(err) => err.error_response(),
+    }
+}
+
+/// Delete the current user
+///
+/// This endpoint deletes the account of the current user. The password is required if the account has one.
+/// Owned promotions are handed over to their highest ranking member, or deleted if they have no other member.
+/// Global administrators have to be demoted before deleting their account.
+#[utoipa::path(
+    delete,
+    path = "",
+    tag = "Me",
+    context_path = "/me",
+    request_body(
+        content = DeleteAccountPostModel,
+        description = "The password of the user",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Account deleted"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[delete("")]
+pub async fn delete_me_route(data: web::Data<AppState>, req: HttpRequest, confirmation: web::Json<DeleteAccountPostModel>) -> HttpResponse {
+    let user = req.extensions().get::<User>().cloned().unwrap();
+    let audit_context = get_audit_context(&req);
+    let result: Result<(), APIError> = async move {
+        let conn = data.database_pool.clone().as_ref().clone();
+
+        if user.role == UserRole::Admin {
+            return Err(APIError::UserError(UserError::Forbidden(ForbiddenError)));
+        }
+        check_current_password(&conn, &user, confirmation.password.as_deref()).await?;
+
+        // The entry outlives the account, so the user is only kept as the deleted resource
+        let audit_context = AuditContext { actor_id: None, ..audit_context };
+        record_audit_log::<()>(&conn, &audit_context, AuditEvent {
+            action: AuditAction::AccountDeleted,
+            resource_type: "user",
+            resource_id: Some(user.id.to_string()),
+            before: None,
+            after: None,
+        }).await;
+        delete_user_account(&conn, user.id).await?;
+
+        Ok(())
+    }.await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(err) => err.error_response(),
+    }
+}
+
+/// Verify a new email address
+///
+/// This endpoint applies the new email address of a user with the link sent to it. A link can only be used once.
+#[utoipa::path(
+    get,
+    path = "/verify",
+    tag = "Me",
+    context_path = "/email",
+    params(
+        ("id" = Uuid, Query, description = "The verification token id")
+    ),
+    responses(
+        (status = 200, description = "Email address verified"),
+        (status = 400, description = "Bad Request", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[get("/verify")]
+pub async fn verify_email_route(data: web::Data<AppState>, req: HttpRequest, query: web::Query<EmailVerificationQuery>) -> HttpResponse {
+    let audit_context = get_audit_context(&req);
+    let result: Result<(), APIError> = async move {
+        let conn = data.database_pool.clone().as_ref().clone();
+        let config = {
+            let config_guard = data.config.read();
+            config_guard.clone()
+        };
+
+        let claims = confirm_email_verification(&conn, &config, query.id).await?;
+
+        record_audit_log(&conn, &audit_context, AuditEvent {
+            action: AuditAction::EmailChanged,
+            resource_type: "user",
+            resource_id: Some(claims.sub.to_string()),
+            before: None,
+            after: Some(&serde_json::json!({ "email": claims.email })),
+        }).await;
+
+        Ok(())
+    }.await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(err) => err.error_response(),
+    }
+}
+
+/// Checks the password of a user, accounts created through single sign-on may have none.
+async fn check_current_password(conn: &DBPool, user: &User, password: Option<&str>) -> Result<(), APIError> {
+    let user_password = match get_user_password_by_user_id(conn, user.id).await {
+        Ok(user_password) => user_password,
+        Err(DBError::NotFound) => return Ok(()),
+        Err(err) => return Err(APIError::from(err)),
+    };
+
+    match password {
+        Some(password) if bcrypt::verify(password, &user_password.password).unwrap_or(false) => Ok(()),
+        _ => Err(APIError::UserError(UserError::Unauthorized(UnauthorizedError))),
+    }
+}
+
+pub fn me_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/me")
+            .wrap(RequireAuth::new(UserTokenValidator))
+            .service(get_me_route)
+            .service(update_me_route)
+            .service(update_password_route)
+            .service(delete_me_route)
+    );
+}
+
+pub fn email_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/email")
+            .service(verify_email_route)
+    );
+}
@@ -0,0 +1,36 @@
+use actix_web::http::header::{self, ContentType};
+use actix_web::{get, web, HttpRequest, HttpResponse, ResponseError};
+use application::metrics::{render_metrics, METRICS_CONTENT_TYPE};
+use shared::app_state_model::AppState;
+use shared::error_models::{UnauthorizedError, UserError};
+
+/// Expose the metrics in the Prometheus text format
+///
+/// When a metrics token is configured, it has to be sent as a bearer token.
+#[get("/metrics")]
+pub async fn metrics_route(data: web::Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let expected_token = data.config.read().metrics_config.token.clone();
+    if let Some(expected_token) = expected_token {
+        let token = req.headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|auth| auth.strip_prefix("Bearer "));
+        if !token.is_some_and(|token| tokens_match(token, &expected_token)) {
+            return UserError::Unauthorized(UnauthorizedError).error_response();
+        }
+    }
+
+    HttpResponse::Ok()
+        .insert_header(ContentType(METRICS_CONTENT_TYPE.parse().unwrap()))
+        .body(render_metrics(&data).await)
+}
+
+/// Compares the tokens in a time which doesn't depend on where they differ.
+fn tokens_match(token: &str, expected_token: &str) -> bool {
+    token.len() == expected_token.len()
+        && token.bytes().zip(expected_token.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+pub fn metrics_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(metrics_route);
+}
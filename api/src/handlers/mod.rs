@@ -3,8 +3,13 @@ pub mod basic_routes_handler;
 pub mod promotions_handler;
 pub mod configurations_handler;
 pub mod admin_handler;
+pub mod organizations_handler;
+pub mod api_keys_handler;
+pub mod me_handler;
 pub mod projects_handler;
 pub mod students_handler;
 pub mod groups_handler;
 pub mod marks_handler;
-pub mod tokens_handler;
\ No newline at end of file
+pub mod tokens_handler;
+pub mod search_handler;
+pub mod metrics_handler;
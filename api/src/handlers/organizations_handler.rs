@@ -0,0 +1,226 @@
+use crate::middlewares::auth::{RequireAuth, UserTokenValidator};
+use crate::models::get_models::OrganizationUserGetModel;
+use crate::models::put_models::{UpdatedOrganizationPutModel, UpdatedOrganizationUserPutModel};
+use actix_web::{get, put, web, HttpMessage, HttpRequest, HttpResponse, ResponseError};
+use application::authorization::organizations::check_organization_admin;
+use application::database::organizations::{get_organization_branding_by_slug, get_organization_by_id, get_users_from_organization_id, update_organization};
+use application::database::users::{get_user_by_id, update_user};
+use domain::models::organizations::UpdatedOrganization;
+use domain::models::users::{UpdatedUser, User};
+use garde::Validate;
+use shared::app_state_model::AppState;
+use shared::error_models::{APIError, DBError, NotFoundError, UserError};
+use uuid::Uuid;
+
+/// Get the branding of an organization
+///
+/// This public endpoint returns the name, logo and colors of an organization to display them before login.
+#[utoipa::path(
+    get,
+    path = "/{slug}/branding",
+    tag = "Organizations",
+    context_path = "/organizations",
+    params(
+        ("slug" = String, description = "The organization slug")
+    ),
+    responses(
+        (status = 200, description = "The branding of the organization", body = OrganizationBranding),
+        (status = 404, description = "Not Found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[get("/{slug}/branding")]
+pub async fn get_organization_branding_route(data: web::Data<AppState>, slug: web::Path<String>) -> HttpResponse {
+    let result = async move {
+        let conn = data.database_pool.clone().as_ref().clone();
+        get_organization_branding_by_slug(&conn, &slug.into_inner()).await
+    }.await;
+
+    match result {
+        Ok(branding) => HttpResponse::Ok().json(branding),
+        Err(err) => APIError::from(err).error_response(),
+    }
+}
+
+/// Get the current organization
+///
+/// This endpoint returns the organization of the current user with its configuration.
+#[utoipa::path(
+    get,
+    path = "/current",
+    tag = "Organizations",
+    context_path = "/organizations",
+    responses(
+        (status = 200, description = "The organization of the current user", body = Organization),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[get("")]
+pub async fn get_current_organization_route(data: web::Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let user = req.extensions().get::<User>().cloned().unwrap();
+    let result = async move {
+        let conn = data.database_pool.clone().as_ref().clone();
+        get_organization_by_id(&conn, user.organization_id).await
+    }.await;
+
+    match result {
+        Ok(organization) => HttpResponse::Ok().json(organization),
+        Err(err) => APIError::from(err).error_response(),
+    }
+}
+
+/// Update the current organization
+///
+/// This endpoint updates the configuration of the current user's organization. Only organization admins can update it.
+#[utoipa::path(
+    put,
+    path = "/current",
+    tag = "Organizations",
+    context_path = "/organizations",
+    request_body(
+        content = UpdatedOrganizationPutModel,
+        description = "The updated organization configuration",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Organization updated successfully"),
+        (status = 400, description = "Bad Request", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[put("")]
+pub async fn update_current_organization_route(data: web::Data<AppState>, req: HttpRequest, organization: web::Json<UpdatedOrganizationPutModel>) -> HttpResponse {
+    let user = req.extensions().get::<User>().cloned().unwrap();
+    let result: Result<(), APIError> = async move {
+        let conn = data.database_pool.clone().as_ref().clone();
+        check_organization_admin(&user)?;
+        let organization = organization.into_inner();
+        organization.validate()?;
+
+        update_organization(&conn, user.organization_id, UpdatedOrganization {
+            name: organization.name,
+            register: organization.register,
+            authorized_domains: organization.authorized_domains,
+            logo_url: organization.logo_url,
+            primary_color: organization.primary_color,
+            mail_sender: organization.mail_sender,
+            timezone: organization.timezone,
+        }).await?;
+
+        Ok(())
+    }.await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(err) => err.error_response(),
+    }
+}
+
+/// Get the users of the current organization
+///
+/// This endpoint returns all the users of the current user's organization. Only organization admins can list them.
+#[utoipa::path(
+    get,
+    path = "/current/users",
+    tag = "Organizations",
+    context_path = "/organizations",
+    responses(
+        (status = 200, description = "The users of the organization", body = [OrganizationUserGetModel]),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[get("/users")]
+pub async fn get_current_organization_users_route(data: web::Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let user = req.extensions().get::<User>().cloned().unwrap();
+    let result: Result<Vec<OrganizationUserGetModel>, APIError> = async move {
+        let conn = data.database_pool.clone().as_ref().clone();
+        check_organization_admin(&user)?;
+
+        let users = get_users_from_organization_id(&conn, user.organization_id).await?;
+        Ok(users.into_iter().map(OrganizationUserGetModel::from).collect())
+    }.await;
+
+    match result {
+        Ok(users) => HttpResponse::Ok().json(users),
+        Err(err) => err.error_response(),
+    }
+}
+
+/// Change the organization role of a user
+///
+/// This endpoint promotes a user of the current organization to organization admin or demotes him. Only organization admins can change roles.
+#[utoipa::path(
+    put,
+    path = "/current/users/{id}",
+    tag = "Organizations",
+    context_path = "/organizations",
+    params(
+        ("id" = Uuid, description = "The user id to update")
+    ),
+    request_body(
+        content = UpdatedOrganizationUserPutModel,
+        description = "The new organization role of the user",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "User updated successfully"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "User Not Found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[put("/users/{id}")]
+pub async fn update_current_organization_user_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>, updated_user: web::Json<UpdatedOrganizationUserPutModel>) -> HttpResponse {
+    let user = req.extensions().get::<User>().cloned().unwrap();
+    let result: Result<(), APIError> = async move {
+        let conn = data.database_pool.clone().as_ref().clone();
+        check_organization_admin(&user)?;
+
+        // Users of other organizations are reported as not found
+        let user_not_found = APIError::UserError(UserError::NotFound(NotFoundError {
+            resource: "User".to_string(),
+        }));
+        let target = match get_user_by_id(&conn, id.into_inner()).await {
+            Ok(target) if target.organization_id == user.organization_id => target,
+            Ok(_) | Err(DBError::NotFound) => return Err(user_not_found),
+            Err(err) => return Err(APIError::from(err)),
+        };
+
+        update_user(&conn, target.id, UpdatedUser {
+            username: None,
+            email: None,
+            has_validated_email: None,
+            role: None,
+            token_version: None,
+            organization_role: Some(updated_user.organization_role),
+        }).await?;
+
+        Ok(())
+    }.await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(err) => err.error_response(),
+    }
+}
+
+pub fn organizations_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/organizations/current")
+            .wrap(RequireAuth::new(UserTokenValidator))
+            .service(get_current_organization_route)
+            .service(update_current_organization_route)
+            .service(get_current_organization_users_route)
+            .service(update_current_organization_user_route)
+    );
+    cfg.service(
+        web::scope("/organizations")
+            .service(get_organization_branding_route)
+    );
+}
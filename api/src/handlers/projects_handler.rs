@@ -1,48 +1,60 @@
-use crate::middlewares::auth::{RequireAuth, UserTokenValidator};
-use crate::models::post_models::NewProjectPostModel;
-use crate::models::put_models::UpdatedProjectPutModel;
-use actix_web::{delete, get, post, put, web, HttpResponse, ResponseError};
-use application::database::projects::{create_project, delete_project, get_project_by_id, get_projects_from_promotion_id, update_project};
-use chrono::Utc;
-use domain::models::projects::{NewProject, ProjectState, UpdatedProject};
+use crate::middlewares::auth::{ApiKeyTokenValidator, RequireAuth};
+use crate::models::post_models::{NewProjectPostModel, ReopenProjectPostModel};
+use crate::models::put_models::{LatePolicyPutModel, ReminderPolicyPutModel, StudentExtensionPutModel, UpdatedProjectPutModel};
+use actix_web::{delete, get, post, put, web, HttpMessage, HttpRequest, HttpResponse, ResponseError};
+use application::authorization::promotions::{check_project_permission, check_promotion_permission};
+use application::database::pagination::{ListParams, ProjectSortField};
+use application::database::projects::{create_project, create_project_state_transition, delete_project, get_project_by_id, get_project_state_transitions, get_projects_page_from_promotion_id};
+use application::database::transaction::{transaction, ScopedFutureExt};
+use application::extensions::{grant_extension, revoke_extension, update_late_policy};
+use application::lifecycle::{advance_project, apply_project_action, check_and_update_project, ProjectAction};
+use application::reminders::{preview_project_reminders, update_reminder_policy};
+use application::scheduler::handler::{renew_project_tokens, reschedule_project_reminders};
+use application::timezone::{from_local, get_project_timezone, get_promotion_timezone, resolve_timezone, ProjectCalendar};
+use domain::models::extensions::{NewProjectLatePolicy, NewStudentExtension};
+use domain::models::projects::{NewProject, NewProjectStateTransition, ProjectState, UpdatedProject};
+use domain::models::promotions_members::PromotionPermission;
+use domain::models::reminder_policies::NewProjectReminderPolicy;
+use domain::models::users::User;
+use domain::repositories::projects::ProjectsRepository;
 use garde::Validate;
 use shared::app_state_model::AppState;
-use shared::error_models::{APIError, InternalError, ServerError};
+use shared::error_models::{APIError, DBError, NotFoundError, UserError};
 use uuid::Uuid;
 
 /// Get all projects from a promotion
 ///
-/// This endpoint returns all projects from a promotion in the database.
+/// This endpoint returns a page of the projects from a promotion, sorted by start date by default.
+/// The text filter applies to the name of the projects.
 #[utoipa::path(
     get,
     path = "/promotion/{promotion_id}",
     tag = "Projects",
     context_path = "/projects",
     params(
-        ("promotion_id" = Uuid, description = "The promotion id to get the projects from")
+        ("promotion_id" = Uuid, description = "The promotion id to get the projects from"),
+        ListParams<ProjectSortField>
     ),
     responses(
-        (status = 200, description = "All the returned projects objects", body = [Project]),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError, examples(
-            ("NoToken" = (value = json!("Token not provided"))),
-            ("InvalidToken" = (value = json!("Error")))
-        )),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 200, description = "A page of the projects", body = ProjectsPage),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[get("/promotion/{promotion_id}")]
-pub async fn get_projects_from_promotion_route(data: web::Data<AppState>, promotion_id: web::Path<Uuid>) -> HttpResponse {
-    let result = web::block(move || {
+pub async fn get_projects_from_promotion_route(data: web::Data<AppState>, req: HttpRequest, promotion_id: web::Path<Uuid>, params: web::Query<ListParams<ProjectSortField>>) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
+    let result = async move {
         let conn = data.database_pool.clone().as_ref().clone();
-        get_projects_from_promotion_id(&conn, promotion_id.into_inner())
-    }).await;
+        let promotion_id = promotion_id.into_inner();
+        check_promotion_permission(&conn, promotion_id, &teacher, PromotionPermission::Read).await?;
+        get_projects_page_from_promotion_id(&conn, teacher.organization_id, promotion_id, &params).await.map_err(APIError::from)
+    }.await;
 
     match result {
-        Ok(response) => match response {
-            Ok(projects) => HttpResponse::Ok().json(projects),
-            Err(err) => APIError::from(err).error_response()
-        },
-        Err(_) => ServerError::InternalError(InternalError).error_response(),
+        Ok(projects) => HttpResponse::Ok().json(projects),
+        Err(err) => err.error_response()
     }
 }
 
@@ -59,27 +71,25 @@ pub async fn get_projects_from_promotion_route(data: web::Data<AppState>, promot
     ),
     responses(
         (status = 200, description = "The returned project object", body = Project),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError, examples(
-            ("NoToken" = (value = json!("Token not provided"))),
-            ("InvalidToken" = (value = json!("Error")))
-        )),
-        (status = 404, description = "Project not found", body = NotFoundError, example = json!("NotFoundError")),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[get("/{id}")]
-pub async fn get_project_route(data: web::Data<AppState>, id: web::Path<Uuid>) -> HttpResponse {
-    let result = web::block(move || {
+pub async fn get_project_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
+    let result = async move {
         let conn = data.database_pool.clone().as_ref().clone();
-        get_project_by_id(&conn, id.into_inner())
-    }).await;
+        let project_id = id.into_inner();
+        check_project_permission(&conn, project_id, &teacher, PromotionPermission::Read).await?;
+        get_project_by_id(&conn, teacher.organization_id, project_id).await.map_err(APIError::from)
+    }.await;
 
     match result {
-        Ok(response) => match response {
-            Ok(project) => HttpResponse::Ok().json(project),
-            Err(err) => APIError::from(err).error_response()
-        },
-        Err(_) => ServerError::InternalError(InternalError).error_response(),
+        Ok(project) => HttpResponse::Ok().json(project),
+        Err(err) => err.error_response()
     }
 }
 
@@ -101,56 +111,75 @@ pub async fn get_project_route(data: web::Data<AppState>, id: web::Path<Uuid>) -
     ),
     responses(
         (status = 201, description = "The project was created successfully", body = Uuid),
-        (status = 400, description = "Bad Request", body = BadRequestError, examples(
-            ("InvalidName" = (value = json!("Invalid name"))),
-        )),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError, examples(
-            ("NoToken" = (value = json!("Token not provided"))),
-            ("InvalidToken" = (value = json!("Error")))
-        )),
-        (status = 404, description = "Project not found", body = NotFoundError, example = json!("NotFoundError")),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 400, description = "Bad Request", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[post("/promotion/{promotion_id}")]
-pub async fn create_project_route(data: web::Data<AppState>, promotion_id: web::Path<Uuid>, project: web::Json<NewProjectPostModel>) -> HttpResponse {
-    let result = web::block(move || {
+pub async fn create_project_route(data: web::Data<AppState>, req: HttpRequest, promotion_id: web::Path<Uuid>, project: web::Json<NewProjectPostModel>) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
+    let result = async move {
         let conn = data.database_pool.clone().as_ref().clone();
         project.validate().map_err(APIError::from)?;
+        let promotion_id = promotion_id.into_inner();
+        check_promotion_permission(&conn, promotion_id, &teacher, PromotionPermission::Edit).await?;
 
-        // Check project date
-        let mut state: ProjectState = ProjectState::NotStarted;
-        if project.start_date.is_some() && project.start_date.unwrap() < Utc::now().naive_utc() {
-            state = ProjectState::InProgress;
-        }
-        if project.end_date < Utc::now().naive_utc() {
-            state = ProjectState::Finished;
-        }
+        // The dates are entered in the timezone of the project
+        let timezone = match &project.timezone {
+            Some(timezone) => resolve_timezone(timezone),
+            None => get_promotion_timezone(&data.repositories(), promotion_id).await?,
+        };
+        let start_date = project.start_date.map(|date| from_local(timezone, date));
+        let end_date = from_local(timezone, project.end_date);
+
+        let state = match project.draft {
+            Some(true) => ProjectState::Draft,
+            _ => ProjectState::Scheduled,
+        };
 
         let new_project = NewProject {
             name: project.name.clone(),
             description: project.description.clone(),
-            start_date: project.start_date,
-            end_date: project.end_date,
+            start_date,
+            end_date,
             notation_period_duration: project.notation_period_duration,
-            promotion_id: promotion_id.into_inner(),
+            promotion_id,
             state: Some(state),
+            timezone: project.timezone.clone(),
         };
-        create_project(&conn, new_project).map_err(APIError::from)
-    }).await;
+
+        // The history of the project begins with its creation
+        let project_id = transaction(&conn, |conn| async move {
+            let project_id = create_project(&mut *conn, new_project).await?;
+            create_project_state_transition(&mut *conn, NewProjectStateTransition {
+                project_id,
+                from_state: None,
+                to_state: state,
+                user_id: Some(teacher.id),
+            }).await?;
+            Ok::<_, DBError>(project_id)
+        }.scope_boxed()).await?;
+
+        // The dates of a scheduled project may already lead further
+        let project = get_project_by_id(&conn, teacher.organization_id, project_id).await?;
+        advance_project(&data.repositories(), &project, &ProjectCalendar::new(&project, timezone)).await?;
+
+        Ok::<_, APIError>(project_id)
+    }.await;
 
     match result {
-        Ok(response) => match response {
-            Ok(project_id) => HttpResponse::Created().json(project_id),
-            Err(err) => APIError::from(err).error_response()
-        },
-        Err(_) => ServerError::InternalError(InternalError).error_response(),
+        Ok(project_id) => HttpResponse::Created().json(project_id),
+        Err(err) => err.error_response()
     }
 }
 
 /// Update a project
 ///
-/// This endpoint updates a project in the database.
+/// This endpoint updates a project in the database. The new dates must lead to the state of the project, or
+/// to a later one it's then moved to: the evaluation of a project is closed or reopened by its own endpoints.
 #[utoipa::path(
     put,
     path = "/{id}",
@@ -166,43 +195,612 @@ pub async fn create_project_route(data: web::Data<AppState>, promotion_id: web::
     ),
     responses(
         (status = 200, description = "The project was updated successfully", body = ()),
-        (status = 400, description = "Bad Request", body = BadRequestError, examples(
-            ("InvalidName" = (value = json!("Invalid name"))),
-        )),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError, examples(
-            ("NoToken" = (value = json!("Token not provided"))),
-            ("InvalidToken" = (value = json!("Error")))
-        )),
-        (status = 404, description = "Project not found", body = NotFoundError, example = json!("NotFoundError")),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 400, description = "Bad Request", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[put("/{id}")]
-pub async fn update_project_route(data: web::Data<AppState>, id: web::Path<Uuid>, project: web::Json<UpdatedProjectPutModel>) -> HttpResponse {
-    // TODO : Check if project has started or not
-    let result = web::block(move || {
+pub async fn update_project_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>, project: web::Json<UpdatedProjectPutModel>) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
+    let result = async move {
         let conn = data.database_pool.clone().as_ref().clone();
         project.validate().map_err(APIError::from)?;
+        let project_id = id.into_inner();
+        check_project_permission(&conn, project_id, &teacher, PromotionPermission::Edit).await?;
+
+        let repos = data.repositories();
+        let current_project = get_project_by_id(&conn, teacher.organization_id, project_id).await?;
+
+        // The dates are entered in the timezone of the project, the new one if it changes
+        let timezone = match &project.timezone {
+            Some(timezone) => resolve_timezone(timezone),
+            None => get_project_timezone(&repos, &current_project).await?,
+        };
         let updated_project = UpdatedProject {
             name: project.name.clone(),
             description: project.description.clone(),
-            start_date: project.start_date,
-            end_date: project.end_date,
+            start_date: project.start_date.map(|date| from_local(timezone, date)),
+            end_date: project.end_date.map(|date| from_local(timezone, date)),
             notation_period_duration: project.notation_period_duration,
             state: None,
+            timezone: project.timezone.clone(),
         };
-        update_project(&conn, id.into_inner(), updated_project).map_err(APIError::from)
-    }).await;
+        // The new dates can't contradict the state of the project
+        check_and_update_project(&repos, &current_project, updated_project).await
+    }.await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(err) => err.error_response()
+    }
+}
+
+/// Get the state history of a project
+///
+/// This endpoint returns the changes of state of a project, from its creation, the oldest first.
+/// The changes made by the scheduler have no user.
+#[utoipa::path(
+    get,
+    path = "/{id}/transitions",
+    tag = "Projects",
+    context_path = "/projects",
+    params(
+        ("id" = Uuid, description = "The project id to get the history of")
+    ),
+    responses(
+        (status = 200, description = "The changes of state of the project", body = Vec<ProjectStateTransition>),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[get("/{id}/transitions")]
+pub async fn get_project_transitions_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
+    let result = async move {
+        let conn = data.database_pool.clone().as_ref().clone();
+        let project_id = id.into_inner();
+        check_project_permission(&conn, project_id, &teacher, PromotionPermission::Read).await?;
+        get_project_state_transitions(&conn, project_id).await.map_err(APIError::from)
+    }.await;
+
+    match result {
+        Ok(transitions) => HttpResponse::Ok().json(transitions),
+        Err(err) => err.error_response()
+    }
+}
+
+/// Get the reminders of a project
+///
+/// This endpoint returns the reminder policy of a project, its own or the alerts of its teacher, with the
+/// reminders it leads to given the dates of the project and whether they were sent.
+#[utoipa::path(
+    get,
+    path = "/{id}/reminders",
+    tag = "Projects",
+    context_path = "/projects",
+    params(
+        ("id" = Uuid, description = "The project id to get the reminders of")
+    ),
+    responses(
+        (status = 200, description = "The reminders of the project", body = ReminderSchedule),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[get("/{id}/reminders")]
+pub async fn get_project_reminders_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
+    let result = async move {
+        let conn = data.database_pool.clone().as_ref().clone();
+        let project_id = id.into_inner();
+        check_project_permission(&conn, project_id, &teacher, PromotionPermission::Read).await?;
+        let project = get_project_by_id(&conn, teacher.organization_id, project_id).await?;
+        preview_project_reminders(&data.repositories(), &project).await.map_err(APIError::from)
+    }.await;
+
+    match result {
+        Ok(schedule) => HttpResponse::Ok().json(schedule),
+        Err(err) => err.error_response()
+    }
+}
+
+/// Replaces or removes the reminder policy of a project, and returns the response with its new reminders.
+async fn reminder_policy_response(data: web::Data<AppState>, req: HttpRequest, project_id: Uuid, policy: Option<ReminderPolicyPutModel>) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
+    let result = async move {
+        let conn = data.database_pool.clone().as_ref().clone();
+        if let Some(policy) = &policy {
+            policy.validate().map_err(APIError::from)?;
+        }
+        check_project_permission(&conn, project_id, &teacher, PromotionPermission::Edit).await?;
+
+        let repos = data.repositories();
+        let project = get_project_by_id(&conn, teacher.organization_id, project_id).await?;
+        let new_policy = policy.map(|policy| NewProjectReminderPolicy {
+            project_id,
+            offsets: policy.offsets,
+            recipients: policy.recipients,
+            escalate_to_teacher: policy.escalate_to_teacher,
+        });
+        let schedule = update_reminder_policy(&repos, &project, new_policy).await?;
+
+        // The reminders waiting to be sent follow the new policy
+        reschedule_project_reminders(&data, &repos, &project).await;
+        Ok::<_, APIError>(schedule)
+    }.await;
 
     match result {
-        Ok(response) => match response {
-            Ok(_) => HttpResponse::Ok().finish(),
-            Err(err) => APIError::from(err).error_response()
-        },
-        Err(_) => ServerError::InternalError(InternalError).error_response(),
+        Ok(schedule) => HttpResponse::Ok().json(schedule),
+        Err(err) => err.error_response()
     }
 }
 
+/// Set the reminder policy of a project
+///
+/// This endpoint sets the reminders of a project, overriding the alerts of its teacher: their offsets from
+/// the opening or the closing of the evaluation, their recipients, and whether the teachers are told which
+/// students still haven't evaluated at the last reminder. It returns the new reminders of the project.
+#[utoipa::path(
+    put,
+    path = "/{id}/reminders",
+    tag = "Projects",
+    context_path = "/projects",
+    params(
+        ("id" = Uuid, description = "The project id to set the reminders of")
+    ),
+    request_body(
+        content = ReminderPolicyPutModel,
+        description = "The reminder policy of the project",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "The new reminders of the project", body = ReminderSchedule),
+        (status = 400, description = "Bad Request", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[put("/{id}/reminders")]
+pub async fn set_project_reminders_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>, body: web::Json<ReminderPolicyPutModel>) -> HttpResponse {
+    reminder_policy_response(data, req, id.into_inner(), Some(body.into_inner())).await
+}
+
+/// Remove the reminder policy of a project
+///
+/// This endpoint removes the reminder policy of a project, which then follows the alerts of its teacher
+/// again. It returns the new reminders of the project.
+#[utoipa::path(
+    delete,
+    path = "/{id}/reminders",
+    tag = "Projects",
+    context_path = "/projects",
+    params(
+        ("id" = Uuid, description = "The project id to reset the reminders of")
+    ),
+    responses(
+        (status = 200, description = "The new reminders of the project", body = ReminderSchedule),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[delete("/{id}/reminders")]
+pub async fn delete_project_reminders_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>) -> HttpResponse {
+    reminder_policy_response(data, req, id.into_inner(), None).await
+}
+
+/// Get the extensions of a project
+///
+/// This endpoint returns the extensions granted to the students of a project, with whether the students were
+/// sent their renewed token.
+#[utoipa::path(
+    get,
+    path = "/{id}/extensions",
+    tag = "Projects",
+    context_path = "/projects",
+    params(
+        ("id" = Uuid, description = "The project id to get the extensions of")
+    ),
+    responses(
+        (status = 200, description = "The extensions of the students of the project", body = Vec<StudentExtension>),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[get("/{id}/extensions")]
+pub async fn get_project_extensions_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
+    let result = async move {
+        let conn = data.database_pool.clone().as_ref().clone();
+        let project_id = id.into_inner();
+        check_project_permission(&conn, project_id, &teacher, PromotionPermission::Read).await?;
+        data.repositories().get_student_extensions(project_id).await.map_err(APIError::from)
+    }.await;
+
+    match result {
+        Ok(extensions) => HttpResponse::Ok().json(extensions),
+        Err(err) => err.error_response()
+    }
+}
+
+/// Grant an extension to a student
+///
+/// This endpoint grants a later deadline to a student of a project, such as a sick one, or replaces theirs.
+/// The scheduler then renews the token of the student so it expires with the extension, and sends it to them.
+#[utoipa::path(
+    put,
+    path = "/{id}/extensions/{student_id}",
+    tag = "Projects",
+    context_path = "/projects",
+    params(
+        ("id" = Uuid, description = "The project id"),
+        ("student_id" = Uuid, description = "The student id to grant the extension to")
+    ),
+    request_body(
+        content = StudentExtensionPutModel,
+        description = "The extension of the student",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "The extension of the student", body = StudentExtension),
+        (status = 400, description = "The extension ends before the evaluation or in the past", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project or student not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "The results of the project are already published", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[put("/{id}/extensions/{student_id}")]
+pub async fn grant_extension_route(data: web::Data<AppState>, req: HttpRequest, path: web::Path<(Uuid, Uuid)>, body: web::Json<StudentExtensionPutModel>) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
+    let result = async move {
+        let conn = data.database_pool.clone().as_ref().clone();
+        let (project_id, student_id) = path.into_inner();
+        let extension = body.into_inner();
+        extension.validate().map_err(APIError::from)?;
+        check_project_permission(&conn, project_id, &teacher, PromotionPermission::Edit).await?;
+
+        let project = get_project_by_id(&conn, teacher.organization_id, project_id).await?;
+        grant_extension(&data.repositories(), &project, NewStudentExtension {
+            project_id,
+            student_id,
+            extended_until: extension.extended_until,
+            reason: extension.reason,
+            granted_by: Some(teacher.id),
+        }).await
+    }.await;
+
+    match result {
+        Ok(extension) => HttpResponse::Ok().json(extension),
+        Err(err) => err.error_response()
+    }
+}
+
+/// Revoke the extension of a student
+///
+/// This endpoint revokes the extension of a student of a project, who then has the deadline of the others.
+#[utoipa::path(
+    delete,
+    path = "/{id}/extensions/{student_id}",
+    tag = "Projects",
+    context_path = "/projects",
+    params(
+        ("id" = Uuid, description = "The project id"),
+        ("student_id" = Uuid, description = "The student id to revoke the extension of")
+    ),
+    responses(
+        (status = 200, description = "The extension was revoked successfully", body = ()),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project or extension not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[delete("/{id}/extensions/{student_id}")]
+pub async fn revoke_extension_route(data: web::Data<AppState>, req: HttpRequest, path: web::Path<(Uuid, Uuid)>) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
+    let result = async move {
+        let conn = data.database_pool.clone().as_ref().clone();
+        let (project_id, student_id) = path.into_inner();
+        check_project_permission(&conn, project_id, &teacher, PromotionPermission::Edit).await?;
+        revoke_extension(&data.repositories(), project_id, student_id).await
+    }.await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(err) => err.error_response()
+    }
+}
+
+/// Get the late policy of a project
+///
+/// This endpoint returns how long a project accepts the evaluations submitted after the deadline of the
+/// students, and the penalty of their authors.
+#[utoipa::path(
+    get,
+    path = "/{id}/late-policy",
+    tag = "Projects",
+    context_path = "/projects",
+    params(
+        ("id" = Uuid, description = "The project id to get the late policy of")
+    ),
+    responses(
+        (status = 200, description = "The late policy of the project", body = ProjectLatePolicy),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found or refusing the late submissions", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[get("/{id}/late-policy")]
+pub async fn get_project_late_policy_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
+    let result = async move {
+        let conn = data.database_pool.clone().as_ref().clone();
+        let project_id = id.into_inner();
+        check_project_permission(&conn, project_id, &teacher, PromotionPermission::Read).await?;
+        data.repositories().get_project_late_policy(project_id).await?
+            .ok_or_else(|| APIError::UserError(UserError::NotFound(NotFoundError { resource: format!("Late policy of project {}", project_id) })))
+    }.await;
+
+    match result {
+        Ok(policy) => HttpResponse::Ok().json(policy),
+        Err(err) => err.error_response()
+    }
+}
+
+/// Replaces or removes the late policy of a project, renews the tokens of its students so they expire with their
+/// new deadline, and returns the response with its new policy.
+async fn late_policy_response(data: web::Data<AppState>, req: HttpRequest, project_id: Uuid, policy: Option<LatePolicyPutModel>) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
+    let result = async move {
+        let conn = data.database_pool.clone().as_ref().clone();
+        if let Some(policy) = &policy {
+            policy.validate().map_err(APIError::from)?;
+        }
+        check_project_permission(&conn, project_id, &teacher, PromotionPermission::Edit).await?;
+
+        let repos = data.repositories();
+        let project = get_project_by_id(&conn, teacher.organization_id, project_id).await?;
+        let new_policy = policy.map(|policy| NewProjectLatePolicy {
+            project_id,
+            late_hours: policy.late_hours,
+            penalty_percent: policy.penalty_percent,
+        });
+        let policy = update_late_policy(&repos, &project, new_policy).await?;
+
+        renew_project_tokens(&data, &repos, &project).await;
+        Ok::<_, APIError>(policy)
+    }.await;
+
+    match result {
+        Ok(Some(policy)) => HttpResponse::Ok().json(policy),
+        Ok(None) => HttpResponse::Ok().finish(),
+        Err(err) => err.error_response()
+    }
+}
+
+/// Set the late policy of a project
+///
+/// This endpoint makes a project accept the evaluations submitted some hours after the deadline of the
+/// students, flagged as late, the marks of their authors being lowered by a penalty. It returns the new
+/// policy of the project.
+#[utoipa::path(
+    put,
+    path = "/{id}/late-policy",
+    tag = "Projects",
+    context_path = "/projects",
+    params(
+        ("id" = Uuid, description = "The project id to set the late policy of")
+    ),
+    request_body(
+        content = LatePolicyPutModel,
+        description = "The late policy of the project",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "The new late policy of the project", body = ProjectLatePolicy),
+        (status = 400, description = "Bad Request", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[put("/{id}/late-policy")]
+pub async fn set_project_late_policy_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>, body: web::Json<LatePolicyPutModel>) -> HttpResponse {
+    late_policy_response(data, req, id.into_inner(), Some(body.into_inner())).await
+}
+
+/// Remove the late policy of a project
+///
+/// This endpoint makes a project refuse the evaluations submitted after the deadline of the students again.
+#[utoipa::path(
+    delete,
+    path = "/{id}/late-policy",
+    tag = "Projects",
+    context_path = "/projects",
+    params(
+        ("id" = Uuid, description = "The project id to remove the late policy of")
+    ),
+    responses(
+        (status = 200, description = "The late policy was removed successfully", body = ()),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[delete("/{id}/late-policy")]
+pub async fn delete_project_late_policy_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>) -> HttpResponse {
+    late_policy_response(data, req, id.into_inner(), None).await
+}
+
+/// Applies a change of state of a teacher to a project, and returns the response with its new state.
+async fn project_action_response(data: web::Data<AppState>, req: HttpRequest, project_id: Uuid, action: ProjectAction) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
+    let result = async move {
+        let conn = data.database_pool.clone().as_ref().clone();
+        check_project_permission(&conn, project_id, &teacher, PromotionPermission::Edit).await?;
+        let project = get_project_by_id(&conn, teacher.organization_id, project_id).await?;
+        apply_project_action(&data.repositories(), &project, action, teacher.id).await
+    }.await;
+
+    match result {
+        Ok(state) => HttpResponse::Ok().json(state),
+        Err(err) => err.error_response()
+    }
+}
+
+/// Schedule a draft project
+///
+/// This endpoint schedules a draft project, which then goes through its lifecycle as its dates pass.
+#[utoipa::path(
+    post,
+    path = "/{id}/schedule",
+    tag = "Projects",
+    context_path = "/projects",
+    params(
+        ("id" = Uuid, description = "The project id to schedule")
+    ),
+    responses(
+        (status = 200, description = "The new state of the project", body = ProjectState),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "The project is not a draft", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[post("/{id}/schedule")]
+pub async fn schedule_project_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>) -> HttpResponse {
+    project_action_response(data, req, id.into_inner(), ProjectAction::Schedule).await
+}
+
+/// Open the evaluation of a project
+///
+/// This endpoint opens the evaluation of a project in progress, which ends now instead of at its end date.
+#[utoipa::path(
+    post,
+    path = "/{id}/open",
+    tag = "Projects",
+    context_path = "/projects",
+    params(
+        ("id" = Uuid, description = "The project id to open the evaluation of")
+    ),
+    responses(
+        (status = 200, description = "The new state of the project", body = ProjectState),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "The project is not in progress", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[post("/{id}/open")]
+pub async fn open_project_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>) -> HttpResponse {
+    project_action_response(data, req, id.into_inner(), ProjectAction::Open).await
+}
+
+/// Close the evaluation of a project
+///
+/// This endpoint closes the evaluation of a project today, the marks of the students being computed and
+/// published by the next run of the scheduler.
+#[utoipa::path(
+    post,
+    path = "/{id}/close",
+    tag = "Projects",
+    context_path = "/projects",
+    params(
+        ("id" = Uuid, description = "The project id to close the evaluation of")
+    ),
+    responses(
+        (status = 200, description = "The new state of the project", body = ProjectState),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "The evaluation of the project is not open", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[post("/{id}/close")]
+pub async fn close_project_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>) -> HttpResponse {
+    project_action_response(data, req, id.into_inner(), ProjectAction::Close).await
+}
+
+/// Reopen the evaluation of a project
+///
+/// This endpoint reopens the evaluation of a project for a number of days, even once its results are published.
+/// The students who didn't evaluate their group get a new link, and the marks are computed again once it's closed.
+#[utoipa::path(
+    post,
+    path = "/{id}/reopen",
+    tag = "Projects",
+    context_path = "/projects",
+    params(
+        ("id" = Uuid, description = "The project id to reopen the evaluation of")
+    ),
+    request_body(
+        content = ReopenProjectPostModel,
+        description = "How long the evaluation stays open",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "The new state of the project", body = ProjectState),
+        (status = 400, description = "Bad Request", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "The evaluation of the project is not over", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[post("/{id}/reopen")]
+pub async fn reopen_project_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>, body: web::Json<ReopenProjectPostModel>) -> HttpResponse {
+    if let Err(err) = body.validate() {
+        return APIError::from(err).error_response();
+    }
+    project_action_response(data, req, id.into_inner(), ProjectAction::Reopen { days: body.days }).await
+}
+
+/// Archive a project
+///
+/// This endpoint archives a project whose results are published.
+#[utoipa::path(
+    post,
+    path = "/{id}/archive",
+    tag = "Projects",
+    context_path = "/projects",
+    params(
+        ("id" = Uuid, description = "The project id to archive")
+    ),
+    responses(
+        (status = 200, description = "The new state of the project", body = ProjectState),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "The results of the project are not published", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[post("/{id}/archive")]
+pub async fn archive_project_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>) -> HttpResponse {
+    project_action_response(data, req, id.into_inner(), ProjectAction::Archive).await
+}
+
 /// Delete a project
 ///
 /// This endpoint deletes a project in the database.
@@ -216,38 +814,51 @@ pub async fn update_project_route(data: web::Data<AppState>, id: web::Path<Uuid>
     ),
     responses(
         (status = 200, description = "The project was deleted successfully", body = ()),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError, examples(
-            ("NoToken" = (value = json!("Token not provided"))),
-            ("InvalidToken" = (value = json!("Error")))
-        )),
-        (status = 404, description = "Project not found", body = NotFoundError, example = json!("NotFoundError")),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[delete("/{id}")]
-pub async fn delete_project_route(data: web::Data<AppState>, id: web::Path<Uuid>) -> HttpResponse {
-    let result = web::block(move || {
+pub async fn delete_project_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
+    let result = async move {
         let conn = data.database_pool.clone().as_ref().clone();
-        delete_project(&conn, id.into_inner())
-    }).await;
+        let project_id = id.into_inner();
+        check_project_permission(&conn, project_id, &teacher, PromotionPermission::Delete).await?;
+        delete_project(&conn, teacher.organization_id, project_id).await.map_err(APIError::from)
+    }.await;
 
     match result {
-        Ok(response) => match response {
-            Ok(_) => HttpResponse::Ok().finish(),
-            Err(err) => APIError::from(err).error_response()
-        },
-        Err(_) => ServerError::InternalError(InternalError).error_response(),
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(err) => err.error_response()
     }
 }
 
 pub fn projects_config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/projects")
-            .wrap(RequireAuth::new(UserTokenValidator))
+            .wrap(RequireAuth::new(ApiKeyTokenValidator))
             .service(get_projects_from_promotion_route)
             .service(get_project_route)
             .service(create_project_route)
             .service(update_project_route)
             .service(delete_project_route)
+            .service(get_project_transitions_route)
+            .service(get_project_reminders_route)
+            .service(set_project_reminders_route)
+            .service(delete_project_reminders_route)
+            .service(get_project_extensions_route)
+            .service(grant_extension_route)
+            .service(revoke_extension_route)
+            .service(get_project_late_policy_route)
+            .service(set_project_late_policy_route)
+            .service(delete_project_late_policy_route)
+            .service(schedule_project_route)
+            .service(open_project_route)
+            .service(close_project_route)
+            .service(reopen_project_route)
+            .service(archive_project_route)
     );
 }
\ No newline at end of file
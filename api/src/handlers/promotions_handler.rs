@@ -1,46 +1,50 @@
-use crate::middlewares::auth::{RequireAuth, UserTokenValidator};
-use crate::models::post_models::NewPromotionPostModel;
-use crate::models::put_models::UpdatedPromotionPutModel;
+use crate::middlewares::auth::{ApiKeyTokenValidator, RequireAuth};
+use crate::models::post_models::{NewPromotionMemberPostModel, NewPromotionPostModel, TransferPromotionOwnershipPostModel};
+use crate::models::put_models::{UpdatedPromotionMemberPutModel, UpdatedPromotionPutModel};
 use actix_web::{delete, get, post, put, web, HttpMessage, HttpRequest, HttpResponse, ResponseError};
-use application::database::promotions::{create_promotion, delete_promotion, get_all_promotions_from_teacher_id, get_promotion_by_id, get_promotions_by_matching_date_and_title, update_promotion, PromotionSearchParams};
-use domain::models::promotions::{NewPromotion, UpdatedPromotion};
+use application::authorization::promotions::check_promotion_permission;
+use application::database::pagination::{ListParams, PromotionSortField};
+use application::database::promotions::{create_promotion, delete_promotion, get_promotion_by_id, search_promotions_from_member_id, update_promotion, PromotionSearchParams};
+use application::database::promotions_members::{create_promotion_member, delete_promotion_member, get_members_from_promotion_id, get_promotion_member, get_promotions_page_from_member_id, transfer_promotion_ownership, update_promotion_member};
+use application::database::transaction::{transaction, ScopedFutureExt};
+use application::database::users::get_user_by_email;
+use domain::models::promotions::{NewPromotion, Promotion, UpdatedPromotion};
+use domain::models::promotions_members::{NewPromotionMember, PromotionPermission, PromotionRole, UpdatedPromotionMember};
 use domain::models::users::User;
 use garde::Validate;
 use shared::app_state_model::AppState;
-use shared::error_models::{APIError, ForbiddenError, InternalError, ServerError, UserError};
+use shared::error_models::{APIError, BadRequestError, DBError, NotFoundError, UserError};
 use uuid::Uuid;
 
 /// Get all existing promotions from the current teacher
 ///
-/// This endpoint returns all promotions from the current teacher in the database.
+/// This endpoint returns a page of the promotions the current teacher is a member of, sorted by start year by default.
+/// The text filter applies to the title of the promotions.
 #[utoipa::path(
     get,
     path = "/",
     tag = "Promotions",
     context_path = "/promotions",
+    params(
+        ListParams<PromotionSortField>
+    ),
     responses(
-        (status = 200, description = "All the returned promotions objects", body = [Promotion]),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError, examples(
-            ("NoToken" = (value = json!("Token not provided"))),
-            ("InvalidToken" = (value = json!("Error")))
-        )),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 200, description = "A page of the promotions", body = PromotionsPage),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[get("/")]
-pub async fn get_all_promotions_from_current_teacher_route(data: web::Data<AppState>, req: HttpRequest) -> HttpResponse {
+pub async fn get_all_promotions_from_current_teacher_route(data: web::Data<AppState>, req: HttpRequest, params: web::Query<ListParams<PromotionSortField>>) -> HttpResponse {
     let teacher = req.extensions().get::<User>().cloned().unwrap();
-    let result = web::block(move || {
+    let result = async move {
         let conn = data.database_pool.clone().as_ref().clone();
-        get_all_promotions_from_teacher_id(&conn, teacher.id)
-    }).await;
+        get_promotions_page_from_member_id(&conn, teacher.organization_id, teacher.id, &params).await
+    }.await;
 
     match result {
-        Ok(response) => match response {
-            Ok(promotions) => HttpResponse::Ok().json(promotions),
-            Err(err) => APIError::from(err).error_response()
-        },
-        Err(_) => ServerError::InternalError(InternalError).error_response(),
+        Ok(promotions) => HttpResponse::Ok().json(promotions),
+        Err(err) => APIError::from(err).error_response()
     }
 }
 
@@ -57,76 +61,58 @@ pub async fn get_all_promotions_from_current_teacher_route(data: web::Data<AppSt
     ),
     responses(
         (status = 200, description = "All the returned promotions objects", body = Promotion),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError, examples(
-            ("NoToken" = (value = json!("Token not provided"))),
-            ("InvalidToken" = (value = json!("Error")))
-        )),
-        (status = 403, description = "Forbidden", body = ForbiddenError, example = json!("Forbidden")),
-        (status = 404, description = "Not Found", body = NotFoundError, example = json!("Database record")),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Not Found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[get("/{id}")]
 pub async fn get_promotion_by_id_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>) -> HttpResponse {
     let teacher = req.extensions().get::<User>().cloned().unwrap();
-    let result = web::block(move || {
+    let result: Result<Promotion, APIError> = async move {
         let conn = data.database_pool.clone().as_ref().clone();
-        let promotion = get_promotion_by_id(&conn, id.into_inner()).map_err(APIError::from)?;
-        if promotion.teacher_id != teacher.id {
-            return Err(APIError::UserError(UserError::Forbidden(ForbiddenError)));
-        }
+        let promotion = get_promotion_by_id(&conn, teacher.organization_id, id.into_inner()).await?;
+        check_promotion_permission(&conn, promotion.id, &teacher, PromotionPermission::Read).await?;
 
         Ok(promotion)
-    }).await;
+    }.await;
 
     match result {
-        Ok(response) => match response {
-            Ok(promotion) => HttpResponse::Ok().json(promotion),
-            Err(err) => APIError::from(err).error_response(),
-        },
-        Err(_) => ServerError::InternalError(InternalError).error_response(),
+        Ok(promotion) => HttpResponse::Ok().json(promotion),
+        Err(err) => err.error_response(),
     }
 }
 
-/// Get a promotion by title and date
-///get_all_promotions_route
-/// This endpoint returns the closest promotions to the specified title and date.
+/// Search the promotions of the current teacher
+///
+/// This endpoint returns the promotions the current teacher is a member of matching all the given filters, most recent first.
+/// The title is matched partially, ignoring case and accents.
 #[utoipa::path(
     get,
     path = "/search",
     tag = "Promotions",
     context_path = "/promotions",
-    request_body(
-        content = PromotionSearchParams,
-        description = "The search parameters for the promotions",
-        content_type = "application/json"
+    params(
+        PromotionSearchParams
     ),
     responses(
-        (status = 200, description = "All the returned promotions objects", body = [Promotion]),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError, examples(
-            ("NoToken" = (value = json!("Token not provided"))),
-            ("InvalidToken" = (value = json!("Error")))
-        )),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 200, description = "The matching promotions", body = [Promotion]),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[get("/search")]
-pub async fn search_promotions_route(data: web::Data<AppState>, req: HttpRequest, params: web::Json<PromotionSearchParams>) -> HttpResponse {
-    // Fix : Not working
-    // Err : TypeError: Failed to execute 'fetch' on 'Window': Request with GET/HEAD method cannot have body.
+pub async fn search_promotions_route(data: web::Data<AppState>, req: HttpRequest, params: web::Query<PromotionSearchParams>) -> HttpResponse {
     let teacher = req.extensions().get::<User>().cloned().unwrap();
-    let result = web::block(move || {
+    let result = async move {
         let conn = data.database_pool.clone().as_ref().clone();
-        let params = params.into_inner();
-        get_promotions_by_matching_date_and_title(&conn, &params, teacher.id)
-    }).await;
+        search_promotions_from_member_id(&conn, &params, teacher.organization_id, teacher.id).await
+    }.await;
 
     match result {
-        Ok(response) => match response {
-            Ok(promotions) => HttpResponse::Ok().json(promotions),
-            Err(err) => APIError::from(err).error_response(),
-        },
-        Err(_) => ServerError::InternalError(InternalError).error_response(),
+        Ok(promotions) => HttpResponse::Ok().json(promotions),
+        Err(err) => APIError::from(err).error_response(),
     }
 }
 
@@ -145,37 +131,40 @@ pub async fn search_promotions_route(data: web::Data<AppState>, req: HttpRequest
     ),
     responses(
         (status = 201, description = "Promotion created successfully", body = Uuid),
-        (status = 400, description = "Bad Request", body = BadRequestError, examples(
-            ("InvalidTitle" = (value = json!("Invalid title"))),
-        )),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError, examples(
-            ("NoToken" = (value = json!("Token not provided"))),
-            ("InvalidToken" = (value = json!("Error")))
-        )),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 400, description = "Bad Request", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[post("/")]
 pub async fn create_promotion_route(data: web::Data<AppState>, req: HttpRequest, promotion: web::Json<NewPromotionPostModel>) -> HttpResponse {
     let teacher = req.extensions().get::<User>().cloned().unwrap();
-    let result = web::block(move || {
+    let result: Result<Uuid, APIError> = async move {
         let conn = data.database_pool.clone().as_ref().clone();
-        promotion.validate().map_err(APIError::from)?;
+        promotion.validate()?;
         let new_promotion = NewPromotion {
             title: promotion.title.clone(),
             start_year: promotion.start_year,
             end_year: promotion.end_year,
             teacher_id: teacher.id,
+            organization_id: teacher.organization_id,
         };
-        create_promotion(&conn, new_promotion).map_err(APIError::from)
-    }).await;
+        let promotion_id = transaction(&conn, |conn| async move {
+            let promotion_id = create_promotion(&mut *conn, new_promotion).await?;
+            create_promotion_member(&mut *conn, NewPromotionMember {
+                promotion_id,
+                user_id: teacher.id,
+                role: PromotionRole::Owner,
+            }).await?;
+            Ok::<_, APIError>(promotion_id)
+        }.scope_boxed()).await?;
+
+        Ok(promotion_id)
+    }.await;
 
     match result {
-        Ok(response) => match response {
-            Ok(id) => HttpResponse::Created().json(id),
-            Err(err) => APIError::from(err).error_response(),
-        },
-        Err(_) => ServerError::InternalError(InternalError).error_response(),
+        Ok(id) => HttpResponse::Created().json(id),
+        Err(err) => err.error_response(),
     }
 }
 
@@ -197,50 +186,40 @@ pub async fn create_promotion_route(data: web::Data<AppState>, req: HttpRequest,
     ),
     responses(
         (status = 200, description = "Promotion updated successfully"),
-        (status = 400, description = "Bad Request", body = BadRequestError, examples(
-            ("InvalidTitle" = (value = json!("Invalid title"))),
-        )),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError, examples(
-            ("NoToken" = (value = json!("Token not provided"))),
-            ("InvalidToken" = (value = json!("Error")))
-        )),
-        (status = 403, description = "Forbidden", body = ForbiddenError, example = json!("Forbidden")),
-        (status = 404, description = "Not Found", body = NotFoundError, example = json!("Database record")),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 400, description = "Bad Request", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Not Found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[put("/{id}")]
 pub async fn update_promotion_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>, promotion: web::Json<UpdatedPromotionPutModel>) -> HttpResponse {
     let teacher = req.extensions().get::<User>().cloned().unwrap();
-    let result = web::block(move || {
+    let result = async move {
         let conn = data.database_pool.clone().as_ref().clone();
         promotion.validate().map_err(APIError::from)?;
 
-        let promotion_ = get_promotion_by_id(&conn, id.into_inner()).map_err(APIError::from)?;
-        if promotion_.teacher_id != teacher.id {
-            return Err(APIError::UserError(UserError::Forbidden(ForbiddenError)));
-        }
+        let promotion_ = get_promotion_by_id(&conn, teacher.organization_id, id.into_inner()).await.map_err(APIError::from)?;
+        check_promotion_permission(&conn, promotion_.id, &teacher, PromotionPermission::Edit).await?;
 
         let updated_promotion = UpdatedPromotion {
             title: promotion.title.clone(),
             start_year: promotion.start_year,
             end_year: promotion.end_year,
         };
-        update_promotion(&conn, promotion_.id, updated_promotion).map_err(APIError::from)
-    }).await;
+        update_promotion(&conn, teacher.organization_id, promotion_.id, updated_promotion).await.map_err(APIError::from)
+    }.await;
 
     match result {
-        Ok(response) => match response {
-            Ok(_) => HttpResponse::Ok().finish(),
-            Err(err) => APIError::from(err).error_response(),
-        },
-        Err(_) => ServerError::InternalError(InternalError).error_response(),
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(err) => err.error_response(),
     }
 }
 
 /// Delete a promotion
 ///
-/// This endpoint deletes a promotion from the database.
+/// This endpoint deletes a promotion from the database. Only the owner of the promotion can delete it.
 #[utoipa::path(
     delete,
     path = "/{id}",
@@ -251,47 +230,286 @@ pub async fn update_promotion_route(data: web::Data<AppState>, req: HttpRequest,
     ),
     responses(
         (status = 200, description = "Promotion deleted successfully"),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError, examples(
-            ("NoToken" = (value = json!("Token not provided"))),
-            ("InvalidToken" = (value = json!("Error")))
-        )),
-        (status = 403, description = "Forbidden", body = ForbiddenError, example = json!("Forbidden")),
-        (status = 404, description = "Not Found", body = NotFoundError, example = json!("Database record")),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Not Found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[delete("/{id}")]
 pub async fn delete_promotion_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>) -> HttpResponse {
     let teacher = req.extensions().get::<User>().cloned().unwrap();
-    let result = web::block(move || {
+    let result = async move {
+        let conn = data.database_pool.clone().as_ref().clone();
+
+        let promotion_ = get_promotion_by_id(&conn, teacher.organization_id, id.into_inner()).await.map_err(APIError::from)?;
+        check_promotion_permission(&conn, promotion_.id, &teacher, PromotionPermission::Manage).await?;
+
+        delete_promotion(&conn, teacher.organization_id, promotion_.id).await.map_err(APIError::from)
+    }.await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(err) => err.error_response(),
+    }
+}
+
+/// Get the members of a promotion
+///
+/// This endpoint returns all the teachers sharing the promotion with their role.
+#[utoipa::path(
+    get,
+    path = "/{id}/members",
+    tag = "Promotions",
+    context_path = "/promotions",
+    params(
+        ("id" = Uuid, description = "The promotion id to get the members from")
+    ),
+    responses(
+        (status = 200, description = "All the members of the promotion", body = [PromotionMemberDetails]),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[get("/{id}/members")]
+pub async fn get_promotion_members_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
+    let result = async move {
+        let conn = data.database_pool.clone().as_ref().clone();
+        let promotion_id = id.into_inner();
+        check_promotion_permission(&conn, promotion_id, &teacher, PromotionPermission::Read).await?;
+
+        get_members_from_promotion_id(&conn, promotion_id).await.map_err(APIError::from)
+    }.await;
+
+    match result {
+        Ok(members) => HttpResponse::Ok().json(members),
+        Err(err) => err.error_response(),
+    }
+}
+
+/// Add a member to a promotion
+///
+/// This endpoint adds an existing user to the promotion with the given role. Only the owner can add members.
+#[utoipa::path(
+    post,
+    path = "/{id}/members",
+    tag = "Promotions",
+    context_path = "/promotions",
+    params(
+        ("id" = Uuid, description = "The promotion id to add the member to")
+    ),
+    request_body(
+        content = NewPromotionMemberPostModel,
+        description = "The email of the user to add and its role",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 201, description = "Member added successfully", body = Uuid),
+        (status = 400, description = "Bad Request", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "User Not Found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[post("/{id}/members")]
+pub async fn add_promotion_member_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>, member: web::Json<NewPromotionMemberPostModel>) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
+    let result = async move {
+        let conn = data.database_pool.clone().as_ref().clone();
+        let promotion_id = id.into_inner();
+        let member = member.into_inner();
+        member.validate()?;
+        check_promotion_permission(&conn, promotion_id, &teacher, PromotionPermission::Manage).await?;
+
+        if member.role == PromotionRole::Owner {
+            return Err(APIError::UserError(UserError::BadRequest(BadRequestError {
+                request: "Use the ownership transfer to set a new owner".to_string(),
+            })));
+        }
+
+        // Only users of the same organization can join the promotion
+        let user = match get_user_by_email(&conn, teacher.organization_id, &member.email).await {
+            Ok(user) => user,
+            Err(DBError::NotFound) => return Err(APIError::UserError(UserError::NotFound(NotFoundError {
+                resource: "User".to_string(),
+            }))),
+            Err(err) => return Err(APIError::from(err)),
+        };
+
+        create_promotion_member(&conn, NewPromotionMember {
+            promotion_id,
+            user_id: user.id,
+            role: member.role,
+        }).await?;
+
+        Ok(user.id)
+    }.await;
+
+    match result {
+        Ok(user_id) => HttpResponse::Created().json(user_id),
+        Err(err) => err.error_response(),
+    }
+}
+
+/// Change the role of a member
+///
+/// This endpoint updates the role of a member of the promotion. Only the owner can change roles.
+#[utoipa::path(
+    put,
+    path = "/{id}/members/{user_id}",
+    tag = "Promotions",
+    context_path = "/promotions",
+    params(
+        ("id" = Uuid, description = "The promotion id"),
+        ("user_id" = Uuid, description = "The member to update")
+    ),
+    request_body(
+        content = UpdatedPromotionMemberPutModel,
+        description = "The new role of the member",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Member updated successfully"),
+        (status = 400, description = "Bad Request", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Not Found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[put("/{id}/members/{user_id}")]
+pub async fn update_promotion_member_route(data: web::Data<AppState>, req: HttpRequest, path: web::Path<(Uuid, Uuid)>, member: web::Json<UpdatedPromotionMemberPutModel>) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
+    let (promotion_id, user_id) = path.into_inner();
+    let result = async move {
+        let conn = data.database_pool.clone().as_ref().clone();
+        check_promotion_permission(&conn, promotion_id, &teacher, PromotionPermission::Manage).await?;
+
+        // The owner role can only be moved with an ownership transfer
+        let current_member = get_promotion_member(&conn, promotion_id, user_id).await?;
+        if member.role == PromotionRole::Owner || current_member.role == PromotionRole::Owner {
+            return Err(APIError::UserError(UserError::BadRequest(BadRequestError {
+                request: "Use the ownership transfer to set a new owner".to_string(),
+            })));
+        }
+
+        update_promotion_member(&conn, promotion_id, user_id, UpdatedPromotionMember {
+            role: Some(member.role),
+        }).await.map_err(APIError::from)
+    }.await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(err) => err.error_response(),
+    }
+}
+
+/// Remove a member from a promotion
+///
+/// This endpoint removes a member from the promotion. The owner can remove anyone but himself, other members can only leave.
+#[utoipa::path(
+    delete,
+    path = "/{id}/members/{user_id}",
+    tag = "Promotions",
+    context_path = "/promotions",
+    params(
+        ("id" = Uuid, description = "The promotion id"),
+        ("user_id" = Uuid, description = "The member to remove")
+    ),
+    responses(
+        (status = 200, description = "Member removed successfully"),
+        (status = 400, description = "Bad Request", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Not Found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[delete("/{id}/members/{user_id}")]
+pub async fn delete_promotion_member_route(data: web::Data<AppState>, req: HttpRequest, path: web::Path<(Uuid, Uuid)>) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
+    let (promotion_id, user_id) = path.into_inner();
+    let result = async move {
         let conn = data.database_pool.clone().as_ref().clone();
+        if user_id != teacher.id {
+            check_promotion_permission(&conn, promotion_id, &teacher, PromotionPermission::Manage).await?;
+        }
 
-        let promotion_ = get_promotion_by_id(&conn, id.into_inner()).map_err(APIError::from)?;
-        if promotion_.teacher_id != teacher.id {
-            return Err(APIError::UserError(UserError::Forbidden(ForbiddenError)));
+        let member = get_promotion_member(&conn, promotion_id, user_id).await?;
+        if member.role == PromotionRole::Owner {
+            return Err(APIError::UserError(UserError::BadRequest(BadRequestError {
+                request: "The owner can't leave the promotion".to_string(),
+            })));
         }
 
-        delete_promotion(&conn, promotion_.id).map_err(APIError::from)
-    }).await;
+        delete_promotion_member(&conn, promotion_id, user_id).await.map_err(APIError::from)
+    }.await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(err) => err.error_response(),
+    }
+}
+
+/// Transfer the ownership of a promotion
+///
+/// This endpoint gives the ownership of the promotion to another member. The previous owner becomes a co-teacher.
+#[utoipa::path(
+    put,
+    path = "/{id}/owner",
+    tag = "Promotions",
+    context_path = "/promotions",
+    params(
+        ("id" = Uuid, description = "The promotion id")
+    ),
+    request_body(
+        content = TransferPromotionOwnershipPostModel,
+        description = "The member who becomes the new owner",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Ownership transferred successfully"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Member Not Found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[put("/{id}/owner")]
+pub async fn transfer_promotion_ownership_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>, body: web::Json<TransferPromotionOwnershipPostModel>) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
+    let result = async move {
+        let conn = data.database_pool.clone().as_ref().clone();
+        let promotion_id = id.into_inner();
+        check_promotion_permission(&conn, promotion_id, &teacher, PromotionPermission::Manage).await?;
+
+        transfer_promotion_ownership(&conn, promotion_id, body.user_id).await.map_err(APIError::from)
+    }.await;
 
     match result {
-        Ok(response) => match response {
-            Ok(_) => HttpResponse::Ok().finish(),
-            Err(err) => APIError::from(err).error_response(),
-        },
-        Err(_) => ServerError::InternalError(InternalError).error_response(),
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(err) => err.error_response(),
     }
 }
 
 pub fn promotions_config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/promotions")
-            .wrap(RequireAuth::new(UserTokenValidator))
+            .wrap(RequireAuth::new(ApiKeyTokenValidator))
             .service(get_all_promotions_from_current_teacher_route)
-            .service(get_promotion_by_id_route)
+            // Registered before "/{id}", which would match "/search" otherwise
             .service(search_promotions_route)
+            .service(get_promotion_by_id_route)
             .service(create_promotion_route)
             .service(update_promotion_route)
             .service(delete_promotion_route)
+            .service(get_promotion_members_route)
+            .service(add_promotion_member_route)
+            .service(update_promotion_member_route)
+            .service(delete_promotion_member_route)
+            .service(transfer_promotion_ownership_route)
     );
 }
\ No newline at end of file
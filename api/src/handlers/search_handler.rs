@@ -0,0 +1,46 @@
+use crate::middlewares::auth::{ApiKeyTokenValidator, RequireAuth};
+use actix_web::{get, web, HttpMessage, HttpRequest, HttpResponse, ResponseError};
+use application::database::search::{global_search, GlobalSearchParams};
+use domain::models::users::User;
+use shared::app_state_model::AppState;
+use shared::error_models::APIError;
+
+/// Search everything the current teacher has access to
+///
+/// This endpoint searches the promotions the current teacher is a member of, and the projects, groups and students of these promotions.
+/// The text is matched partially, ignoring case and accents. Students match on their name, surname, full name or email.
+#[utoipa::path(
+    get,
+    path = "",
+    tag = "Search",
+    context_path = "/search",
+    params(
+        GlobalSearchParams
+    ),
+    responses(
+        (status = 200, description = "The matching promotions, projects, groups and students", body = GlobalSearchResults),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[get("")]
+pub async fn global_search_route(data: web::Data<AppState>, req: HttpRequest, params: web::Query<GlobalSearchParams>) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
+    let result = async move {
+        let conn = data.database_pool.clone().as_ref().clone();
+        global_search(&conn, teacher.organization_id, teacher.id, &params).await
+    }.await;
+
+    match result {
+        Ok(results) => HttpResponse::Ok().json(results),
+        Err(err) => APIError::from(err).error_response(),
+    }
+}
+
+pub fn search_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/search")
+            .wrap(RequireAuth::new(ApiKeyTokenValidator))
+            .service(global_search_route)
+    );
+}
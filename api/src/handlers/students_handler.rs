@@ -1,15 +1,24 @@
-use crate::middlewares::auth::{RequireAuth, UserTokenValidator};
+use crate::middlewares::auth::{ApiKeyTokenValidator, RequireAuth};
 use crate::models::post_models::NewStudentPostModel;
 use crate::models::put_models::UpdatedStudentPutModel;
-use actix_web::{delete, get, post, put, web, HttpResponse, ResponseError};
+use crate::utils::audit::get_audit_context;
+use actix_web::{delete, get, post, put, web, HttpMessage, HttpRequest, HttpResponse, ResponseError};
+use application::audit::log::{record_audit_log, AuditEvent};
+use application::authorization::promotions::{check_promotion_permission, check_student_permission, check_student_permission_in_all_promotions};
 use application::database::groups::remove_students_from_groups;
 use application::database::marks::delete_all_marks_from_student;
-use application::database::students::{create_promotion_students, create_student, delete_student, get_student_by_id, get_students_from_promotion_id, remove_student_from_all_promotions, update_student};
+use application::database::pagination::{ListParams, StudentSortField};
+use application::database::promotions::get_promotion_by_id;
+use application::database::students::{create_promotion_students, create_student, delete_student, get_student_by_id, get_students_page_from_promotion_id, remove_student_from_all_promotions, update_student};
 use application::database::students_tokens::delete_all_tokens_from_student;
+use application::database::transaction::{transaction, ScopedFutureExt};
+use domain::models::audit_logs::AuditAction;
+use domain::models::promotions_members::PromotionPermission;
 use domain::models::students::{NewPromotionStudent, NewStudent, UpdatedStudent};
+use domain::models::users::User;
 use garde::Validate;
 use shared::app_state_model::AppState;
-use shared::error_models::{APIError, InternalError, ServerError};
+use shared::error_models::APIError;
 use uuid::Uuid;
 
 /// Get a student
@@ -25,63 +34,61 @@ use uuid::Uuid;
     ),
     responses(
         (status = 200, description = "The returned student", body = Student),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError, examples(
-            ("NoToken" = (value = json!("Token not provided"))),
-            ("InvalidToken" = (value = json!("Error")))
-        )),
-        (status = 404, description = "Student not found", body = NotFoundError, example = json!("NotFoundError")),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Student not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[get("/{id}")]
-pub async fn get_student_route(data: web::Data<AppState>, id: web::Path<Uuid>) -> HttpResponse {
-    let result = web::block(move || {
+pub async fn get_student_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
+    let result = async move {
         let conn = data.database_pool.clone().as_ref().clone();
-        get_student_by_id(&conn, id.into_inner())
-    }).await;
+        let student_id = id.into_inner();
+        check_student_permission(&conn, student_id, &teacher, PromotionPermission::Read).await?;
+        get_student_by_id(&conn, teacher.organization_id, student_id).await.map_err(APIError::from)
+    }.await;
 
     match result {
-        Ok(response) => match response {
-            Ok(student) => HttpResponse::Ok().json(student),
-            Err(err) => APIError::from(err).error_response(),
-        },
-        Err(_) => ServerError::InternalError(InternalError).error_response(),
+        Ok(student) => HttpResponse::Ok().json(student),
+        Err(err) => err.error_response(),
     }
 }
 
 /// Get all students from a promotion
 ///
-/// This endpoint allows you to get all students from a promotion in the database.
+/// This endpoint returns a page of the students from a promotion, sorted by surname by default.
+/// The text filter applies to the name, the surname and the email of the students.
 #[utoipa::path(
     get,
     path = "/promotion/{id}",
     tag = "Students",
     context_path = "/students",
     params(
-        ("id" = Uuid, description = "The promotion id to get the students from")
+        ("id" = Uuid, description = "The promotion id to get the students from"),
+        ListParams<StudentSortField>
     ),
     responses(
-        (status = 200, description = "All the returned students", body = Promotion),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError, examples(
-            ("NoToken" = (value = json!("Token not provided"))),
-            ("InvalidToken" = (value = json!("Error")))
-        )),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 200, description = "A page of the students", body = StudentsPage),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[get("/promotion/{id}")]
-pub async fn get_students_from_promotion_id_route(data: web::Data<AppState>, id: web::Path<Uuid>) -> HttpResponse {
-    let result = web::block(move || {
+pub async fn get_students_from_promotion_id_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>, params: web::Query<ListParams<StudentSortField>>) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
+    let result = async move {
         let conn = data.database_pool.clone().as_ref().clone();
-        get_students_from_promotion_id(&conn, id.into_inner())
-    }).await;
+        let promotion_id = id.into_inner();
+        check_promotion_permission(&conn, promotion_id, &teacher, PromotionPermission::Read).await?;
+        get_students_page_from_promotion_id(&conn, teacher.organization_id, promotion_id, &params).await.map_err(APIError::from)
+    }.await;
 
     match result {
-        Ok(response) => match response {
-            Ok(students) => HttpResponse::Ok().json(students),
-            Err(err) => APIError::from(err).error_response(),
-        },
-        Err(_) => ServerError::InternalError(InternalError).error_response(),
+        Ok(students) => HttpResponse::Ok().json(students),
+        Err(err) => err.error_response(),
     }
 }
 
@@ -103,46 +110,43 @@ pub async fn get_students_from_promotion_id_route(data: web::Data<AppState>, id:
     ),
     responses(
         (status = 201, description = "The student(s) has been created and added to the promotion", body = Uuid),
-        (status = 400, description = "Bad Request", body = BadRequestError, examples(
-            ("InvalidEmail" = (value = json!("Invalid email"))),
-            ("InvalidName" = (value = json!("Invalid name"))),
-            ("InvalidSurname" = (value = json!("Invalid surname"))),
-        )),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError, examples(
-            ("NoToken" = (value = json!("Token not provided"))),
-            ("InvalidToken" = (value = json!("Error")))
-        )),
-        (status = 404, description = "Promotion not found", body = NotFoundError, example = json!("NotFoundError")),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 400, description = "Bad Request", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Promotion not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[post("/promotion/{id}")]
-pub async fn create_student_for_promotion_route(data: web::Data<AppState>, id: web::Path<Uuid>, student: web::Json<NewStudentPostModel>) -> HttpResponse {
-    let result = web::block(move || -> Result<Uuid, APIError> {
-        println!("Creating student for promotion");
+pub async fn create_student_for_promotion_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>, student: web::Json<NewStudentPostModel>) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
+    let result: Result<Uuid, APIError> = async move {
         let conn = data.database_pool.clone().as_ref().clone();
         let promotion_id = id.into_inner();
+        check_promotion_permission(&conn, promotion_id, &teacher, PromotionPermission::Edit).await?;
         let student = student.into_inner();
         student.validate().map_err(APIError::from)?;
+        let promotion = get_promotion_by_id(&conn, teacher.organization_id, promotion_id).await?;
         let new_student = NewStudent {
             name: student.name,
             surname: student.surname,
             email: student.email,
+            organization_id: promotion.organization_id,
         };
-        let student_id = create_student(&conn, new_student).map_err(APIError::from)?;
-        create_promotion_students(&conn, vec![NewPromotionStudent {
-            promotion_id,
-            student_id,
-        }]).map_err(APIError::from)?;
+        let student_id = transaction(&conn, |conn| async move {
+            let student_id = create_student(&mut *conn, new_student).await?;
+            create_promotion_students(&mut *conn, vec![NewPromotionStudent {
+                promotion_id,
+                student_id,
+            }]).await?;
+            Ok::<_, APIError>(student_id)
+        }.scope_boxed()).await?;
         Ok(student_id)
-    }).await;
+    }.await;
 
     match result {
-        Ok(response) => match response {
-            Ok(student_id) => HttpResponse::Created().json(student_id),
-            Err(err) => APIError::from(err).error_response(),
-        },
-        Err(_) => ServerError::InternalError(InternalError).error_response(),
+        Ok(student_id) => HttpResponse::Created().json(student_id),
+        Err(err) => err.error_response(),
     }
 }
 
@@ -164,24 +168,20 @@ pub async fn create_student_for_promotion_route(data: web::Data<AppState>, id: w
     ),
     responses(
         (status = 200, description = "The student has been updated"),
-        (status = 400, description = "Bad Request", body = BadRequestError, examples(
-            ("InvalidEmail" = (value = json!("Invalid email"))),
-            ("InvalidName" = (value = json!("Invalid name"))),
-            ("InvalidSurname" = (value = json!("Invalid surname"))),
-        )),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError, examples(
-            ("NoToken" = (value = json!("Token not provided"))),
-            ("InvalidToken" = (value = json!("Error")))
-        )),
-        (status = 404, description = "Student not found", body = NotFoundError, example = json!("NotFoundError")),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 400, description = "Bad Request", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Student not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[put("/{id}")]
-pub async fn update_student_route(data: web::Data<AppState>, id: web::Path<Uuid>, student_: web::Json<UpdatedStudentPutModel>) -> HttpResponse {
-    let result = web::block(move || {
+pub async fn update_student_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>, student_: web::Json<UpdatedStudentPutModel>) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
+    let result = async move {
         let conn = data.database_pool.clone().as_ref().clone();
         let student_id = id.into_inner();
+        check_student_permission(&conn, student_id, &teacher, PromotionPermission::Edit).await?;
         let student = student_.into_inner();
         student.validate().map_err(APIError::from)?;
         let updated_student = UpdatedStudent {
@@ -189,21 +189,19 @@ pub async fn update_student_route(data: web::Data<AppState>, id: web::Path<Uuid>
             surname: student.surname,
             email: student.email,
         };
-        update_student(&conn, student_id, updated_student).map_err(APIError::from)
-    }).await;
+        update_student(&conn, teacher.organization_id, student_id, updated_student).await.map_err(APIError::from)
+    }.await;
 
     match result {
-        Ok(response) => match response {
-            Ok(_) => HttpResponse::Ok().finish(),
-            Err(err) => APIError::from(err).error_response(),
-        },
-        Err(_) => ServerError::InternalError(InternalError).error_response(),
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(err) => err.error_response(),
     }
 }
 
 /// Delete a student
 ///
-/// This endpoint allows you to delete a student in the database.
+/// This endpoint allows you to delete a student in the database. The student is removed from all their promotions,
+/// so the user must be allowed to delete students in each of them.
 #[utoipa::path(
     delete,
     path = "/{id}",
@@ -214,39 +212,50 @@ pub async fn update_student_route(data: web::Data<AppState>, id: web::Path<Uuid>
     ),
     responses(
         (status = 200, description = "The student has been deleted"),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError, examples(
-            ("NoToken" = (value = json!("Token not provided"))),
-            ("InvalidToken" = (value = json!("Error")))
-        )),
-        (status = 404, description = "Student not found", body = NotFoundError, example = json!("NotFoundError")),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Forbidden", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Student not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[delete("/{id}")]
-pub async fn delete_student_route(data: web::Data<AppState>, id: web::Path<Uuid>) -> HttpResponse {
-    let result = web::block(move || {
+pub async fn delete_student_route(data: web::Data<AppState>, req: HttpRequest, id: web::Path<Uuid>) -> HttpResponse {
+    let teacher = req.extensions().get::<User>().cloned().unwrap();
+    let audit_context = get_audit_context(&req);
+    let result: Result<(), APIError> = async move {
         let conn = data.database_pool.clone().as_ref().clone();
         let student_id = id.into_inner();
-        remove_students_from_groups(&conn, student_id).map_err(APIError::from)?;
-        delete_all_tokens_from_student(&conn, student_id).map_err(APIError::from)?;
-        delete_all_marks_from_student(&conn, student_id).map_err(APIError::from)?;
-        remove_student_from_all_promotions(&conn, student_id).map_err(APIError::from)?;
-        delete_student(&conn, student_id).map_err(APIError::from)
-    }).await;
+        check_student_permission_in_all_promotions(&conn, student_id, &teacher, PromotionPermission::Delete).await?;
+        let student = get_student_by_id(&conn, teacher.organization_id, student_id).await?;
+        transaction(&conn, |conn| async move {
+            remove_students_from_groups(&mut *conn, student_id).await?;
+            delete_all_tokens_from_student(&mut *conn, teacher.organization_id, student_id).await?;
+            delete_all_marks_from_student(&mut *conn, teacher.organization_id, student_id).await?;
+            remove_student_from_all_promotions(&mut *conn, student_id).await?;
+            delete_student(&mut *conn, teacher.organization_id, student_id).await
+        }.scope_boxed()).await.map_err(APIError::from)?;
+
+        record_audit_log(&conn, &audit_context, AuditEvent {
+            action: AuditAction::StudentDeleted,
+            resource_type: "student",
+            resource_id: Some(student_id.to_string()),
+            before: Some(&student),
+            after: None,
+        }).await;
+
+        Ok(())
+    }.await;
 
     match result {
-        Ok(response) => match response {
-            Ok(_) => HttpResponse::Ok().finish(),
-            Err(err) => APIError::from(err).error_response(),
-        },
-        Err(_) => ServerError::InternalError(InternalError).error_response(),
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(err) => err.error_response(),
     }
 }
 
 pub fn students_config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/students")
-            .wrap(RequireAuth::new(UserTokenValidator))
+            .wrap(RequireAuth::new(ApiKeyTokenValidator))
             .service(get_student_route)
             .service(get_students_from_promotion_id_route)
             .service(create_student_for_promotion_route)
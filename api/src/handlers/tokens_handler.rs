@@ -2,11 +2,12 @@ use actix_web::cookie::{CookieBuilder, SameSite};
 use actix_web::{get, web, HttpResponse, ResponseError};
 use application::database::students_tokens::get_student_token_by_id;
 use application::database::tokens::get_token_by_id;
+use chrono::Utc;
 use domain::models::tokens::TokenType;
 use infrastructure::DBPool;
 use serde::Deserialize;
 use shared::app_state_model::AppState;
-use shared::error_models::{APIError, DBError, InternalError, ServerError, UnauthorizedError, UserError};
+use shared::error_models::{APIError, DBError, UnauthorizedError, UserError};
 use uuid::Uuid;
 
 #[derive(Deserialize)]
@@ -22,6 +23,7 @@ struct EvaluationTokenQuery {
 /// Request password reset token
 ///
 /// This endpoint allows users to request a password reset token which is returned in a cookie.
+/// The id comes from the link sent by `/auth/reset-request`, the token is then used by `/reset/password`.
 #[utoipa::path(
     get,
     path = "/reset",
@@ -32,8 +34,8 @@ struct EvaluationTokenQuery {
     ),
     responses(
         (status = 200, description = "Responded with JWT", body = String),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[get("/reset")]
@@ -41,12 +43,12 @@ async fn get_reset_token_route(
     data: web::Data<AppState>,
     query: web::Query<ResetTokenQuery>,
 ) -> HttpResponse {
-    let result = web::block(move || {
+    let result = async move {
         let conn: DBPool = data.database_pool.clone().as_ref().clone();
         let token_id = query.id;
 
         // Check if token exists
-        let token_object = match get_token_by_id(&conn, token_id) {
+        let token_object = match get_token_by_id(&conn, token_id).await {
             Ok(token) => token,
             Err(err) => return match err {
                 DBError::NotFound => Err(APIError::UserError(UserError::Unauthorized(UnauthorizedError))),
@@ -58,27 +60,24 @@ async fn get_reset_token_route(
         if token_object.type_ != TokenType::PassReset {
             return Err(APIError::UserError(UserError::Unauthorized(UnauthorizedError)));
         }
-        if token_object.used {
+        if token_object.used || token_object.exp < Utc::now().naive_utc() {
             return Err(APIError::UserError(UserError::Unauthorized(UnauthorizedError)));
         }
 
         Ok(token_object.token)
-    }).await;
+    }.await;
 
     match result {
-        Ok(user) => match user {
-            Ok(token) => {
-                let cookie = CookieBuilder::new("token", token)
-                    .http_only(false)
-                    .secure(false)
-                    .same_site(SameSite::Strict)
-                    .path("/")
-                    .finish();
-                HttpResponse::Ok().cookie(cookie).finish()
-            },
-            Err(err) => err.error_response(),
+        Ok(token) => {
+            let cookie = CookieBuilder::new("token", token)
+                .http_only(false)
+                .secure(false)
+                .same_site(SameSite::Strict)
+                .path("/")
+                .finish();
+            HttpResponse::Ok().cookie(cookie).finish()
         },
-        Err(_) => ServerError::InternalError(InternalError).error_response()
+        Err(err) => err.error_response(),
     }
 }
 
@@ -95,8 +94,8 @@ async fn get_reset_token_route(
     ),
     responses(
         (status = 200, description = "Responded with JWT", body = String),
-        (status = 401, description = "Unauthorized", body = UnauthorizedError, example = json!("UnauthorizedError")),
-        (status = 500, description = "Internal Server Error", body = InternalError, example = json!("InternalError")),
+        (status = 401, description = "Unauthorized", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error", body = ProblemDetails, content_type = "application/problem+json"),
     )
 )]
 #[get("/evaluation")]
@@ -104,12 +103,12 @@ pub async fn get_evaluation_token_route(
     data: web::Data<AppState>,
     query: web::Query<EvaluationTokenQuery>,
 ) -> HttpResponse {
-    let result = web::block(move || {
+    let result = async move {
         let conn: DBPool = data.database_pool.clone().as_ref().clone();
         let token_id = query.id;
 
         // Check if token exists
-        let token_object = match get_student_token_by_id(&conn, token_id) {
+        let token_object = match get_student_token_by_id(&conn, token_id).await {
             Ok(token) => token,
             Err(err) => return match err {
                 DBError::NotFound => Err(APIError::UserError(UserError::Unauthorized(UnauthorizedError))),
@@ -123,22 +122,19 @@ pub async fn get_evaluation_token_route(
         }
 
         Ok(token_object.token)
-    }).await;
+    }.await;
 
     match result {
-        Ok(user) => match user {
-            Ok(token) => {
-                let cookie = CookieBuilder::new("token", token)
-                    .http_only(false)
-                    .secure(false)
-                    .same_site(SameSite::Strict)
-                    .path("/")
-                    .finish();
-                HttpResponse::Ok().cookie(cookie).finish()
-            },
-            Err(err) => err.error_response(),
+        Ok(token) => {
+            let cookie = CookieBuilder::new("token", token)
+                .http_only(false)
+                .secure(false)
+                .same_site(SameSite::Strict)
+                .path("/")
+                .finish();
+            HttpResponse::Ok().cookie(cookie).finish()
         },
-        Err(_) => ServerError::InternalError(InternalError).error_response()
+        Err(err) => err.error_response(),
     }
 }
 
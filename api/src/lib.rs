@@ -1,4 +1,5 @@
 pub mod handlers;
 pub mod middlewares;
 pub mod docs;
-pub mod models;
\ No newline at end of file
+pub mod models;
+pub mod utils;
\ No newline at end of file
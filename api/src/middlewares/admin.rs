@@ -1,12 +1,13 @@
 use std::rc::Rc;
 use std::task::{Context, Poll};
 
+use actix_web::body::{BoxBody, MessageBody};
 use actix_web::{Error, HttpMessage};
 use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
-use actix_web::error::ErrorForbidden;
 use futures_util::future::{LocalBoxFuture, ready, Ready};
 
 use domain::models::users::{User, UserRole};
+use shared::error_models::{ForbiddenError, UserError};
 
 pub struct AdminMiddleware<S> {
     service: Rc<S>,
@@ -16,9 +17,9 @@ impl<S, B> Service<ServiceRequest> for AdminMiddleware<S>
     where
         S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
         S::Future: 'static,
-        B: 'static,
+        B: MessageBody + 'static,
 {
-    type Response = ServiceResponse<B>;
+    type Response = ServiceResponse<BoxBody>;
     type Error = Error;
     type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
 
@@ -32,13 +33,15 @@ impl<S, B> Service<ServiceRequest> for AdminMiddleware<S>
             .map(|user| user.role == UserRole::Admin)
             .unwrap_or(false);
 
+        if !is_admin {
+            // Returned as a response to go through the outer middlewares, such as the request id
+            let res = req.error_response(UserError::Forbidden(ForbiddenError));
+            return Box::pin(async move { Ok(res) });
+        }
+
         let fut = self.service.call(req);
         Box::pin(async move {
-            if is_admin {
-                fut.await
-            } else {
-                Err(ErrorForbidden("Forbidden"))
-            }
+            Ok(fut.await?.map_into_boxed_body())
         })
     }
 }
@@ -49,9 +52,9 @@ impl<S, B> Transform<S, ServiceRequest> for RequireAdminRole
     where
         S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
         S::Future: 'static,
-        B: 'static,
+        B: MessageBody + 'static,
 {
-    type Response = ServiceResponse<B>;
+    type Response = ServiceResponse<BoxBody>;
     type Error = Error;
     type Transform = AdminMiddleware<S>;
     type InitError = ();
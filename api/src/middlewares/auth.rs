@@ -1,49 +1,53 @@
+use std::future::Future;
 use std::rc::Rc;
 use std::task::{Context, Poll};
 
 use actix_web::body::BoxBody;
 use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
-use actix_web::error::ErrorUnauthorized;
 use actix_web::{http, web, HttpMessage};
+use application::authentication::api_keys::{hash_api_key, is_api_key};
 use application::authentication::tokens::decode_token;
+use application::database::api_keys::{get_api_key_by_hash, update_api_key_last_used};
 use application::database::groups::get_group_by_id;
+use application::database::projects::get_promotion_from_project_id;
 use application::database::students::get_student_by_id;
 use application::database::students_tokens::get_student_token_by_token;
 use application::database::tokens::get_token_by_token_string;
 use futures_util::future::{ready, LocalBoxFuture, Ready};
-use futures_util::FutureExt;
 use uuid::Uuid;
 
 use application::database::users::get_user_by_id;
+use domain::models::api_keys::ApiKeyScope;
 use domain::models::groups::Group;
 use domain::models::students::Student;
 use domain::models::users::User;
 use shared::app_state_model::AppState;
+use shared::error_models::{ForbiddenError, InternalError, ServerError, UnauthorizedError, UserError};
 use shared::token_models::{SpecificClaims, StudentClaims, UserClaims};
 
 #[derive(Clone)]
 pub struct UserTokenValidator;
 impl TokenValidator for UserTokenValidator {
-    fn validate(&self, token: &str, app_state: &AppState, req: &ServiceRequest) -> Result<(), actix_web::Error> {
+    async fn validate(&self, token: &str, app_state: &AppState, req: &ServiceRequest) -> Result<(), actix_web::Error> {
         let config = app_state.config.read().clone();
 
         // Decode token and handle errors
         let decoded_token = decode_token::<UserClaims>(&token, &config)
-            .map_err(|_| ErrorUnauthorized("Error"))?;
+            .map_err(|_| unauthorized("Invalid token"))?;
 
         // Check token expiration
         if decoded_token.exp < chrono::Utc::now().timestamp() as usize {
-            return Err(ErrorUnauthorized("Token expired"));
+            return Err(unauthorized("Token expired"));
         }
 
         // Check if user exists
         let conn = app_state.database_pool.clone().as_ref().clone();
-        let user = get_user_by_id(&conn, decoded_token.sub)
-            .map_err(|_| ErrorUnauthorized("Error with user"))?;
+        let user = get_user_by_id(&conn, decoded_token.sub).await
+            .map_err(|_| unauthorized("Error with user"))?;
 
         // Check user token version
         if user.token_version != decoded_token.token_version {
-            return Err(ErrorUnauthorized("Invalid token"));
+            return Err(unauthorized("Invalid token"));
         }
 
         req.extensions_mut().insert::<User>(user);
@@ -52,39 +56,82 @@ impl TokenValidator for UserTokenValidator {
     }
 }
 
+/// Accepts personal API keys, and falls back to user tokens otherwise.
+///
+/// Read-only keys can only send safe requests.
+#[derive(Clone)]
+pub struct ApiKeyTokenValidator;
+impl TokenValidator for ApiKeyTokenValidator {
+    async fn validate(&self, token: &str, app_state: &AppState, req: &ServiceRequest) -> Result<(), actix_web::Error> {
+        if !is_api_key(token) {
+            return UserTokenValidator.validate(token, app_state, req).await;
+        }
+
+        // Get key in the database
+        let conn = app_state.database_pool.clone().as_ref().clone();
+        let api_key = get_api_key_by_hash(&conn, &hash_api_key(token)).await
+            .map_err(|_| unauthorized("Invalid API key"))?;
+
+        // Check key revocation and expiration
+        if !api_key.is_active(chrono::Utc::now().naive_utc()) {
+            return Err(unauthorized("API key expired"));
+        }
+
+        // Check key scope
+        let scope = match *req.method() {
+            http::Method::GET | http::Method::HEAD | http::Method::OPTIONS => ApiKeyScope::Read,
+            _ => ApiKeyScope::Write,
+        };
+        if !api_key.has_scope(scope) {
+            return Err(UserError::Forbidden(ForbiddenError).into());
+        }
+
+        // Check if user exists
+        let user = get_user_by_id(&conn, api_key.user_id).await
+            .map_err(|_| unauthorized("Error with user"))?;
+
+        update_api_key_last_used(&conn, api_key.id).await
+            .map_err(|_| ServerError::InternalError(InternalError))?;
+
+        req.extensions_mut().insert::<User>(user);
+
+        Ok(())
+    }
+}
+
 #[derive(Clone)]
 pub struct SpecificTokenValidator;
 impl TokenValidator for SpecificTokenValidator {
-    fn validate(&self, token: &str, app_state: &AppState, req: &ServiceRequest) -> Result<(), actix_web::Error> {
+    async fn validate(&self, token: &str, app_state: &AppState, req: &ServiceRequest) -> Result<(), actix_web::Error> {
         let config = app_state.config.read().clone();
 
         // Decode token and handle errors
         let decoded_token = decode_token::<SpecificClaims>(&token, &config)
-            .map_err(|_| ErrorUnauthorized("Error"))?;
+            .map_err(|_| unauthorized("Invalid token"))?;
 
         // Check token expiration
         if decoded_token.exp < chrono::Utc::now().timestamp() as usize {
-            return Err(ErrorUnauthorized("Token expired"));
+            return Err(unauthorized("Token expired"));
         }
 
         // Get token in the database
-        let token = get_token_by_token_string(&app_state.clone().database_pool, token)
-            .map_err(|_| ErrorUnauthorized("Error"))?;
+        let token = get_token_by_token_string(&app_state.database_pool, token).await
+            .map_err(|_| unauthorized("Unknown token"))?;
 
         // Check if token type is the same
         if token.type_ != decoded_token.type_ {
-            return Err(ErrorUnauthorized("Invalid token"));
+            return Err(unauthorized("Invalid token"));
         }
 
         // Check if token is used
         if token.used {
-            return Err(ErrorUnauthorized("Token already used"));
+            return Err(unauthorized("Token already used"));
         }
 
         // Check if user exists
         let conn = app_state.database_pool.clone().as_ref().clone();
-        let user = get_user_by_id(&conn, decoded_token.sub)
-            .map_err(|_| ErrorUnauthorized("Error with user"))?;
+        let user = get_user_by_id(&conn, decoded_token.sub).await
+            .map_err(|_| unauthorized("Error with user"))?;
 
         req.extensions_mut().insert::<User>(user);
         req.extensions_mut().insert::<Uuid>(token.id);
@@ -96,34 +143,38 @@ impl TokenValidator for SpecificTokenValidator {
 #[derive(Clone)]
 pub struct StudentTokenValidator;
 impl TokenValidator for StudentTokenValidator {
-    fn validate(&self, token: &str, app_state: &AppState, req: &ServiceRequest) -> Result<(), actix_web::Error> {
+    async fn validate(&self, token: &str, app_state: &AppState, req: &ServiceRequest) -> Result<(), actix_web::Error> {
         let config = app_state.config.read().clone();
 
         // Decode token and handle errors
         let decoded_token = decode_token::<StudentClaims>(&token, &config)
-            .map_err(|_| ErrorUnauthorized("Error"))?;
+            .map_err(|_| unauthorized("Invalid token"))?;
 
         // Check token expiration
         if decoded_token.exp < chrono::Utc::now().timestamp() as usize {
-            return Err(ErrorUnauthorized("Token expired"));
+            return Err(unauthorized("Token expired"));
         }
 
         // Get token in the database
-        let token = get_student_token_by_token(&app_state.clone().database_pool, token.to_string())
-            .map_err(|_| ErrorUnauthorized("Error"))?;
+        let token = get_student_token_by_token(&app_state.database_pool, token.to_string()).await
+            .map_err(|_| unauthorized("Unknown token"))?;
 
         // Check if token is used
         if token.used {
-            return Err(ErrorUnauthorized("Token already used"));
+            return Err(unauthorized("Token already used"));
         }
 
-        // Check if student exists
+        // The student and their group belong to the organization of the project of the token
         let conn = app_state.database_pool.clone().as_ref().clone();
-        let student = get_student_by_id(&conn, decoded_token.sub)
-            .map_err(|_| ErrorUnauthorized("Error with student"))?;
+        let promotion = get_promotion_from_project_id(&conn, token.project_id).await
+            .map_err(|_| unauthorized("Error with project"))?;
 
-        let group = get_group_by_id(&conn, decoded_token.group_id)
-            .map_err(|_| ErrorUnauthorized("Error with group"))?;
+        // Check if student exists
+        let student = get_student_by_id(&conn, promotion.organization_id, decoded_token.sub).await
+            .map_err(|_| unauthorized("Error with student"))?;
+
+        let group = get_group_by_id(&conn, promotion.organization_id, decoded_token.group_id).await
+            .map_err(|_| unauthorized("Error with group"))?;
 
         req.extensions_mut().insert::<Student>(student);
         req.extensions_mut().insert::<Group>(group);
@@ -133,8 +184,13 @@ impl TokenValidator for StudentTokenValidator {
     }
 }
 
+/// Unauthorized error response explaining why the token was rejected.
+fn unauthorized(detail: &str) -> actix_web::Error {
+    UserError::Unauthorized(UnauthorizedError).problem().with_detail(detail).into()
+}
+
 pub trait TokenValidator {
-    fn validate(&self, token: &str, app_state: &AppState, req: &ServiceRequest) -> Result<(), actix_web::Error>;
+    fn validate(&self, token: &str, app_state: &AppState, req: &ServiceRequest) -> impl Future<Output = Result<(), actix_web::Error>>;
 }
 
 pub struct AuthMiddleware<S, T: TokenValidator> {
@@ -174,8 +230,14 @@ impl<S, T: Clone> Service<ServiceRequest> for AuthMiddleware<S, T>
                 req.headers()
                     .get(http::header::AUTHORIZATION)
                     .and_then(|h| h.to_str().ok())
-                    .map(|auth| auth.split_at(7).1.to_string())
+                    .and_then(|auth| auth.strip_prefix("Bearer "))
+                    .map(|auth| auth.to_string())
             }).or_else(|| {
+            req.headers()
+                .get("X-API-Key")
+                .and_then(|h| h.to_str().ok())
+                .map(|key| key.to_string())
+        }).or_else(|| {
             req.headers()
                 .get("credentials")
                 .and_then(|h| h.to_str().ok())
@@ -183,11 +245,16 @@ impl<S, T: Clone> Service<ServiceRequest> for AuthMiddleware<S, T>
         });
 
         Box::pin(async move {
+            // Errors are returned as responses to go through the outer middlewares, such as the request id
             // If token is missing, return unauthorized error
-            let token = token.ok_or_else(|| ErrorUnauthorized("Token not provided"))?;
+            let Some(token) = token else {
+                return Ok(req.error_response(unauthorized("Token not provided")));
+            };
 
             // Decode token
-            validator.validate(&token, &app_state, &req)?;
+            if let Err(err) = validator.validate(&token, &app_state, &req).await {
+                return Ok(req.error_response(err));
+            }
 
             // Call the wrapped service to handle the request
             let res = srv.call(req).await?;
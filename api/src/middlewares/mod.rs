@@ -1,2 +1,4 @@
 pub mod auth;
-pub mod admin;
\ No newline at end of file
+pub mod admin;
+pub mod request_id;
+pub mod startup;
\ No newline at end of file
@@ -0,0 +1,157 @@
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use actix_web::body::{to_bytes, BoxBody, MessageBody};
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderName, HeaderValue, CONTENT_TYPE};
+use actix_web::{Error, HttpMessage};
+use application::metrics::{observe_http_request, UNMATCHED_ROUTE};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use shared::error_models::{ProblemDetails, PROBLEM_JSON};
+use tracing::{field, Instrument};
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Identifier of a request, taken from the `X-Request-Id` header or generated.
+///
+/// Every request is traced in a span holding its id, and counted in the metrics.
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    /// Ids sent by clients are kept if they are short printable strings, to follow a request across services.
+    fn from_request(req: &ServiceRequest) -> Self {
+        let id = req.headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|header| header.to_str().ok())
+            .filter(|id| !id.is_empty() && id.len() <= 128 && id.chars().all(|c| c.is_ascii_graphic()))
+            .map(|id| id.to_string())
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+        RequestId(id)
+    }
+}
+
+pub struct RequestIdMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestIdMiddleware<S>
+    where
+        S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+        S::Future: 'static,
+        B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let request_id = RequestId::from_request(&req);
+        req.extensions_mut().insert(request_id.clone());
+
+        // The query string is left out as it can hold tokens
+        let span = tracing::info_span!(
+            "http_request",
+            request_id = %request_id.0,
+            method = %req.method(),
+            path = %req.path(),
+            route = field::Empty,
+            status = field::Empty,
+        );
+        let start = Instant::now();
+
+        let srv = Rc::clone(&self.service);
+        Box::pin(async move {
+            let res = match srv.call(req).await {
+                Ok(res) => res.map_into_boxed_body(),
+                Err(err) => {
+                    tracing::error!(error = %err, "request failed");
+                    return Err(err);
+                }
+            };
+
+            let mut res = add_request_id_to_problem(res, &request_id.0).await;
+            if let Ok(value) = HeaderValue::from_str(&request_id.0) {
+                res.headers_mut().insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+            }
+
+            let latency = start.elapsed();
+            let route = res.request().match_pattern();
+            let span = tracing::Span::current();
+            span.record("status", res.status().as_u16());
+            if let Some(route) = &route {
+                span.record("route", route.as_str());
+            }
+            observe_http_request(
+                res.request().method().as_str(),
+                route.as_deref().unwrap_or(UNMATCHED_ROUTE),
+                res.status().as_u16(),
+                latency,
+            );
+            let latency_ms = latency.as_millis() as u64;
+            if res.status().is_server_error() {
+                tracing::error!(latency_ms, "request completed");
+            } else {
+                tracing::info!(latency_ms, "request completed");
+            }
+            Ok(res)
+        }.instrument(span))
+    }
+}
+
+/// Fills the `request_id` of problem details bodies.
+async fn add_request_id_to_problem(res: ServiceResponse<BoxBody>, request_id: &str) -> ServiceResponse<BoxBody> {
+    let is_problem = res.headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .map(|content_type| content_type.starts_with(PROBLEM_JSON))
+        .unwrap_or(false);
+    if !is_problem {
+        return res;
+    }
+
+    let (req, res) = res.into_parts();
+    let (res, body) = res.into_parts();
+    let body = match to_bytes(body).await {
+        Ok(body) => body,
+        Err(_) => return ServiceResponse::new(req, res.set_body(BoxBody::new(()))),
+    };
+
+    let body = match serde_json::from_slice::<ProblemDetails>(&body) {
+        Ok(mut problem) => {
+            problem.request_id = Some(request_id.to_string());
+            serde_json::to_vec(&problem).map(BoxBody::new).unwrap_or_else(|_| BoxBody::new(body))
+        }
+        Err(_) => BoxBody::new(body),
+    };
+    ServiceResponse::new(req, res.set_body(body))
+}
+
+/// Middleware factory identifying and tracing every request.
+pub struct AssignRequestId;
+
+impl<S, B> Transform<S, ServiceRequest> for AssignRequestId
+    where
+        S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+        S::Future: 'static,
+        B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Transform = RequestIdMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestIdMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
@@ -0,0 +1,22 @@
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::web::Data;
+use actix_web::{Error, ResponseError};
+use shared::app_state_model::{AppState, StartupStatus};
+use shared::error_models::{ServerError, UnavailableError};
+
+/// Answers the requests with a 503 problem until the database is prepared, the health probes being served apart.
+pub async fn require_startup(req: ServiceRequest, next: Next<impl MessageBody + 'static>) -> Result<ServiceResponse<BoxBody>, Error> {
+    let startup_status = req.app_data::<Data<AppState>>()
+        .map(|app_state| app_state.get_startup_status())
+        .unwrap_or(StartupStatus::Started);
+
+    let reason = match startup_status {
+        StartupStatus::Started => return Ok(next.call(req).await?.map_into_boxed_body()),
+        StartupStatus::Starting => "The API is starting".to_string(),
+        StartupStatus::Failed(_) => "The API failed to start".to_string(),
+    };
+    let response = ServerError::Unavailable(UnavailableError { reason }).error_response();
+    Ok(req.into_response(response))
+}
@@ -1,6 +1,9 @@
-use application::database::groups::ProjectGroup;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use domain::models::api_keys::{ApiKey, ApiKeyScope};
+use domain::models::users::{OrganizationRole, User, UserRole};
 use serde::Serialize;
 use utoipa::ToSchema;
+use uuid::Uuid;
 
 #[derive(Serialize, ToSchema)]
 pub struct GenericResponse {
@@ -8,6 +11,91 @@ pub struct GenericResponse {
 }
 
 #[derive(Serialize, ToSchema)]
-pub struct ProjectGroupsGetModel {
-    pub groups: Vec<ProjectGroup>,
-}
\ No newline at end of file
+pub struct OrganizationUserGetModel {
+    pub id: Uuid,
+    pub username: String,
+    pub email: String,
+    pub organization_role: OrganizationRole,
+}
+
+impl From<User> for OrganizationUserGetModel {
+    fn from(user: User) -> Self {
+        OrganizationUserGetModel {
+            id: user.id,
+            username: user.username,
+            email: user.email,
+            organization_role: user.organization_role,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct MeGetModel {
+    pub id: Uuid,
+    pub username: String,
+    pub email: String,
+    pub has_validated_email: bool,
+    pub role: UserRole,
+    pub organization_id: Uuid,
+    pub organization_role: OrganizationRole,
+    pub created_at: NaiveDateTime,
+}
+
+impl From<User> for MeGetModel {
+    fn from(user: User) -> Self {
+        MeGetModel {
+            id: user.id,
+            username: user.username,
+            email: user.email,
+            has_validated_email: user.has_validated_email,
+            role: user.role,
+            organization_id: user.organization_id,
+            organization_role: user.organization_role,
+            created_at: user.created_at,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ApiKeyGetModel {
+    pub id: Uuid,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<ApiKeyScope>,
+    pub expires_at: Option<NaiveDateTime>,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+impl From<ApiKey> for ApiKeyGetModel {
+    fn from(api_key: ApiKey) -> Self {
+        ApiKeyGetModel {
+            id: api_key.id,
+            name: api_key.name,
+            prefix: api_key.prefix,
+            scopes: api_key.scopes,
+            expires_at: api_key.expires_at,
+            last_used_at: api_key.last_used_at,
+            revoked_at: api_key.revoked_at,
+            created_at: api_key.created_at,
+        }
+    }
+}
+
+/// Created API key, the key itself can't be retrieved afterward.
+#[derive(Serialize, ToSchema)]
+pub struct CreatedApiKeyGetModel {
+    pub id: Uuid,
+    pub key: String,
+}
+
+/// Runs of the scheduler, which checks the projects at the times of its cron expression.
+#[derive(Serialize, ToSchema)]
+pub struct SchedulerGetModel {
+    /// Cron expression of the runs, in UTC
+    pub schedule: String,
+    /// When the last run completed
+    pub last_run: Option<DateTime<Utc>>,
+    pub next_run: Option<DateTime<Utc>>,
+}
@@ -1,7 +1,13 @@
-use application::database::users::{get_user_by_email, get_user_by_username};
+use application::authentication::passwords::PasswordPolicy;
+use application::database::users::{get_user_by_email_in_any_organization, get_user_by_username};
+use application::timezone::parse_timezone;
 use chrono::NaiveDateTime;
-use garde::{Error, Validate};
-use infrastructure::init_pool;
+use domain::models::api_keys::ApiKeyScope;
+use domain::models::config::MainConfig;
+use domain::models::promotions_members::PromotionRole;
+use garde::error::Path;
+use garde::{Error, Report, Validate};
+use infrastructure::DBPool;
 use once_cell::sync::Lazy as SyncLazy;
 use regex::Regex;
 use serde::Deserialize;
@@ -9,71 +15,63 @@ use utoipa::ToSchema;
 use uuid::Uuid;
 
 #[derive(Debug, Deserialize, Validate, ToSchema)]
+#[garde(context(PasswordContext))]
 pub struct RegisterUserPostModel {
     #[garde(email)]
-    #[garde(custom(validate_unique_email))]
     pub email: String,
     #[garde(length(min = 3, max = 20))]
     #[garde(pattern(USERNAME_REGEX))]
-    #[garde(custom(validate_unique_username))]
     pub username: String,
-    #[garde(length(min = 8, max = 64))]
     #[garde(custom(validate_password_policy))]
     pub password: String,
+    /// Slug of the organization to join, the default organization is used if not provided
+    #[garde(skip)]
+    pub organization: Option<String>,
 }
 
-struct PasswordContext {
-    min_lower_case: usize,
-    min_upper_case: usize,
-    min_digits: usize,
-    min_special_chars: usize
+/// Context of the password validation, built from the main configuration.
+pub struct PasswordContext {
+    pub policy: PasswordPolicy,
+    /// Username of the user changing its password, if known
+    pub username: Option<String>,
 }
 
-static USERNAME_REGEX: SyncLazy<Regex> = SyncLazy::new(|| {
-    Regex::new(r#"^[a-zA-Z0-9_.-]+$"#).unwrap()
-});
-
-fn validate_unique_email(value: &str, _: &()) -> garde::Result {
-    let conn = init_pool(dotenvy::var("DATABASE_URL").unwrap().as_str());
-    match get_user_by_email(&conn, value) {
-        Ok(_) => Err(Error::new("Email already exists")),
-        Err(_) => Ok(()),
+impl PasswordContext {
+    pub fn new(main_config: &MainConfig, username: Option<String>) -> Self {
+        PasswordContext {
+            policy: PasswordPolicy::from(main_config),
+            username,
+        }
     }
 }
 
-fn validate_unique_username(value: &str, _: &()) -> garde::Result {
-    let conn = init_pool(dotenvy::var("DATABASE_URL").unwrap().as_str());
-    match get_user_by_username(&conn, value) {
-        Ok(_) => Err(Error::new("Username already exists")),
-        Err(_) => Ok(()),
-    }
-}
+pub(crate) static USERNAME_REGEX: SyncLazy<Regex> = SyncLazy::new(|| {
+    Regex::new(r#"^[a-zA-Z0-9_.-]+$"#).unwrap()
+});
 
-// TODO: Re-try context implementation
-fn validate_password_policy(value: &str, _: &()) -> garde::Result {
-    let context = PasswordContext {
-        min_lower_case: 1,
-        min_upper_case: 1,
-        min_digits: 1,
-        min_special_chars: 1,
-    };
-    // Lower case check
-    if value.chars().filter(|c| c.is_lowercase()).count() < context.min_lower_case {
-        return Err(Error::new("Password must contain at least 1 lower case character"));
-    }
-    // Upper case check
-    if value.chars().filter(|c| c.is_uppercase()).count() < context.min_upper_case {
-        return Err(Error::new("Password must contain at least 1 upper case character"));
+/// Checks that the email and the username are not used by another user, extending the report of the validation.
+///
+/// The check needs the database, so it can't be a custom validator of garde.
+pub(crate) async fn validate_unique_user(conn: &DBPool, email: Option<&str>, username: Option<&str>, validation: Result<(), Report>) -> Result<(), Report> {
+    let mut report = validation.err().unwrap_or_else(Report::new);
+    if let Some(email) = email {
+        if get_user_by_email_in_any_organization(conn, email).await.is_ok() {
+            report.append(Path::new("email"), Error::new("Email already exists"));
+        }
     }
-    // Digit check
-    if value.chars().filter(|c| c.is_digit(10)).count() < context.min_digits {
-        return Err(Error::new("Password must contain at least 1 digit"));
+    if let Some(username) = username {
+        if get_user_by_username(conn, username).await.is_ok() {
+            report.append(Path::new("username"), Error::new("Username already exists"));
+        }
     }
-    // Special character check
-    if value.chars().filter(|c| !c.is_alphanumeric()).count() < context.min_special_chars {
-        return Err(Error::new("Password must contain at least 1 special character"));
+    match report.is_empty() {
+        true => Ok(()),
+        false => Err(report),
     }
-    Ok(())
+}
+
+pub(crate) fn validate_password_policy(value: &str, context: &PasswordContext) -> garde::Result {
+    context.policy.check(value, context.username.as_deref()).map_err(Error::new)
 }
 
 #[derive(Debug, Deserialize, ToSchema)]
@@ -89,12 +87,18 @@ pub struct ResetPasswordRequestPostModel {
 }
 
 #[derive(Debug, Deserialize, Validate, ToSchema)]
+#[garde(context(PasswordContext))]
 pub struct ResetPasswordPostModel {
-    #[garde(length(min = 8, max = 64))]
     #[garde(custom(validate_password_policy))]
     pub password: String,
 }
 
+/// Password confirming the deletion of an account, required when the account has one.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DeleteAccountPostModel {
+    pub password: Option<String>,
+}
+
 #[derive(Debug, Deserialize, Validate, ToSchema)]
 pub struct NewPromotionPostModel {
     #[garde(length(max = 255))]
@@ -113,12 +117,27 @@ pub struct NewProjectPostModel {
     pub name: String,
     #[garde(skip)]
     pub description: Option<String>,
+    /// Date and time in the timezone of the project
     #[garde(skip)]
     pub start_date: Option<NaiveDateTime>,
+    /// Date and time in the timezone of the project
     #[garde(skip)]
     pub end_date: NaiveDateTime,
     #[garde(skip)]
     pub notation_period_duration: Option<i32>,
+    /// IANA name of the timezone, the one of the teacher or organization being used if not provided
+    #[garde(custom(validate_timezone))]
+    pub timezone: Option<String>,
+    /// Whether the project is created as a draft, which the scheduler ignores until it's scheduled
+    #[garde(skip)]
+    pub draft: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct ReopenProjectPostModel {
+    /// Number of days the evaluation stays open, from today
+    #[garde(range(min = 1, max = 365))]
+    pub days: i32,
 }
 
 #[derive(Debug, Deserialize, Validate, ToSchema)]
@@ -159,4 +178,61 @@ pub struct ValidateMFACodePostModel {
     #[garde(length(min = 6, max = 6))]
     #[garde(pattern(r"^[0-9]{6}$"))]
     pub code: String,
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct NewPromotionMemberPostModel {
+    #[garde(email)]
+    pub email: String,
+    #[garde(skip)]
+    pub role: PromotionRole,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TransferPromotionOwnershipPostModel {
+    pub user_id: Uuid,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct NewOrganizationPostModel {
+    #[garde(length(min = 1, max = 255))]
+    pub name: String,
+    #[garde(length(min = 2, max = 64))]
+    #[garde(pattern(SLUG_REGEX))]
+    pub slug: String,
+    #[garde(skip)]
+    pub register: Option<bool>,
+    #[garde(skip)]
+    pub authorized_domains: Option<Vec<String>>,
+    #[garde(skip)]
+    pub mail_sender: Option<String>,
+}
+
+static SLUG_REGEX: SyncLazy<Regex> = SyncLazy::new(|| {
+    Regex::new(r#"^[a-z0-9-]+$"#).unwrap()
+});
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct ApiKeyPostModel {
+    #[garde(length(min = 1, max = 64))]
+    pub name: String,
+    #[garde(length(min = 1))]
+    pub scopes: Vec<ApiKeyScope>,
+    /// The key never expires if not provided
+    #[garde(custom(validate_future_date))]
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+pub(crate) fn validate_timezone(value: &Option<String>, _: &()) -> garde::Result {
+    match value {
+        Some(timezone) if parse_timezone(timezone).is_none() => Err(Error::new("Unknown timezone")),
+        _ => Ok(()),
+    }
+}
+
+fn validate_future_date(value: &Option<NaiveDateTime>, _: &()) -> garde::Result {
+    match value {
+        Some(date) if *date <= chrono::Utc::now().naive_utc() => Err(Error::new("Expiration date must be in the future")),
+        _ => Ok(()),
+    }
+}
@@ -1,5 +1,9 @@
-use chrono::NaiveDateTime;
-use garde::Validate;
+use crate::models::post_models::{validate_password_policy, validate_timezone, PasswordContext, USERNAME_REGEX};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use domain::models::promotions_members::PromotionRole;
+use domain::models::reminder_policies::{ReminderOffset, ReminderRecipients};
+use domain::models::users::OrganizationRole;
+use garde::{Error, Validate};
 use serde::Deserialize;
 use utoipa::ToSchema;
 
@@ -21,12 +25,62 @@ pub struct UpdatedProjectPutModel {
     pub name: Option<String>,
     #[garde(skip)]
     pub description: Option<String>,
+    /// Date and time in the timezone of the project
     #[garde(skip)]
     pub start_date: Option<NaiveDateTime>,
+    /// Date and time in the timezone of the project
     #[garde(skip)]
     pub end_date: Option<NaiveDateTime>,
     #[garde(skip)]
     pub notation_period_duration: Option<i32>,
+    /// IANA name of the timezone, such as `Europe/Paris`
+    #[garde(custom(validate_timezone))]
+    pub timezone: Option<String>,
+}
+
+/// Longest offset of a reminder, a year
+const MAX_REMINDER_HOURS: i32 = 365 * 24;
+
+/// Reminders of a project, replacing the alerts of the configuration of its teacher.
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct ReminderPolicyPutModel {
+    #[garde(length(max = 20), custom(validate_reminder_offsets))]
+    pub offsets: Vec<ReminderOffset>,
+    #[garde(skip)]
+    pub recipients: ReminderRecipients,
+    /// Whether the teachers are told which students still haven't evaluated at the last reminder
+    #[garde(skip)]
+    pub escalate_to_teacher: bool,
+}
+
+fn validate_reminder_offsets(value: &[ReminderOffset], _: &()) -> garde::Result {
+    match value.iter().any(|offset| !(0..=MAX_REMINDER_HOURS).contains(&offset.hours)) {
+        true => Err(Error::new("The offsets of the reminders must be between 0 and 8760 hours")),
+        false => Ok(()),
+    }
+}
+
+/// Later deadline granted to a student, replacing theirs if they already have one.
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct StudentExtensionPutModel {
+    #[garde(skip)]
+    pub extended_until: DateTime<Utc>,
+    #[garde(length(max = 255))]
+    pub reason: Option<String>,
+}
+
+/// Longest time the late submissions are accepted after the deadline, a month
+const MAX_LATE_HOURS: i32 = 31 * 24;
+
+/// Submissions accepted after the deadline of the students, flagged as late.
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct LatePolicyPutModel {
+    /// Hours the submissions are accepted after the deadline
+    #[garde(range(min = 1, max = MAX_LATE_HOURS))]
+    pub late_hours: i32,
+    /// Percentage removed from the marks of the students who submitted late
+    #[garde(range(min = 0.0, max = 100.0))]
+    pub penalty_percent: f64,
 }
 
 #[derive(Debug, Deserialize, Validate, ToSchema)]
@@ -49,4 +103,52 @@ pub struct UpdatedGroupPutModel {
     pub name: Option<String>,
     #[garde(skip)]
     pub mark: Option<f64>,
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdatedPromotionMemberPutModel {
+    pub role: PromotionRole,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct UpdatedOrganizationPutModel {
+    #[garde(length(min = 1, max = 255))]
+    pub name: Option<String>,
+    #[garde(skip)]
+    pub register: Option<bool>,
+    #[garde(skip)]
+    pub authorized_domains: Option<Vec<String>>,
+    #[garde(url)]
+    pub logo_url: Option<String>,
+    #[garde(length(max = 16))]
+    pub primary_color: Option<String>,
+    #[garde(length(max = 255))]
+    pub mail_sender: Option<String>,
+    /// IANA name of the timezone of the projects, such as `Europe/Paris`
+    #[garde(custom(validate_timezone))]
+    pub timezone: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdatedOrganizationUserPutModel {
+    pub organization_role: OrganizationRole,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct UpdatedMePutModel {
+    #[garde(length(min = 3, max = 20))]
+    #[garde(pattern(USERNAME_REGEX))]
+    pub username: Option<String>,
+    /// The new address is only applied once verified
+    #[garde(email)]
+    pub email: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[garde(context(PasswordContext))]
+pub struct UpdatedPasswordPutModel {
+    #[garde(skip)]
+    pub current_password: String,
+    #[garde(custom(validate_password_policy))]
+    pub new_password: String,
+}
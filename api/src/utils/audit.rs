@@ -0,0 +1,11 @@
+use actix_web::{HttpMessage, HttpRequest};
+use application::audit::log::AuditContext;
+use domain::models::users::User;
+
+/// Builds the audit context of a request from the authenticated user and the client address.
+pub fn get_audit_context(req: &HttpRequest) -> AuditContext {
+    AuditContext {
+        actor_id: req.extensions().get::<User>().map(|user| user.id),
+        ip: req.connection_info().realip_remote_addr().map(|ip| ip.to_string()),
+    }
+}
@@ -0,0 +1,210 @@
+//! Number of queries and duration of the loaders of a project, for projects of growing size.
+//!
+//! The loaders query a whole project at once, so their number of queries must not depend on the
+//! number of groups and students. The benchmark fails if it does:
+//!
+//! ```sh
+//! cd application
+//! cargo bench --bench project_queries
+//! ```
+
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use application::database::groups::{create_group, create_group_students, get_group_student_mark_details, get_groups_and_students_from_project_id, get_groups_page_and_students_from_project_id, get_students_without_group, update_group};
+use application::database::marks::create_mark;
+use application::database::organizations::create_organization;
+use application::database::pagination::{GroupSortField, ListParams};
+use application::database::projects::create_project;
+use application::database::promotions::create_promotion;
+use application::database::students::{create_promotion_students, create_student};
+use application::database::users::create_user;
+use application::marks::handler::handle_project_rating;
+use chrono::NaiveDate;
+use diesel::connection::{set_default_instrumentation, Instrumentation, InstrumentationEvent};
+use diesel_async::pooled_connection::deadpool::Pool;
+use diesel_async::pooled_connection::{AsyncDieselConnectionManager, ManagerConfig, RecyclingMethod};
+use diesel_async::AsyncPgConnection;
+use domain::models::groups::{NewGroup, NewGroupStudent, UpdatedGroup};
+use domain::models::marks::NewMark;
+use domain::models::organizations::NewOrganization;
+use domain::models::projects::NewProject;
+use domain::models::promotions::NewPromotion;
+use domain::models::students::{NewPromotionStudent, NewStudent};
+use domain::models::users::NewUser;
+use infrastructure::repositories::PgRepositories;
+use infrastructure::DBPool;
+use uuid::Uuid;
+
+/// Number of students of the benchmarked projects
+const PROJECT_SIZES: &[usize] = &[10, 50, 250];
+const GROUP_SIZE: usize = 5;
+const ITERATIONS: u32 = 10;
+
+static QUERIES: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts the queries run by every connection.
+struct QueryCounter;
+
+impl Instrumentation for QueryCounter {
+    fn on_connection_event(&mut self, event: InstrumentationEvent<'_>) {
+        if let InstrumentationEvent::StartQuery { .. } = event {
+            QUERIES.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// A project whose students are split in groups, each student having been marked by the others.
+struct SeededProject {
+    organization_id: Uuid,
+    project_id: Uuid,
+    group_id: Uuid,
+    student_id: Uuid,
+}
+
+async fn seed_project(conn: &DBPool, students_count: usize) -> SeededProject {
+    let random = Uuid::new_v4();
+    let organization_id = create_organization(conn, NewOrganization {
+        name: "bench".to_string(),
+        slug: format!("bench-{}", random),
+        register: None,
+        authorized_domains: None,
+        logo_url: None,
+        primary_color: None,
+        mail_sender: None,
+    }).await.unwrap();
+    let teacher_id = create_user(conn, NewUser {
+        username: format!("bench-{}", random),
+        email: format!("bench-{}", random),
+        organization_id,
+    }).await.unwrap();
+    let promotion_id = create_promotion(conn, NewPromotion {
+        title: "bench".to_string(),
+        start_year: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+        end_year: NaiveDate::from_ymd_opt(2024, 12, 31).unwrap(),
+        teacher_id,
+        organization_id,
+    }).await.unwrap();
+    let project_id = create_project(conn, NewProject {
+        name: format!("bench-{}", random),
+        description: None,
+        start_date: None,
+        end_date: Default::default(),
+        notation_period_duration: None,
+        promotion_id,
+        state: None,
+        timezone: None,
+    }).await.unwrap();
+
+    let mut students_ids = Vec::new();
+    for index in 0..students_count {
+        let student_id = create_student(conn, NewStudent {
+            name: format!("student-{}", index),
+            surname: "bench".to_string(),
+            email: format!("student-{}@example.com", index),
+            organization_id,
+        }).await.unwrap();
+        students_ids.push(student_id);
+    }
+    create_promotion_students(conn, students_ids.iter()
+        .map(|&student_id| NewPromotionStudent { promotion_id, student_id })
+        .collect()).await.unwrap();
+
+    let mut groups_ids = Vec::new();
+    // The last student stays without group
+    for (index, members) in students_ids[1..].chunks(GROUP_SIZE).enumerate() {
+        let group_id = create_group(conn, organization_id, NewGroup {
+            name: format!("group-{}", index),
+            project_id,
+            max_mark: None,
+        }).await.unwrap();
+        update_group(conn, organization_id, group_id, UpdatedGroup { name: None, mark: Some(15.0), max_mark: None }).await.unwrap();
+        create_group_students(conn, members.iter()
+            .map(|&student_id| NewGroupStudent { group_id, student_id })
+            .collect()).await.unwrap();
+
+        for (noted_index, &noted_student_id) in members.iter().enumerate() {
+            for &grader_student_id in members.iter().filter(|&&grader| grader != noted_student_id) {
+                create_mark(conn, organization_id, NewMark {
+                    project_id,
+                    group_id,
+                    noted_student_id,
+                    grader_student_id,
+                    mark: 10.0 + noted_index as f64,
+                    max_mark: None,
+                    comment: None,
+                }).await.unwrap();
+            }
+        }
+        groups_ids.push(group_id);
+    }
+
+    SeededProject {
+        organization_id,
+        project_id,
+        group_id: groups_ids[0],
+        student_id: students_ids[1],
+    }
+}
+
+/// Runs the loader once to count its queries, then several times to time it.
+async fn measure<F, Fut, T, E>(loader: F) -> (usize, Duration)
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: std::fmt::Debug,
+{
+    let before = QUERIES.load(Ordering::Relaxed);
+    loader().await.unwrap();
+    let queries = QUERIES.load(Ordering::Relaxed) - before;
+
+    let started_at = Instant::now();
+    for _ in 0..ITERATIONS {
+        loader().await.unwrap();
+    }
+
+    (queries, started_at.elapsed() / ITERATIONS)
+}
+
+#[tokio::main]
+async fn main() {
+    let database_url = dotenvy::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    set_default_instrumentation(|| Some(Box::new(QueryCounter))).unwrap();
+
+    // A single connection, which isn't checked when it's reused, so only the queries of the loaders are counted
+    let mut config = ManagerConfig::default();
+    config.recycling_method = RecyclingMethod::Fast;
+    let manager = AsyncDieselConnectionManager::<AsyncPgConnection>::new_with_config(database_url, config);
+    let conn = Pool::builder(manager).max_size(1).build().unwrap();
+    let repos = PgRepositories::new(conn.clone());
+    let params = ListParams::<GroupSortField>::default();
+
+    let mut counts: Vec<(&str, Vec<usize>)> = Vec::new();
+    for &students_count in PROJECT_SIZES {
+        let project = seed_project(&conn, students_count).await;
+
+        let results = [
+            ("groups and students", measure(|| get_groups_and_students_from_project_id(&conn, project.organization_id, project.project_id)).await),
+            ("groups page and students", measure(|| get_groups_page_and_students_from_project_id(&conn, project.organization_id, project.project_id, &params)).await),
+            ("student mark details", measure(|| get_group_student_mark_details(&conn, project.organization_id, project.group_id, project.student_id)).await),
+            ("students without group", measure(|| get_students_without_group(&conn, project.organization_id, project.project_id)).await),
+            ("project rating", measure(|| handle_project_rating(&repos, project.project_id)).await),
+        ];
+
+        for (index, (name, (queries, duration))) in results.into_iter().enumerate() {
+            println!("{:>4} students  {:<26} {:>3} queries  {:>10.3?}", students_count, name, queries, duration);
+            match counts.get_mut(index) {
+                Some((_, loader_counts)) => loader_counts.push(queries),
+                None => counts.push((name, vec![queries])),
+            }
+        }
+    }
+
+    for (name, loader_counts) in counts {
+        assert!(
+            loader_counts.windows(2).all(|pair| pair[0] == pair[1]),
+            "the number of queries of \"{}\" depends on the size of the project: {:?}", name, loader_counts
+        );
+    }
+}
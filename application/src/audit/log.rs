@@ -0,0 +1,104 @@
+use crate::database::audit_logs::{create_audit_log, delete_audit_logs_older_than};
+use chrono::{Duration, Utc};
+use domain::models::audit_logs::{AuditAction, NewAuditLog};
+use infrastructure::DBPool;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+/// Who performed an audited action and from where.
+#[derive(Debug, Clone, Default)]
+pub struct AuditContext {
+    pub actor_id: Option<Uuid>,
+    pub ip: Option<String>,
+}
+
+/// Describes the audited resource and its state around the action.
+pub struct AuditEvent<'a, T: Serialize> {
+    pub action: AuditAction,
+    pub resource_type: &'a str,
+    pub resource_id: Option<String>,
+    pub before: Option<&'a T>,
+    pub after: Option<&'a T>,
+}
+
+/// Appends an entry to the audit log.
+///
+/// Only the fields that changed between `before` and `after` are stored. A failure is logged and
+/// doesn't fail the audited action, which has already been applied.
+pub async fn record_audit_log<T: Serialize>(conn: &DBPool, context: &AuditContext, event: AuditEvent<'_, T>) {
+    let before = event.before.and_then(|value| serde_json::to_value(value).ok());
+    let after = event.after.and_then(|value| serde_json::to_value(value).ok());
+    let (before, after) = diff_values(before, after);
+
+    let new_audit_log = NewAuditLog {
+        actor_id: context.actor_id,
+        action: event.action,
+        resource_type: event.resource_type.to_string(),
+        resource_id: event.resource_id,
+        before,
+        after,
+        ip: context.ip.clone(),
+    };
+
+    if let Err(e) = create_audit_log(conn, new_audit_log).await {
+        tracing::error!(error = %e, "failed to write the audit log");
+    }
+}
+
+/// Deletes the entries older than the retention period.
+pub async fn purge_expired_audit_logs(conn: &DBPool, retention_days: i32) {
+    let limit = Utc::now().naive_utc() - Duration::days(retention_days as i64);
+    if let Err(e) = delete_audit_logs_older_than(conn, limit).await {
+        tracing::error!(error = %e, "failed to purge the audit logs");
+    }
+}
+
+/// Keeps only the keys whose value differs when both sides are JSON objects.
+fn diff_values(before: Option<Value>, after: Option<Value>) -> (Option<Value>, Option<Value>) {
+    match (before, after) {
+        (Some(Value::Object(before)), Some(Value::Object(after))) => {
+            let mut before_diff = Map::new();
+            let mut after_diff = Map::new();
+
+            for (key, value) in &before {
+                if after.get(key) != Some(value) {
+                    before_diff.insert(key.clone(), value.clone());
+                }
+            }
+            for (key, value) in &after {
+                if before.get(key) != Some(value) {
+                    after_diff.insert(key.clone(), value.clone());
+                }
+            }
+
+            (Some(Value::Object(before_diff)), Some(Value::Object(after_diff)))
+        }
+        (before, after) => (before, after),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diff_values() {
+        let before = json!({ "name": "group", "mark": 12.0 });
+        let after = json!({ "name": "group", "mark": 15.0 });
+
+        let (before, after) = diff_values(Some(before), Some(after));
+        assert_eq!(before, Some(json!({ "mark": 12.0 })));
+        assert_eq!(after, Some(json!({ "mark": 15.0 })));
+    }
+
+    #[test]
+    fn test_diff_values_without_after() {
+        let before = json!({ "name": "student" });
+
+        let (before, after) = diff_values(Some(before.clone()), None);
+        assert_eq!(before, Some(json!({ "name": "student" })));
+        assert_eq!(after, None);
+    }
+}
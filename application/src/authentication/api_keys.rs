@@ -0,0 +1,47 @@
+use crate::authentication::oidc::generate_random_string;
+use sha2::{Digest, Sha256};
+
+/// Prefix of every API key, used to tell them apart from JWTs.
+pub const API_KEY_PREFIX: &str = "bbx_";
+
+pub struct GeneratedApiKey {
+    /// Full key, only shown once to the user
+    pub key: String,
+    /// Beginning of the key, stored to help users recognize their keys
+    pub prefix: String,
+    pub key_hash: String,
+}
+
+pub fn is_api_key(token: &str) -> bool {
+    token.starts_with(API_KEY_PREFIX)
+}
+
+/// Keys are long random strings, so a fast hash is enough and allows looking them up.
+pub fn hash_api_key(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
+
+pub fn generate_api_key() -> GeneratedApiKey {
+    let key = format!("{}{}", API_KEY_PREFIX, generate_random_string(40));
+    GeneratedApiKey {
+        prefix: key[..API_KEY_PREFIX.len() + 8].to_string(),
+        key_hash: hash_api_key(&key),
+        key,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_api_key() {
+        let api_key = generate_api_key();
+
+        assert!(is_api_key(&api_key.key));
+        assert!(api_key.key.starts_with(&api_key.prefix));
+        assert_eq!(api_key.key_hash, hash_api_key(&api_key.key));
+        assert_eq!(api_key.key_hash.len(), 64);
+        assert_ne!(generate_api_key().key, api_key.key);
+    }
+}
@@ -0,0 +1,184 @@
+use crate::authentication::tokens::{decode_token, encode_token};
+use crate::database::tokens::{consume_token, create_token, invalidate_user_tokens};
+use crate::database::transaction::{transaction, ScopedFutureExt};
+use crate::database::users::{get_user_by_email_in_any_organization, update_user};
+use crate::mail::send::{build_mail, get_organization_mail_sender, send_mail, MailKind, MailProps};
+use chrono::{Duration, Utc};
+use domain::models::tokens::{NewToken, TokenType};
+use domain::models::users::{UpdatedUser, User};
+use infrastructure::DBPool;
+use shared::app_config::Config;
+use shared::app_state_model::AppState;
+use shared::error_models::{APIError, DBError, UnauthorizedError, UserError, ValidationError};
+use shared::token_models::EmailVerificationClaims;
+use uuid::Uuid;
+
+const EMAIL_VERIFICATION_EXPIRES_IN_HOURS: i64 = 24;
+
+/// Sends a verification link to the new email address of a user.
+///
+/// The address is only changed once the link has been followed.
+pub async fn request_email_verification(app_state: &AppState, user: &User, new_email: String) -> Result<(), APIError> {
+    let config = {
+        let config_guard = app_state.config.read();
+        config_guard.clone()
+    };
+
+    let now = Utc::now();
+    let expiration = now + Duration::hours(EMAIL_VERIFICATION_EXPIRES_IN_HOURS);
+    let verification_claims = EmailVerificationClaims {
+        sub: user.id,
+        type_: TokenType::EmailVerification,
+        email: new_email.clone(),
+        iat: now.timestamp() as usize,
+        exp: expiration.timestamp() as usize,
+    };
+    let token = encode_token::<EmailVerificationClaims>(&verification_claims, &config)?;
+
+    // Links sent to a previous new address stop working
+    invalidate_user_tokens(&app_state.database_pool, user.id, TokenType::EmailVerification).await?;
+    let token_id = create_token(app_state.database_pool.as_ref(), NewToken {
+        token,
+        type_: TokenType::EmailVerification,
+        user_id: user.id,
+        exp: expiration.naive_utc(),
+    }).await?;
+
+    let url = format!("{}/api/email/verify?id={}", config.url_config.api_url, token_id);
+    let email = build_mail(MailProps {
+        from: get_organization_mail_sender(&app_state.database_pool, user.organization_id).await,
+        to: format!("{} <{}>", user.username, new_email),
+        subject: "Email address verification".to_string(),
+        body: format!("Here's the link you have to click in order to confirm your new email address :\n{}", url),
+    });
+    send_mail(&app_state.smtp_transport, email, MailKind::EmailVerification).await
+}
+
+/// Applies the email address of a verification token, which can only be used once.
+pub async fn confirm_email_verification(conn: &DBPool, config: &Config, token_id: Uuid) -> Result<EmailVerificationClaims, APIError> {
+    // The token is only used once the address is applied
+    transaction(conn, |conn| async move {
+        let token = consume_token(&mut *conn, token_id, TokenType::EmailVerification)
+            .await.map_err(|_| APIError::UserError(UserError::Unauthorized(UnauthorizedError)))?;
+        let claims = decode_token::<EmailVerificationClaims>(&token.token, config)?;
+
+        // The address may have been taken since the link was sent
+        match get_user_by_email_in_any_organization(&mut *conn, &claims.email).await {
+            Ok(other_user) if other_user.id != claims.sub => return Err(APIError::UserError(UserError::ValidationError(ValidationError::new("email", "Email already exists")))),
+            Ok(_) | Err(DBError::NotFound) => (),
+            Err(err) => return Err(APIError::from(err)),
+        }
+
+        update_user(&mut *conn, claims.sub, UpdatedUser {
+            username: None,
+            email: Some(claims.email.clone()),
+            has_validated_email: Some(true),
+            role: None,
+            token_version: None,
+            organization_role: None,
+        }).await?;
+        Ok(claims)
+    }.scope_boxed()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::users::get_user_by_id;
+    use crate::database::users::tests::test_create_user;
+    use crate::database::config::{create_main_config, get_main_config};
+    use domain::models::config::NewMainConfig;
+    use shared::app_config::{JwtConfig, MetricsConfig, SchedulerConfig, ServerConfig, SmtpConfig, UrlConfig};
+    use infrastructure::{init_pool, PoolSettings};
+
+    struct TestContext {
+        conn: DBPool,
+    }
+
+    impl TestContext {
+        fn new() -> Self {
+            TestContext {
+                conn: init_pool(
+                    dotenvy::var("DATABASE_URL")
+                        .expect("DATABASE_URL must be set")
+                        .as_str()
+                ),
+            }
+        }
+    }
+
+    async fn test_config(conn: &DBPool) -> Config {
+        let main_config = match get_main_config(conn).await {
+            Ok(main_config) => main_config,
+            Err(_) => {
+                create_main_config(conn, NewMainConfig {
+                    register: None,
+                    authorized_domains: None,
+                }).await.unwrap();
+                get_main_config(conn).await.unwrap()
+            }
+        };
+
+        Config {
+            database_url: String::new(),
+            database_pool: PoolSettings::default(),
+            jwt_config: JwtConfig {
+                secret: "test-secret".to_string(),
+                expires_in: Duration::hours(1),
+                password_reset_expires_in: Duration::hours(1),
+            },
+            smtp_config: SmtpConfig {
+                host: String::new(),
+                port: 0,
+                username: String::new(),
+                password: String::new(),
+                readiness_check: false,
+            },
+            url_config: UrlConfig {
+                web_url: String::new(),
+                api_url: String::new(),
+            },
+            oidc_config: None,
+            metrics_config: MetricsConfig::default(),
+            server_config: ServerConfig::default(),
+            scheduler_config: SchedulerConfig::default(),
+            main_config,
+        }
+    }
+
+    async fn test_create_verification_token(conn: &DBPool, config: &Config, user_id: Uuid, email: &str) -> Uuid {
+        let now = Utc::now();
+        let claims = EmailVerificationClaims {
+            sub: user_id,
+            type_: TokenType::EmailVerification,
+            email: email.to_string(),
+            iat: now.timestamp() as usize,
+            exp: (now + Duration::hours(1)).timestamp() as usize,
+        };
+
+        create_token(conn, NewToken {
+            token: encode_token(&claims, config).unwrap(),
+            type_: TokenType::EmailVerification,
+            user_id,
+            exp: (now + Duration::hours(1)).naive_utc(),
+        }).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_confirm_email_verification() {
+        let context = TestContext::new();
+        let config = test_config(&context.conn).await;
+
+        let user_id = test_create_user().await;
+        let email = format!("verified-{}@example.com", user_id);
+        let token_id = test_create_verification_token(&context.conn, &config, user_id, &email).await;
+
+        confirm_email_verification(&context.conn, &config, token_id).await.unwrap();
+        let user = get_user_by_id(&context.conn, user_id).await.unwrap();
+        assert_eq!(user.email, email);
+        assert!(user.has_validated_email);
+
+        // The link can't be used twice
+        assert!(confirm_email_verification(&context.conn, &config, token_id).await.is_err());
+    }
+}
@@ -1,3 +1,7 @@
 pub mod password_reset;
+pub mod passwords;
 pub mod tokens;
-pub mod codes;
\ No newline at end of file
+pub mod codes;
+pub mod oidc;
+pub mod api_keys;
+pub mod email_verification;
\ No newline at end of file
@@ -0,0 +1,409 @@
+use crate::authentication::codes::generate_random_code;
+use crate::authorization::organizations::check_email_domain;
+use crate::database::config::create_user_config;
+use crate::database::organizations::get_organization_by_id;
+use crate::database::transaction::{transaction, Executor, ScopedFutureExt};
+use crate::database::users::{create_user, get_user_by_email, get_user_by_id, get_user_by_username, update_user};
+use crate::database::users_identities::{create_user_identity, get_user_identity};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use domain::models::config::{Alert, NewUserConfig};
+use domain::models::organizations::Organization;
+use domain::models::users::{NewUser, UpdatedUser, User};
+use domain::models::users_identities::NewUserIdentity;
+use infrastructure::DBPool;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use reqwest::Url;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use shared::app_config::OidcConfig;
+use shared::error_models::{APIError, DBError, InternalError, ServerError, UnauthorizedError, UserError};
+use uuid::Uuid;
+
+/// Endpoints advertised by the identity provider discovery document.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OidcProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub userinfo_endpoint: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OidcTokenResponse {
+    access_token: String,
+}
+
+/// Claims returned by the userinfo endpoint.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OidcUserInfo {
+    pub sub: String,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub preferred_username: Option<String>,
+}
+
+pub struct PkceChallenge {
+    pub code_verifier: String,
+    pub code_challenge: String,
+}
+
+/// Random string used for the `state` parameter and the PKCE verifier.
+pub fn generate_random_string(length: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
+
+/// Builds a S256 PKCE challenge (RFC 7636) from the given verifier.
+pub fn build_pkce_challenge(code_verifier: String) -> PkceChallenge {
+    let code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()));
+    PkceChallenge {
+        code_verifier,
+        code_challenge,
+    }
+}
+
+pub fn generate_pkce_challenge() -> PkceChallenge {
+    build_pkce_challenge(generate_random_string(64))
+}
+
+fn internal_error() -> APIError {
+    APIError::ServerError(ServerError::InternalError(InternalError))
+}
+
+fn unauthorized_error() -> APIError {
+    APIError::UserError(UserError::Unauthorized(UnauthorizedError))
+}
+
+/// Fetches the discovery document of the configured issuer.
+pub async fn discover_provider(oidc_config: &OidcConfig) -> Result<OidcProviderMetadata, APIError> {
+    let issuer_url = oidc_config.issuer_url.trim_end_matches('/');
+    let url = format!("{}/.well-known/openid-configuration", issuer_url);
+
+    let metadata = reqwest::get(url).await
+        .and_then(|response| response.error_for_status())
+        .map_err(|_| internal_error())?
+        .json::<OidcProviderMetadata>().await
+        .map_err(|_| internal_error())?;
+
+    // The discovery document must describe the configured issuer
+    if metadata.issuer.trim_end_matches('/') != issuer_url {
+        return Err(internal_error());
+    }
+
+    Ok(metadata)
+}
+
+pub fn build_authorization_url(metadata: &OidcProviderMetadata, oidc_config: &OidcConfig, state: &str, pkce_challenge: &PkceChallenge) -> Result<String, APIError> {
+    let url = Url::parse_with_params(&metadata.authorization_endpoint, &[
+        ("response_type", "code"),
+        ("client_id", oidc_config.client_id.as_str()),
+        ("redirect_uri", oidc_config.redirect_url.as_str()),
+        ("scope", oidc_config.scopes.as_str()),
+        ("state", state),
+        ("code_challenge", pkce_challenge.code_challenge.as_str()),
+        ("code_challenge_method", "S256"),
+    ]).map_err(|_| internal_error())?;
+
+    Ok(url.to_string())
+}
+
+/// Exchanges the authorization code for an access token and returns the user claims.
+///
+/// Rejected codes or verifiers are reported as unauthorized.
+pub async fn exchange_code(metadata: &OidcProviderMetadata, oidc_config: &OidcConfig, code: &str, code_verifier: &str) -> Result<OidcUserInfo, APIError> {
+    let client = reqwest::Client::new();
+
+    let token = client.post(&metadata.token_endpoint)
+        .basic_auth(&oidc_config.client_id, Some(&oidc_config.client_secret))
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", oidc_config.redirect_url.as_str()),
+            ("code_verifier", code_verifier),
+        ])
+        .send().await
+        .map_err(|_| internal_error())?
+        .error_for_status()
+        .map_err(|_| unauthorized_error())?
+        .json::<OidcTokenResponse>().await
+        .map_err(|_| unauthorized_error())?;
+
+    client.get(&metadata.userinfo_endpoint)
+        .bearer_auth(token.access_token)
+        .send().await
+        .map_err(|_| internal_error())?
+        .error_for_status()
+        .map_err(|_| unauthorized_error())?
+        .json::<OidcUserInfo>().await
+        .map_err(|_| unauthorized_error())
+}
+
+/// Finds the user matching an identity provider account.
+///
+/// Known identities log in directly. Otherwise the account is linked to the user of the organization with the
+/// same verified email, or a new user is provisioned if the email domain is authorized by the organization.
+pub async fn resolve_oidc_user(conn: &DBPool, organization_id: Uuid, issuer: &str, user_info: OidcUserInfo) -> Result<User, APIError> {
+    match get_user_identity(conn, issuer, &user_info.sub).await {
+        Ok(identity) => return Ok(get_user_by_id(conn, identity.user_id).await?),
+        Err(DBError::NotFound) => {},
+        Err(err) => return Err(APIError::from(err)),
+    }
+
+    // Only emails verified by the identity provider can be trusted to link or create accounts
+    let email = match (user_info.email, user_info.email_verified) {
+        (Some(email), Some(true)) => email,
+        _ => return Err(unauthorized_error()),
+    };
+
+    let organization = get_organization_by_id(conn, organization_id).await?;
+
+    // An account is only created along with the identity linked to it
+    transaction(conn, |conn| async move {
+        let user = match get_user_by_email(&mut *conn, organization_id, &email).await {
+            Ok(user) => user,
+            Err(DBError::NotFound) => provision_oidc_user(&mut *conn, &organization, &email, user_info.preferred_username).await?,
+            Err(err) => return Err(APIError::from(err)),
+        };
+
+        create_user_identity(&mut *conn, NewUserIdentity {
+            user_id: user.id,
+            issuer: issuer.to_string(),
+            subject: user_info.sub,
+        }).await?;
+
+        Ok(user)
+    }.scope_boxed()).await
+}
+
+async fn provision_oidc_user(conn: impl Executor, organization: &Organization, email: &str, preferred_username: Option<String>) -> Result<User, APIError> {
+    // Check if email domain is part of the domains' whitelist
+    check_email_domain(organization, email)?;
+
+    // Usernames are used to log in, so they have to stay unique and must not look like an email
+    let mut username = preferred_username
+        .filter(|username| !username.is_empty() && !username.contains('@'))
+        .unwrap_or(email.split('@').next().unwrap_or_default().to_string());
+
+    transaction(conn, |conn| async move {
+        if get_user_by_username(&mut *conn, &username).await.is_ok() {
+            username = format!("{}-{}", username, generate_random_code(6));
+        }
+
+        let user_id = create_user(&mut *conn, NewUser {
+            username: username.clone(),
+            email: email.to_string(),
+            organization_id: organization.id,
+        }).await?;
+        update_user(&mut *conn, user_id, UpdatedUser {
+            username: None,
+            email: None,
+            has_validated_email: Some(true),
+            role: None,
+            token_version: None,
+            organization_role: None,
+        }).await?;
+
+        // Init user config
+        create_user_config(&mut *conn, NewUserConfig {
+            user_id,
+            alerts: Some(vec![
+                Alert {
+                    before_event: false,
+                    hours: 24,
+                },
+                Alert {
+                    before_event: true,
+                    hours: 24,
+                },
+            ]),
+        }).await?;
+
+        Ok(get_user_by_username(&mut *conn, &username).await?)
+    }.scope_boxed()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::organizations::tests::test_create_organization;
+    use crate::database::organizations::update_organization;
+    use crate::database::users::tests::test_create_user_in_organization;
+    use domain::models::organizations::UpdatedOrganization;
+    use infrastructure::init_pool;
+    use wiremock::matchers::{body_string_contains, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    struct TestContext {
+        conn: DBPool,
+    }
+
+    impl TestContext {
+        fn new() -> Self {
+            TestContext {
+                conn: init_pool(
+                    dotenvy::var("DATABASE_URL")
+                        .expect("DATABASE_URL must be set")
+                        .as_str()
+                ),
+            }
+        }
+    }
+
+    fn test_oidc_config(issuer_url: &str) -> OidcConfig {
+        OidcConfig {
+            issuer_url: issuer_url.to_string(),
+            client_id: "bitbox".to_string(),
+            client_secret: "secret".to_string(),
+            redirect_url: "http://localhost:8080/api/auth/oidc/callback".to_string(),
+            post_login_redirect_url: "/".to_string(),
+            scopes: "openid email".to_string(),
+        }
+    }
+
+    fn test_user_info(email: &str, email_verified: bool) -> OidcUserInfo {
+        OidcUserInfo {
+            sub: Uuid::new_v4().to_string(),
+            email: Some(email.to_string()),
+            email_verified: Some(email_verified),
+            preferred_username: None,
+        }
+    }
+
+    /// Starts a local identity provider accepting the given code and verifier.
+    async fn test_start_mock_idp(code: &str, code_verifier: &str) -> MockServer {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/.well-known/openid-configuration"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "issuer": server.uri(),
+                "authorization_endpoint": format!("{}/authorize", server.uri()),
+                "token_endpoint": format!("{}/token", server.uri()),
+                "userinfo_endpoint": format!("{}/userinfo", server.uri()),
+            })))
+            .mount(&server).await;
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .and(body_string_contains(format!("code={}", code)))
+            .and(body_string_contains(format!("code_verifier={}", code_verifier)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "access_token": "access-token",
+                "token_type": "Bearer",
+            })))
+            .mount(&server).await;
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .respond_with(ResponseTemplate::new(400))
+            .mount(&server).await;
+        Mock::given(method("GET"))
+            .and(path("/userinfo"))
+            .and(header("authorization", "Bearer access-token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "sub": "teacher-1",
+                "email": "teacher@university.edu",
+                "email_verified": true,
+            })))
+            .mount(&server).await;
+
+        server
+    }
+
+    #[test]
+    fn test_build_pkce_challenge() {
+        // Example of the RFC 7636 appendix B
+        let challenge = build_pkce_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk".to_string());
+        assert_eq!(challenge.code_challenge, "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM");
+    }
+
+    #[tokio::test]
+    async fn test_authorization_code_flow() {
+        let pkce_challenge = generate_pkce_challenge();
+        let server = test_start_mock_idp("code", &pkce_challenge.code_verifier).await;
+        let oidc_config = test_oidc_config(&server.uri());
+
+        let metadata = discover_provider(&oidc_config).await.unwrap();
+        let url = build_authorization_url(&metadata, &oidc_config, "state", &pkce_challenge).unwrap();
+        assert!(url.contains(&format!("code_challenge={}", pkce_challenge.code_challenge)));
+        assert!(url.contains("code_challenge_method=S256"));
+
+        let user_info = exchange_code(&metadata, &oidc_config, "code", &pkce_challenge.code_verifier).await.unwrap();
+        assert_eq!(user_info.sub, "teacher-1");
+        assert_eq!(user_info.email_verified, Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_exchange_code_with_wrong_verifier() {
+        let server = test_start_mock_idp("code", "verifier").await;
+        let oidc_config = test_oidc_config(&server.uri());
+
+        let metadata = discover_provider(&oidc_config).await.unwrap();
+        let result = exchange_code(&metadata, &oidc_config, "code", "another-verifier").await;
+        assert!(matches!(result, Err(APIError::UserError(UserError::Unauthorized(_)))));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_oidc_user_provisioning() {
+        let context = TestContext::new();
+
+        let organization_id = test_create_organization().await;
+        let user_info = test_user_info("teacher@university.edu", true);
+
+        let user = resolve_oidc_user(&context.conn, organization_id, "https://idp", user_info.clone()).await.unwrap();
+        assert_eq!(user.organization_id, organization_id);
+        assert!(user.has_validated_email);
+
+        // The next login uses the linked identity
+        let same_user = resolve_oidc_user(&context.conn, organization_id, "https://idp", user_info).await.unwrap();
+        assert_eq!(same_user.id, user.id);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_oidc_user_linking() {
+        let context = TestContext::new();
+
+        let organization_id = test_create_organization().await;
+        let user_id = test_create_user_in_organization(organization_id).await;
+        let email = get_user_by_id(&context.conn, user_id).await.unwrap().email;
+
+        let user = resolve_oidc_user(&context.conn, organization_id, "https://idp", test_user_info(&email, true)).await.unwrap();
+        assert_eq!(user.id, user_id);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_oidc_user_unverified_email() {
+        let context = TestContext::new();
+
+        let organization_id = test_create_organization().await;
+        let user_id = test_create_user_in_organization(organization_id).await;
+        let email = get_user_by_id(&context.conn, user_id).await.unwrap().email;
+
+        let result = resolve_oidc_user(&context.conn, organization_id, "https://idp", test_user_info(&email, false)).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_oidc_user_unauthorized_domain() {
+        let context = TestContext::new();
+
+        let organization_id = test_create_organization().await;
+        update_organization(&context.conn, organization_id, UpdatedOrganization {
+            name: None,
+            register: None,
+            authorized_domains: Some(vec!["university.edu".to_string()]),
+            logo_url: None,
+            primary_color: None,
+            mail_sender: None,
+            timezone: None,
+        }).await.unwrap();
+
+        let result = resolve_oidc_user(&context.conn, organization_id, "https://idp", test_user_info("teacher@gmail.com", true)).await;
+        assert!(result.is_err());
+        let user = resolve_oidc_user(&context.conn, organization_id, "https://idp", test_user_info("teacher@university.edu", true)).await.unwrap();
+        assert_eq!(user.organization_id, organization_id);
+    }
+}
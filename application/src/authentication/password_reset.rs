@@ -1,57 +1,143 @@
+use crate::authentication::passwords::set_user_password;
 use crate::authentication::tokens::encode_token;
-use crate::database::tokens::create_token;
+use crate::database::tokens::{consume_token, create_token, invalidate_user_tokens};
+use crate::database::transaction::{transaction, ScopedFutureExt};
 use crate::database::users::update_user;
-use crate::mail::send::{build_mail, send_mail, MailProps};
-use diesel::internal::derives::multiconnection::chrono::{Duration, Utc};
+use crate::mail::send::{build_mail, get_organization_mail_sender, send_mail, MailKind, MailProps};
+use chrono::Utc;
 use domain::models::tokens::{NewToken, TokenType};
 use domain::models::users::{UpdatedUser, User};
+use infrastructure::DBPool;
 use shared::app_state_model::AppState;
+use shared::error_models::{APIError, UnauthorizedError, UserError};
 use shared::token_models::SpecificClaims;
+use uuid::Uuid;
 
-pub fn request_password_reset(app_state: &AppState, user: User) {
-    let smtp_transport = app_state.smtp_transport.clone().as_ref().clone();
-
+/// Sends a password reset link to a user.
+///
+/// The previous links of the user stop working, and the new one expires after
+/// `JWT_PASSWORD_RESET_EXPIRES_IN` minutes.
+pub async fn request_password_reset(app_state: &AppState, user: &User) -> Result<(), APIError> {
     let config = {
         let config_guard = app_state.config.read();
         config_guard.clone()
     };
 
-    // Increment the token version to invalidate all existing tokens
-    let updated_user = UpdatedUser {
-        username: None,
-        email: None,
-        has_validated_email: None,
-        role: None,
-        token_version: Some(user.clone().token_version + 1),
-    };
-    update_user(&app_state.database_pool, user.id, updated_user).unwrap();
-
-    // Invalid possible previous tokens ?
+    invalidate_user_tokens(&app_state.database_pool, user.id, TokenType::PassReset).await?;
 
     // Generate new token for password reset
     let now = Utc::now();
-    let expiration = now + Duration::minutes(config.jwt_config.password_reset_expires_in.parse::<i64>().unwrap());
-    let reset_claim = SpecificClaims{
+    let expiration = now + config.jwt_config.password_reset_expires_in;
+    let reset_claim = SpecificClaims {
         sub: user.id,
         type_: TokenType::PassReset,
         iat: now.timestamp() as usize,
         exp: expiration.timestamp() as usize,
     };
-    let token = encode_token::<SpecificClaims>(&reset_claim, &config).unwrap();
+    let token = encode_token::<SpecificClaims>(&reset_claim, &config)?;
 
-    // Add token to database
-    let token_id = create_token(&app_state.database_pool, NewToken{
+    let token_id = create_token(app_state.database_pool.as_ref(), NewToken {
         token,
         type_: TokenType::PassReset,
-    }).unwrap();
+        user_id: user.id,
+        exp: expiration.naive_utc(),
+    }).await?;
 
-    // Send email with token
-    let url = format!("http://127.0.0.1:8080/api/auth/reset-token?id={}", token_id);
-    let email = build_mail(MailProps{
-        from: "Brandy <no-reply@sigma-bot.fr>".to_string(),
-        to: format!("Brandy test <{}>", user.email),
+    // Send email with the link to the reset page of the web app
+    let url = format!("{}/reset-password/{}", config.url_config.web_url, token_id);
+    let email = build_mail(MailProps {
+        from: get_organization_mail_sender(&app_state.database_pool, user.organization_id).await,
+        to: format!("{} <{}>", user.username, user.email),
         subject: "Password reset request".to_string(),
         body: format!("Here's the link you have to click in order to reset your password :\n{}", url),
     });
-    println!("{:?}", send_mail(&smtp_transport, email).unwrap());
-}
\ No newline at end of file
+    send_mail(&app_state.smtp_transport, email, MailKind::PasswordReset).await
+}
+
+/// Changes the password of a user with a reset token, which can only be used once.
+///
+/// All the sessions of the user are invalidated.
+pub async fn reset_password(conn: &DBPool, user: &User, token_id: Uuid, password: &str, history_size: i32) -> Result<(), APIError> {
+    // The token stays usable to retry with another password when the new one is rejected
+    transaction(conn, |conn| async move {
+        consume_token(&mut *conn, token_id, TokenType::PassReset)
+            .await.map_err(|_| APIError::UserError(UserError::Unauthorized(UnauthorizedError)))?;
+
+        set_user_password(&mut *conn, user.id, password, history_size).await?;
+
+        update_user(&mut *conn, user.id, UpdatedUser {
+            username: None,
+            email: None,
+            has_validated_email: None,
+            role: None,
+            token_version: Some(user.token_version + 1),
+            organization_role: None,
+        }).await?;
+
+        Ok(())
+    }.scope_boxed()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use crate::database::tokens::get_token_by_id;
+    use crate::database::users::get_user_by_id;
+    use crate::database::users::tests::test_create_user;
+    use infrastructure::init_pool;
+
+    struct TestContext {
+        conn: DBPool,
+    }
+
+    impl TestContext {
+        fn new() -> Self {
+            TestContext {
+                conn: init_pool(
+                    dotenvy::var("DATABASE_URL")
+                        .expect("DATABASE_URL must be set")
+                        .as_str()
+                ),
+            }
+        }
+    }
+
+    async fn test_create_reset_token(conn: &DBPool, user_id: Uuid) -> Uuid {
+        create_token(conn, NewToken {
+            token: Uuid::new_v4().to_string(),
+            type_: TokenType::PassReset,
+            user_id,
+            exp: (Utc::now() + Duration::hours(1)).naive_utc(),
+        }).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_reset_password() {
+        let context = TestContext::new();
+
+        let user_id = test_create_user().await;
+        let user = get_user_by_id(&context.conn, user_id).await.unwrap();
+        let token_id = test_create_reset_token(&context.conn, user_id).await;
+
+        reset_password(&context.conn, &user, token_id, "First-passw0rd", 2).await.unwrap();
+        assert!(get_token_by_id(&context.conn, token_id).await.unwrap().used);
+        assert_eq!(get_user_by_id(&context.conn, user_id).await.unwrap().token_version, user.token_version + 1);
+
+        // The token can't be used twice
+        assert!(reset_password(&context.conn, &user, token_id, "Second-passw0rd", 2).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reset_password_keeps_token_on_rejected_password() {
+        let context = TestContext::new();
+
+        let user_id = test_create_user().await;
+        let user = get_user_by_id(&context.conn, user_id).await.unwrap();
+        set_user_password(&context.conn, user_id, "First-passw0rd", 2).await.unwrap();
+        let token_id = test_create_reset_token(&context.conn, user_id).await;
+
+        assert!(reset_password(&context.conn, &user, token_id, "First-passw0rd", 2).await.is_err());
+        assert!(!get_token_by_id(&context.conn, token_id).await.unwrap().used);
+    }
+}
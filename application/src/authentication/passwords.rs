@@ -0,0 +1,218 @@
+use crate::database::password_history::{create_password_history, delete_old_password_history, get_password_history_from_user_id};
+use crate::database::transaction::{transaction, Executor, ScopedFutureExt};
+use crate::database::user_passwords::{create_user_password, get_user_password_by_user_id, update_user_password};
+use chrono::{Duration, NaiveDateTime};
+use domain::models::config::MainConfig;
+use domain::models::password_history::NewPasswordHistory;
+use domain::models::user_passwords::{NewUserPassword, UpdatedUserPassword, UserPassword};
+use once_cell::sync::Lazy;
+use shared::error_models::{APIError, DBError, InternalError, ServerError, UserError, ValidationError};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+static COMMON_PASSWORDS: Lazy<HashSet<&'static str>> = Lazy::new(|| {
+    include_str!("common_passwords.txt").lines().collect()
+});
+
+/// Rules every new password has to follow, defined in the main configuration.
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub max_length: usize,
+    pub min_lower_case: usize,
+    pub min_upper_case: usize,
+    pub min_digits: usize,
+    pub min_special_chars: usize,
+    pub forbid_common: bool,
+    pub forbid_username: bool,
+}
+
+impl From<&MainConfig> for PasswordPolicy {
+    fn from(main_config: &MainConfig) -> Self {
+        PasswordPolicy {
+            min_length: main_config.password_min_length.max(0) as usize,
+            max_length: main_config.password_max_length.max(0) as usize,
+            min_lower_case: main_config.password_min_lower_case.max(0) as usize,
+            min_upper_case: main_config.password_min_upper_case.max(0) as usize,
+            min_digits: main_config.password_min_digits.max(0) as usize,
+            min_special_chars: main_config.password_min_special_chars.max(0) as usize,
+            forbid_common: main_config.password_forbid_common,
+            forbid_username: main_config.password_forbid_username,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// Returns the first rule the password breaks.
+    pub fn check(&self, password: &str, username: Option<&str>) -> Result<(), String> {
+        let length = password.chars().count();
+        if length < self.min_length || length > self.max_length {
+            return Err(format!("Password must be between {} and {} characters long", self.min_length, self.max_length));
+        }
+        // Lower case check
+        if password.chars().filter(|c| c.is_lowercase()).count() < self.min_lower_case {
+            return Err(format!("Password must contain at least {} lower case character(s)", self.min_lower_case));
+        }
+        // Upper case check
+        if password.chars().filter(|c| c.is_uppercase()).count() < self.min_upper_case {
+            return Err(format!("Password must contain at least {} upper case character(s)", self.min_upper_case));
+        }
+        // Digit check
+        if password.chars().filter(|c| c.is_ascii_digit()).count() < self.min_digits {
+            return Err(format!("Password must contain at least {} digit(s)", self.min_digits));
+        }
+        // Special character check
+        if password.chars().filter(|c| !c.is_alphanumeric()).count() < self.min_special_chars {
+            return Err(format!("Password must contain at least {} special character(s)", self.min_special_chars));
+        }
+        if self.forbid_common && COMMON_PASSWORDS.contains(password.to_lowercase().as_str()) {
+            return Err("Password is too common".to_string());
+        }
+        if let Some(username) = username.filter(|username| self.forbid_username && !username.is_empty()) {
+            if password.to_lowercase().contains(&username.to_lowercase()) {
+                return Err("Password must not contain the username".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn is_password_expired(main_config: &MainConfig, user_password: &UserPassword, now: NaiveDateTime) -> bool {
+    main_config.password_expiry_days > 0 &&
+        user_password.updated_at + Duration::days(main_config.password_expiry_days as i64) < now
+}
+
+fn reused_password_error() -> APIError {
+    APIError::UserError(UserError::ValidationError(ValidationError::new("password", "Password has already been used recently")))
+}
+
+/// Changes the password of a user, refusing the current one and the `history_size` previous ones.
+///
+/// The replaced password is kept in the history.
+pub async fn set_user_password(conn: impl Executor, user_id: Uuid, password: &str, history_size: i32) -> Result<(), APIError> {
+    // The password and its history are changed together
+    transaction(conn, |conn| async move {
+        let current_password = match get_user_password_by_user_id(&mut *conn, user_id).await {
+            Ok(current_password) => Some(current_password),
+            Err(DBError::NotFound) => None,
+            Err(err) => return Err(APIError::from(err)),
+        };
+
+        if history_size > 0 {
+            if let Some(current_password) = &current_password {
+                if bcrypt::verify(password, &current_password.password).unwrap_or(false) {
+                    return Err(reused_password_error());
+                }
+            }
+            for previous_password in get_password_history_from_user_id(&mut *conn, user_id, history_size as i64).await? {
+                if bcrypt::verify(password, &previous_password.password).unwrap_or(false) {
+                    return Err(reused_password_error());
+                }
+            }
+        }
+
+        // Hash password
+        let hashed_password = bcrypt::hash(password, bcrypt::DEFAULT_COST)
+            .map_err(|_| APIError::ServerError(ServerError::InternalError(InternalError)))?;
+
+        match current_password {
+            Some(current_password) => {
+                update_user_password(&mut *conn, user_id, UpdatedUserPassword {
+                    password: Some(hashed_password),
+                }).await?;
+
+                if history_size > 0 {
+                    create_password_history(&mut *conn, NewPasswordHistory {
+                        user_id,
+                        password: current_password.password,
+                    }).await?;
+                }
+                delete_old_password_history(&mut *conn, user_id, history_size.max(0) as i64).await?;
+            },
+            // Users created through single sign-on have no password yet
+            None => create_user_password(&mut *conn, NewUserPassword {
+                user_id,
+                password: hashed_password,
+            }).await?,
+        }
+
+        Ok(())
+    }.scope_boxed()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::users::tests::test_create_user;
+    use infrastructure::{init_pool, DBPool};
+
+    struct TestContext {
+        conn: DBPool,
+    }
+
+    impl TestContext {
+        fn new() -> Self {
+            TestContext {
+                conn: init_pool(
+                    dotenvy::var("DATABASE_URL")
+                        .expect("DATABASE_URL must be set")
+                        .as_str()
+                ),
+            }
+        }
+    }
+
+    fn test_policy() -> PasswordPolicy {
+        PasswordPolicy {
+            min_length: 8,
+            max_length: 64,
+            min_lower_case: 1,
+            min_upper_case: 1,
+            min_digits: 1,
+            min_special_chars: 1,
+            forbid_common: true,
+            forbid_username: true,
+        }
+    }
+
+    #[test]
+    fn test_check_password_policy() {
+        let policy = test_policy();
+
+        assert!(policy.check("Sh0rt!", None).is_err());
+        assert!(policy.check("nouppercase1!", None).is_err());
+        assert!(policy.check("NoDigits!!", None).is_err());
+        assert!(policy.check("NoSpecial11", None).is_err());
+        assert!(policy.check("P@ssw0rd1", None).is_err());
+        assert!(policy.check("Jdupont-2024!", Some("jdupont")).is_err());
+        assert!(policy.check("Tr0ub4dor&3x", Some("jdupont")).is_ok());
+    }
+
+    #[test]
+    fn test_check_relaxed_password_policy() {
+        let policy = PasswordPolicy {
+            min_upper_case: 0,
+            min_special_chars: 0,
+            forbid_common: false,
+            ..test_policy()
+        };
+
+        assert!(policy.check("password1", None).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_set_user_password_history() {
+        let context = TestContext::new();
+
+        let user_id = test_create_user().await;
+        set_user_password(&context.conn, user_id, "First-passw0rd", 2).await.unwrap();
+        set_user_password(&context.conn, user_id, "Second-passw0rd", 2).await.unwrap();
+
+        // Current and previous passwords can't be reused
+        assert!(set_user_password(&context.conn, user_id, "Second-passw0rd", 2).await.is_err());
+        assert!(set_user_password(&context.conn, user_id, "First-passw0rd", 2).await.is_err());
+
+        // Without history, only the current password has to change
+        set_user_password(&context.conn, user_id, "First-passw0rd", 0).await.unwrap();
+    }
+}
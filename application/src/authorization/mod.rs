@@ -0,0 +1,2 @@
+pub mod organizations;
+pub mod promotions;
@@ -0,0 +1,57 @@
+use domain::models::organizations::Organization;
+use domain::models::users::{OrganizationRole, User, UserRole};
+use shared::error_models::{APIError, ForbiddenError, UnauthorizedError, UserError};
+
+/// Global administrators are allowed to manage every organization.
+pub fn check_organization_admin(user: &User) -> Result<(), APIError> {
+    if user.organization_role == OrganizationRole::Admin || user.role == UserRole::Admin {
+        Ok(())
+    } else {
+        Err(APIError::UserError(UserError::Forbidden(ForbiddenError)))
+    }
+}
+
+/// Checks that the domain of an email is one of the domains the organization accepts, if it restricts them.
+pub fn check_email_domain(organization: &Organization, email: &str) -> Result<(), APIError> {
+    let email_domain = email.rsplit('@').next().unwrap_or_default();
+    if !organization.authorized_domains.is_empty() &&
+        !organization.authorized_domains.contains(&Some(email_domain.to_string())) {
+        return Err(APIError::UserError(UserError::Unauthorized(UnauthorizedError)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn organization(authorized_domains: &[&str]) -> Organization {
+        Organization {
+            id: Uuid::new_v4(),
+            name: "School".to_string(),
+            slug: "school".to_string(),
+            register: true,
+            authorized_domains: authorized_domains.iter().map(|domain| Some(domain.to_string())).collect(),
+            logo_url: None,
+            primary_color: None,
+            mail_sender: None,
+            created_at: Utc::now().naive_utc(),
+            updated_at: Utc::now().naive_utc(),
+            timezone: "UTC".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_check_email_domain() {
+        let school = organization(&["school.edu", "staff.school.edu"]);
+
+        assert!(check_email_domain(&school, "student@school.edu").is_ok());
+        assert!(check_email_domain(&school, "teacher@staff.school.edu").is_ok());
+        assert!(check_email_domain(&school, "student@gmail.com").is_err());
+        assert!(check_email_domain(&school, "student@school.edu.example.com").is_err());
+        // Every domain is accepted when the organization doesn't restrict them
+        assert!(check_email_domain(&organization(&[]), "student@gmail.com").is_ok());
+    }
+}
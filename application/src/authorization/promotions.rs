@@ -0,0 +1,139 @@
+use crate::database::groups::get_group_by_id;
+use crate::database::projects::get_project_by_id;
+use crate::database::promotions::get_promotion_by_id;
+use crate::database::promotions_members::get_promotion_member;
+use crate::database::students::get_promotions_ids_from_student_id;
+use domain::models::promotions_members::{PromotionPermission, PromotionRole};
+use domain::models::users::User;
+use infrastructure::DBPool;
+use shared::error_models::{APIError, DBError, ForbiddenError, UserError};
+use uuid::Uuid;
+
+/// Returns the role of the user in the promotion if it allows the given permission.
+///
+/// The promotions of the other organizations are refused as if the user wasn't a member of them.
+pub async fn check_promotion_permission(conn: &DBPool, promotion_id: Uuid, user: &User, permission: PromotionPermission) -> Result<PromotionRole, APIError> {
+    let member = match get_promotion_by_id(conn, user.organization_id, promotion_id).await {
+        Ok(_) => get_promotion_member(conn, promotion_id, user.id).await,
+        Err(err) => Err(err),
+    };
+    let member = match member {
+        Ok(member) => member,
+        Err(DBError::NotFound) => return Err(APIError::UserError(UserError::Forbidden(ForbiddenError))),
+        Err(err) => return Err(APIError::from(err)),
+    };
+
+    if !member.role.has_permission(permission) {
+        return Err(APIError::UserError(UserError::Forbidden(ForbiddenError)));
+    }
+
+    Ok(member.role)
+}
+
+pub async fn check_project_permission(conn: &DBPool, project_id: Uuid, user: &User, permission: PromotionPermission) -> Result<PromotionRole, APIError> {
+    let project = get_project_by_id(conn, user.organization_id, project_id).await?;
+    check_promotion_permission(conn, project.promotion_id, user, permission).await
+}
+
+pub async fn check_group_permission(conn: &DBPool, group_id: Uuid, user: &User, permission: PromotionPermission) -> Result<PromotionRole, APIError> {
+    let group = get_group_by_id(conn, user.organization_id, group_id).await?;
+    check_project_permission(conn, group.project_id, user, permission).await
+}
+
+/// A student can belong to several promotions, the permission is granted if any of them allows it.
+pub async fn check_student_permission(conn: &DBPool, student_id: Uuid, user: &User, permission: PromotionPermission) -> Result<PromotionRole, APIError> {
+    let promotions_ids = get_promotions_ids_from_student_id(conn, student_id).await?;
+    for promotion_id in promotions_ids {
+        if let Ok(role) = check_promotion_permission(conn, promotion_id, user, permission).await {
+            return Ok(role);
+        }
+    }
+
+    Err(APIError::UserError(UserError::Forbidden(ForbiddenError)))
+}
+
+/// Deleting a student removes them from all their promotions, so each of them must allow it.
+pub async fn check_student_permission_in_all_promotions(conn: &DBPool, student_id: Uuid, user: &User, permission: PromotionPermission) -> Result<(), APIError> {
+    let promotions_ids = get_promotions_ids_from_student_id(conn, student_id).await?;
+    if promotions_ids.is_empty() {
+        return Err(APIError::UserError(UserError::Forbidden(ForbiddenError)));
+    }
+
+    for promotion_id in promotions_ids {
+        check_promotion_permission(conn, promotion_id, user, permission).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::promotions::create_promotion;
+    use crate::database::promotions::tests::test_create_promotion;
+    use crate::database::promotions_members::create_promotion_member;
+    use crate::database::students::create_promotion_students;
+    use crate::database::students::test::test_create_student_in_organization;
+    use crate::database::users::get_user_by_id;
+    use crate::database::users::tests::test_create_user_in_organization;
+    use chrono::NaiveDate;
+    use domain::models::promotions::NewPromotion;
+    use domain::models::promotions_members::NewPromotionMember;
+    use domain::models::students::NewPromotionStudent;
+    use infrastructure::init_pool;
+
+    struct TestContext {
+        conn: DBPool,
+    }
+
+    impl TestContext {
+        fn new() -> Self {
+            TestContext {
+                conn: init_pool(
+                    dotenvy::var("DATABASE_URL")
+                        .expect("DATABASE_URL must be set")
+                        .as_str()
+                ),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_student_permission_in_all_promotions() {
+        let context = TestContext::new();
+
+        // The student belongs to the promotion of the teacher and to the one of a colleague
+        let (promotion_id, teacher_id) = test_create_promotion().await;
+        let teacher = get_user_by_id(&context.conn, teacher_id).await.unwrap();
+        let colleague_id = test_create_user_in_organization(teacher.organization_id).await;
+        let other_promotion_id = create_promotion(&context.conn, NewPromotion {
+            title: "test".to_string(),
+            start_year: NaiveDate::from_ymd_opt(2021, 1, 1).unwrap(),
+            end_year: NaiveDate::from_ymd_opt(2021, 12, 31).unwrap(),
+            teacher_id: colleague_id,
+            organization_id: teacher.organization_id,
+        }).await.unwrap();
+        for (promotion_id, user_id) in [(promotion_id, teacher_id), (other_promotion_id, colleague_id)] {
+            create_promotion_member(&context.conn, NewPromotionMember {
+                promotion_id,
+                user_id,
+                role: PromotionRole::Owner,
+            }).await.unwrap();
+        }
+        let student_id = test_create_student_in_organization(teacher.organization_id).await;
+        create_promotion_students(&context.conn, vec![
+            NewPromotionStudent { promotion_id, student_id },
+            NewPromotionStudent { promotion_id: other_promotion_id, student_id },
+        ]).await.unwrap();
+
+        assert!(check_student_permission(&context.conn, student_id, &teacher, PromotionPermission::Delete).await.is_ok());
+        assert!(check_student_permission_in_all_promotions(&context.conn, student_id, &teacher, PromotionPermission::Delete).await.is_err());
+
+        create_promotion_member(&context.conn, NewPromotionMember {
+            promotion_id: other_promotion_id,
+            user_id: teacher_id,
+            role: PromotionRole::CoTeacher,
+        }).await.unwrap();
+        assert!(check_student_permission_in_all_promotions(&context.conn, student_id, &teacher, PromotionPermission::Delete).await.is_ok());
+    }
+}
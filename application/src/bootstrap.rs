@@ -0,0 +1,235 @@
+use crate::authentication::passwords::{set_user_password, PasswordPolicy};
+use crate::database::config::{create_main_config, get_main_config};
+use crate::database::organizations::{create_organization, get_organization_by_slug, DEFAULT_ORGANIZATION_SLUG};
+use crate::database::transaction::{transaction, ScopedFutureExt};
+use crate::database::user_passwords::create_user_password;
+use crate::database::users::{create_user, get_user_by_username, update_user};
+use domain::models::config::{MainConfig, NewMainConfig};
+use domain::models::organizations::{NewOrganization, Organization};
+use domain::models::user_passwords::NewUserPassword;
+use domain::models::users::{NewUser, OrganizationRole, UpdatedUser, UserRole};
+use infrastructure::{run_pending_migrations, DBPool};
+use shared::error_models::{APIError, DBError, InternalError, NotFoundError, ServerError, UserError, ValidationError};
+use uuid::Uuid;
+
+/// Username of the account administrating the platform
+pub const ADMIN_USERNAME: &str = "admin";
+
+/// Applies the migrations embedded in the binary which have not been applied yet.
+///
+/// The migrations run on a blocking thread, with a connection of their own.
+pub async fn migrate_database(database_url: &str) -> Result<Vec<String>, String> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || run_pending_migrations(&database_url))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Returns the main configuration, creating the default one if needed.
+pub async fn get_or_create_main_config(conn: &DBPool) -> Result<MainConfig, DBError> {
+    match get_main_config(conn).await {
+        Err(DBError::NotFound) => {
+            create_main_config(conn, NewMainConfig {
+                register: None,
+                authorized_domains: None,
+            }).await?;
+            get_main_config(conn).await
+        }
+        result => result,
+    }
+}
+
+/// Returns the organization holding the data created before multi-tenancy, creating it if needed.
+pub async fn get_default_organization(conn: &DBPool) -> Result<Organization, DBError> {
+    match get_organization_by_slug(conn, DEFAULT_ORGANIZATION_SLUG).await {
+        Err(DBError::NotFound) => {
+            create_organization(conn, NewOrganization {
+                name: "Default".to_string(),
+                slug: DEFAULT_ORGANIZATION_SLUG.to_string(),
+                register: None,
+                authorized_domains: None,
+                logo_url: None,
+                primary_color: None,
+                mail_sender: None,
+            }).await?;
+            get_organization_by_slug(conn, DEFAULT_ORGANIZATION_SLUG).await
+        }
+        result => result,
+    }
+}
+
+/// Creates the admin account from the environment if it doesn't exist.
+///
+/// The password is `ADMIN_PASSWORD`, or `DEFAULT_ADMIN_PASSWORD` when it is not set.
+pub async fn ensure_admin(conn: &DBPool) -> Result<(), APIError> {
+    match get_user_by_username(conn, ADMIN_USERNAME).await {
+        Ok(_) => Ok(()),
+        Err(DBError::NotFound) => {
+            let email = dotenvy::var("ADMIN_EMAIL").unwrap_or_default();
+            let password = dotenvy::var("ADMIN_PASSWORD")
+                .ok()
+                .filter(|password| !password.is_empty())
+                .or_else(|| dotenvy::var("DEFAULT_ADMIN_PASSWORD").ok())
+                .ok_or(APIError::ServerError(ServerError::InternalError(InternalError)))?;
+
+            create_admin(conn, &email, &password).await.map(|_| ())
+        }
+        Err(err) => Err(APIError::from(err)),
+    }
+}
+
+/// Creates the admin account in the default organization.
+///
+/// The email is considered validated, as it is provided by whoever runs the platform.
+pub async fn create_admin(conn: &DBPool, email: &str, password: &str) -> Result<Uuid, APIError> {
+    let organization = get_default_organization(conn).await?;
+    let hashed_password = bcrypt::hash(password, bcrypt::DEFAULT_COST)
+        .map_err(|_| APIError::ServerError(ServerError::InternalError(InternalError)))?;
+
+    transaction(conn, |conn| async move {
+        let user_id = create_user(&mut *conn, NewUser {
+            username: ADMIN_USERNAME.to_string(),
+            email: email.to_string(),
+            organization_id: organization.id,
+        }).await?;
+        update_user(&mut *conn, user_id, UpdatedUser {
+            username: None,
+            email: None,
+            has_validated_email: if email.is_empty() { None } else { Some(true) },
+            role: Some(UserRole::Admin),
+            token_version: None,
+            organization_role: Some(OrganizationRole::Admin),
+        }).await?;
+        create_user_password(&mut *conn, NewUserPassword {
+            user_id,
+            password: hashed_password,
+        }).await?;
+
+        Ok(user_id)
+    }.scope_boxed()).await
+}
+
+/// Replaces the password of the admin account, which has to follow the password policy.
+pub async fn reset_admin_password(conn: &DBPool, password: &str) -> Result<(), APIError> {
+    let admin = get_user_by_username(conn, ADMIN_USERNAME).await.map_err(|err| match err {
+        DBError::NotFound => APIError::UserError(UserError::NotFound(NotFoundError {
+            resource: "Admin account".to_string(),
+        })),
+        err => APIError::from(err),
+    })?;
+    check_password_policy(conn, password, ADMIN_USERNAME).await?;
+
+    transaction(conn, |conn| async move {
+        // The history is ignored, as the admin may have lost its password
+        set_user_password(&mut *conn, admin.id, password, 0).await?;
+
+        // Revoke the sessions opened with the previous password
+        update_user(&mut *conn, admin.id, UpdatedUser {
+            username: None,
+            email: None,
+            has_validated_email: None,
+            role: None,
+            token_version: Some(admin.token_version + 1),
+            organization_role: None,
+        }).await?;
+        Ok(())
+    }.scope_boxed()).await
+}
+
+/// Account created from the command line.
+#[derive(Clone)]
+pub struct NewAccount<'a> {
+    pub username: &'a str,
+    pub email: &'a str,
+    pub password: &'a str,
+    pub organization_slug: &'a str,
+    pub organization_role: OrganizationRole,
+}
+
+/// Creates a user with a password in an organization, the email being considered validated.
+pub async fn create_account(conn: &DBPool, account: NewAccount<'_>) -> Result<Uuid, APIError> {
+    let organization = get_organization_by_slug(conn, account.organization_slug).await.map_err(|err| match err {
+        DBError::NotFound => APIError::UserError(UserError::NotFound(NotFoundError {
+            resource: "Organization".to_string(),
+        })),
+        err => APIError::from(err),
+    })?;
+    check_password_policy(conn, account.password, account.username).await?;
+
+    transaction(conn, |conn| async move {
+        let user_id = create_user(&mut *conn, NewUser {
+            username: account.username.to_string(),
+            email: account.email.to_string(),
+            organization_id: organization.id,
+        }).await?;
+        update_user(&mut *conn, user_id, UpdatedUser {
+            username: None,
+            email: None,
+            has_validated_email: Some(true),
+            role: None,
+            token_version: None,
+            organization_role: Some(account.organization_role),
+        }).await?;
+        set_user_password(&mut *conn, user_id, account.password, 0).await?;
+
+        Ok(user_id)
+    }.scope_boxed()).await
+}
+
+async fn check_password_policy(conn: &DBPool, password: &str, username: &str) -> Result<(), APIError> {
+    let main_config = get_or_create_main_config(conn).await?;
+    PasswordPolicy::from(&main_config)
+        .check(password, Some(username))
+        .map_err(|message| APIError::UserError(UserError::ValidationError(ValidationError::new("password", &message))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::organizations::get_organization_by_id;
+    use crate::database::organizations::tests::test_create_organization;
+    use crate::database::user_passwords::get_user_password_by_user_id;
+    use crate::database::users::get_user_by_id;
+    use infrastructure::init_pool;
+
+    struct TestContext {
+        conn: DBPool,
+    }
+
+    impl TestContext {
+        fn new() -> Self {
+            TestContext {
+                conn: init_pool(
+                    dotenvy::var("DATABASE_URL")
+                        .expect("DATABASE_URL must be set")
+                        .as_str()
+                ),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_account() {
+        let context = TestContext::new();
+        let organization = get_organization_by_id(&context.conn, test_create_organization().await).await.unwrap();
+        let username = format!("cli-{}", Uuid::new_v4().simple());
+
+        let mut account = NewAccount {
+            username: &username,
+            email: "cli@example.com",
+            password: "short",
+            organization_slug: &organization.slug,
+            organization_role: OrganizationRole::Admin,
+        };
+        assert!(create_account(&context.conn, account.clone()).await.is_err());
+
+        account.password = "Another-passw0rd";
+        let user_id = create_account(&context.conn, account).await.unwrap();
+        let user = get_user_by_id(&context.conn, user_id).await.unwrap();
+        assert_eq!(user.organization_id, organization.id);
+        assert_eq!(user.organization_role, OrganizationRole::Admin);
+        assert!(user.has_validated_email);
+        let password = get_user_password_by_user_id(&context.conn, user_id).await.unwrap();
+        assert!(bcrypt::verify("Another-passw0rd", &password.password).unwrap());
+    }
+}
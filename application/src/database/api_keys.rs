@@ -0,0 +1,147 @@
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use diesel::result::Error as DBError;
+use domain::models::api_keys::*;
+use infrastructure::{get_connection, DBPool};
+use uuid::Uuid;
+
+pub async fn get_api_keys_from_user_id(conn: &DBPool, user_id_: Uuid) -> Result<Vec<ApiKey>, DBError> {
+    use domain::schema::api_keys::dsl::*;
+
+    api_keys
+        .filter(user_id.eq(user_id_))
+        .order(created_at.desc())
+        .load(&mut get_connection(conn).await?).await
+}
+
+pub async fn get_api_key_by_hash(conn: &DBPool, key_hash_: &str) -> Result<ApiKey, DBError> {
+    use domain::schema::api_keys::dsl::*;
+
+    api_keys
+        .filter(key_hash.eq(key_hash_))
+        .first(&mut get_connection(conn).await?).await
+}
+
+pub async fn create_api_key(conn: &DBPool, new_api_key: NewApiKey) -> Result<Uuid, DBError> {
+    use domain::schema::api_keys::dsl::*;
+
+    diesel::insert_into(api_keys)
+        .values(&new_api_key)
+        .returning(id)
+        .get_result(&mut get_connection(conn).await?).await
+}
+
+pub async fn update_api_key_last_used(conn: &DBPool, id_: Uuid) -> Result<(), DBError> {
+    use domain::schema::api_keys::dsl::*;
+
+    diesel::update(api_keys.filter(id.eq(id_)))
+        .set(last_used_at.eq(diesel::dsl::now))
+        .execute(&mut get_connection(conn).await?).await?;
+
+    Ok(())
+}
+
+/// Revokes a key of the given user, keys of other users are reported as not found.
+pub async fn revoke_api_key(conn: &DBPool, user_id_: Uuid, id_: Uuid) -> Result<(), DBError> {
+    use domain::schema::api_keys::dsl::*;
+
+    let updated = diesel::update(api_keys
+        .filter(id.eq(id_))
+        .filter(user_id.eq(user_id_))
+        .filter(revoked_at.is_null())
+    ).set(revoked_at.eq(diesel::dsl::now))
+        .execute(&mut get_connection(conn).await?).await?;
+
+    if updated == 0 {
+        return Err(DBError::NotFound);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authentication::api_keys::generate_api_key;
+    use crate::database::users::tests::test_create_user;
+    use infrastructure::init_pool;
+
+    struct TestContext {
+        conn: DBPool,
+    }
+
+    impl TestContext {
+        fn new() -> Self {
+            TestContext {
+                conn: init_pool(
+                    dotenvy::var("DATABASE_URL")
+                        .expect("DATABASE_URL must be set")
+                        .as_str()
+                ),
+            }
+        }
+    }
+
+    async fn test_create_api_key() -> (Uuid, Uuid, String) {
+        let context = TestContext::new();
+
+        let user_id = test_create_user().await;
+        let generated = generate_api_key();
+        let id_ = create_api_key(&context.conn, NewApiKey {
+            user_id,
+            name: "test".to_string(),
+            prefix: generated.prefix,
+            key_hash: generated.key_hash.clone(),
+            scopes: vec![ApiKeyScope::Read],
+            expires_at: None,
+        }).await.unwrap();
+
+        (id_, user_id, generated.key_hash)
+    }
+
+    #[tokio::test]
+    async fn test_get_api_key_by_hash() {
+        let context = TestContext::new();
+
+        let (id_, _, key_hash_) = test_create_api_key().await;
+
+        let api_key = get_api_key_by_hash(&context.conn, &key_hash_).await.unwrap();
+        assert_eq!(api_key.id, id_);
+        assert_eq!(api_key.scopes, vec![ApiKeyScope::Read]);
+    }
+
+    #[tokio::test]
+    async fn test_get_api_keys_from_user_id() {
+        let context = TestContext::new();
+
+        let (_, user_id_, _) = test_create_api_key().await;
+
+        let api_keys_ = get_api_keys_from_user_id(&context.conn, user_id_).await.unwrap();
+        assert_eq!(api_keys_.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_update_api_key_last_used() {
+        let context = TestContext::new();
+
+        let (id_, _, key_hash_) = test_create_api_key().await;
+
+        update_api_key_last_used(&context.conn, id_).await.unwrap();
+        let api_key = get_api_key_by_hash(&context.conn, &key_hash_).await.unwrap();
+        assert!(api_key.last_used_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_revoke_api_key() {
+        let context = TestContext::new();
+
+        let (id_, user_id_, key_hash_) = test_create_api_key().await;
+
+        // Other users can't revoke the key
+        assert!(revoke_api_key(&context.conn, test_create_user().await, id_).await.is_err());
+
+        revoke_api_key(&context.conn, user_id_, id_).await.unwrap();
+        let api_key = get_api_key_by_hash(&context.conn, &key_hash_).await.unwrap();
+        assert!(!api_key.is_active(chrono::Utc::now().naive_utc()));
+    }
+}
@@ -0,0 +1,197 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use diesel::result::Error as DBError;
+use domain::models::audit_logs::*;
+use infrastructure::{get_connection, DBPool};
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+#[derive(Debug, Default, Deserialize, ToSchema, IntoParams)]
+pub struct AuditLogSearchParams {
+    /// The user who performed the actions
+    pub actor_id: Option<Uuid>,
+    /// The type of the modified resource (group, student, main_config, user)
+    pub resource_type: Option<String>,
+    pub resource_id: Option<String>,
+    /// Only return the actions performed after this date
+    pub from: Option<NaiveDateTime>,
+    /// Only return the actions performed before this date
+    pub to: Option<NaiveDateTime>,
+}
+
+pub async fn get_audit_logs(conn: &DBPool, params: &AuditLogSearchParams) -> Result<Vec<AuditLog>, DBError> {
+    use domain::schema::audit_logs::dsl::*;
+
+    let mut query = audit_logs.into_boxed();
+
+    if let Some(actor_id_) = params.actor_id {
+        query = query.filter(actor_id.eq(actor_id_));
+    }
+
+    if let Some(resource_type_) = &params.resource_type {
+        query = query.filter(resource_type.eq(resource_type_));
+    }
+
+    if let Some(resource_id_) = &params.resource_id {
+        query = query.filter(resource_id.eq(resource_id_));
+    }
+
+    if let Some(from) = params.from {
+        query = query.filter(created_at.ge(from));
+    }
+
+    if let Some(to) = params.to {
+        query = query.filter(created_at.le(to));
+    }
+
+    query.order(created_at.desc())
+        .load::<AuditLog>(&mut get_connection(conn).await?).await
+}
+
+pub async fn create_audit_log(conn: &DBPool, new_audit_log: NewAuditLog) -> Result<Uuid, DBError> {
+    use domain::schema::audit_logs::dsl::*;
+
+    diesel::insert_into(audit_logs)
+        .values(&new_audit_log)
+        .returning(id)
+        .get_result(&mut get_connection(conn).await?).await
+}
+
+/// Removes the entries older than the given date, returns the number of deleted entries.
+pub async fn delete_audit_logs_older_than(conn: &DBPool, date: NaiveDateTime) -> Result<usize, DBError> {
+    use domain::schema::audit_logs::dsl::*;
+
+    diesel::delete(audit_logs.filter(created_at.lt(date)))
+        .execute(&mut get_connection(conn).await?).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::users::delete_user;
+    use crate::database::users::tests::test_create_user;
+    use chrono::{Duration, Utc};
+    use infrastructure::init_pool;
+    use serde_json::json;
+
+    struct TestContext {
+        conn: DBPool,
+    }
+
+    impl TestContext {
+        fn new() -> Self {
+            TestContext {
+                conn: init_pool(
+                    dotenvy::var("DATABASE_URL")
+                        .expect("DATABASE_URL must be set")
+                        .as_str()
+                ),
+            }
+        }
+    }
+
+    async fn test_create_audit_log() -> (Uuid, Uuid) {
+        let context = TestContext::new();
+
+        let user_id = test_create_user().await;
+        let new_audit_log = NewAuditLog {
+            actor_id: Some(user_id),
+            action: AuditAction::GroupUpdated,
+            resource_type: "group".to_string(),
+            resource_id: Some(Uuid::new_v4().to_string()),
+            before: Some(json!({ "mark": 12.0 })),
+            after: Some(json!({ "mark": 14.0 })),
+            ip: Some("127.0.0.1".to_string()),
+        };
+
+        (create_audit_log(&context.conn, new_audit_log).await.unwrap(), user_id)
+    }
+
+    #[tokio::test]
+    async fn test_get_audit_logs() {
+        let context = TestContext::new();
+
+        let (id_, user_id) = test_create_audit_log().await;
+
+        let logs = get_audit_logs(&context.conn, &AuditLogSearchParams {
+            actor_id: Some(user_id),
+            resource_type: Some("group".to_string()),
+            ..Default::default()
+        }).await.unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].id, id_);
+
+        let logs = get_audit_logs(&context.conn, &AuditLogSearchParams {
+            actor_id: Some(user_id),
+            from: Some(Utc::now().naive_utc() + Duration::days(1)),
+            ..Default::default()
+        }).await.unwrap();
+        assert!(logs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_audit_logs_are_append_only() {
+        use domain::schema::audit_logs::dsl::*;
+
+        let context = TestContext::new();
+
+        let (id_, _) = test_create_audit_log().await;
+
+        let result = diesel::update(audit_logs.filter(id.eq(id_)))
+            .set(ip.eq("0.0.0.0"))
+            .execute(&mut context.conn.get().await.unwrap()).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_deleting_an_actor_keeps_their_audit_logs() {
+        use domain::schema::audit_logs::dsl::*;
+
+        let context = TestContext::new();
+
+        let (id_, user_id) = test_create_audit_log().await;
+
+        // Only clearing the actor is allowed
+        let other_user_id = test_create_user().await;
+        let result = diesel::update(audit_logs.filter(id.eq(id_)))
+            .set(actor_id.eq(other_user_id))
+            .execute(&mut context.conn.get().await.unwrap()).await;
+        assert!(result.is_err());
+
+        delete_user(&context.conn, user_id).await.unwrap();
+
+        let log: AuditLog = audit_logs.filter(id.eq(id_))
+            .first(&mut context.conn.get().await.unwrap()).await
+            .unwrap();
+        assert!(log.actor_id.is_none());
+        assert_eq!(log.action, AuditAction::GroupUpdated);
+    }
+
+    #[tokio::test]
+    async fn test_delete_audit_logs_older_than() {
+        use domain::schema::audit_logs::dsl::*;
+
+        let context = TestContext::new();
+
+        let (recent_id, user_id) = test_create_audit_log().await;
+        diesel::insert_into(audit_logs)
+            .values((
+                actor_id.eq(user_id),
+                action.eq(AuditAction::StudentDeleted),
+                resource_type.eq("student"),
+                created_at.eq(Utc::now().naive_utc() - Duration::days(400)),
+            ))
+            .execute(&mut context.conn.get().await.unwrap()).await
+            .unwrap();
+
+        delete_audit_logs_older_than(&context.conn, Utc::now().naive_utc() - Duration::days(365)).await.unwrap();
+        let logs = get_audit_logs(&context.conn, &AuditLogSearchParams {
+            actor_id: Some(user_id),
+            ..Default::default()
+        }).await.unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].id, recent_id);
+    }
+}
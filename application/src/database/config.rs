@@ -1,72 +1,73 @@
+use crate::database::transaction::Executor;
 use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
 use diesel::result::Error as DBError;
 use uuid::Uuid;
 
 use domain::models::config::*;
-use infrastructure::DBPool;
+use domain::repositories::users::UsersRepository;
+use infrastructure::repositories::PgRepositories;
+use infrastructure::{get_connection, DBPool};
 
-pub fn get_main_config(conn: &DBPool) -> Result<MainConfig, DBError> {
+pub async fn get_main_config(conn: &DBPool) -> Result<MainConfig, DBError> {
     use domain::schema::main_config::dsl::*;
 
-    main_config.first(&mut conn.get().unwrap())
+    main_config.first(&mut get_connection(conn).await?).await
 }
 
-pub fn get_config_by_user_id(conn: &DBPool, id_: Uuid) -> Result<UserConfig, DBError> {
-    use domain::schema::user_config::dsl::*;
-
-    user_config.filter(user_id.eq(id_))
-        .first(&mut conn.get().unwrap())
+pub async fn get_config_by_user_id(conn: &DBPool, id_: Uuid) -> Result<UserConfig, DBError> {
+    PgRepositories::new(conn.clone()).get_config_by_user_id(id_).await
 }
 
-pub fn create_main_config(conn: &DBPool, new_main_config: NewMainConfig) -> Result<i32, DBError> {
+pub async fn create_main_config(conn: &DBPool, new_main_config: NewMainConfig) -> Result<i32, DBError> {
     use domain::schema::main_config::dsl::*;
 
     let result: Result<i32, DBError> = diesel::insert_into(main_config)
         .values(&new_main_config)
         .returning(id)
-        .get_result(&mut conn.get().unwrap());
+        .get_result(&mut get_connection(conn).await?).await;
 
     result
 }
 
-pub fn create_user_config(conn: &DBPool, new_user_config: NewUserConfig) -> Result<i32, DBError> {
+pub async fn create_user_config(conn: impl Executor, new_user_config: NewUserConfig) -> Result<i32, DBError> {
     use domain::schema::user_config::dsl::*;
 
     let result: Result<i32, DBError> = diesel::insert_into(user_config)
         .values(&new_user_config)
         .returning(id)
-        .get_result(&mut conn.get().unwrap());
+        .get_result(&mut conn.connection().await?).await;
 
     result
 }
 
-pub fn update_main_config(conn: &DBPool, updated_main_config: UpdatedMainConfig) -> Result<(), DBError> {
+pub async fn update_main_config(conn: &DBPool, updated_main_config: UpdatedMainConfig) -> Result<(), DBError> {
     use domain::schema::main_config::dsl::*;
 
-    let id_ = get_main_config(&conn)?.id;
+    let id_ = get_main_config(&conn).await?.id;
 
     diesel::update(main_config.filter(id.eq(id_)))
         .set(&updated_main_config)
-        .execute(&mut conn.get().unwrap())?;
+        .execute(&mut get_connection(conn).await?).await?;
 
     Ok(())
 }
 
-pub fn update_user_config(conn: &DBPool, id_: Uuid, updated_user_config: UpdatedUserConfig) -> Result<(), DBError> {
+pub async fn update_user_config(conn: &DBPool, id_: Uuid, updated_user_config: UpdatedUserConfig) -> Result<(), DBError> {
     use domain::schema::user_config::dsl::*;
 
     diesel::update(user_config.filter(user_id.eq(id_)))
         .set(&updated_user_config)
-        .execute(&mut conn.get().unwrap())?;
+        .execute(&mut get_connection(conn).await?).await?;
 
     Ok(())
 }
 
-pub fn delete_user_config(conn: &DBPool, id_: i32) -> Result<(), DBError> {
+pub async fn delete_user_config(conn: &DBPool, id_: i32) -> Result<(), DBError> {
     use domain::schema::user_config::dsl::*;
 
     diesel::delete(user_config.filter(id.eq(id_)))
-        .execute(&mut conn.get().unwrap())?;
+        .execute(&mut get_connection(conn).await?).await?;
 
     Ok(())
 }
@@ -97,7 +98,7 @@ mod tests {
         }
     }
 
-    fn test_create_main_config() -> i32 {
+    async fn test_create_main_config() -> i32 {
         let context = TestContext::new();
 
         let new_main_config = NewMainConfig{
@@ -105,87 +106,99 @@ mod tests {
             authorized_domains: None,
         };
 
-        create_main_config(&context.conn, new_main_config).unwrap()
+        create_main_config(&context.conn, new_main_config).await.unwrap()
     }
 
-    fn test_create_user_config() -> (i32, Uuid) {
+    async fn test_create_user_config() -> (i32, Uuid) {
         let context = TestContext::new();
 
-        let user_id = test_create_user();
+        let user_id = test_create_user().await;
         let new_user_config = NewUserConfig{
             user_id,
             alerts: None,
         };
 
-        (create_user_config(&context.conn, new_user_config).unwrap(), user_id)
+        (create_user_config(&context.conn, new_user_config).await.unwrap(), user_id)
     }
 
-    fn delete_main_config(id_: i32) -> Result<(), DBError> {
+    async fn delete_main_config(id_: i32) -> Result<(), DBError> {
         use domain::schema::main_config::dsl::*;
 
         let context = TestContext::new();
         diesel::delete(main_config.filter(id.eq(id_)))
-            .execute(&mut context.conn.get().unwrap())?;
+            .execute(&mut context.conn.get().await.unwrap()).await?;
 
         Ok(())
     }
 
-    #[test]
-    fn test_get_main_config() {
+    #[tokio::test]
+    async fn test_get_main_config() {
         let context = TestContext::new();
 
-        let _ = test_create_main_config();
-        let main_config = get_main_config(&context.conn).unwrap();
-        delete_main_config(main_config.id).unwrap();
+        let _ = test_create_main_config().await;
+        let main_config = get_main_config(&context.conn).await.unwrap();
+        delete_main_config(main_config.id).await.unwrap();
         assert_eq!(main_config.register, true);
     }
 
-    #[test]
-    fn test_get_config_by_user_id() {
+    #[tokio::test]
+    async fn test_get_config_by_user_id() {
         let context = TestContext::new();
 
-        let (_, user_id) = test_create_user_config();
-        let user_config = get_config_by_user_id(&context.conn, user_id).unwrap();
+        let (_, user_id) = test_create_user_config().await;
+        let user_config = get_config_by_user_id(&context.conn, user_id).await.unwrap();
         assert_eq!(user_config.user_id, user_id);
     }
 
-    #[test]
-    fn test_update_main_config() {
+    #[tokio::test]
+    async fn test_update_main_config() {
         let context = TestContext::new();
 
-        let _ = test_create_main_config();
-        let main_config = get_main_config(&context.conn).unwrap();
+        let _ = test_create_main_config().await;
+        let main_config = get_main_config(&context.conn).await.unwrap();
         let updated_main_config = UpdatedMainConfig{
             register: Some(true),
             authorized_domains: None,
+            audit_log_retention_days: None,
+            password_min_length: None,
+            password_max_length: None,
+            password_min_lower_case: None,
+            password_min_upper_case: None,
+            password_min_digits: None,
+            password_min_special_chars: None,
+            password_forbid_common: None,
+            password_forbid_username: None,
+            password_history_size: None,
+            password_expiry_days: None,
         };
 
-        update_main_config(&context.conn, updated_main_config).unwrap();
-        delete_main_config(main_config.id).unwrap();
+        update_main_config(&context.conn, updated_main_config).await.unwrap();
+        delete_main_config(main_config.id).await.unwrap();
     }
 
-    #[test]
-    fn test_update_user_config() {
+    #[tokio::test]
+    async fn test_update_user_config() {
         let context = TestContext::new();
 
-        let (_, user_id) = test_create_user_config();
+        let (_, user_id) = test_create_user_config().await;
         let updated_user_config = UpdatedUserConfig{
             alerts: Some(vec![
                 Alert{
                     before_event: true,
                     hours: 1,
                 }
-            ])
+            ]),
+            timezone: Some("Europe/Paris".to_string()),
         };
 
-        update_user_config(&context.conn, user_id, updated_user_config).unwrap();
+        update_user_config(&context.conn, user_id, updated_user_config).await.unwrap();
     }
 
-    #[test]
-    fn test_delete_user_config() {
+    #[tokio::test]
+    async fn test_delete_user_config() {
         let context = TestContext::new();
 
-        let (id_, _) = test_create_user_config();
-        delete_user_config(&context.conn, id_).unwrap()
+        let (id_, _) = test_create_user_config().await;
+        delete_user_config(&context.conn, id_).await.unwrap()
     }
 }
\ No newline at end of file
@@ -1,25 +1,16 @@
-use diesel::prelude::*;
 use diesel::result::Error as DBError;
 use domain::models::done_alerts::*;
+use domain::repositories::projects::ProjectsRepository;
+use infrastructure::repositories::PgRepositories;
 use infrastructure::DBPool;
 use uuid::Uuid;
 
-pub fn get_done_alerts_by_project_id_and_type(conn: &DBPool, project_id_: Uuid, alert_type_: AlertType) -> Result<Vec<DoneAlert>, DBError> {
-    use domain::schema::done_alerts::dsl::*;
-
-    done_alerts.filter(project_id.eq(project_id_))
-        .filter(type_.eq(alert_type_))
-        .get_results(&mut conn.get().unwrap())
+pub async fn get_done_alerts_by_project_id_and_type(conn: &DBPool, project_id_: Uuid, alert_type_: AlertType) -> Result<Vec<DoneAlert>, DBError> {
+    PgRepositories::new(conn.clone()).get_done_alerts_by_project_id_and_type(project_id_, alert_type_).await
 }
 
-pub fn create_done_alert(conn: &DBPool, new_done_alert: NewDoneAlert) -> Result<(), DBError> {
-    use domain::schema::done_alerts::dsl::*;
-
-    diesel::insert_into(done_alerts)
-        .values(&new_done_alert)
-        .execute(&mut conn.get().unwrap())?;
-
-    Ok(())
+pub async fn create_done_alert(conn: &DBPool, new_done_alert: NewDoneAlert) -> Result<(), DBError> {
+    PgRepositories::new(conn.clone()).create_done_alert(new_done_alert).await
 }
 
 #[cfg(test)]
@@ -44,26 +35,27 @@ mod tests {
         }
     }
 
-    fn test_create_done_alert() -> (Uuid, AlertType) {
+    async fn test_create_done_alert() -> (Uuid, AlertType) {
         let ctx = TestContext::new();
-        let (project_id, _) = test_create_project();
+        let (project_id, _) = test_create_project().await;
         let new_done_alert = NewDoneAlert {
             description: Some("Test done alert".to_string()),
             project_id,
             type_: AlertType::Started,
+            planned_at: None,
         };
 
-        create_done_alert(&ctx.conn, new_done_alert).unwrap();
+        create_done_alert(&ctx.conn, new_done_alert).await.unwrap();
 
         (project_id, AlertType::Started)
     }
 
-    #[test]
-    fn test_get_done_alerts_by_project_id_and_type() {
+    #[tokio::test]
+    async fn test_get_done_alerts_by_project_id_and_type() {
         let ctx = TestContext::new();
-        let (project_id, alert_type) = test_create_done_alert();
+        let (project_id, alert_type) = test_create_done_alert().await;
 
-        let done_alerts = get_done_alerts_by_project_id_and_type(&ctx.conn, project_id, alert_type).unwrap();
+        let done_alerts = get_done_alerts_by_project_id_and_type(&ctx.conn, project_id, alert_type).await.unwrap();
 
         assert_eq!(done_alerts[0].clone().description.unwrap(), "Test done alert".to_string());
     }
@@ -0,0 +1,162 @@
+use std::collections::BTreeMap;
+
+use diesel::prelude::*;
+use diesel::result::Error as DBError;
+use diesel::sql_query;
+use diesel::sql_types::Text;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use infrastructure::{get_connection, schema_version, DBPool};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Exported tables, the referenced ones before the ones referencing them.
+///
+/// The tables of `EXCLUDED_TABLES` are left out.
+pub const EXPORTED_TABLES: &[&str] = &[
+    "main_config",
+    "organizations",
+    "users",
+    "user_passwords",
+    "password_history",
+    "user_config",
+    "users_identities",
+    "api_keys",
+    "audit_logs",
+    "students",
+    "promotions",
+    "promotions_members",
+    "promotions_students",
+    "projects",
+    "project_state_transitions",
+    "project_reminder_policies",
+    "project_late_policies",
+    "student_extensions",
+    "groups",
+    "groups_students",
+    "marks",
+    "students_tokens",
+    "done_alerts",
+];
+
+/// Tables left out of the exports, as their rows are short-lived
+pub const EXCLUDED_TABLES: &[&str] = &["tokens", "mfa_codes", "oidc_login_states"];
+
+/// Tables whose ids are generated by a sequence, which has to follow the imported ids
+const SEQUENCED_TABLES: &[&str] = &["main_config", "user_config", "done_alerts", "project_state_transitions"];
+
+/// Content of the database, as rows of JSON objects by table.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DataExport {
+    /// Version of the latest migration applied to the exported database
+    pub schema_version: Option<String>,
+    pub tables: BTreeMap<String, Vec<Value>>,
+}
+
+#[derive(QueryableByName)]
+struct TableRows {
+    #[diesel(sql_type = Text)]
+    rows: String,
+}
+
+/// Exports all the rows of the exported tables.
+pub async fn export_data(conn: &DBPool) -> Result<DataExport, DBError> {
+    let mut conn = get_connection(conn).await?;
+
+    conn.transaction(|conn| async move {
+        let mut tables = BTreeMap::new();
+        for table in EXPORTED_TABLES {
+            let table_rows = sql_query(format!("SELECT COALESCE(json_agg(t), '[]')::text AS rows FROM {} t", table))
+                .get_result::<TableRows>(conn)
+                .await?;
+            let rows = serde_json::from_str(&table_rows.rows)
+                .map_err(|e| DBError::DeserializationError(Box::new(e)))?;
+            tables.insert(table.to_string(), rows);
+        }
+
+        Ok(DataExport {
+            schema_version: schema_version(),
+            tables,
+        })
+    }.scope_boxed()).await
+}
+
+/// Replaces the content of the exported tables by the rows of an export, in a single transaction.
+///
+/// The export has to come from a database with the same schema version.
+pub async fn import_data(conn: &DBPool, data: &DataExport) -> Result<(), String> {
+    if data.schema_version != schema_version() {
+        return Err(format!(
+            "The export has the schema version {}, while the database has the schema version {}",
+            data.schema_version.as_deref().unwrap_or("unknown"),
+            schema_version().as_deref().unwrap_or("unknown"),
+        ));
+    }
+    if let Some(table) = data.tables.keys().find(|table| !EXPORTED_TABLES.contains(&table.as_str())) {
+        return Err(format!("The table {} can't be imported", table));
+    }
+
+    let mut conn = conn.get().await.map_err(|e| e.to_string())?;
+    conn.transaction::<_, DBError, _>(|conn| async move {
+        sql_query(format!("TRUNCATE {} CASCADE", EXPORTED_TABLES.join(", "))).execute(conn).await?;
+
+        for table in EXPORTED_TABLES {
+            let rows = match data.tables.get(*table) {
+                Some(rows) if !rows.is_empty() => rows,
+                _ => continue,
+            };
+            let rows = serde_json::to_string(rows).map_err(|e| DBError::SerializationError(Box::new(e)))?;
+            sql_query(format!(
+                "INSERT INTO {table} OVERRIDING SYSTEM VALUE SELECT * FROM json_populate_recordset(NULL::{table}, $1::json)"
+            ))
+                .bind::<Text, _>(rows)
+                .execute(conn)
+                .await?;
+        }
+
+        for table in SEQUENCED_TABLES {
+            sql_query(format!(
+                "SELECT setval(pg_get_serial_sequence('{table}', 'id'), COALESCE(MAX(id), 0) + 1, false) FROM {table}"
+            )).execute(conn).await?;
+        }
+        Ok(())
+    }.scope_boxed()).await.map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::organizations::tests::test_create_organization;
+    use infrastructure::init_pool;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_export_data() {
+        let conn = init_pool(dotenvy::var("DATABASE_URL").expect("DATABASE_URL must be set").as_str());
+        let organization_id = test_create_organization().await;
+
+        let export = export_data(&conn).await.unwrap();
+        assert_eq!(export.schema_version, schema_version());
+        assert_eq!(export.tables.len(), EXPORTED_TABLES.len());
+        assert!(export.tables["organizations"].iter()
+            .any(|organization| organization["id"] == Value::String(organization_id.to_string())));
+
+        // Every table of the schema is either exported or deliberately left out
+        let schema_tables: Vec<&str> = include_str!("../../../domain/src/schema.rs").lines()
+            .filter(|line| line.starts_with("    ") && !line.starts_with("     ") && line.ends_with(") {"))
+            .filter_map(|line| line.split_whitespace().next())
+            .collect();
+        assert!(schema_tables.contains(&"project_state_transitions"));
+        for table in &schema_tables {
+            assert!(EXPORTED_TABLES.contains(table) || EXCLUDED_TABLES.contains(table), "{} is neither exported nor excluded", table);
+        }
+        assert_eq!(EXPORTED_TABLES.len() + EXCLUDED_TABLES.len(), schema_tables.len());
+
+        // Exports of another schema are refused before anything is changed
+        let outdated_export = DataExport {
+            schema_version: Some(Uuid::new_v4().to_string()),
+            tables: BTreeMap::new(),
+        };
+        assert!(import_data(&conn, &outdated_export).await.is_err());
+    }
+}
@@ -1,11 +1,21 @@
-use crate::database::marks::get_marks_given_to_student_id_and_group_id;
-use crate::database::projects::get_promotion_from_project_id;
-use crate::database::students::{get_student_by_id, get_students_from_promotion_id};
+use std::collections::HashMap;
+
+use crate::database::pagination::{GroupSortField, ListParams, Page, SortDirection};
+use crate::database::projects::{get_project_by_id, organization_projects_ids};
+use crate::database::students::get_students_from_promotion_id;
+use crate::database::transaction::Executor;
+use diesel::pg::Pg;
 use diesel::prelude::*;
+use diesel::sql_types;
+use diesel_async::RunQueryDsl;
 use diesel::result::Error as DBError;
 use domain::models::groups::*;
+use domain::models::marks::Mark;
 use domain::models::students::Student;
-use infrastructure::DBPool;
+use domain::repositories::groups::GroupsRepository;
+use domain::schema::groups;
+use infrastructure::repositories::PgRepositories;
+use infrastructure::{get_connection, DBPool};
 use serde::Serialize;
 use utoipa::ToSchema;
 use uuid::Uuid;
@@ -49,97 +59,136 @@ pub struct MinimalStudent {
     pub surname: String,
 }
 
-pub fn get_group_id_of_student(conn: &DBPool, student_id_: Uuid, project_id_: Uuid) -> Result<Option<Uuid>, DBError> {
+/// Ids of the groups of an organization, a group belonging to the organization of its project.
+pub fn organization_groups_ids(organization_id_: Uuid) -> groups::BoxedQuery<'static, Pg, sql_types::Uuid> {
+    groups::table
+        .filter(groups::project_id.eq_any(organization_projects_ids(organization_id_)))
+        .select(groups::id)
+        .into_boxed()
+}
+
+pub async fn get_group_id_of_student(conn: impl Executor, organization_id_: Uuid, student_id_: Uuid, project_id_: Uuid) -> Result<Option<Uuid>, DBError> {
     use domain::schema::groups_students::dsl::*;
 
     groups_students
         .filter(student_id.eq(student_id_))
+        .filter(group_id.eq_any(organization_groups_ids(organization_id_)))
         .inner_join(domain::schema::groups::dsl::groups)
         .filter(domain::schema::groups::dsl::project_id.eq(project_id_))
         .select(group_id)
-        .first::<Uuid>(&mut conn.get().unwrap())
+        .first::<Uuid>(&mut conn.connection().await?).await
         .optional()
 }
 
-pub fn get_group_by_id(conn: &DBPool, id_: Uuid) -> Result<Group, DBError> {
+pub async fn get_group_by_id(conn: &DBPool, organization_id_: Uuid, id_: Uuid) -> Result<Group, DBError> {
     use domain::schema::groups::dsl::*;
 
     groups.filter(id.eq(id_))
-        .first(&mut conn.get().unwrap())
+        .filter(project_id.eq_any(organization_projects_ids(organization_id_)))
+        .first(&mut get_connection(conn).await?).await
 }
 
-pub fn get_groups_from_project_id(conn: &DBPool, project_id_: Uuid) -> Result<Vec<Group>, DBError> {
+pub async fn get_groups_from_project_id(conn: &DBPool, organization_id_: Uuid, project_id_: Uuid) -> Result<Vec<Group>, DBError> {
     use domain::schema::groups::dsl::*;
 
     groups.filter(project_id.eq(project_id_))
-        .load::<Group>(&mut conn.get().unwrap())
+        .filter(project_id.eq_any(organization_projects_ids(organization_id_)))
+        .load::<Group>(&mut get_connection(conn).await?).await
 }
 
-pub fn get_groups_and_students_from_project_id(conn: &DBPool, project_id_: Uuid) -> Result<Vec<ProjectGroup>, DBError> {
-    let groups = get_groups_from_project_id(conn, project_id_)?;
-    let groups_ids: Vec<Uuid> = groups.iter().map(|group| group.id).collect();
+pub async fn get_groups_and_students_from_project_id(conn: &DBPool, organization_id_: Uuid, project_id_: Uuid) -> Result<Vec<ProjectGroup>, DBError> {
+    let groups = get_groups_from_project_id(conn, organization_id_, project_id_).await?;
+    let mut groups_students = get_students_and_marks_from_groups(conn, organization_id_, groups.iter().map(|group| group.id).collect()).await?;
 
-    let mut result: Vec<ProjectGroup> = Vec::new();
-    for group_id in groups_ids {
-        let students = get_students_and_marks_from_group(conn, group_id)?;
-        result.push(ProjectGroup {
-            group: get_group_by_id(conn, group_id)?,
-            students
-        });
-    }
+    Ok(groups.into_iter().map(|group| ProjectGroup {
+        students: groups_students.remove(&group.id).unwrap_or_default(),
+        group,
+    }).collect())
+}
 
-    Ok(result)
+/// Returns a page of the groups of a project with their students, matching the text filter on the group name.
+pub async fn get_groups_page_and_students_from_project_id(conn: &DBPool, organization_id_: Uuid, project_id_: Uuid, params: &ListParams<GroupSortField>) -> Result<Page<ProjectGroup>, DBError> {
+    use domain::schema::groups::dsl::*;
+
+    let filtered = || {
+        let mut query = groups
+            .filter(project_id.eq(project_id_))
+            .filter(project_id.eq_any(organization_projects_ids(organization_id_)))
+            .into_boxed();
+        if let Some(pattern) = params.search_pattern() {
+            query = query.filter(name.ilike(pattern));
+        }
+        query
+    };
+
+    let total = filtered().count().get_result::<i64>(&mut get_connection(conn).await?).await?;
+
+    let query = match params.sort_or(GroupSortField::Name) {
+        (GroupSortField::Name, SortDirection::Asc) => filtered().order(name.asc()),
+        (GroupSortField::Name, SortDirection::Desc) => filtered().order(name.desc()),
+        (GroupSortField::Mark, SortDirection::Asc) => filtered().order(mark.asc().nulls_first()),
+        (GroupSortField::Mark, SortDirection::Desc) => filtered().order(mark.desc().nulls_last()),
+    };
+    let page_groups = query.then_order_by(id.asc())
+        .offset(params.offset())
+        .limit(params.limit())
+        .load::<Group>(&mut get_connection(conn).await?).await?;
+
+    let mut groups_students = get_students_and_marks_from_groups(conn, organization_id_, page_groups.iter().map(|group| group.id).collect()).await?;
+    let items = page_groups.into_iter().map(|group| ProjectGroup {
+        students: groups_students.remove(&group.id).unwrap_or_default(),
+        group,
+    }).collect();
+
+    Ok(Page::new(items, total, params))
 }
 
-pub fn get_group_from_student_and_project_id(conn: &DBPool, student_id_: Uuid, project_id_: Uuid) -> Result<Option<Group>, DBError> {
+pub async fn get_group_from_student_and_project_id(conn: &DBPool, organization_id_: Uuid, student_id_: Uuid, project_id_: Uuid) -> Result<Option<Group>, DBError> {
     use domain::schema::groups::dsl::*;
     use domain::schema::groups_students::dsl::*;
 
-    let group_id_ = groups_students
+    groups_students
         .filter(student_id.eq(student_id_))
         .inner_join(groups)
         .filter(project_id.eq(project_id_))
-        .select(group_id)
-        .first::<Uuid>(&mut conn.get().unwrap())
-        .optional();
-
-    match group_id_ {
-        Ok(Some(group_id_)) => Ok(Some(get_group_by_id(conn, group_id_)?)),
-        _ => Ok(None)
-    }
+        .filter(project_id.eq_any(organization_projects_ids(organization_id_)))
+        .select(Group::as_select())
+        .first::<Group>(&mut get_connection(conn).await?).await
+        .optional()
 }
 
-pub fn get_group_student(conn: &DBPool, group_id_: Uuid, student_id_: Uuid) -> Result<GroupStudent, DBError> {
+pub async fn get_group_student(conn: &DBPool, organization_id_: Uuid, group_id_: Uuid, student_id_: Uuid) -> Result<GroupStudent, DBError> {
     use domain::schema::groups_students::dsl::*;
 
     groups_students
         .filter(group_id.eq(group_id_))
+        .filter(group_id.eq_any(organization_groups_ids(organization_id_)))
         .filter(student_id.eq(student_id_))
-        .first(&mut conn.get().unwrap())
+        .first(&mut get_connection(conn).await?).await
 }
 
-pub fn get_students_from_groups(conn: &DBPool, group_ids_: Vec<Uuid>) -> Result<Vec<Student>, DBError> {
+pub async fn get_students_from_groups(conn: &DBPool, organization_id_: Uuid, group_ids_: Vec<Uuid>) -> Result<Vec<Student>, DBError> {
     use domain::schema::groups_students::dsl::*;
     use domain::schema::students::dsl::*;
 
-    let students_ids = groups_students
-        .filter(group_id.eq_any(group_ids_))
-        .select(student_id)
-        .load::<Uuid>(&mut conn.get().unwrap())?;
-
-    students.filter(id.eq_any(students_ids))
-        .load::<Student>(&mut conn.get().unwrap())
+    students.filter(id.eq_any(groups_students
+            .filter(group_id.eq_any(group_ids_))
+            .filter(group_id.eq_any(organization_groups_ids(organization_id_)))
+            .select(student_id)
+        ))
+        .load::<Student>(&mut get_connection(conn).await?).await
 }
 
-pub fn get_students_from_group_for_evaluation(conn: &DBPool, group_id_: Uuid) -> Result<MinimalGroupStudents, DBError> {
+pub async fn get_students_from_group_for_evaluation(conn: &DBPool, organization_id_: Uuid, group_id_: Uuid) -> Result<MinimalGroupStudents, DBError> {
     use domain::schema::groups_students::dsl::*;
     use domain::schema::students::dsl::*;
 
     let student_records = students
         .inner_join(groups_students)
         .filter(group_id.eq(group_id_))
+        .filter(group_id.eq_any(organization_groups_ids(organization_id_)))
         .select((id, name, surname))
-        .load::<(Uuid, String, String)>(&mut conn.get().unwrap())?;
+        .load::<(Uuid, String, String)>(&mut get_connection(conn).await?).await?;
 
     Ok(MinimalGroupStudents {
         group_id: group_id_,
@@ -153,31 +202,37 @@ pub fn get_students_from_group_for_evaluation(conn: &DBPool, group_id_: Uuid) ->
     })
 }
 
-pub fn get_students_and_marks_from_group(conn: &DBPool, group_id_: Uuid) -> Result<Vec<StudentGroup>, DBError> {
+/// Returns the students of the groups with their mark, by group.
+pub async fn get_students_and_marks_from_groups(conn: &DBPool, organization_id_: Uuid, group_ids_: Vec<Uuid>) -> Result<HashMap<Uuid, Vec<StudentGroup>>, DBError> {
     use domain::schema::groups_students::dsl::*;
     use domain::schema::students::dsl::*;
 
     let result = students
         .inner_join(groups_students)
-        .filter(group_id.eq(group_id_))
-        .load::<(Student, GroupStudent)>(&mut conn.get().unwrap())?;
+        .filter(group_id.eq_any(group_ids_))
+        .filter(group_id.eq_any(organization_groups_ids(organization_id_)))
+        .order((surname, name))
+        .load::<(Student, GroupStudent)>(&mut get_connection(conn).await?).await?;
 
-    Ok(result.into_iter().map(|(student, group_student)| {
-        StudentGroup {
+    let mut groups_students_: HashMap<Uuid, Vec<StudentGroup>> = HashMap::new();
+    for (student, group_student) in result {
+        groups_students_.entry(group_student.group_id).or_default().push(StudentGroup {
             student,
             mark: group_student.student_mark
-        }
-    }).collect())
+        });
+    }
+
+    Ok(groups_students_)
 }
 
-pub fn get_students_without_group(conn: &DBPool, project_id_: Uuid) -> Result<Vec<Student>, DBError> {
-    let promotion = get_promotion_from_project_id(conn, project_id_)?;
-    let students = get_students_from_promotion_id(conn, promotion.id)?;
+pub async fn get_students_without_group(conn: &DBPool, organization_id_: Uuid, project_id_: Uuid) -> Result<Vec<Student>, DBError> {
+    let project = get_project_by_id(conn, organization_id_, project_id_).await?;
+    let students = get_students_from_promotion_id(conn, organization_id_, project.promotion_id).await?;
 
     // Get all students from all groups of the project
-    let groups = get_groups_from_project_id(conn, project_id_)?;
+    let groups = get_groups_from_project_id(conn, organization_id_, project_id_).await?;
     let groups_ids = groups.iter().map(|group| group.id).collect();
-    let groups_students = get_students_from_groups(conn, groups_ids)?;
+    let groups_students = get_students_from_groups(conn, organization_id_, groups_ids).await?;
 
     // Return array with students from promotion that are not in any group
     Ok(students.into_iter().filter(|student| {
@@ -185,14 +240,14 @@ pub fn get_students_without_group(conn: &DBPool, project_id_: Uuid) -> Result<Ve
     }).collect())
 }
 
-pub fn get_students_with_group(conn: &DBPool, project_id_: Uuid) -> Result<Vec<Student>, DBError> {
-    let promotion = get_promotion_from_project_id(conn, project_id_)?;
-    let students = get_students_from_promotion_id(conn, promotion.id)?;
+pub async fn get_students_with_group(conn: &DBPool, organization_id_: Uuid, project_id_: Uuid) -> Result<Vec<Student>, DBError> {
+    let project = get_project_by_id(conn, organization_id_, project_id_).await?;
+    let students = get_students_from_promotion_id(conn, organization_id_, project.promotion_id).await?;
 
     // Get all students from all groups of the project
-    let groups = get_groups_from_project_id(conn, project_id_)?;
+    let groups = get_groups_from_project_id(conn, organization_id_, project_id_).await?;
     let groups_ids = groups.iter().map(|group| group.id).collect();
-    let groups_students = get_students_from_groups(conn, groups_ids)?;
+    let groups_students = get_students_from_groups(conn, organization_id_, groups_ids).await?;
 
     // Return array with students from promotion that are in a group
     Ok(students.into_iter().filter(|student| {
@@ -200,133 +255,147 @@ pub fn get_students_with_group(conn: &DBPool, project_id_: Uuid) -> Result<Vec<S
     }).collect())
 }
 
-pub fn get_group_student_mark_details(conn: &DBPool, group_id_: Uuid, student_id_: Uuid) -> Result<StudentGroupMarkDetails, DBError> {
-    let graded_student = get_student_by_id(conn, student_id_)?;
-
-    let group_students = get_students_from_groups(conn, vec![group_id_])?;
-    let mut filtered_group_students: Vec<Student> = group_students.into_iter().filter(|student| student.id != student_id_).collect();
-
-    let marks = get_marks_given_to_student_id_and_group_id(conn, student_id_, group_id_)?;
-    let mut student_group_marks: Vec<StudentGroupMark> = Vec::new();
-    for value in marks {
-        let grader = get_student_by_id(conn, value.grader_student_id)?;
-        filtered_group_students = filtered_group_students.into_iter().filter(|student| student.id != grader.id).collect();
+pub async fn get_group_student_mark_details(conn: &DBPool, organization_id_: Uuid, group_id_: Uuid, student_id_: Uuid) -> Result<StudentGroupMarkDetails, DBError> {
+    use domain::schema::{groups_students, marks, students};
+
+    let graded_student = students::table
+        .inner_join(groups_students::table)
+        .filter(groups_students::group_id.eq(group_id_))
+        .filter(groups_students::group_id.eq_any(organization_groups_ids(organization_id_)))
+        .filter(students::id.eq(student_id_))
+        .select(Student::as_select())
+        .first::<Student>(&mut get_connection(conn).await?).await?;
+
+    let group_students = students::table
+        .inner_join(groups_students::table)
+        .filter(groups_students::group_id.eq(group_id_))
+        .filter(students::id.ne(student_id_))
+        .select(Student::as_select())
+        .load::<Student>(&mut get_connection(conn).await?).await?;
+
+    // The graders are loaded with their marks
+    let marks = marks::table
+        .inner_join(students::table.on(students::id.eq(marks::grader_student_id)))
+        .filter(marks::noted_student_id.eq(student_id_))
+        .filter(marks::group_id.eq(group_id_))
+        .select((Mark::as_select(), Student::as_select()))
+        .load::<(Mark, Student)>(&mut get_connection(conn).await?).await?;
+
+    let ungraded_students: Vec<Student> = group_students.into_iter()
+        .filter(|student| !marks.iter().any(|(_, grader)| grader.id == student.id))
+        .collect();
+
+    let mut student_group_marks: Vec<StudentGroupMark> = marks.into_iter().map(|(value, grader)| StudentGroupMark {
+        grader,
+        mark: Some(value.mark),
+        max_mark: value.max_mark,
+        comment: value.comment
+    }).collect();
+
+    for student in ungraded_students {
         student_group_marks.push(StudentGroupMark {
-            grader,
-            mark: Some(value.mark),
-            max_mark: value.max_mark,
-            comment: value.comment
+            grader: student,
+            mark: None,
+            max_mark: 20,
+            comment: None
         });
     }
 
-    if filtered_group_students.len() > 0 {
-        for student in filtered_group_students {
-            student_group_marks.push(StudentGroupMark {
-                grader: student,
-                mark: None,
-                max_mark: 20,
-                comment: None
-            });
-        }
-    }
-
     Ok(StudentGroupMarkDetails {
         student: graded_student,
         marks: student_group_marks,
     })
 }
 
-pub fn create_group(conn: &DBPool, new_group: NewGroup) -> Result<Uuid, DBError> {
+pub async fn create_group(conn: &DBPool, organization_id_: Uuid, new_group: NewGroup) -> Result<Uuid, DBError> {
     use domain::schema::groups::dsl::*;
 
+    // Check if the project exists in the organization
+    get_project_by_id(conn, organization_id_, new_group.project_id).await?;
+
     let result: Result<Uuid, DBError> = diesel::insert_into(groups)
         .values(&new_group)
         .returning(id)
-        .get_result(&mut conn.get().unwrap());
+        .get_result(&mut get_connection(conn).await?).await;
 
     result
 }
 
-pub fn create_group_students(conn: &DBPool, new_group_students: Vec<NewGroupStudent>) -> Result<(), DBError> {
+pub async fn create_group_students(conn: impl Executor, new_group_students: Vec<NewGroupStudent>) -> Result<(), DBError> {
     use domain::schema::groups_students::dsl::*;
 
     diesel::insert_into(groups_students)
         .values(&new_group_students)
-        .execute(&mut conn.get().unwrap())?;
+        .execute(&mut conn.connection().await?).await?;
 
     Ok(())
 }
 
-pub fn update_group(conn: &DBPool, id_: Uuid, updated_group: UpdatedGroup) -> Result<(), DBError> {
+pub async fn update_group(conn: &DBPool, organization_id_: Uuid, id_: Uuid, updated_group: UpdatedGroup) -> Result<(), DBError> {
     use domain::schema::groups::dsl::*;
 
-    // Check if the student exists
-    groups.filter(id.eq(id_.clone()))
-        .first::<Group>(&mut conn.get().unwrap())?;
+    // Check if the group exists in the organization
+    get_group_by_id(conn, organization_id_, id_).await?;
 
     diesel::update(groups.filter(id.eq(id_)))
         .set(&updated_group)
-        .execute(&mut conn.get().unwrap())?;
+        .execute(&mut get_connection(conn).await?).await?;
 
     Ok(())
 }
 
-pub fn update_group_student(conn: &DBPool, group_id_: Uuid, student_id_: Uuid, updated_group_student: UpdatedGroupStudent) -> Result<(), DBError> {
-    use domain::schema::groups_students::dsl::*;
-
-    // Check if the student exists
-    groups_students
-        .filter(group_id.eq(group_id_))
-        .filter(student_id.eq(student_id_))
-        .first::<GroupStudent>(&mut conn.get().unwrap())?;
-
-    diesel::update(groups_students
-        .filter(group_id.eq(group_id_))
-        .filter(student_id.eq(student_id_))
-    ).set(&updated_group_student)
-        .execute(&mut conn.get().unwrap())?;
+pub async fn update_group_student(conn: &DBPool, organization_id_: Uuid, group_id_: Uuid, student_id_: Uuid, updated_group_student: UpdatedGroupStudent) -> Result<(), DBError> {
+    // Check if the group exists in the organization
+    get_group_by_id(conn, organization_id_, group_id_).await?;
 
-    Ok(())
+    PgRepositories::new(conn.clone()).update_group_student(group_id_, student_id_, updated_group_student).await
 }
 
-pub fn delete_group(conn: &DBPool, id_: Uuid) -> Result<(), DBError> {
+pub async fn delete_group(conn: impl Executor, organization_id_: Uuid, id_: Uuid) -> Result<(), DBError> {
     use domain::schema::groups::dsl::*;
 
-    // Check if the student exists
-    groups.filter(id.eq(id_.clone()))
-        .first::<Group>(&mut conn.get().unwrap())?;
+    let mut connection = conn.connection().await?;
+
+    // Check if the group exists in the organization
+    groups.filter(id.eq(id_))
+        .filter(project_id.eq_any(organization_projects_ids(organization_id_)))
+        .first::<Group>(&mut connection).await?;
 
     diesel::delete(groups.filter(id.eq(id_)))
-        .execute(&mut conn.get().unwrap())?;
+        .execute(&mut connection).await?;
 
     Ok(())
 }
 
-pub fn delete_group_student(conn: &DBPool, group_id_: Uuid, student_id_: Uuid) -> Result<(), DBError> {
+pub async fn delete_group_student(conn: &DBPool, organization_id_: Uuid, group_id_: Uuid, student_id_: Uuid) -> Result<(), DBError> {
     use domain::schema::groups_students::dsl::*;
 
     diesel::delete(groups_students
         .filter(group_id.eq(group_id_))
+        .filter(group_id.eq_any(organization_groups_ids(organization_id_)))
         .filter(student_id.eq(student_id_))
-    ).execute(&mut conn.get().unwrap())?;
+    ).execute(&mut get_connection(conn).await?).await?;
 
     Ok(())
 }
 
-pub fn remove_students_from_groups(conn: &DBPool, student_id_: Uuid) -> Result<(), DBError> {
+pub async fn remove_students_from_groups(conn: impl Executor, student_id_: Uuid) -> Result<(), DBError> {
     use domain::schema::groups_students::dsl::*;
 
     diesel::delete(groups_students.filter(student_id.eq(student_id_)))
-        .execute(&mut conn.get().unwrap())?;
+        .execute(&mut conn.connection().await?).await?;
 
     Ok(())
 }
 
-pub fn remove_all_students_from_a_group(conn: &DBPool, group_id_: Uuid) -> Result<(), DBError> {
+pub async fn remove_all_students_from_a_group(conn: impl Executor, organization_id_: Uuid, group_id_: Uuid) -> Result<(), DBError> {
     use domain::schema::groups_students::dsl::*;
 
-    diesel::delete(groups_students.filter(group_id.eq(group_id_)))
-        .execute(&mut conn.get().unwrap())?;
+    diesel::delete(groups_students
+        .filter(group_id.eq(group_id_))
+        .filter(group_id.eq_any(organization_groups_ids(organization_id_)))
+    )
+        .execute(&mut conn.connection().await?).await?;
 
     Ok(())
 }
@@ -334,8 +403,12 @@ pub fn remove_all_students_from_a_group(conn: &DBPool, group_id_: Uuid) -> Resul
 #[cfg(test)]
 pub mod test {
     use super::*;
+    use crate::database::marks::create_mark;
+    use crate::database::organizations::tests::test_create_organization;
+    use crate::database::projects::get_promotion_from_project_id;
     use crate::database::projects::test::test_create_project;
-    use crate::database::students::test::test_create_student;
+    use crate::database::students::test::test_create_student_in_organization;
+    use domain::models::marks::NewMark;
     use infrastructure::init_pool;
 
     struct TestContext {
@@ -354,10 +427,11 @@ pub mod test {
         }
     }
 
-    pub fn test_create_group() -> (Uuid, Uuid) {
+    pub async fn test_create_group() -> (Uuid, Uuid, Uuid) {
         let context = TestContext::new();
 
-        let (project_id, _) = test_create_project();
+        let (project_id, _) = test_create_project().await;
+        let organization_id = get_promotion_from_project_id(&context.conn, project_id).await.unwrap().organization_id;
         let random = Uuid::new_v4();
         let new_group = NewGroup{
             name: format!("test-{}", random),
@@ -365,59 +439,89 @@ pub mod test {
             max_mark: None,
         };
 
-        (create_group(&context.conn, new_group).unwrap(), project_id)
+        (create_group(&context.conn, organization_id, new_group).await.unwrap(), project_id, organization_id)
     }
 
-    fn test_create_group_student() -> (Uuid, Uuid) {
+    async fn test_create_group_student() -> (Uuid, Uuid, Uuid) {
         let context = TestContext::new();
 
-        let (group_id, _) = test_create_group();
-        let student_id = test_create_student();
+        let (group_id, _, organization_id) = test_create_group().await;
+        let student_id = test_create_student_in_organization(organization_id).await;
 
         let new_group_student = vec![NewGroupStudent {
             group_id,
             student_id
         }];
 
-        create_group_students(&context.conn, new_group_student).unwrap();
+        create_group_students(&context.conn, new_group_student).await.unwrap();
 
-        (group_id, student_id)
+        (group_id, student_id, organization_id)
     }
 
-    #[test]
-    fn test_get_group_by_id() {
+    #[tokio::test]
+    async fn test_get_group_by_id() {
         let context = TestContext::new();
 
-        let (group_id, _) = test_create_group();
+        let (group_id, _, organization_id) = test_create_group().await;
 
-        let group = get_group_by_id(&context.conn, group_id).unwrap();
-        assert_eq!(group_id, group.id)
+        let group = get_group_by_id(&context.conn, organization_id, group_id).await.unwrap();
+        assert_eq!(group_id, group.id);
     }
 
-    #[test]
-    fn test_get_group_by_project_id() {
+    #[tokio::test]
+    async fn test_groups_of_other_organizations_are_hidden() {
         let context = TestContext::new();
 
-        let (group_id, project_id) = test_create_group();
+        let (group_id, student_id, _) = test_create_group_student().await;
+        let other_organization_id = test_create_organization().await;
 
-        let groups = get_groups_from_project_id(&context.conn, project_id).unwrap();
+        assert!(matches!(get_group_by_id(&context.conn, other_organization_id, group_id).await, Err(DBError::NotFound)));
+        assert!(matches!(get_group_student(&context.conn, other_organization_id, group_id, student_id).await, Err(DBError::NotFound)));
+        assert!(get_students_and_marks_from_groups(&context.conn, other_organization_id, vec![group_id]).await.unwrap().is_empty());
+        let updated_group = UpdatedGroup {
+            name: Some("updated".to_string()),
+            mark: None,
+            max_mark: None,
+        };
+        assert!(matches!(update_group(&context.conn, other_organization_id, group_id, updated_group).await, Err(DBError::NotFound)));
+        assert!(matches!(delete_group(&context.conn, other_organization_id, group_id).await, Err(DBError::NotFound)));
+        remove_all_students_from_a_group(&context.conn, other_organization_id, group_id).await.unwrap();
+        delete_group_student(&context.conn, other_organization_id, group_id, student_id).await.unwrap();
+
+        // The group and its students are left untouched
+        assert!(get_students_from_groups(&context.conn, other_organization_id, vec![group_id]).await.unwrap().is_empty());
+        let students = PgRepositories::new(context.conn.clone()).get_students_from_groups(vec![group_id]).await.unwrap();
+        assert_eq!(students.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_group_by_project_id() {
+        let context = TestContext::new();
+
+        let (group_id, project_id, organization_id) = test_create_group().await;
+
+        let groups = get_groups_from_project_id(&context.conn, organization_id, project_id).await.unwrap();
         assert_eq!(groups[0].id, group_id);
+        let groups = get_groups_from_project_id(&context.conn, Uuid::new_v4(), project_id).await.unwrap();
+        assert!(groups.is_empty());
     }
 
-    #[test]
-    fn test_get_students_without_group() {
+    #[tokio::test]
+    async fn test_get_students_without_group() {
         let context = TestContext::new();
 
-        let (project_id, _) = test_create_project();
+        let (project_id, _) = test_create_project().await;
+        let organization_id = get_promotion_from_project_id(&context.conn, project_id).await.unwrap().organization_id;
 
-        get_students_without_group(&context.conn, project_id).unwrap();
+        get_students_without_group(&context.conn, organization_id, project_id).await.unwrap();
+        assert!(matches!(get_students_without_group(&context.conn, Uuid::new_v4(), project_id).await, Err(DBError::NotFound)));
     }
 
-    #[test]
-    fn test_update_group() {
+    #[tokio::test]
+    async fn test_update_group() {
         let context = TestContext::new();
 
-        let (group_id, _) = test_create_group();
+        let (group_id, _, organization_id) = test_create_group().await;
 
         let random = Uuid::new_v4();
         let updated_group = UpdatedGroup {
@@ -425,50 +529,119 @@ pub mod test {
             mark: None,
             max_mark: None,
         };
-        update_group(&context.conn, group_id, updated_group).unwrap();
+        update_group(&context.conn, organization_id, group_id, updated_group).await.unwrap();
 
-        let group = get_group_by_id(&context.conn, group_id).unwrap();
+        let group = get_group_by_id(&context.conn, organization_id, group_id).await.unwrap();
         assert_eq!(group.name, format!("updated-{}", random));
     }
 
-    #[test]
-    fn test_update_group_student() {
+    #[tokio::test]
+    async fn test_update_group_student() {
         let context = TestContext::new();
 
-        let (group_id, student_id) = test_create_group_student();
+        let (group_id, student_id, organization_id) = test_create_group_student().await;
 
         let updated_group_student = UpdatedGroupStudent {
             student_mark: Some(10.0),
         };
-        update_group_student(&context.conn, group_id, student_id, updated_group_student).unwrap();
+        update_group_student(&context.conn, organization_id, group_id, student_id, updated_group_student).await.unwrap();
 
-        let group_student = get_group_student(&context.conn, group_id, student_id).unwrap();
+        let group_student = get_group_student(&context.conn, organization_id, group_id, student_id).await.unwrap();
         assert_eq!(group_student.student_mark, Some(10.0));
     }
 
-    #[test]
-    fn test_delete_group() {
+    #[tokio::test]
+    async fn test_update_groups_students_marks() {
+        let context = TestContext::new();
+
+        let (group_id, student_id, organization_id) = test_create_group_student().await;
+
+        PgRepositories::new(context.conn.clone())
+            .update_groups_students_marks(vec![(group_id, student_id, 12.5), (group_id, Uuid::new_v4(), 8.0)]).await.unwrap();
+
+        let group_student = get_group_student(&context.conn, organization_id, group_id, student_id).await.unwrap();
+        assert_eq!(group_student.student_mark, Some(12.5));
+    }
+
+    #[tokio::test]
+    async fn test_get_groups_and_students_from_project_id() {
+        let context = TestContext::new();
+
+        let (group_id, student_id, organization_id) = test_create_group_student().await;
+        let project_id = get_group_by_id(&context.conn, organization_id, group_id).await.unwrap().project_id;
+        let empty_group_id = create_group(&context.conn, organization_id, NewGroup {
+            name: "empty".to_string(),
+            project_id,
+            max_mark: None,
+        }).await.unwrap();
+
+        let groups = get_groups_and_students_from_project_id(&context.conn, organization_id, project_id).await.unwrap();
+
+        assert_eq!(groups.len(), 2);
+        let group = groups.iter().find(|project_group| project_group.group.id == group_id).unwrap();
+        assert_eq!(group.students.len(), 1);
+        assert_eq!(group.students[0].student.id, student_id);
+        let empty_group = groups.iter().find(|project_group| project_group.group.id == empty_group_id).unwrap();
+        assert!(empty_group.students.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_group_student_mark_details() {
+        let context = TestContext::new();
+
+        let (group_id, student_id, organization_id) = test_create_group_student().await;
+        let project_id = get_group_by_id(&context.conn, organization_id, group_id).await.unwrap().project_id;
+        let (grader_id, ungraded_id) = (
+            test_create_student_in_organization(organization_id).await,
+            test_create_student_in_organization(organization_id).await,
+        );
+        create_group_students(&context.conn, vec![
+            NewGroupStudent { group_id, student_id: grader_id },
+            NewGroupStudent { group_id, student_id: ungraded_id },
+        ]).await.unwrap();
+        create_mark(&context.conn, organization_id, NewMark {
+            project_id,
+            group_id,
+            noted_student_id: student_id,
+            grader_student_id: grader_id,
+            mark: 14.0,
+            max_mark: None,
+            comment: Some("test".to_string()),
+        }).await.unwrap();
+
+        let details = get_group_student_mark_details(&context.conn, organization_id, group_id, student_id).await.unwrap();
+
+        assert_eq!(details.student.id, student_id);
+        assert_eq!(details.marks.len(), 2);
+        assert_eq!(details.marks[0].grader.id, grader_id);
+        assert_eq!(details.marks[0].mark, Some(14.0));
+        assert_eq!(details.marks[1].grader.id, ungraded_id);
+        assert_eq!(details.marks[1].mark, None);
+    }
+
+    #[tokio::test]
+    async fn test_delete_group() {
         let context = TestContext::new();
 
-        let (group_id, _) = test_create_group();
+        let (group_id, _, organization_id) = test_create_group().await;
 
-        delete_group(&context.conn, group_id).unwrap();
+        delete_group(&context.conn, organization_id, group_id).await.unwrap();
     }
 
-    #[test]
-    fn test_delete_group_student() {
+    #[tokio::test]
+    async fn test_delete_group_student() {
         let context = TestContext::new();
 
-        let (group_id, student_id) = test_create_group_student();
+        let (group_id, student_id, organization_id) = test_create_group_student().await;
 
-        delete_group_student(&context.conn, group_id, student_id).unwrap();
+        delete_group_student(&context.conn, organization_id, group_id, student_id).await.unwrap();
     }
 
-    fn test_remove_student_from_groups() {
+    async fn test_remove_student_from_groups() {
         let context = TestContext::new();
 
-        let (_, student_id) = test_create_group_student();
+        let (_, student_id, _) = test_create_group_student().await;
 
-        remove_students_from_groups(&context.conn, student_id).unwrap();
+        remove_students_from_groups(&context.conn, student_id).await.unwrap();
     }
 }
\ No newline at end of file
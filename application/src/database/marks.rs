@@ -1,78 +1,73 @@
-use crate::database::groups::get_students_with_group;
+use crate::database::projects::{get_project_by_id, organization_projects_ids};
+use crate::database::transaction::Executor;
 use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
 use diesel::result::Error as DBError;
 use domain::models::marks::*;
 use domain::models::students::Student;
-use infrastructure::DBPool;
+use domain::repositories::marks::MarksRepository;
+use infrastructure::repositories::PgRepositories;
+use infrastructure::{get_connection, DBPool};
 use uuid::Uuid;
 
-fn get_all_marks_of_project(conn: &DBPool, project_id_: Uuid) -> Result<Vec<Mark>, DBError> {
-    use domain::schema::marks::dsl::*;
-
-    marks.filter(project_id.eq(project_id_))
-        .get_results(&mut conn.get().unwrap())
-}
-
-pub fn get_marks_from_group_id(conn: &DBPool, group_id_: Uuid) -> Result<Vec<Mark>, DBError> {
+pub async fn get_marks_from_group_id(conn: &DBPool, organization_id_: Uuid, group_id_: Uuid) -> Result<Vec<Mark>, DBError> {
     use domain::schema::marks::dsl::*;
 
     marks.filter(group_id.eq(group_id_))
-        .get_results(&mut conn.get().unwrap())
+        .filter(project_id.eq_any(organization_projects_ids(organization_id_)))
+        .get_results(&mut get_connection(conn).await?).await
 }
 
-pub fn get_marks_given_to_student_id(conn: &DBPool, student_id_: Uuid) -> Result<Vec<Mark>, DBError> {
+pub async fn get_marks_given_to_student_id(conn: &DBPool, organization_id_: Uuid, student_id_: Uuid) -> Result<Vec<Mark>, DBError> {
     use domain::schema::marks::dsl::*;
 
     marks.filter(noted_student_id.eq(student_id_))
-        .get_results(&mut conn.get().unwrap())
+        .filter(project_id.eq_any(organization_projects_ids(organization_id_)))
+        .get_results(&mut get_connection(conn).await?).await
 }
 
-pub fn get_marks_given_to_student_id_and_group_id(conn: &DBPool, student_id_: Uuid, group_id_: Uuid) -> Result<Vec<Mark>, DBError> {
+pub async fn get_marks_given_to_student_id_and_group_id(conn: &DBPool, organization_id_: Uuid, student_id_: Uuid, group_id_: Uuid) -> Result<Vec<Mark>, DBError> {
     use domain::schema::marks::dsl::*;
 
     marks.filter(noted_student_id.eq(student_id_))
         .filter(group_id.eq(group_id_))
-        .get_results(&mut conn.get().unwrap())
+        .filter(project_id.eq_any(organization_projects_ids(organization_id_)))
+        .get_results(&mut get_connection(conn).await?).await
 }
 
-pub fn get_students_who_didnt_evaluate_group(conn: &DBPool, project_id_: Uuid) -> Result<Vec<Student>, DBError> {
-    let project_students = get_students_with_group(conn, project_id_)?;
-    let project_marks = get_all_marks_of_project(conn, project_id_)?;
-
-    let mut students = Vec::new();
-    for student in project_students {
-        let mut found = false;
-        for mark in project_marks.iter() {
-            if mark.grader_student_id == student.id {
-                found = true;
-                break;
-            }
-        }
+pub async fn get_students_who_didnt_evaluate_group(conn: &DBPool, organization_id_: Uuid, project_id_: Uuid) -> Result<Vec<Student>, DBError> {
+    // Check if the project exists
+    get_project_by_id(conn, organization_id_, project_id_).await?;
 
-        if !found {
-            students.push(student);
-        }
-    }
-
-    Ok(students)
+    PgRepositories::new(conn.clone()).get_students_who_didnt_evaluate_group(project_id_).await
 }
 
-pub fn create_mark(conn: &DBPool, new_mark: NewMark) -> Result<(), DBError> {
+pub async fn create_mark(conn: impl Executor, organization_id_: Uuid, new_mark: NewMark) -> Result<(), DBError> {
     use domain::schema::marks::dsl::*;
+    use domain::schema::projects;
+
+    let mut connection = conn.connection().await?;
+
+    // Check if the project of the mark exists
+    projects::table.filter(projects::id.eq(new_mark.project_id))
+        .filter(projects::id.eq_any(organization_projects_ids(organization_id_)))
+        .select(projects::id)
+        .first::<Uuid>(&mut connection).await?;
 
     diesel::insert_into(marks)
         .values(&new_mark)
-        .execute(&mut conn.get().unwrap())?;
+        .execute(&mut connection).await?;
 
     Ok(())
 }
 
-pub fn delete_all_marks_from_student(conn: &DBPool, student_id_: Uuid) -> Result<(), DBError> {
+pub async fn delete_all_marks_from_student(conn: impl Executor, organization_id_: Uuid, student_id_: Uuid) -> Result<(), DBError> {
     use domain::schema::marks::dsl::*;
 
     diesel::delete(marks.filter(noted_student_id.eq(student_id_))
-        .filter(grader_student_id.eq(student_id_)))
-        .execute(&mut conn.get().unwrap())?;
+        .filter(grader_student_id.eq(student_id_))
+        .filter(project_id.eq_any(organization_projects_ids(organization_id_))))
+        .execute(&mut conn.connection().await?).await?;
 
     Ok(())
 }
@@ -81,7 +76,8 @@ pub fn delete_all_marks_from_student(conn: &DBPool, student_id_: Uuid) -> Result
 pub mod tests {
     use super::*;
     use crate::database::groups::test::test_create_group;
-    use crate::database::students::test::test_create_student;
+    use crate::database::organizations::tests::test_create_organization;
+    use crate::database::students::test::test_create_student_in_organization;
     use infrastructure::init_pool;
 
     struct TestContext {
@@ -100,11 +96,11 @@ pub mod tests {
         }
     }
 
-    fn test_create_mark() -> (Uuid, Uuid, Uuid) {
+    async fn test_create_mark() -> (Uuid, Uuid, Uuid) {
         let context = TestContext::new();
 
-        let (group_id, project_id) = test_create_group();
-        let student_id = test_create_student();
+        let (group_id, project_id, organization_id) = test_create_group().await;
+        let student_id = test_create_student_in_organization(organization_id).await;
         let new_mark = NewMark {
             project_id,
             group_id,
@@ -115,53 +111,61 @@ pub mod tests {
             comment: Some("test".to_string()),
         };
 
-        create_mark(&context.conn, new_mark).unwrap();
+        create_mark(&context.conn, organization_id, new_mark).await.unwrap();
 
-        (group_id, student_id, student_id)
+        (group_id, student_id, organization_id)
     }
 
-    #[test]
-    fn test_get_marks_from_group_id() {
+    #[tokio::test]
+    async fn test_get_marks_from_group_id() {
         let context = TestContext::new();
 
-        let (group_id, _, _) = test_create_mark();
+        let (group_id, _, organization_id) = test_create_mark().await;
 
-        let marks = get_marks_from_group_id(&context.conn, group_id).unwrap();
+        let marks = get_marks_from_group_id(&context.conn, organization_id, group_id).await.unwrap();
 
         assert_eq!(marks.len(), 1);
+        // The marks of the other organizations are hidden
+        assert!(get_marks_from_group_id(&context.conn, test_create_organization().await, group_id).await.unwrap().is_empty());
     }
 
-    #[test]
-    fn test_get_marks_given_to_student_id() {
+    #[tokio::test]
+    async fn test_get_marks_given_to_student_id() {
         let context = TestContext::new();
 
-        let (_, student_id, _) = test_create_mark();
+        let (_, student_id, organization_id) = test_create_mark().await;
 
-        let marks = get_marks_given_to_student_id(&context.conn, student_id).unwrap();
+        let marks = get_marks_given_to_student_id(&context.conn, organization_id, student_id).await.unwrap();
 
         assert_eq!(marks.len(), 1);
+        assert!(get_marks_given_to_student_id(&context.conn, test_create_organization().await, student_id).await.unwrap().is_empty());
     }
 
-    #[test]
-    fn test_get_marks_given_to_student_id_and_group_id() {
+    #[tokio::test]
+    async fn test_get_marks_given_to_student_id_and_group_id() {
         let context = TestContext::new();
 
-        let (group_id, student_id, _) = test_create_mark();
+        let (group_id, student_id, organization_id) = test_create_mark().await;
 
-        let marks = get_marks_given_to_student_id_and_group_id(&context.conn, student_id, group_id).unwrap();
+        let marks = get_marks_given_to_student_id_and_group_id(&context.conn, organization_id, student_id, group_id).await.unwrap();
 
         assert_eq!(marks.len(), 1);
+        let other_organization_id = test_create_organization().await;
+        assert!(get_marks_given_to_student_id_and_group_id(&context.conn, other_organization_id, student_id, group_id).await.unwrap().is_empty());
     }
 
-    #[test]
-    fn test_delete_all_marks_from_student() {
+    #[tokio::test]
+    async fn test_delete_all_marks_from_student() {
         let context = TestContext::new();
 
-        let (_, student_id, _) = test_create_mark();
+        let (_, student_id, organization_id) = test_create_mark().await;
 
-        delete_all_marks_from_student(&context.conn, student_id).unwrap();
+        // The marks of the other organizations are kept
+        delete_all_marks_from_student(&context.conn, test_create_organization().await, student_id).await.unwrap();
+        assert_eq!(get_marks_given_to_student_id(&context.conn, organization_id, student_id).await.unwrap().len(), 1);
+        delete_all_marks_from_student(&context.conn, organization_id, student_id).await.unwrap();
 
-        let marks = get_marks_given_to_student_id(&context.conn, student_id).unwrap();
+        let marks = get_marks_given_to_student_id(&context.conn, organization_id, student_id).await.unwrap();
 
         assert_eq!(marks.len(), 0);
     }
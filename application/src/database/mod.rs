@@ -1,7 +1,13 @@
+pub mod organizations;
 pub mod users;
+pub mod users_identities;
+pub mod api_keys;
 pub mod user_passwords;
+pub mod password_history;
 pub mod promotions;
+pub mod promotions_members;
 pub mod tokens;
+pub mod audit_logs;
 pub mod config;
 pub mod students;
 pub mod projects;
@@ -9,4 +15,9 @@ pub mod groups;
 pub mod marks;
 pub mod students_tokens;
 pub mod done_alerts;
-pub mod mfa_codes;
\ No newline at end of file
+pub mod mfa_codes;
+pub mod oidc_login_states;
+pub mod pagination;
+pub mod search;
+pub mod export;
+pub mod transaction;
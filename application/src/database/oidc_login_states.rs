@@ -0,0 +1,100 @@
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use diesel::result::Error as DBError;
+use domain::models::oidc_login_states::*;
+use infrastructure::{get_connection, DBPool};
+use uuid::Uuid;
+
+pub async fn create_oidc_login_state(conn: &DBPool, new_oidc_login_state: NewOidcLoginState) -> Result<Uuid, DBError> {
+    use domain::schema::oidc_login_states::dsl::*;
+
+    diesel::insert_into(oidc_login_states)
+        .values(&new_oidc_login_state)
+        .returning(id)
+        .get_result(&mut get_connection(conn).await?).await
+}
+
+/// Consumes a pending login: the state is deleted so it can only be used once.
+///
+/// Expired states are reported as not found.
+pub async fn take_oidc_login_state(conn: &DBPool, state_: &str) -> Result<OidcLoginState, DBError> {
+    use domain::schema::oidc_login_states::dsl::*;
+
+    let login_state: OidcLoginState = diesel::delete(oidc_login_states.filter(state.eq(state_)))
+        .returning(OidcLoginState::as_returning())
+        .get_result(&mut get_connection(conn).await?).await?;
+
+    if login_state.exp < chrono::Utc::now().naive_utc() {
+        return Err(DBError::NotFound);
+    }
+
+    Ok(login_state)
+}
+
+pub async fn delete_expired_oidc_login_states(conn: &DBPool) -> Result<usize, DBError> {
+    use domain::schema::oidc_login_states::dsl::*;
+
+    diesel::delete(oidc_login_states.filter(exp.lt(diesel::dsl::now)))
+        .execute(&mut get_connection(conn).await?).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::organizations::tests::test_create_organization;
+    use infrastructure::init_pool;
+
+    struct TestContext {
+        conn: DBPool,
+    }
+
+    impl TestContext {
+        fn new() -> Self {
+            TestContext {
+                conn: init_pool(
+                    dotenvy::var("DATABASE_URL")
+                        .expect("DATABASE_URL must be set")
+                        .as_str()
+                ),
+            }
+        }
+    }
+
+    async fn test_create_oidc_login_state() -> String {
+        let context = TestContext::new();
+
+        let state = Uuid::new_v4().to_string();
+        create_oidc_login_state(&context.conn, NewOidcLoginState {
+            state: state.clone(),
+            code_verifier: "verifier".to_string(),
+            organization_id: test_create_organization().await,
+        }).await.unwrap();
+
+        state
+    }
+
+    #[tokio::test]
+    async fn test_take_oidc_login_state() {
+        let context = TestContext::new();
+
+        let state = test_create_oidc_login_state().await;
+
+        let login_state = take_oidc_login_state(&context.conn, &state).await.unwrap();
+        assert_eq!(login_state.code_verifier, "verifier");
+        assert!(take_oidc_login_state(&context.conn, &state).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_take_expired_oidc_login_state() {
+        use domain::schema::oidc_login_states::dsl::*;
+        let context = TestContext::new();
+
+        let state_ = test_create_oidc_login_state().await;
+        diesel::update(oidc_login_states.filter(state.eq(&state_)))
+            .set(exp.eq(chrono::Utc::now().naive_utc() - chrono::Duration::minutes(1)))
+            .execute(&mut context.conn.get().await.unwrap()).await
+            .unwrap();
+
+        assert!(take_oidc_login_state(&context.conn, &state_).await.is_err());
+    }
+}
@@ -0,0 +1,211 @@
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use diesel::result::Error as DBError;
+use domain::models::organizations::*;
+use domain::models::users::User;
+use domain::repositories::organizations::OrganizationsRepository;
+use infrastructure::repositories::PgRepositories;
+use infrastructure::{get_connection, DBPool};
+use uuid::Uuid;
+
+pub const DEFAULT_ORGANIZATION_SLUG: &str = "default";
+
+pub async fn get_all_organizations(conn: &DBPool) -> Result<Vec<Organization>, DBError> {
+    use domain::schema::organizations::dsl::*;
+
+    organizations.order(name.asc())
+        .load::<Organization>(&mut get_connection(conn).await?).await
+}
+
+pub async fn get_organization_by_id(conn: &DBPool, id_: Uuid) -> Result<Organization, DBError> {
+    PgRepositories::new(conn.clone()).get_organization_by_id(id_).await
+}
+
+pub async fn get_organization_by_slug(conn: &DBPool, slug_: &str) -> Result<Organization, DBError> {
+    use domain::schema::organizations::dsl::*;
+
+    organizations.filter(slug.eq(slug_))
+        .first(&mut get_connection(conn).await?).await
+}
+
+pub async fn get_organization_branding_by_slug(conn: &DBPool, slug_: &str) -> Result<OrganizationBranding, DBError> {
+    use domain::schema::organizations::dsl::*;
+
+    organizations.filter(slug.eq(slug_))
+        .select(OrganizationBranding::as_select())
+        .first(&mut get_connection(conn).await?).await
+}
+
+pub async fn get_organization_from_promotion_id(conn: &DBPool, promotion_id_: Uuid) -> Result<Organization, DBError> {
+    use domain::schema::organizations;
+    use domain::schema::promotions;
+
+    promotions::table
+        .inner_join(organizations::table)
+        .filter(promotions::id.eq(promotion_id_))
+        .select(organizations::all_columns)
+        .first::<Organization>(&mut get_connection(conn).await?).await
+}
+
+pub async fn get_users_from_organization_id(conn: &DBPool, organization_id_: Uuid) -> Result<Vec<User>, DBError> {
+    use domain::schema::users::dsl::*;
+
+    users.filter(organization_id.eq(organization_id_))
+        .order(username.asc())
+        .load::<User>(&mut get_connection(conn).await?).await
+}
+
+pub async fn create_organization(conn: &DBPool, new_organization: NewOrganization) -> Result<Uuid, DBError> {
+    use domain::schema::organizations::dsl::*;
+
+    diesel::insert_into(organizations)
+        .values(&new_organization)
+        .returning(id)
+        .get_result(&mut get_connection(conn).await?).await
+}
+
+pub async fn update_organization(conn: &DBPool, id_: Uuid, updated_organization: UpdatedOrganization) -> Result<(), DBError> {
+    use domain::schema::organizations::dsl::*;
+
+    diesel::update(organizations.filter(id.eq(id_)))
+        .set(&updated_organization)
+        .execute(&mut get_connection(conn).await?).await?;
+
+    Ok(())
+}
+
+pub async fn delete_organization(conn: &DBPool, id_: Uuid) -> Result<(), DBError> {
+    use domain::schema::organizations::dsl::*;
+
+    diesel::delete(organizations.filter(id.eq(id_)))
+        .execute(&mut get_connection(conn).await?).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::database::promotions::tests::test_create_promotion;
+    use crate::database::users::tests::test_create_user_in_organization;
+    use infrastructure::init_pool;
+
+    struct TestContext {
+        conn: DBPool,
+    }
+
+    impl TestContext {
+        fn new() -> Self {
+            TestContext {
+                conn: init_pool(
+                    dotenvy::var("DATABASE_URL")
+                        .expect("DATABASE_URL must be set")
+                        .as_str()
+                ),
+            }
+        }
+    }
+
+    pub async fn test_create_organization() -> Uuid {
+        let context = TestContext::new();
+
+        let new_organization = NewOrganization {
+            name: "test".to_string(),
+            slug: "test-".to_string() + &Uuid::new_v4().to_string(),
+            register: None,
+            authorized_domains: None,
+            logo_url: None,
+            primary_color: None,
+            mail_sender: None,
+        };
+
+        create_organization(&context.conn, new_organization).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_get_organization_by_slug() {
+        let context = TestContext::new();
+
+        let id_ = test_create_organization().await;
+        let organization = get_organization_by_id(&context.conn, id_).await.unwrap();
+
+        let found = get_organization_by_slug(&context.conn, &organization.slug).await.unwrap();
+        assert_eq!(found.id, id_);
+        assert!(found.register);
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_slug_is_a_conflict() {
+        use shared::error_models::APIError;
+
+        let context = TestContext::new();
+
+        let id_ = test_create_organization().await;
+        let organization = get_organization_by_id(&context.conn, id_).await.unwrap();
+
+        let err = create_organization(&context.conn, NewOrganization {
+            name: "test".to_string(),
+            slug: organization.slug,
+            register: None,
+            authorized_domains: None,
+            logo_url: None,
+            primary_color: None,
+            mail_sender: None,
+        }).await.unwrap_err();
+        assert_eq!(APIError::from(err).problem().status, 409);
+    }
+
+    #[tokio::test]
+    async fn test_get_organization_from_promotion_id() {
+        let context = TestContext::new();
+
+        let (promotion_id, _) = test_create_promotion().await;
+        let organization = get_organization_from_promotion_id(&context.conn, promotion_id).await.unwrap();
+
+        let promotion = crate::database::promotions::get_promotion_by_id(&context.conn, organization.id, promotion_id).await.unwrap();
+        assert_eq!(organization.id, promotion.organization_id);
+    }
+
+    #[tokio::test]
+    async fn test_get_users_from_organization_id() {
+        let context = TestContext::new();
+
+        let id_ = test_create_organization().await;
+        let user_id = test_create_user_in_organization(id_).await;
+        let _ = test_create_user_in_organization(test_create_organization().await).await;
+
+        let users = get_users_from_organization_id(&context.conn, id_).await.unwrap();
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].id, user_id);
+    }
+
+    #[tokio::test]
+    async fn test_update_organization() {
+        let context = TestContext::new();
+
+        let id_ = test_create_organization().await;
+        update_organization(&context.conn, id_, UpdatedOrganization {
+            name: None,
+            register: Some(false),
+            authorized_domains: Some(vec!["example.com".to_string()]),
+            logo_url: None,
+            primary_color: Some("#123456".to_string()),
+            mail_sender: None,
+            timezone: Some("Europe/Paris".to_string()),
+        }).await.unwrap();
+
+        let organization = get_organization_by_id(&context.conn, id_).await.unwrap();
+        assert!(!organization.register);
+        assert_eq!(organization.timezone, "Europe/Paris");
+        assert_eq!(organization.authorized_domains, vec![Some("example.com".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_delete_organization() {
+        let context = TestContext::new();
+
+        let id_ = test_create_organization().await;
+        delete_organization(&context.conn, id_).await.unwrap();
+        assert!(get_organization_by_id(&context.conn, id_).await.is_err());
+    }
+}
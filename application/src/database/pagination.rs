@@ -0,0 +1,173 @@
+use crate::database::groups::ProjectGroup;
+use domain::models::projects::Project;
+use domain::models::promotions::Promotion;
+use domain::models::students::Student;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+pub const DEFAULT_PAGE_LIMIT: i64 = 50;
+pub const MAX_PAGE_LIMIT: i64 = 200;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// Paging, sorting and filtering of a list, read from the query string.
+///
+/// `S` lists the fields the resource can be sorted on.
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListParams<S> {
+    /// Page to return, starting at 1
+    pub page: Option<i64>,
+    /// Number of items per page, 50 by default and at most 200
+    pub limit: Option<i64>,
+    /// Field to sort on
+    #[param(value_type = Option<String>)]
+    pub sort: Option<S>,
+    #[param(value_type = Option<SortDirection>)]
+    pub direction: Option<SortDirection>,
+    /// Case insensitive text the items have to contain
+    pub search: Option<String>,
+}
+
+impl<S> Default for ListParams<S> {
+    fn default() -> Self {
+        ListParams {
+            page: None,
+            limit: None,
+            sort: None,
+            direction: None,
+            search: None,
+        }
+    }
+}
+
+impl<S: Copy> ListParams<S> {
+    pub fn page(&self) -> i64 {
+        self.page.unwrap_or(1).max(1)
+    }
+
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_PAGE_LIMIT).clamp(1, MAX_PAGE_LIMIT)
+    }
+
+    /// Returns the number of items before the page, the pages too far to be counted being past the last one anyway.
+    pub fn offset(&self) -> i64 {
+        (self.page() - 1).saturating_mul(self.limit())
+    }
+
+    pub fn sort_or(&self, default: S) -> (S, SortDirection) {
+        (self.sort.unwrap_or(default), self.direction.unwrap_or_default())
+    }
+
+    /// `ILIKE` pattern of the text filter, with its wildcards escaped.
+    pub fn search_pattern(&self) -> Option<String> {
+        self.search.as_deref().and_then(like_pattern)
+    }
+}
+
+/// `ILIKE` pattern matching the texts containing `search`, with its wildcards escaped.
+///
+/// Returns `None` for blank searches.
+pub fn like_pattern(search: &str) -> Option<String> {
+    Some(search.trim())
+        .filter(|search| !search.is_empty())
+        .map(|search| format!("%{}%", search.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")))
+}
+
+/// A page of a list, with the total number of items matching the filters.
+#[derive(Debug, Serialize, ToSchema)]
+#[aliases(
+    StudentsPage = Page<Student>,
+    ProjectsPage = Page<Project>,
+    PromotionsPage = Page<Promotion>,
+    ProjectGroupsPage = Page<ProjectGroup>
+)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub page: i64,
+    pub limit: i64,
+}
+
+impl<T> Page<T> {
+    pub fn new<S: Copy>(items: Vec<T>, total: i64, params: &ListParams<S>) -> Self {
+        Page {
+            items,
+            total,
+            page: params.page(),
+            limit: params.limit(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StudentSortField {
+    Name,
+    Surname,
+    Email,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectSortField {
+    Name,
+    StartDate,
+    EndDate,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PromotionSortField {
+    Title,
+    StartYear,
+    EndYear,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupSortField {
+    Name,
+    Mark,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_params_bounds() {
+        let params = ListParams::<StudentSortField> {
+            page: Some(0),
+            limit: Some(1000),
+            ..Default::default()
+        };
+
+        assert_eq!(params.page(), 1);
+        assert_eq!(params.limit(), MAX_PAGE_LIMIT);
+        assert_eq!(params.offset(), 0);
+
+        let last_page = ListParams::<StudentSortField> {
+            page: Some(i64::MAX),
+            ..Default::default()
+        };
+        assert_eq!(last_page.offset(), i64::MAX);
+    }
+
+    #[test]
+    fn test_search_pattern_escapes_wildcards() {
+        let params = ListParams::<StudentSortField> {
+            search: Some(" 100%_ ".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(params.search_pattern().unwrap(), "%100\\%\\_%");
+        assert!(ListParams::<StudentSortField>::default().search_pattern().is_none());
+    }
+}
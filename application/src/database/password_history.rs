@@ -0,0 +1,88 @@
+use crate::database::transaction::Executor;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use diesel::result::Error as DBError;
+use domain::models::password_history::*;
+use uuid::Uuid;
+
+/// Returns the most recent previous passwords of a user.
+pub async fn get_password_history_from_user_id(conn: impl Executor, user_id_: Uuid, limit: i64) -> Result<Vec<PasswordHistory>, DBError> {
+    use domain::schema::password_history::dsl::*;
+
+    password_history
+        .filter(user_id.eq(user_id_))
+        .order(created_at.desc())
+        .limit(limit)
+        .load(&mut conn.connection().await?).await
+}
+
+pub async fn create_password_history(conn: impl Executor, new_password_history: NewPasswordHistory) -> Result<(), DBError> {
+    use domain::schema::password_history::dsl::*;
+
+    diesel::insert_into(password_history)
+        .values(&new_password_history)
+        .execute(&mut conn.connection().await?).await?;
+
+    Ok(())
+}
+
+/// Only keeps the `keep` most recent previous passwords of a user.
+pub async fn delete_old_password_history(conn: impl Executor, user_id_: Uuid, keep: i64) -> Result<(), DBError> {
+    use domain::schema::password_history::dsl::*;
+
+    let mut connection = conn.connection().await?;
+
+    let kept_ids: Vec<Uuid> = password_history
+        .filter(user_id.eq(user_id_))
+        .order(created_at.desc())
+        .limit(keep)
+        .select(id)
+        .load(&mut connection).await?;
+
+    diesel::delete(password_history
+        .filter(user_id.eq(user_id_))
+        .filter(id.ne_all(kept_ids))
+    ).execute(&mut connection).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::users::tests::test_create_user;
+    use infrastructure::{init_pool, DBPool};
+
+    struct TestContext {
+        conn: DBPool,
+    }
+
+    impl TestContext {
+        fn new() -> Self {
+            TestContext {
+                conn: init_pool(
+                    dotenvy::var("DATABASE_URL")
+                        .expect("DATABASE_URL must be set")
+                        .as_str()
+                ),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_delete_old_password_history() {
+        let context = TestContext::new();
+
+        let user_id = test_create_user().await;
+        for i in 0..3 {
+            create_password_history(&context.conn, NewPasswordHistory {
+                user_id,
+                password: format!("hash-{}", i),
+            }).await.unwrap();
+        }
+        assert_eq!(get_password_history_from_user_id(&context.conn, user_id, 10).await.unwrap().len(), 3);
+
+        delete_old_password_history(&context.conn, user_id, 1).await.unwrap();
+        assert_eq!(get_password_history_from_user_id(&context.conn, user_id, 10).await.unwrap().len(), 1);
+    }
+}
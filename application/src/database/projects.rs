@@ -1,79 +1,133 @@
-use diesel::internal::derives::multiconnection::chrono;
+use crate::database::pagination::{ListParams, Page, ProjectSortField, SortDirection};
+use crate::database::transaction::Executor;
+use diesel::pg::Pg;
 use diesel::prelude::*;
+use diesel::sql_types;
+use diesel_async::RunQueryDsl;
 use diesel::result::Error as DBError;
 use domain::models::projects::*;
 use domain::models::promotions::Promotion;
-use infrastructure::DBPool;
+use domain::repositories::projects::ProjectsRepository;
+use domain::schema::{projects, promotions};
+use infrastructure::repositories::PgRepositories;
+use infrastructure::{get_connection, DBPool};
 use uuid::Uuid;
 
-pub fn get_project_by_id(conn: &DBPool, id_: Uuid) -> Result<Project, DBError> {
+pub async fn get_project_by_id(conn: &DBPool, organization_id_: Uuid, id_: Uuid) -> Result<Project, DBError> {
     use domain::schema::projects::dsl::*;
 
     projects.filter(id.eq(id_))
-        .first(&mut conn.get().unwrap())
+        .filter(id.eq_any(organization_projects_ids(organization_id_)))
+        .first(&mut get_connection(conn).await?).await
 }
 
-pub fn get_current_projects(conn: &DBPool) -> Result<Vec<Project>, DBError> {
-    use domain::schema::projects::dsl::*;
-
-    let now = chrono::Utc::now().naive_utc();
+/// Ids of the projects of an organization, a project belonging to the organization of its promotion.
+pub fn organization_projects_ids(organization_id_: Uuid) -> projects::BoxedQuery<'static, Pg, sql_types::Uuid> {
+    projects::table
+        .filter(projects::promotion_id.eq_any(promotions::table
+            .filter(promotions::organization_id.eq(organization_id_))
+            .select(promotions::id)
+        ))
+        .select(projects::id)
+        .into_boxed()
+}
 
-    projects.filter(start_date.le(now))
-        .filter(state.ne(ProjectState::NotationFinished))
-        .get_results(&mut conn.get().unwrap())
+pub async fn get_current_projects(conn: &DBPool) -> Result<Vec<Project>, DBError> {
+    PgRepositories::new(conn.clone()).get_current_projects().await
 }
 
-pub fn get_projects_from_promotion_id(conn: &DBPool, promotion_id_: Uuid) -> Result<Vec<Project>, DBError> {
+pub async fn get_projects_from_promotion_id(conn: &DBPool, organization_id_: Uuid, promotion_id_: Uuid) -> Result<Vec<Project>, DBError> {
     use domain::schema::projects::dsl::*;
 
     projects.filter(promotion_id.eq(promotion_id_))
-        .get_results(&mut conn.get().unwrap())
+        .filter(id.eq_any(organization_projects_ids(organization_id_)))
+        .get_results(&mut get_connection(conn).await?).await
+}
+
+/// Returns a page of the projects of a promotion, matching the text filter on their name.
+pub async fn get_projects_page_from_promotion_id(conn: &DBPool, organization_id_: Uuid, promotion_id_: Uuid, params: &ListParams<ProjectSortField>) -> Result<Page<Project>, DBError> {
+    use domain::schema::projects::dsl::*;
+
+    let filtered = || {
+        let mut query = projects.filter(promotion_id.eq(promotion_id_))
+            .filter(id.eq_any(organization_projects_ids(organization_id_)))
+            .into_boxed();
+        if let Some(pattern) = params.search_pattern() {
+            query = query.filter(name.ilike(pattern));
+        }
+        query
+    };
+
+    let mut conn = get_connection(conn).await?;
+    let total = filtered().count().get_result::<i64>(&mut conn).await?;
+
+    let query = match params.sort_or(ProjectSortField::StartDate) {
+        (ProjectSortField::Name, SortDirection::Asc) => filtered().order(name.asc()),
+        (ProjectSortField::Name, SortDirection::Desc) => filtered().order(name.desc()),
+        (ProjectSortField::StartDate, SortDirection::Asc) => filtered().order(start_date.asc()),
+        (ProjectSortField::StartDate, SortDirection::Desc) => filtered().order(start_date.desc()),
+        (ProjectSortField::EndDate, SortDirection::Asc) => filtered().order(end_date.asc()),
+        (ProjectSortField::EndDate, SortDirection::Desc) => filtered().order(end_date.desc()),
+    };
+    let items = query.then_order_by(id.asc())
+        .offset(params.offset())
+        .limit(params.limit())
+        .load::<Project>(&mut conn).await?;
+
+    Ok(Page::new(items, total, params))
 }
 
-pub fn get_promotion_from_project_id(conn: &DBPool, project_id_: Uuid) -> Result<Promotion, DBError> {
+pub async fn get_promotion_from_project_id(conn: &DBPool, project_id_: Uuid) -> Result<Promotion, DBError> {
     use domain::schema::projects::dsl::*;
 
     projects.select(promotion_id)
         .filter(id.eq(project_id_))
         .inner_join(domain::schema::promotions::table)
         .select(domain::schema::promotions::all_columns)
-        .first(&mut conn.get().unwrap())
+        .first(&mut get_connection(conn).await?).await
 }
 
-pub fn create_project(conn: &DBPool, new_project: NewProject) -> Result<Uuid, DBError> {
+pub async fn create_project(conn: impl Executor, new_project: NewProject) -> Result<Uuid, DBError> {
     use domain::schema::projects::dsl::*;
 
     let result: Result<Uuid, DBError> = diesel::insert_into(projects)
         .values(&new_project)
         .returning(id)
-        .get_result(&mut conn.get().unwrap());
+        .get_result(&mut conn.connection().await?).await;
 
     result
 }
 
-pub fn update_project(conn: &DBPool, id_: Uuid, updated_project: UpdatedProject) -> Result<(), DBError> {
-    use domain::schema::projects::dsl::*;
-
-    // Check if the student exists
-    projects.filter(id.eq(id_.clone()))
-        .first::<Project>(&mut conn.get().unwrap())?;
+/// Records a change of state of a project, its creation included.
+pub async fn create_project_state_transition(conn: impl Executor, new_transition: NewProjectStateTransition) -> Result<(), DBError> {
+    use domain::schema::project_state_transitions::dsl::*;
 
-    diesel::update(projects.filter(id.eq(id_)))
-        .set(&updated_project)
-        .execute(&mut conn.get().unwrap())?;
+    diesel::insert_into(project_state_transitions)
+        .values(&new_transition)
+        .execute(&mut conn.connection().await?).await?;
 
     Ok(())
 }
 
-pub fn delete_project(conn: &DBPool, id_: Uuid) -> Result<(), DBError> {
+pub async fn get_project_state_transitions(conn: &DBPool, project_id_: Uuid) -> Result<Vec<ProjectStateTransition>, DBError> {
+    PgRepositories::new(conn.clone()).get_project_state_transitions(project_id_).await
+}
+
+pub async fn update_project(conn: &DBPool, organization_id_: Uuid, id_: Uuid, updated_project: UpdatedProject) -> Result<(), DBError> {
+    // Check if the project exists
+    get_project_by_id(conn, organization_id_, id_).await?;
+
+    PgRepositories::new(conn.clone()).update_project(id_, updated_project).await
+}
+
+pub async fn delete_project(conn: &DBPool, organization_id_: Uuid, id_: Uuid) -> Result<(), DBError> {
     use domain::schema::projects::dsl::*;
 
-    // Check if the student exists
-    projects.filter(id.eq(id_.clone()))
-        .first::<Project>(&mut conn.get().unwrap())?;
+    // Check if the project exists
+    get_project_by_id(conn, organization_id_, id_).await?;
 
-    diesel::delete(projects.filter(id.eq(id_)))
-        .execute(&mut conn.get().unwrap())?;
+    diesel::delete(projects.filter(id.eq(id_)).filter(id.eq_any(organization_projects_ids(organization_id_))))
+        .execute(&mut get_connection(conn).await?).await?;
 
     Ok(())
 }
@@ -81,7 +135,12 @@ pub fn delete_project(conn: &DBPool, id_: Uuid) -> Result<(), DBError> {
 #[cfg(test)]
 pub mod test {
     use super::*;
+    use crate::database::organizations::tests::test_create_organization;
     use crate::database::promotions::tests::test_create_promotion;
+    use crate::database::students::test::test_create_student;
+    use chrono::{Duration, Utc};
+    use domain::models::extensions::NewStudentExtension;
+    use domain::models::reminder_policies::{NewProjectReminderPolicy, ReminderAnchor, ReminderOffset, ReminderRecipients};
     use infrastructure::init_pool;
 
     struct TestContext {
@@ -100,10 +159,10 @@ pub mod test {
         }
     }
 
-    pub fn test_create_project() -> (Uuid, Uuid) {
+    pub async fn test_create_project() -> (Uuid, Uuid) {
         let context = TestContext::new();
 
-        let (promotion_id, _) = test_create_promotion();
+        let (promotion_id, _) = test_create_promotion().await;
         let random = Uuid::new_v4();
         let new_project = NewProject{
             name: format!("test-{}", random),
@@ -113,47 +172,56 @@ pub mod test {
             notation_period_duration: None,
             promotion_id,
             state: None,
+            timezone: None,
         };
 
-        (create_project(&context.conn, new_project).unwrap(), promotion_id)
+        (create_project(&context.conn, new_project).await.unwrap(), promotion_id)
     }
 
-    #[test]
-    fn test_get_project_by_id() {
+    #[tokio::test]
+    async fn test_get_project_by_id() {
         let context = TestContext::new();
 
-        let (project_id, _) = test_create_project();
+        let (project_id, _) = test_create_project().await;
+        let organization_id = get_promotion_from_project_id(&context.conn, project_id).await.unwrap().organization_id;
 
-        let project = get_project_by_id(&context.conn, project_id).unwrap();
-        assert_eq!(project_id, project.id)
+        let project = get_project_by_id(&context.conn, organization_id, project_id).await.unwrap();
+        assert_eq!(project_id, project.id);
+        // The projects of the other organizations are hidden
+        let other_organization_id = test_create_organization().await;
+        assert!(matches!(get_project_by_id(&context.conn, other_organization_id, project_id).await, Err(DBError::NotFound)));
     }
 
-    #[test]
-    fn test_get_projects_from_promotion_id() {
+    #[tokio::test]
+    async fn test_get_projects_from_promotion_id() {
         let context = TestContext::new();
 
-        let (project_id, promotion_id) = test_create_project();
+        let (project_id, promotion_id) = test_create_project().await;
+        let organization_id = get_promotion_from_project_id(&context.conn, project_id).await.unwrap().organization_id;
 
-        let projects = get_projects_from_promotion_id(&context.conn, promotion_id).unwrap();
+        let projects = get_projects_from_promotion_id(&context.conn, organization_id, promotion_id).await.unwrap();
         assert_eq!(projects[0].id, project_id);
+        assert!(get_projects_from_promotion_id(&context.conn, test_create_organization().await, promotion_id).await.unwrap().is_empty());
     }
 
-    #[test]
-    fn test_get_promotion_from_project_id() {
+    #[tokio::test]
+    async fn test_get_promotion_from_project_id() {
         let context = TestContext::new();
 
-        let (project_id, promotion_id) = test_create_project();
+        let (project_id, promotion_id) = test_create_project().await;
 
-        let promotion = get_promotion_from_project_id(&context.conn, project_id).unwrap();
+        let promotion = get_promotion_from_project_id(&context.conn, project_id).await.unwrap();
 
         assert_eq!(promotion.id, promotion_id);
     }
 
-    #[test]
-    fn test_update_project() {
+    #[tokio::test]
+    async fn test_update_project() {
         let context = TestContext::new();
 
-        let (project_id, _) = test_create_project();
+        let (project_id, _) = test_create_project().await;
+
+        let organization_id = get_promotion_from_project_id(&context.conn, project_id).await.unwrap().organization_id;
 
         let random = Uuid::new_v4();
         let updated_project = UpdatedProject {
@@ -163,19 +231,105 @@ pub mod test {
             end_date: None,
             notation_period_duration: None,
             state: None,
+            timezone: None,
         };
-        update_project(&context.conn, project_id, updated_project).unwrap();
+        // The projects of the other organizations can't be changed
+        let other_organization_id = test_create_organization().await;
+        assert!(matches!(update_project(&context.conn, other_organization_id, project_id, UpdatedProject::default()).await, Err(DBError::NotFound)));
+        update_project(&context.conn, organization_id, project_id, updated_project).await.unwrap();
 
-        let project = get_project_by_id(&context.conn, project_id).unwrap();
+        let project = get_project_by_id(&context.conn, organization_id, project_id).await.unwrap();
         assert_eq!(project.name, format!("updated-{}", random));
     }
 
-    #[test]
-    fn test_delete_project() {
+    #[tokio::test]
+    async fn test_transition_project() {
+        let context = TestContext::new();
+        let repos = PgRepositories::new(context.conn.clone());
+
+        let (project_id, _) = test_create_project().await;
+        create_project_state_transition(&context.conn, NewProjectStateTransition {
+            project_id,
+            from_state: None,
+            to_state: ProjectState::Scheduled,
+            user_id: None,
+        }).await.unwrap();
+
+        assert!(repos.transition_project(project_id, ProjectState::Scheduled, ProjectState::InProgress, UpdatedProject::default(), None).await.unwrap());
+        // The project is no longer scheduled
+        assert!(!repos.transition_project(project_id, ProjectState::Scheduled, ProjectState::InProgress, UpdatedProject::default(), None).await.unwrap());
+
+        assert_eq!(repos.get_project_by_id(project_id).await.unwrap().state, ProjectState::InProgress);
+        let transitions: Vec<_> = get_project_state_transitions(&context.conn, project_id).await.unwrap().into_iter()
+            .map(|transition| (transition.from_state, transition.to_state))
+            .collect();
+        assert_eq!(transitions, vec![(None, ProjectState::Scheduled), (Some(ProjectState::Scheduled), ProjectState::InProgress)]);
+    }
+
+    #[tokio::test]
+    async fn test_project_reminder_policy() {
+        let context = TestContext::new();
+        let repos = PgRepositories::new(context.conn.clone());
+
+        let (project_id, _) = test_create_project().await;
+        assert!(repos.get_project_reminder_policy(project_id).await.unwrap().is_none());
+
+        let mut new_policy = NewProjectReminderPolicy {
+            project_id,
+            offsets: vec![ReminderOffset { anchor: ReminderAnchor::EvaluationOpen, hours: 300 }],
+            recipients: ReminderRecipients::Students,
+            escalate_to_teacher: true,
+        };
+        repos.set_project_reminder_policy(new_policy.clone()).await.unwrap();
+        // Setting it again replaces it
+        new_policy.recipients = ReminderRecipients::Teacher;
+        repos.set_project_reminder_policy(new_policy).await.unwrap();
+
+        let policy = repos.get_project_reminder_policy(project_id).await.unwrap().unwrap();
+        assert_eq!(policy.offsets, vec![ReminderOffset { anchor: ReminderAnchor::EvaluationOpen, hours: 300 }]);
+        assert_eq!(policy.recipients, ReminderRecipients::Teacher);
+        assert!(policy.escalate_to_teacher);
+
+        repos.delete_project_reminder_policy(project_id).await.unwrap();
+        assert!(repos.get_project_reminder_policy(project_id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_student_extension() {
+        let context = TestContext::new();
+        let repos = PgRepositories::new(context.conn.clone());
+
+        let (project_id, _) = test_create_project().await;
+        let student_id = test_create_student().await;
+        let new_extension = NewStudentExtension {
+            project_id,
+            student_id,
+            extended_until: Utc::now() + Duration::days(3),
+            reason: Some("Sick".to_string()),
+            granted_by: None,
+        };
+        repos.set_student_extension(new_extension.clone()).await.unwrap();
+        repos.set_student_extension_notified(project_id, student_id, Utc::now()).await.unwrap();
+        assert!(repos.get_student_extensions(project_id).await.unwrap()[0].notified_at.is_some());
+
+        // Granting it again replaces it, the student being notified again
+        let extension = repos.set_student_extension(NewStudentExtension { reason: None, ..new_extension }).await.unwrap();
+        assert!(extension.reason.is_none());
+        assert!(extension.notified_at.is_none());
+        assert_eq!(repos.get_student_extensions(project_id).await.unwrap().len(), 1);
+
+        assert!(repos.delete_student_extension(project_id, student_id).await.unwrap());
+        assert!(!repos.delete_student_extension(project_id, student_id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_delete_project() {
         let context = TestContext::new();
 
-        let (project_id, _) = test_create_project();
+        let (project_id, _) = test_create_project().await;
+        let organization_id = get_promotion_from_project_id(&context.conn, project_id).await.unwrap().organization_id;
 
-        delete_project(&context.conn, project_id).unwrap();
+        assert!(matches!(delete_project(&context.conn, test_create_organization().await, project_id).await, Err(DBError::NotFound)));
+        delete_project(&context.conn, organization_id, project_id).await.unwrap();
     }
 }
\ No newline at end of file
@@ -1,81 +1,158 @@
+use crate::database::pagination::like_pattern;
+use crate::database::search::unaccent;
+use crate::database::transaction::Executor;
+use diesel::dsl::count_star;
 use diesel::internal::derives::multiconnection::chrono::NaiveDate;
 use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
 use diesel::result::Error as DBError;
+use domain::models::projects::ProjectState;
 use domain::models::promotions::{NewPromotion, Promotion, UpdatedPromotion};
-use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use serde::Deserialize;
+use utoipa::IntoParams;
 use uuid::Uuid;
 
-use infrastructure::DBPool;
+use infrastructure::{get_connection, DBPool};
 
-#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct PromotionSearchParams {
-    title: Option<String>,
-    start_year: Option<NaiveDate>,
-    end_year: Option<NaiveDate>,
+    /// Part of the title, ignoring case and accents
+    pub title: Option<String>,
+    /// Only return the promotions starting this year or later
+    pub start_year_from: Option<i32>,
+    /// Only return the promotions starting this year or earlier
+    pub start_year_to: Option<i32>,
+    /// Only return the promotions ending this year or later
+    pub end_year_from: Option<i32>,
+    /// Only return the promotions ending this year or earlier
+    pub end_year_to: Option<i32>,
+    /// Only return the promotions with at least this number of projects
+    pub min_projects: Option<i64>,
+    /// Only return the promotions with at most this number of projects
+    pub max_projects: Option<i64>,
+    /// Only return the promotions with a project in this state
+    pub project_state: Option<ProjectState>,
 }
 
-pub fn get_all_promotions_from_teacher_id(conn: &DBPool, teacher_id_: Uuid) -> Result<Vec<Promotion>, DBError> {
+pub async fn get_all_promotions_from_teacher_id(conn: &DBPool, organization_id_: Uuid, teacher_id_: Uuid) -> Result<Vec<Promotion>, DBError> {
     use domain::schema::promotions::dsl::*;
 
-    promotions.filter(teacher_id.eq(teacher_id_))
-        .load::<Promotion>(&mut conn.get().unwrap())
+    promotions.filter(organization_id.eq(organization_id_))
+        .filter(teacher_id.eq(teacher_id_))
+        .load::<Promotion>(&mut get_connection(conn).await?).await
 }
 
-pub fn get_promotion_by_id(conn: &DBPool, _id: Uuid) -> Result<Promotion, DBError> {
+pub async fn get_promotion_by_id(conn: &DBPool, organization_id_: Uuid, id_: Uuid) -> Result<Promotion, DBError> {
     use domain::schema::promotions::dsl::*;
 
-    promotions.filter(id.eq(_id))
-        .first(&mut conn.get().unwrap())
+    promotions.filter(id.eq(id_))
+        .filter(organization_id.eq(organization_id_))
+        .first(&mut get_connection(conn).await?).await
 }
 
-pub fn get_promotions_by_matching_date_and_title(conn: &DBPool, params: &PromotionSearchParams, teacher_id_: Uuid) -> Result<Vec<Promotion>, DBError> {
+/// Searches the promotions a user is a member of, most recent first.
+pub async fn search_promotions_from_member_id(conn: &DBPool, params: &PromotionSearchParams, organization_id_: Uuid, user_id_: Uuid) -> Result<Vec<Promotion>, DBError> {
     use domain::schema::promotions::dsl::*;
+    use domain::schema::{projects, promotions_members};
+
+    let mut query = promotions
+        .filter(organization_id.eq(organization_id_))
+        .filter(id.eq_any(promotions_members::table
+            .filter(promotions_members::user_id.eq(user_id_))
+            .select(promotions_members::promotion_id)
+        ))
+        .into_boxed();
+
+    if let Some(pattern) = params.title.as_deref().and_then(like_pattern) {
+        query = query.filter(unaccent(title).ilike(unaccent(pattern)));
+    }
+
+    if let Some(year) = params.start_year_from {
+        query = query.filter(start_year.ge(first_day_of_year(year)));
+    }
 
-    let mut query = promotions.into_boxed();
+    if let Some(year) = params.start_year_to {
+        query = query.filter(start_year.le(last_day_of_year(year)));
+    }
+
+    if let Some(year) = params.end_year_from {
+        query = query.filter(end_year.ge(first_day_of_year(year)));
+    }
 
-    if let Some(_title) = &params.title {
-        query = query.filter(title.ilike(_title));
+    if let Some(year) = params.end_year_to {
+        query = query.filter(end_year.le(last_day_of_year(year)));
     }
 
-    if let Some(_start_year) = params.start_year {
-        query = query.filter(start_year.ge(_start_year));
+    // Promotions without projects are missing from the grouped counts
+    if let Some(min_projects) = params.min_projects.filter(|min_projects| *min_projects > 0) {
+        query = query.filter(id.eq_any(projects::table
+            .group_by(projects::promotion_id)
+            .having(count_star().ge(min_projects))
+            .select(projects::promotion_id)
+        ));
     }
 
-    if let Some(_end_year) = params.end_year {
-        query = query.filter(end_year.le(_end_year));
+    if let Some(max_projects) = params.max_projects {
+        query = query.filter(id.ne_all(projects::table
+            .group_by(projects::promotion_id)
+            .having(count_star().gt(max_projects))
+            .select(projects::promotion_id)
+        ));
     }
 
-    query.filter(teacher_id.eq(teacher_id_))
-        .load::<Promotion>(&mut conn.get().unwrap())
+    if let Some(project_state) = params.project_state {
+        query = query.filter(id.eq_any(projects::table
+            .filter(projects::state.eq(project_state))
+            .select(projects::promotion_id)
+        ));
+    }
+
+    query.order((start_year.desc(), title.asc(), id.asc()))
+        .load::<Promotion>(&mut get_connection(conn).await?).await
 }
 
-pub fn create_promotion(conn: &DBPool, new_promotion: NewPromotion) -> Result<Uuid, DBError> {
+/// Years are bounded to the ones PostgreSQL dates and chrono both handle.
+fn first_day_of_year(year: i32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year.clamp(1, 9999), 1, 1).unwrap()
+}
+
+fn last_day_of_year(year: i32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year.clamp(1, 9999), 12, 31).unwrap()
+}
+
+pub async fn create_promotion(conn: impl Executor, new_promotion: NewPromotion) -> Result<Uuid, DBError> {
     use domain::schema::promotions::dsl::*;
 
     let result: Result<Uuid, DBError> = diesel::insert_into(promotions)
         .values(&new_promotion)
         .returning(id)
-        .get_result(&mut conn.get().unwrap());
+        .get_result(&mut conn.connection().await?).await;
 
     result
 }
 
-pub fn update_promotion(conn: &DBPool, _id: Uuid, update_promotion: UpdatedPromotion) -> Result<(), DBError> {
+pub async fn update_promotion(conn: &DBPool, organization_id_: Uuid, id_: Uuid, update_promotion: UpdatedPromotion) -> Result<(), DBError> {
     use domain::schema::promotions::dsl::*;
 
-    diesel::update(promotions.filter(id.eq(_id)))
+    // Check if the promotion exists
+    get_promotion_by_id(conn, organization_id_, id_).await?;
+
+    diesel::update(promotions.filter(id.eq(id_)).filter(organization_id.eq(organization_id_)))
         .set(&update_promotion)
-        .execute(&mut conn.get().unwrap())?;
+        .execute(&mut get_connection(conn).await?).await?;
 
     Ok(())
 }
 
-pub fn delete_promotion(conn: &DBPool, _id: Uuid) -> Result<(), DBError> {
+pub async fn delete_promotion(conn: &DBPool, organization_id_: Uuid, id_: Uuid) -> Result<(), DBError> {
     use domain::schema::promotions::dsl::*;
 
-    diesel::delete(promotions.filter(id.eq(_id)))
-        .execute(&mut conn.get().unwrap())?;
+    // Check if the promotion exists
+    get_promotion_by_id(conn, organization_id_, id_).await?;
+
+    diesel::delete(promotions.filter(id.eq(id_)).filter(organization_id.eq(organization_id_)))
+        .execute(&mut get_connection(conn).await?).await?;
 
     Ok(())
 }
@@ -83,9 +160,17 @@ pub fn delete_promotion(conn: &DBPool, _id: Uuid) -> Result<(), DBError> {
 #[cfg(test)]
 pub mod tests {
     use super::*;
+    use crate::database::config::{create_user_config, update_user_config};
+    use crate::database::organizations::tests::test_create_organization;
+    use crate::database::users::get_user_by_id;
     use crate::database::users::tests::test_create_user;
+    use crate::database::promotions_members::create_promotion_member;
+    use domain::models::config::{NewUserConfig, UpdatedUserConfig};
     use domain::models::promotions::{NewPromotion, UpdatedPromotion};
+    use domain::models::promotions_members::{NewPromotionMember, PromotionRole};
+    use domain::repositories::promotions::PromotionsRepository;
     use infrastructure::init_pool;
+    use infrastructure::repositories::PgRepositories;
 
     struct TestContext {
         conn: DBPool,
@@ -103,72 +188,135 @@ pub mod tests {
         }
     }
 
-    pub fn test_create_promotion() -> (Uuid, Uuid) {
+    pub async fn test_create_promotion() -> (Uuid, Uuid) {
         let context = TestContext::new();
 
-        let teacher_id = test_create_user();
+        let teacher_id = test_create_user().await;
+        let teacher = get_user_by_id(&context.conn, teacher_id).await.unwrap();
         let new_promotion = NewPromotion {
             title: "test".to_string(),
             start_year: NaiveDate::from_ymd(2021, 1, 1),
             end_year: NaiveDate::from_ymd(2021, 12, 31),
             teacher_id,
+            organization_id: teacher.organization_id,
         };
     
-        (create_promotion(&context.conn, new_promotion).unwrap(), teacher_id)
+        (create_promotion(&context.conn, new_promotion).await.unwrap(), teacher_id)
     }
 
-    #[test]
-    fn test_get_all_promotions_from_teacher_id() {
+    #[tokio::test]
+    async fn test_get_promotion_timezone() {
         let context = TestContext::new();
+        let repos = PgRepositories::new(context.conn.clone());
 
-        let (_, teacher_id) = test_create_promotion();
+        // The organization's timezone is used until the teacher chooses one
+        let (id, teacher_id) = test_create_promotion().await;
+        assert_eq!(repos.get_promotion_timezone(id).await.unwrap(), "UTC");
 
-        get_all_promotions_from_teacher_id(&context.conn, teacher_id).unwrap();
+        create_user_config(&context.conn, NewUserConfig { user_id: teacher_id, alerts: None }).await.unwrap();
+        assert_eq!(repos.get_promotion_timezone(id).await.unwrap(), "UTC");
+
+        update_user_config(&context.conn, teacher_id, UpdatedUserConfig {
+            alerts: None,
+            timezone: Some("Europe/Paris".to_string()),
+        }).await.unwrap();
+        assert_eq!(repos.get_promotion_timezone(id).await.unwrap(), "Europe/Paris");
     }
 
-    #[test]
-    fn test_get_promotion_by_id() {
+    #[tokio::test]
+    async fn test_get_all_promotions_from_teacher_id() {
         let context = TestContext::new();
 
-        let (id, _) = test_create_promotion();
+        let (_, teacher_id) = test_create_promotion().await;
+        let organization_id = get_user_by_id(&context.conn, teacher_id).await.unwrap().organization_id;
 
-        get_promotion_by_id(&context.conn, id).unwrap();
+        let promotions = get_all_promotions_from_teacher_id(&context.conn, organization_id, teacher_id).await.unwrap();
+        assert_eq!(promotions.len(), 1);
+        let promotions = get_all_promotions_from_teacher_id(&context.conn, Uuid::new_v4(), teacher_id).await.unwrap();
+        assert!(promotions.is_empty());
     }
 
-    #[test]
-    fn test_get_promotions_by_matching_date_and_title() {
+    #[tokio::test]
+    async fn test_get_promotion_by_id() {
         let context = TestContext::new();
 
-        let (_, teacher_id) = test_create_promotion();
+        let (id, teacher_id) = test_create_promotion().await;
+        let organization_id = get_user_by_id(&context.conn, teacher_id).await.unwrap().organization_id;
+
+        get_promotion_by_id(&context.conn, organization_id, id).await.unwrap();
+        // The promotions of the other organizations are hidden
+        let other_organization_id = test_create_organization().await;
+        assert!(matches!(get_promotion_by_id(&context.conn, other_organization_id, id).await, Err(DBError::NotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_search_promotions_from_member_id() {
+        let context = TestContext::new();
 
-        get_promotions_by_matching_date_and_title(&context.conn, &PromotionSearchParams {
-            title: Some("test".to_string()),
-            start_year: Some(NaiveDate::from_ymd(2021, 1, 1)),
-            end_year: Some(NaiveDate::from_ymd(2021, 12, 31))
-        }, teacher_id).unwrap();
+        let (id, teacher_id) = test_create_promotion().await;
+        let organization_id = get_user_by_id(&context.conn, teacher_id).await.unwrap().organization_id;
+        create_promotion_member(&context.conn, NewPromotionMember {
+            promotion_id: id,
+            user_id: teacher_id,
+            role: PromotionRole::Owner,
+        }).await.unwrap();
+        update_promotion(&context.conn, organization_id, id, UpdatedPromotion {
+            title: Some(format!("Génie logiciel {}", id)),
+            start_year: None,
+            end_year: None,
+        }).await.unwrap();
+
+        let promotions = search_promotions_from_member_id(&context.conn, &PromotionSearchParams {
+            title: Some("GENIE LOG".to_string()),
+            start_year_from: Some(2021),
+            end_year_to: Some(2021),
+            max_projects: Some(0),
+            ..Default::default()
+        }, organization_id, teacher_id).await.unwrap();
+        assert_eq!(promotions.len(), 1);
+        assert_eq!(promotions[0].id, id);
+
+        let promotions = search_promotions_from_member_id(&context.conn, &PromotionSearchParams {
+            start_year_from: Some(2022),
+            ..Default::default()
+        }, organization_id, teacher_id).await.unwrap();
+        assert!(promotions.is_empty());
+
+        let promotions = search_promotions_from_member_id(&context.conn, &PromotionSearchParams {
+            min_projects: Some(1),
+            ..Default::default()
+        }, organization_id, teacher_id).await.unwrap();
+        assert!(promotions.is_empty());
     }
 
-    #[test]
-    fn test_update_promotion() {
+    #[tokio::test]
+    async fn test_update_promotion() {
         let context = TestContext::new();
 
-        let (id, _) = test_create_promotion();
+        let (id, teacher_id) = test_create_promotion().await;
+        let organization_id = get_user_by_id(&context.conn, teacher_id).await.unwrap().organization_id;
 
-        let updated_promotion = UpdatedPromotion {
+        let updated_promotion = || UpdatedPromotion {
             title: Some("Re-test".to_string()),
             start_year: None,
             end_year: None,
         };
 
-        update_promotion(&context.conn, id, updated_promotion).unwrap();
+        // The promotions of the other organizations can't be changed
+        let other_organization_id = test_create_organization().await;
+        assert!(matches!(update_promotion(&context.conn, other_organization_id, id, updated_promotion()).await, Err(DBError::NotFound)));
+        update_promotion(&context.conn, organization_id, id, updated_promotion()).await.unwrap();
+        assert_eq!(get_promotion_by_id(&context.conn, organization_id, id).await.unwrap().title, "Re-test");
     }
 
-    #[test]
-    fn test_delete_promotion() {
+    #[tokio::test]
+    async fn test_delete_promotion() {
         let context = TestContext::new();
 
-        let (id, _) = test_create_promotion();
+        let (id, teacher_id) = test_create_promotion().await;
+        let organization_id = get_user_by_id(&context.conn, teacher_id).await.unwrap().organization_id;
 
-        delete_promotion(&context.conn, id).unwrap();
+        assert!(matches!(delete_promotion(&context.conn, test_create_organization().await, id).await, Err(DBError::NotFound)));
+        delete_promotion(&context.conn, organization_id, id).await.unwrap();
     }
 }
\ No newline at end of file
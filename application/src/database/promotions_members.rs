@@ -0,0 +1,323 @@
+use crate::database::pagination::{ListParams, Page, PromotionSortField, SortDirection};
+use crate::database::transaction::Executor;
+use diesel::prelude::*;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use diesel::result::Error as DBError;
+use domain::models::promotions::Promotion;
+use domain::models::promotions_members::*;
+use domain::repositories::promotions::PromotionsRepository;
+use infrastructure::repositories::PgRepositories;
+use infrastructure::{get_connection, DBPool};
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Serialize, ToSchema)]
+pub struct PromotionMemberDetails {
+    pub user_id: Uuid,
+    pub username: String,
+    pub email: String,
+    pub role: PromotionRole,
+}
+
+pub async fn get_promotion_member(conn: &DBPool, promotion_id_: Uuid, user_id_: Uuid) -> Result<PromotionMember, DBError> {
+    use domain::schema::promotions_members::dsl::*;
+
+    promotions_members
+        .filter(promotion_id.eq(promotion_id_))
+        .filter(user_id.eq(user_id_))
+        .first(&mut get_connection(conn).await?).await
+}
+
+pub async fn get_members_from_promotion_id(conn: &DBPool, promotion_id_: Uuid) -> Result<Vec<PromotionMemberDetails>, DBError> {
+    use domain::schema::promotions_members::dsl::*;
+    use domain::schema::users;
+
+    let result = promotions_members
+        .inner_join(users::table)
+        .filter(promotion_id.eq(promotion_id_))
+        .select((user_id, users::username, users::email, role))
+        .order(created_at.asc())
+        .load::<(Uuid, String, String, PromotionRole)>(&mut get_connection(conn).await?).await?;
+
+    Ok(result.into_iter().map(|(user_id_, username, email, role_)| {
+        PromotionMemberDetails {
+            user_id: user_id_,
+            username,
+            email,
+            role: role_,
+        }
+    }).collect())
+}
+
+pub async fn get_members_emails_from_promotion_id(conn: &DBPool, promotion_id_: Uuid, roles: &[PromotionRole]) -> Result<Vec<String>, DBError> {
+    PgRepositories::new(conn.clone()).get_members_emails_from_promotion_id(promotion_id_, roles).await
+}
+
+pub async fn get_all_promotions_from_member_id(conn: &DBPool, organization_id_: Uuid, user_id_: Uuid) -> Result<Vec<Promotion>, DBError> {
+    use domain::schema::promotions;
+    use domain::schema::promotions_members::dsl::*;
+
+    promotions::table
+        .inner_join(promotions_members)
+        .filter(promotions::organization_id.eq(organization_id_))
+        .filter(user_id.eq(user_id_))
+        .select(promotions::all_columns)
+        .load::<Promotion>(&mut get_connection(conn).await?).await
+}
+
+/// Returns a page of the promotions a user is a member of, matching the text filter on their title.
+pub async fn get_promotions_page_from_member_id(conn: &DBPool, organization_id_: Uuid, user_id_: Uuid, params: &ListParams<PromotionSortField>) -> Result<Page<Promotion>, DBError> {
+    use domain::schema::promotions::dsl::*;
+    use domain::schema::promotions_members;
+
+    let filtered = || {
+        let mut query = promotions
+            .filter(organization_id.eq(organization_id_))
+            .filter(id.eq_any(promotions_members::table
+                .filter(promotions_members::user_id.eq(user_id_))
+                .select(promotions_members::promotion_id)
+            ))
+            .into_boxed();
+        if let Some(pattern) = params.search_pattern() {
+            query = query.filter(title.ilike(pattern));
+        }
+        query
+    };
+
+    let mut conn = get_connection(conn).await?;
+    let total = filtered().count().get_result::<i64>(&mut conn).await?;
+
+    let query = match params.sort_or(PromotionSortField::StartYear) {
+        (PromotionSortField::Title, SortDirection::Asc) => filtered().order(title.asc()),
+        (PromotionSortField::Title, SortDirection::Desc) => filtered().order(title.desc()),
+        (PromotionSortField::StartYear, SortDirection::Asc) => filtered().order(start_year.asc()),
+        (PromotionSortField::StartYear, SortDirection::Desc) => filtered().order(start_year.desc()),
+        (PromotionSortField::EndYear, SortDirection::Asc) => filtered().order(end_year.asc()),
+        (PromotionSortField::EndYear, SortDirection::Desc) => filtered().order(end_year.desc()),
+    };
+    let items = query.then_order_by(id.asc())
+        .offset(params.offset())
+        .limit(params.limit())
+        .load::<Promotion>(&mut conn).await?;
+
+    Ok(Page::new(items, total, params))
+}
+
+pub async fn create_promotion_member(conn: impl Executor, new_promotion_member: NewPromotionMember) -> Result<(), DBError> {
+    use domain::schema::promotions_members::dsl::*;
+
+    diesel::insert_into(promotions_members)
+        .values(&new_promotion_member)
+        .execute(&mut conn.connection().await?).await?;
+
+    Ok(())
+}
+
+pub async fn update_promotion_member(conn: &DBPool, promotion_id_: Uuid, user_id_: Uuid, updated_promotion_member: UpdatedPromotionMember) -> Result<(), DBError> {
+    use domain::schema::promotions_members::dsl::*;
+
+    // Check if the member exists
+    get_promotion_member(conn, promotion_id_, user_id_).await?;
+
+    diesel::update(promotions_members
+        .filter(promotion_id.eq(promotion_id_))
+        .filter(user_id.eq(user_id_))
+    ).set(&updated_promotion_member)
+        .execute(&mut get_connection(conn).await?).await?;
+
+    Ok(())
+}
+
+pub async fn delete_promotion_member(conn: &DBPool, promotion_id_: Uuid, user_id_: Uuid) -> Result<(), DBError> {
+    use domain::schema::promotions_members::dsl::*;
+
+    diesel::delete(promotions_members
+        .filter(promotion_id.eq(promotion_id_))
+        .filter(user_id.eq(user_id_))
+    ).execute(&mut get_connection(conn).await?).await?;
+
+    Ok(())
+}
+
+/// Gives the ownership of a promotion to one of its members.
+///
+/// The previous owner stays in the promotion as a co-teacher.
+pub async fn transfer_promotion_ownership(conn: &DBPool, promotion_id_: Uuid, new_owner_id: Uuid) -> Result<(), DBError> {
+    use domain::schema::promotions;
+    use domain::schema::promotions_members::dsl::*;
+
+    get_connection(conn).await?.transaction::<_, DBError, _>(|conn| async move {
+        // The new owner has to be a member of the promotion
+        promotions_members
+            .filter(promotion_id.eq(promotion_id_))
+            .filter(user_id.eq(new_owner_id))
+            .first::<PromotionMember>(conn).await?;
+
+        diesel::update(promotions_members
+            .filter(promotion_id.eq(promotion_id_))
+            .filter(role.eq(PromotionRole::Owner))
+        ).set(role.eq(PromotionRole::CoTeacher))
+            .execute(conn).await?;
+
+        diesel::update(promotions_members
+            .filter(promotion_id.eq(promotion_id_))
+            .filter(user_id.eq(new_owner_id))
+        ).set(role.eq(PromotionRole::Owner))
+            .execute(conn).await?;
+
+        diesel::update(promotions::table.filter(promotions::id.eq(promotion_id_)))
+            .set(promotions::teacher_id.eq(new_owner_id))
+            .execute(conn).await?;
+
+        Ok(())
+    }.scope_boxed()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::organizations::get_organization_from_promotion_id;
+    use crate::database::promotions::get_promotion_by_id;
+    use crate::database::promotions::tests::test_create_promotion;
+    use crate::database::users::tests::test_create_user_in_organization;
+    use infrastructure::init_pool;
+
+    struct TestContext {
+        conn: DBPool,
+    }
+
+    impl TestContext {
+        fn new() -> Self {
+            TestContext {
+                conn: init_pool(
+                    dotenvy::var("DATABASE_URL")
+                        .expect("DATABASE_URL must be set")
+                        .as_str()
+                ),
+            }
+        }
+    }
+
+    async fn test_create_promotion_member(role: PromotionRole) -> (Uuid, Uuid, Uuid) {
+        let context = TestContext::new();
+
+        let (promotion_id, teacher_id) = test_create_promotion().await;
+        create_promotion_member(&context.conn, NewPromotionMember {
+            promotion_id,
+            user_id: teacher_id,
+            role: PromotionRole::Owner,
+        }).await.unwrap();
+
+        let organization_id = get_organization_from_promotion_id(&context.conn, promotion_id).await.unwrap().id;
+        let user_id = test_create_user_in_organization(organization_id).await;
+        create_promotion_member(&context.conn, NewPromotionMember {
+            promotion_id,
+            user_id,
+            role,
+        }).await.unwrap();
+
+        (promotion_id, teacher_id, user_id)
+    }
+
+    #[tokio::test]
+    async fn test_get_promotion_member() {
+        let context = TestContext::new();
+
+        let (promotion_id, _, user_id) = test_create_promotion_member(PromotionRole::Assistant).await;
+
+        let member = get_promotion_member(&context.conn, promotion_id, user_id).await.unwrap();
+        assert_eq!(member.role, PromotionRole::Assistant);
+    }
+
+    #[tokio::test]
+    async fn test_get_members_from_promotion_id() {
+        let context = TestContext::new();
+
+        let (promotion_id, _, _) = test_create_promotion_member(PromotionRole::Observer).await;
+
+        let members = get_members_from_promotion_id(&context.conn, promotion_id).await.unwrap();
+        assert_eq!(members.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_get_members_emails_from_promotion_id() {
+        let context = TestContext::new();
+
+        let (promotion_id, _, _) = test_create_promotion_member(PromotionRole::Observer).await;
+
+        let emails = get_members_emails_from_promotion_id(&context.conn, promotion_id, &[PromotionRole::Owner]).await.unwrap();
+        assert_eq!(emails.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_all_promotions_from_member_id() {
+        let context = TestContext::new();
+
+        let (promotion_id, _, user_id) = test_create_promotion_member(PromotionRole::CoTeacher).await;
+        let organization_id = get_organization_from_promotion_id(&context.conn, promotion_id).await.unwrap().id;
+
+        let promotions = get_all_promotions_from_member_id(&context.conn, organization_id, user_id).await.unwrap();
+        assert_eq!(promotions[0].id, promotion_id);
+    }
+
+    #[tokio::test]
+    async fn test_get_promotions_page_from_member_id() {
+        let context = TestContext::new();
+
+        let (promotion_id, _, user_id) = test_create_promotion_member(PromotionRole::CoTeacher).await;
+        let organization_id = get_organization_from_promotion_id(&context.conn, promotion_id).await.unwrap().id;
+
+        let page = get_promotions_page_from_member_id(&context.conn, organization_id, user_id, &ListParams::default()).await.unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.items[0].id, promotion_id);
+
+        let params = ListParams::<PromotionSortField> {
+            search: Some("no matching title".to_string()),
+            ..Default::default()
+        };
+        let page = get_promotions_page_from_member_id(&context.conn, organization_id, user_id, &params).await.unwrap();
+        assert_eq!(page.total, 0);
+        assert!(page.items.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_update_promotion_member() {
+        let context = TestContext::new();
+
+        let (promotion_id, _, user_id) = test_create_promotion_member(PromotionRole::Observer).await;
+
+        update_promotion_member(&context.conn, promotion_id, user_id, UpdatedPromotionMember {
+            role: Some(PromotionRole::CoTeacher),
+        }).await.unwrap();
+
+        let member = get_promotion_member(&context.conn, promotion_id, user_id).await.unwrap();
+        assert_eq!(member.role, PromotionRole::CoTeacher);
+    }
+
+    #[tokio::test]
+    async fn test_delete_promotion_member() {
+        let context = TestContext::new();
+
+        let (promotion_id, _, user_id) = test_create_promotion_member(PromotionRole::Observer).await;
+
+        delete_promotion_member(&context.conn, promotion_id, user_id).await.unwrap();
+        assert!(get_promotion_member(&context.conn, promotion_id, user_id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_transfer_promotion_ownership() {
+        let context = TestContext::new();
+
+        let (promotion_id, teacher_id, user_id) = test_create_promotion_member(PromotionRole::CoTeacher).await;
+
+        transfer_promotion_ownership(&context.conn, promotion_id, user_id).await.unwrap();
+
+        let organization_id = get_organization_from_promotion_id(&context.conn, promotion_id).await.unwrap().id;
+        let promotion = get_promotion_by_id(&context.conn, organization_id, promotion_id).await.unwrap();
+        assert_eq!(promotion.teacher_id, user_id);
+        let previous_owner = get_promotion_member(&context.conn, promotion_id, teacher_id).await.unwrap();
+        assert_eq!(previous_owner.role, PromotionRole::CoTeacher);
+    }
+}
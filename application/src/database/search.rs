@@ -0,0 +1,177 @@
+use crate::database::pagination::like_pattern;
+use diesel::define_sql_function;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use diesel::result::Error as DBError;
+use diesel::sql_types::Text;
+use domain::models::groups::Group;
+use domain::models::projects::Project;
+use domain::models::promotions::Promotion;
+use domain::models::students::Student;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use infrastructure::{get_connection, DBPool};
+
+pub const DEFAULT_SEARCH_LIMIT: i64 = 10;
+pub const MAX_SEARCH_LIMIT: i64 = 50;
+
+define_sql_function! {
+    /// Removes the accents of a text, provided by the `unaccent` extension.
+    fn unaccent(text: Text) -> Text;
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GlobalSearchParams {
+    /// Text the results have to contain, ignoring case and accents
+    pub q: String,
+    /// Maximum number of results of each kind, 10 by default and at most 50
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct GlobalSearchResults {
+    pub promotions: Vec<Promotion>,
+    pub projects: Vec<Project>,
+    pub groups: Vec<Group>,
+    pub students: Vec<Student>,
+}
+
+/// Searches the promotions of a user, and the projects, groups and students of these promotions.
+///
+/// Promotions match on their title, projects and groups on their name, and students on
+/// their name, surname, full name or email.
+pub async fn global_search(conn: &DBPool, organization_id_: Uuid, user_id_: Uuid, params: &GlobalSearchParams) -> Result<GlobalSearchResults, DBError> {
+    use domain::schema::{groups, projects, promotions, promotions_members, promotions_students, students};
+
+    let Some(pattern) = like_pattern(&params.q) else {
+        return Ok(GlobalSearchResults::default());
+    };
+    let limit = params.limit.unwrap_or(DEFAULT_SEARCH_LIMIT).clamp(1, MAX_SEARCH_LIMIT);
+    // Aliased so the promotions can be filtered with it too
+    let member_promotions_table = diesel::alias!(promotions as member_promotions);
+    let member_promotions = || member_promotions_table
+        .filter(member_promotions_table.field(promotions::organization_id).eq(organization_id_))
+        .filter(member_promotions_table.field(promotions::id).eq_any(promotions_members::table
+            .filter(promotions_members::user_id.eq(user_id_))
+            .select(promotions_members::promotion_id)
+        ))
+        .select(member_promotions_table.field(promotions::id));
+
+    let mut conn = get_connection(conn).await?;
+
+    let promotions = promotions::table
+        .filter(promotions::id.eq_any(member_promotions()))
+        .filter(unaccent(promotions::title).ilike(unaccent(&pattern)))
+        .order((promotions::start_year.desc(), promotions::title.asc()))
+        .limit(limit)
+        .load::<Promotion>(&mut conn).await?;
+
+    let projects = projects::table
+        .filter(projects::promotion_id.eq_any(member_promotions()))
+        .filter(unaccent(projects::name).ilike(unaccent(&pattern)))
+        .order((projects::start_date.desc(), projects::name.asc()))
+        .limit(limit)
+        .load::<Project>(&mut conn).await?;
+
+    let groups = groups::table
+        .filter(groups::project_id.eq_any(projects::table
+            .filter(projects::promotion_id.eq_any(member_promotions()))
+            .select(projects::id)
+        ))
+        .filter(unaccent(groups::name).ilike(unaccent(&pattern)))
+        .order(groups::name.asc())
+        .limit(limit)
+        .load::<Group>(&mut conn).await?;
+
+    let students = students::table
+        .filter(students::id.eq_any(promotions_students::table
+            .filter(promotions_students::promotion_id.eq_any(member_promotions()))
+            .select(promotions_students::student_id)
+        ))
+        .filter(unaccent(students::name.concat(" ").concat(students::surname)).ilike(unaccent(&pattern))
+            .or(unaccent(students::surname.concat(" ").concat(students::name)).ilike(unaccent(&pattern)))
+            .or(students::email.ilike(&pattern))
+        )
+        .order((students::surname.asc(), students::name.asc()))
+        .limit(limit)
+        .load::<Student>(&mut conn).await?;
+
+    Ok(GlobalSearchResults {
+        promotions,
+        projects,
+        groups,
+        students,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::projects::create_project;
+    use crate::database::organizations::get_organization_from_promotion_id;
+    use crate::database::promotions::tests::test_create_promotion;
+    use crate::database::promotions_members::create_promotion_member;
+    use crate::database::users::tests::test_create_user_in_organization;
+    use domain::models::projects::NewProject;
+    use domain::models::promotions_members::{NewPromotionMember, PromotionRole};
+    use infrastructure::init_pool;
+
+    struct TestContext {
+        conn: DBPool,
+    }
+
+    impl TestContext {
+        fn new() -> Self {
+            TestContext {
+                conn: init_pool(
+                    dotenvy::var("DATABASE_URL")
+                        .expect("DATABASE_URL must be set")
+                        .as_str()
+                ),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_global_search() {
+        let context = TestContext::new();
+
+        let (promotion_id, teacher_id) = test_create_promotion().await;
+        create_promotion_member(&context.conn, NewPromotionMember {
+            promotion_id,
+            user_id: teacher_id,
+            role: PromotionRole::Owner,
+        }).await.unwrap();
+        let organization_id = get_organization_from_promotion_id(&context.conn, promotion_id).await.unwrap().id;
+
+        let random = Uuid::new_v4();
+        let project_id = create_project(&context.conn, NewProject {
+            name: format!("Élection-{}", random),
+            description: None,
+            start_date: None,
+            end_date: Default::default(),
+            notation_period_duration: None,
+            promotion_id,
+            state: None,
+            timezone: None,
+        }).await.unwrap();
+
+        // Case and accents are ignored
+        let params = GlobalSearchParams {
+            q: format!("ELECTION-{}", random),
+            limit: None,
+        };
+        let results = global_search(&context.conn, organization_id, teacher_id, &params).await.unwrap();
+        assert_eq!(results.projects.len(), 1);
+        assert_eq!(results.projects[0].id, project_id);
+        assert!(results.promotions.is_empty());
+
+        // Only the promotions the user is a member of are searched
+        let other_user_id = test_create_user_in_organization(organization_id).await;
+        let results = global_search(&context.conn, organization_id, other_user_id, &params).await.unwrap();
+        assert!(results.projects.is_empty());
+    }
+}
@@ -1,93 +1,160 @@
+use crate::database::pagination::{ListParams, Page, SortDirection, StudentSortField};
+use crate::database::transaction::Executor;
+use diesel::pg::Pg;
 use diesel::prelude::*;
+use diesel::sql_types;
+use diesel_async::RunQueryDsl;
 use diesel::result::Error as DBError;
 use domain::models::students::*;
-use infrastructure::DBPool;
+use domain::schema::students;
+use infrastructure::{get_connection, DBPool};
 use uuid::Uuid;
 
-pub fn get_student_by_id(conn: &DBPool, id_: Uuid) -> Result<Student, DBError> {
+pub async fn get_student_by_id(conn: &DBPool, organization_id_: Uuid, id_: Uuid) -> Result<Student, DBError> {
     use domain::schema::students::dsl::*;
 
     students.filter(id.eq(id_))
-        .first(&mut conn.get().unwrap())
+        .filter(organization_id.eq(organization_id_))
+        .first(&mut get_connection(conn).await?).await
 }
 
-pub fn get_students_from_promotion_id(conn: &DBPool, promotion_id_: Uuid) -> Result<Vec<Student>, DBError> {
+/// Ids of the students of an organization.
+pub fn organization_students_ids(organization_id_: Uuid) -> students::BoxedQuery<'static, Pg, sql_types::Uuid> {
+    students::table
+        .filter(students::organization_id.eq(organization_id_))
+        .select(students::id)
+        .into_boxed()
+}
+
+pub async fn get_students_from_promotion_id(conn: &DBPool, organization_id_: Uuid, promotion_id_: Uuid) -> Result<Vec<Student>, DBError> {
     use domain::schema::students::dsl::*;
     use domain::schema::promotions_students::dsl::*;
 
     let students_ids = promotions_students
         .filter(promotion_id.eq(promotion_id_))
         .select(student_id)
-        .load::<Uuid>(&mut conn.get().unwrap())?;
+        .load::<Uuid>(&mut get_connection(conn).await?).await?;
 
     students.filter(id.eq_any(students_ids))
-        .load::<Student>(&mut conn.get().unwrap())
+        .filter(organization_id.eq(organization_id_))
+        .load::<Student>(&mut get_connection(conn).await?).await
+}
+
+/// Returns a page of the students of a promotion, matching the text filter on their name, surname or email.
+pub async fn get_students_page_from_promotion_id(conn: &DBPool, organization_id_: Uuid, promotion_id_: Uuid, params: &ListParams<StudentSortField>) -> Result<Page<Student>, DBError> {
+    use domain::schema::promotions_students;
+    use domain::schema::students::dsl::*;
+
+    let filtered = || {
+        let mut query = students
+            .filter(organization_id.eq(organization_id_))
+            .filter(id.eq_any(promotions_students::table
+                .filter(promotions_students::promotion_id.eq(promotion_id_))
+                .select(promotions_students::student_id)
+            ))
+            .into_boxed();
+        if let Some(pattern) = params.search_pattern() {
+            query = query.filter(name.ilike(pattern.clone())
+                .or(surname.ilike(pattern.clone()))
+                .or(email.ilike(pattern)));
+        }
+        query
+    };
+
+    let mut conn = get_connection(conn).await?;
+    let total = filtered().count().get_result::<i64>(&mut conn).await?;
+
+    let query = match params.sort_or(StudentSortField::Surname) {
+        (StudentSortField::Name, SortDirection::Asc) => filtered().order(name.asc()),
+        (StudentSortField::Name, SortDirection::Desc) => filtered().order(name.desc()),
+        (StudentSortField::Surname, SortDirection::Asc) => filtered().order(surname.asc()),
+        (StudentSortField::Surname, SortDirection::Desc) => filtered().order(surname.desc()),
+        (StudentSortField::Email, SortDirection::Asc) => filtered().order(email.asc()),
+        (StudentSortField::Email, SortDirection::Desc) => filtered().order(email.desc()),
+    };
+    let items = query.then_order_by(id.asc())
+        .offset(params.offset())
+        .limit(params.limit())
+        .load::<Student>(&mut conn).await?;
+
+    Ok(Page::new(items, total, params))
+}
+
+pub async fn get_promotions_ids_from_student_id(conn: &DBPool, student_id_: Uuid) -> Result<Vec<Uuid>, DBError> {
+    use domain::schema::promotions_students::dsl::*;
+
+    promotions_students
+        .filter(student_id.eq(student_id_))
+        .select(promotion_id)
+        .load::<Uuid>(&mut get_connection(conn).await?).await
 }
 
-pub fn create_student(conn: &DBPool, new_student: NewStudent) -> Result<Uuid, DBError> {
+pub async fn create_student(conn: impl Executor, new_student: NewStudent) -> Result<Uuid, DBError> {
     use domain::schema::students::dsl::*;
 
     let result: Result<Uuid, DBError> = diesel::insert_into(students)
         .values(&new_student)
         .returning(id)
-        .get_result(&mut conn.get().unwrap());
+        .get_result(&mut conn.connection().await?).await;
 
     result
 }
 
-pub fn create_promotion_students(conn: &DBPool, new_promotion_students: Vec<NewPromotionStudent>) -> Result<(), DBError> {
+pub async fn create_promotion_students(conn: impl Executor, new_promotion_students: Vec<NewPromotionStudent>) -> Result<(), DBError> {
     use domain::schema::promotions_students::dsl::*;
 
     diesel::insert_into(promotions_students)
         .values(&new_promotion_students)
-        .execute(&mut conn.get().unwrap())?;
+        .execute(&mut conn.connection().await?).await?;
 
     Ok(())
 }
 
-pub fn update_student(conn: &DBPool, id_: Uuid, updated_student: UpdatedStudent) -> Result<(), DBError> {
+pub async fn update_student(conn: &DBPool, organization_id_: Uuid, id_: Uuid, updated_student: UpdatedStudent) -> Result<(), DBError> {
     use domain::schema::students::dsl::*;
 
     // Check if the student exists
-    students.filter(id.eq(id_.clone()))
-        .first::<Student>(&mut conn.get().unwrap())?;
+    get_student_by_id(conn, organization_id_, id_).await?;
 
-    diesel::update(students.filter(id.eq(id_)))
+    diesel::update(students.filter(id.eq(id_)).filter(organization_id.eq(organization_id_)))
         .set(&updated_student)
-        .execute(&mut conn.get().unwrap())?;
+        .execute(&mut get_connection(conn).await?).await?;
 
     Ok(())
 }
 
-pub fn delete_student(conn: &DBPool, id_: Uuid) -> Result<(), DBError> {
+pub async fn delete_student(conn: impl Executor, organization_id_: Uuid, id_: Uuid) -> Result<(), DBError> {
     use domain::schema::students::dsl::*;
 
+    let mut connection = conn.connection().await?;
+
     // Check if the student exists
-    students.filter(id.eq(id_.clone()))
-        .first::<Student>(&mut conn.get().unwrap())?;
+    students.filter(id.eq(id_))
+        .filter(organization_id.eq(organization_id_))
+        .first::<Student>(&mut connection).await?;
 
-    diesel::delete(students.filter(id.eq(id_)))
-        .execute(&mut conn.get().unwrap())?;
+    diesel::delete(students.filter(id.eq(id_)).filter(organization_id.eq(organization_id_)))
+        .execute(&mut connection).await?;
 
     Ok(())
 }
 
-pub fn delete_promotion_student(conn: &DBPool, promotion_id_: Uuid, student_id_: Uuid) -> Result<(), DBError> {
+pub async fn delete_promotion_student(conn: &DBPool, promotion_id_: Uuid, student_id_: Uuid) -> Result<(), DBError> {
     use domain::schema::promotions_students::dsl::*;
 
     diesel::delete(promotions_students
         .filter(promotion_id.eq(promotion_id_))
         .filter(student_id.eq(student_id_))
-    ).execute(&mut conn.get().unwrap())?;
+    ).execute(&mut get_connection(conn).await?).await?;
 
     Ok(())
 }
 
-pub fn remove_student_from_all_promotions(conn: &DBPool, student_id_: Uuid) -> Result<(), DBError> {
+pub async fn remove_student_from_all_promotions(conn: impl Executor, student_id_: Uuid) -> Result<(), DBError> {
     use domain::schema::promotions_students::dsl::*;
 
     diesel::delete(promotions_students.filter(student_id.eq(student_id_))
-    ).execute(&mut conn.get().unwrap())?;
+    ).execute(&mut conn.connection().await?).await?;
 
     Ok(())
 }
@@ -95,6 +162,8 @@ pub fn remove_student_from_all_promotions(conn: &DBPool, student_id_: Uuid) -> R
 #[cfg(test)]
 pub mod test {
     use super::*;
+    use crate::database::organizations::get_organization_from_promotion_id;
+    use crate::database::organizations::tests::test_create_organization;
     use crate::database::promotions::tests::test_create_promotion;
     use infrastructure::init_pool;
 
@@ -114,7 +183,11 @@ pub mod test {
         }
     }
 
-    pub fn test_create_student() -> Uuid {
+    pub async fn test_create_student() -> Uuid {
+        test_create_student_in_organization(test_create_organization().await).await
+    }
+
+    pub async fn test_create_student_in_organization(organization_id: Uuid) -> Uuid {
         let context = TestContext::new();
 
         let random = Uuid::new_v4();
@@ -122,52 +195,117 @@ pub mod test {
             name: format!("test-{}", random),
             surname: format!("test-{}", random),
             email: "none".to_string(),
+            organization_id,
         };
 
-        create_student(&context.conn, new_student).unwrap()
+        create_student(&context.conn, new_student).await.unwrap()
     }
 
-    fn test_create_promotion_student() -> (Uuid, Uuid) {
+    async fn test_create_promotion_student() -> (Uuid, Uuid) {
         let context = TestContext::new();
 
-        let (promotion_id, _) = test_create_promotion();
-        let student_id = test_create_student();
+        let (promotion_id, _) = test_create_promotion().await;
+        let organization_id = get_organization_from_promotion_id(&context.conn, promotion_id).await.unwrap().id;
+        let student_id = test_create_student_in_organization(organization_id).await;
 
         let new_promotion_student = vec![NewPromotionStudent {
             promotion_id,
             student_id
         }];
 
-        create_promotion_students(&context.conn, new_promotion_student).unwrap();
+        create_promotion_students(&context.conn, new_promotion_student).await.unwrap();
 
         (promotion_id, student_id)
     }
 
-    #[test]
-    fn test_get_student_by_id() {
+    #[tokio::test]
+    async fn test_get_students_page_from_promotion_id() {
         let context = TestContext::new();
 
-        let student_id = test_create_student();
+        let (promotion_id, _) = test_create_promotion().await;
+        let organization_id = get_organization_from_promotion_id(&context.conn, promotion_id).await.unwrap().id;
+        let mut students_ids: Vec<Uuid> = Vec::new();
+        for _ in 0..3 {
+            students_ids.push(test_create_student_in_organization(organization_id).await);
+        }
+        create_promotion_students(&context.conn, students_ids.iter().map(|student_id| NewPromotionStudent {
+            promotion_id,
+            student_id: *student_id,
+        }).collect()).await.unwrap();
+
+        let params = ListParams {
+            limit: Some(2),
+            sort: Some(StudentSortField::Name),
+            direction: Some(SortDirection::Desc),
+            ..Default::default()
+        };
+        let page = get_students_page_from_promotion_id(&context.conn, organization_id, promotion_id, &params).await.unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(page.items.len(), 2);
+        assert!(page.items[0].name > page.items[1].name);
+
+        let params = ListParams {
+            page: Some(2),
+            limit: Some(2),
+            ..params
+        };
+        let page = get_students_page_from_promotion_id(&context.conn, organization_id, promotion_id, &params).await.unwrap();
+        assert_eq!(page.items.len(), 1);
+
+        let params = ListParams::<StudentSortField> {
+            search: Some(get_student_by_id(&context.conn, organization_id, students_ids[0]).await.unwrap().name.to_uppercase()),
+            ..Default::default()
+        };
+        let page = get_students_page_from_promotion_id(&context.conn, organization_id, promotion_id, &params).await.unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.items[0].id, students_ids[0]);
+        // The promotion is empty for the other organizations
+        let page = get_students_page_from_promotion_id(&context.conn, test_create_organization().await, promotion_id, &params).await.unwrap();
+        assert_eq!(page.total, 0);
+    }
+
+    #[tokio::test]
+    async fn test_get_student_by_id() {
+        let context = TestContext::new();
+
+        let organization_id = test_create_organization().await;
+        let student_id = test_create_student_in_organization(organization_id).await;
 
-        let student = get_student_by_id(&context.conn, student_id).unwrap();
-        assert_eq!(student_id, student.id)
+        let student = get_student_by_id(&context.conn, organization_id, student_id).await.unwrap();
+        assert_eq!(student_id, student.id);
+        // The students of the other organizations are hidden
+        let other_organization_id = test_create_organization().await;
+        assert!(matches!(get_student_by_id(&context.conn, other_organization_id, student_id).await, Err(DBError::NotFound)));
     }
 
-    #[test]
-    fn test_get_student_by_promotion_id() {
+    #[tokio::test]
+    async fn test_get_student_by_promotion_id() {
         let context = TestContext::new();
 
-        let (promotion_id, student_id) = test_create_promotion_student();
+        let (promotion_id, student_id) = test_create_promotion_student().await;
+        let organization_id = get_organization_from_promotion_id(&context.conn, promotion_id).await.unwrap().id;
 
-        let students = get_students_from_promotion_id(&context.conn, promotion_id).unwrap();
+        let students = get_students_from_promotion_id(&context.conn, organization_id, promotion_id).await.unwrap();
         assert_eq!(students[0].id, student_id);
+        assert!(get_students_from_promotion_id(&context.conn, test_create_organization().await, promotion_id).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_promotions_ids_from_student_id() {
+        let context = TestContext::new();
+
+        let (promotion_id, student_id) = test_create_promotion_student().await;
+
+        let promotions_ids = get_promotions_ids_from_student_id(&context.conn, student_id).await.unwrap();
+        assert_eq!(promotions_ids, vec![promotion_id]);
     }
 
-    #[test]
-    fn test_update_student() {
+    #[tokio::test]
+    async fn test_update_student() {
         let context = TestContext::new();
 
-        let student_id = test_create_student();
+        let organization_id = test_create_organization().await;
+        let student_id = test_create_student_in_organization(organization_id).await;
 
         let random = Uuid::new_v4();
         let updated_student = UpdatedStudent {
@@ -175,36 +313,45 @@ pub mod test {
             surname: Some(format!("updated-{}", random)),
             email: None,
         };
-        update_student(&context.conn, student_id, updated_student).unwrap();
+        // The students of the other organizations can't be changed
+        let other_organization_id = test_create_organization().await;
+        assert!(matches!(update_student(&context.conn, other_organization_id, student_id, UpdatedStudent {
+            name: Some("hidden".to_string()),
+            surname: None,
+            email: None,
+        }).await, Err(DBError::NotFound)));
+        update_student(&context.conn, organization_id, student_id, updated_student).await.unwrap();
 
-        let student = get_student_by_id(&context.conn, student_id).unwrap();
+        let student = get_student_by_id(&context.conn, organization_id, student_id).await.unwrap();
         assert_eq!(student.name, format!("updated-{}", random));
     }
 
-    #[test]
-    fn test_delete_student() {
+    #[tokio::test]
+    async fn test_delete_student() {
         let context = TestContext::new();
 
-        let student_id = test_create_student();
+        let organization_id = test_create_organization().await;
+        let student_id = test_create_student_in_organization(organization_id).await;
 
-        delete_student(&context.conn, student_id).unwrap();
+        assert!(matches!(delete_student(&context.conn, test_create_organization().await, student_id).await, Err(DBError::NotFound)));
+        delete_student(&context.conn, organization_id, student_id).await.unwrap();
     }
 
-    #[test]
-    fn test_delete_promotion_student() {
+    #[tokio::test]
+    async fn test_delete_promotion_student() {
         let context = TestContext::new();
 
-        let (promotion_id, student_id) = test_create_promotion_student();
+        let (promotion_id, student_id) = test_create_promotion_student().await;
 
-        delete_promotion_student(&context.conn, promotion_id, student_id).unwrap();
+        delete_promotion_student(&context.conn, promotion_id, student_id).await.unwrap();
     }
 
-    #[test]
-    fn test_remove_student_from_all_promotions() {
+    #[tokio::test]
+    async fn test_remove_student_from_all_promotions() {
         let context = TestContext::new();
 
-        let student_id = test_create_student();
+        let student_id = test_create_student().await;
 
-        remove_student_from_all_promotions(&context.conn, student_id).unwrap();
+        remove_student_from_all_promotions(&context.conn, student_id).await.unwrap();
     }
 }
\ No newline at end of file
@@ -1,84 +1,122 @@
+use crate::database::projects::organization_projects_ids;
+use crate::database::transaction::Executor;
 use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
 use diesel::result::Error as DBError;
+use domain::models::projects::ProjectState;
 use domain::models::students_tokens::*;
-use domain::schema::students_tokens::dsl::students_tokens;
-use infrastructure::DBPool;
+use infrastructure::{get_connection, DBPool};
 use uuid::Uuid;
 
-pub fn get_student_token_by_id(conn: &DBPool, id_: Uuid) -> Result<StudentToken, DBError> {
+/// Finds a token from the link sent to the student, before they are authenticated, so across the organizations.
+pub async fn get_student_token_by_id(conn: &DBPool, id_: Uuid) -> Result<StudentToken, DBError> {
     use domain::schema::students_tokens::dsl::*;
 
     students_tokens.filter(id.eq(id_))
-        .first(&mut conn.get().unwrap())
+        .first(&mut get_connection(conn).await?).await
 }
 
-pub fn get_student_token_by_token(conn: &DBPool, token_: String) -> Result<StudentToken, DBError> {
+/// Finds the token authenticating a student, so across the organizations.
+pub async fn get_student_token_by_token(conn: &DBPool, token_: String) -> Result<StudentToken, DBError> {
     use domain::schema::students_tokens::dsl::*;
 
     students_tokens.filter(token.eq(token_))
-        .first(&mut conn.get().unwrap())
+        .first(&mut get_connection(conn).await?).await
 }
 
-pub fn get_student_tokens_from_student_and_project_id(conn: &DBPool, student_id_: Uuid, project_id_: Uuid) -> Result<StudentToken, DBError> {
+pub async fn get_student_tokens_from_student_and_project_id(conn: &DBPool, organization_id_: Uuid, student_id_: Uuid, project_id_: Uuid) -> Result<StudentToken, DBError> {
     use domain::schema::students_tokens::dsl::*;
 
     students_tokens.filter(student_id.eq(student_id_))
         .filter(project_id.eq(project_id_))
-        .first(&mut conn.get().unwrap())
+        .filter(project_id.eq_any(organization_projects_ids(organization_id_)))
+        .first(&mut get_connection(conn).await?).await
 }
 
-pub fn create_student_token(conn: &DBPool, new_student_token: NewStudentToken) -> Result<Uuid, DBError> {
+pub async fn create_student_token(conn: &DBPool, organization_id_: Uuid, new_student_token: NewStudentToken) -> Result<Uuid, DBError> {
+    use domain::schema::projects;
     use domain::schema::students_tokens::dsl::*;
 
-    let result: Result<Uuid, DBError> = diesel::insert_into(students_tokens)
+    let mut connection = get_connection(conn).await?;
+
+    // Check if the project of the token exists
+    projects::table.filter(projects::id.eq(new_student_token.project_id))
+        .filter(projects::id.eq_any(organization_projects_ids(organization_id_)))
+        .select(projects::id)
+        .first::<Uuid>(&mut connection).await?;
+
+    diesel::insert_into(students_tokens)
         .values(&new_student_token)
         .returning(id)
-        .get_result(&mut conn.get().unwrap());
-
-    result
+        .get_result(&mut connection).await
 }
 
-pub fn update_student_token(conn: &DBPool, id_: Uuid, updated_student_token: UpdatedStudentToken) -> Result<(), DBError> {
+pub async fn update_student_token(conn: impl Executor, organization_id_: Uuid, id_: Uuid, updated_student_token: UpdatedStudentToken) -> Result<(), DBError> {
     use domain::schema::students_tokens::dsl::*;
 
+    let mut connection = conn.connection().await?;
+
     // Check if the student exists
     students_tokens.filter(id.eq(id_.clone()))
-        .first::<StudentToken>(&mut conn.get().unwrap())?;
+        .filter(project_id.eq_any(organization_projects_ids(organization_id_)))
+        .first::<StudentToken>(&mut connection).await?;
 
     diesel::update(students_tokens.filter(id.eq(id_)))
         .set(&updated_student_token)
-        .execute(&mut conn.get().unwrap())?;
+        .execute(&mut connection).await?;
 
     Ok(())
 }
 
-pub fn delete_student_token(conn: &DBPool, id_: Uuid) -> Result<(), DBError> {
+pub async fn delete_student_token(conn: &DBPool, organization_id_: Uuid, id_: Uuid) -> Result<(), DBError> {
     use domain::schema::students_tokens::dsl::*;
 
+    let mut connection = get_connection(conn).await?;
+
     // Check if the student exists
     students_tokens.filter(id.eq(id_))
-        .first::<StudentToken>(&mut conn.get().unwrap())?;
+        .filter(project_id.eq_any(organization_projects_ids(organization_id_)))
+        .first::<StudentToken>(&mut connection).await?;
 
     diesel::delete(students_tokens.filter(id.eq(id_)))
-        .execute(&mut conn.get().unwrap())?;
+        .execute(&mut connection).await?;
 
     Ok(())
 }
 
-pub fn delete_all_tokens_from_student(conn: &DBPool, student_id_: Uuid) -> Result<(), DBError> {
+pub async fn delete_all_tokens_from_student(conn: impl Executor, organization_id_: Uuid, student_id_: Uuid) -> Result<(), DBError> {
     use domain::schema::students_tokens::dsl::*;
 
     diesel::delete(students_tokens.filter(student_id.eq(student_id_))
-    ).execute(&mut conn.get().unwrap())?;
+        .filter(project_id.eq_any(organization_projects_ids(organization_id_)))
+    ).execute(&mut conn.connection().await?).await?;
 
     Ok(())
 }
 
+/// Counts the tokens not used yet of the projects whose evaluation is open.
+pub async fn count_active_student_tokens(conn: &DBPool) -> Result<i64, DBError> {
+    use domain::schema::projects;
+    use domain::schema::students_tokens::dsl::*;
+
+    students_tokens.filter(used.eq(false))
+        .filter(project_id.eq_any(projects::table
+            .filter(projects::state.eq(ProjectState::EvaluationOpen))
+            .select(projects::id)
+        ))
+        .count()
+        .get_result(&mut get_connection(conn).await?).await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::database::projects::test::test_create_project;
-    use crate::database::students::test::test_create_student;
+    use crate::database::projects::update_project;
+    use domain::models::projects::UpdatedProject;
+    use crate::database::organizations::tests::test_create_organization;
+    use crate::database::projects::get_promotion_from_project_id;
+    use crate::database::students::test::test_create_student_in_organization;
     use infrastructure::init_pool;
 
     struct TestContext {
@@ -97,71 +135,113 @@ mod tests {
         }
     }
 
-    fn test_create_student_token() -> (Uuid, Uuid, Uuid) {
+    async fn test_create_student_token() -> (Uuid, Uuid, Uuid, Uuid) {
         let ctx = TestContext::new();
-        let student_id = test_create_student();
-        let (project_id, _) = test_create_project();
+        let (project_id, _) = test_create_project().await;
+        let organization_id = get_promotion_from_project_id(&ctx.conn, project_id).await.unwrap().organization_id;
+        let student_id = test_create_student_in_organization(organization_id).await;
+
+        // The projects of the other organizations are hidden
+        let other_organization_id = test_create_organization().await;
+        assert_eq!(create_student_token(&ctx.conn, other_organization_id, NewStudentToken {
+            student_id,
+            project_id,
+            token: "token".to_string(),
+        }).await, Err(DBError::NotFound));
+
         let new_student_token = NewStudentToken {
             student_id,
             project_id,
             token: "token".to_string(),
         };
 
-        let result = create_student_token(&ctx.conn, new_student_token).unwrap();
-        (result, student_id, project_id)
+        let result = create_student_token(&ctx.conn, organization_id, new_student_token).await.unwrap();
+        (result, student_id, project_id, organization_id)
     }
 
-    #[test]
-    fn test_get_student_token_by_id() {
-        let (id, _, _) = test_create_student_token();
+    #[tokio::test]
+    async fn test_get_student_token_by_id() {
+        let (id, _, _, _) = test_create_student_token().await;
         let ctx = TestContext::new();
-        let result = get_student_token_by_id(&ctx.conn, id).unwrap();
+        let result = get_student_token_by_id(&ctx.conn, id).await.unwrap();
         assert_eq!(result.id, id);
     }
 
-    #[test]
-    fn test_get_student_token_by_token() {
-        let (_, _, _) = test_create_student_token();
+    #[tokio::test]
+    async fn test_get_student_token_by_token() {
+        let (_, _, _, _) = test_create_student_token().await;
         let ctx = TestContext::new();
-        let result = get_student_token_by_token(&ctx.conn, "token".to_string()).unwrap();
+        let result = get_student_token_by_token(&ctx.conn, "token".to_string()).await.unwrap();
         assert_eq!(result.token, "token".to_string());
     }
 
-    #[test]
-    fn test_get_student_tokens_from_student_and_project_id() {
-        let (_, student_id, project_id) = test_create_student_token();
+    #[tokio::test]
+    async fn test_get_student_tokens_from_student_and_project_id() {
+        let (_, student_id, project_id, organization_id) = test_create_student_token().await;
         let ctx = TestContext::new();
-        let result = get_student_tokens_from_student_and_project_id(&ctx.conn, student_id, project_id).unwrap();
+        let result = get_student_tokens_from_student_and_project_id(&ctx.conn, organization_id, student_id, project_id).await.unwrap();
         assert_eq!(result.student_id, student_id);
+        let other_organization_id = test_create_organization().await;
+        assert!(get_student_tokens_from_student_and_project_id(&ctx.conn, other_organization_id, student_id, project_id).await.is_err());
     }
 
-    #[test]
-    fn test_update_student_token() {
-        let (id, _, _) = test_create_student_token();
+    #[tokio::test]
+    async fn test_update_student_token() {
+        let (id, _, _, organization_id) = test_create_student_token().await;
         let ctx = TestContext::new();
         let updated_student_token = UpdatedStudentToken {
+            token: None,
             used: Some(true),
+            submitted_late: None,
         };
-        update_student_token(&ctx.conn, id, updated_student_token).unwrap();
-        let result = get_student_token_by_id(&ctx.conn, id).unwrap();
+        assert_eq!(update_student_token(&ctx.conn, test_create_organization().await, id, UpdatedStudentToken {
+            token: None,
+            used: Some(true),
+            submitted_late: None,
+        }).await, Err(DBError::NotFound));
+        update_student_token(&ctx.conn, organization_id, id, updated_student_token).await.unwrap();
+        let result = get_student_token_by_id(&ctx.conn, id).await.unwrap();
         assert_eq!(result.used, true);
     }
 
-    #[test]
-    fn test_delete_student_token() {
-        let (id, _, _) = test_create_student_token();
+    #[tokio::test]
+    async fn test_count_active_student_tokens() {
+        let (_, _, project_id, organization_id) = test_create_student_token().await;
+        let ctx = TestContext::new();
+        let before = count_active_student_tokens(&ctx.conn).await.unwrap();
+
+        // Tokens become active when the evaluation of their project opens
+        update_project(&ctx.conn, organization_id, project_id, UpdatedProject {
+            name: None,
+            description: None,
+            start_date: None,
+            end_date: None,
+            notation_period_duration: None,
+            state: Some(ProjectState::EvaluationOpen),
+            timezone: None,
+        }).await.unwrap();
+        assert!(count_active_student_tokens(&ctx.conn).await.unwrap() > before);
+    }
+
+    #[tokio::test]
+    async fn test_delete_student_token() {
+        let (id, _, _, organization_id) = test_create_student_token().await;
         let ctx = TestContext::new();
-        delete_student_token(&ctx.conn, id).unwrap();
-        let result = get_student_token_by_id(&ctx.conn, id);
+        assert_eq!(delete_student_token(&ctx.conn, test_create_organization().await, id).await, Err(DBError::NotFound));
+        delete_student_token(&ctx.conn, organization_id, id).await.unwrap();
+        let result = get_student_token_by_id(&ctx.conn, id).await;
         assert!(result.is_err());
     }
 
-    #[test]
-    fn test_delete_all_tokens_from_student() {
-        let (_, student_id, _) = test_create_student_token();
+    #[tokio::test]
+    async fn test_delete_all_tokens_from_student() {
+        let (_, student_id, project_id, organization_id) = test_create_student_token().await;
         let ctx = TestContext::new();
-        delete_all_tokens_from_student(&ctx.conn, student_id).unwrap();
-        let result = get_student_tokens_from_student_and_project_id(&ctx.conn, student_id, Uuid::new_v4());
+        // The tokens of the other organizations are kept
+        delete_all_tokens_from_student(&ctx.conn, test_create_organization().await, student_id).await.unwrap();
+        assert!(get_student_tokens_from_student_and_project_id(&ctx.conn, organization_id, student_id, project_id).await.is_ok());
+        delete_all_tokens_from_student(&ctx.conn, organization_id, student_id).await.unwrap();
+        let result = get_student_tokens_from_student_and_project_id(&ctx.conn, organization_id, student_id, project_id).await;
         assert!(result.is_err());
     }
 }
\ No newline at end of file
@@ -1,52 +1,89 @@
+use crate::database::transaction::Executor;
 use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
 use diesel::result::Error as DBError;
-use domain::models::tokens::{NewToken, Token, UpdatedToken};
-use infrastructure::DBPool;
+use domain::models::tokens::{NewToken, Token, TokenType, UpdatedToken};
+use infrastructure::{get_connection, DBPool};
 use uuid::Uuid;
 
-pub fn get_token_by_id(conn: &DBPool, id_: Uuid) -> Result<Token, DBError> {
+pub async fn get_token_by_id(conn: &DBPool, id_: Uuid) -> Result<Token, DBError> {
     use domain::schema::tokens::dsl::*;
 
     tokens.filter(id.eq(id_))
-        .first(&mut conn.get().unwrap())
+        .first(&mut get_connection(conn).await?).await
 }
 
-pub fn get_token_by_token_string(conn: &DBPool, token_string: &str) -> Result<Token, DBError> {
+pub async fn get_token_by_token_string(conn: &DBPool, token_string: &str) -> Result<Token, DBError> {
     use domain::schema::tokens::dsl::*;
 
     tokens.filter(token.eq(token_string))
-        .first(&mut conn.get().unwrap())
+        .first(&mut get_connection(conn).await?).await
 }
 
-pub fn create_token(conn: &DBPool, new_token: NewToken) -> Result<Uuid, DBError> {
+pub async fn create_token(conn: impl Executor, new_token: NewToken) -> Result<Uuid, DBError> {
     use domain::schema::tokens::dsl::*;
 
     let result: Result<Uuid, DBError> = diesel::insert_into(tokens)
         .values(&new_token)
         .returning(id)
-        .get_result(&mut conn.get().unwrap());
+        .get_result(&mut conn.connection().await?).await;
 
     result
 }
 
-pub fn update_token(conn: &DBPool, id_: Uuid, updated_token: UpdatedToken) -> Result<(), DBError> {
+pub async fn update_token(conn: impl Executor, id_: Uuid, updated_token: UpdatedToken) -> Result<(), DBError> {
     use domain::schema::tokens::dsl::*;
 
+    let mut connection = conn.connection().await?;
+
     // Check if the token exists
     tokens.filter(id.eq(id_.clone()))
-        .first::<Token>(&mut conn.get().unwrap())?;
+        .first::<Token>(&mut connection).await?;
 
     diesel::update(tokens.filter(id.eq(id_)))
         .set(&updated_token)
-        .execute(&mut conn.get().unwrap())?;
+        .execute(&mut connection).await?;
 
     Ok(())
 }
 
+/// Marks an unused and unexpired token of the given type as used, so it can only be consumed once.
+pub async fn consume_token(conn: impl Executor, id_: Uuid, type__: TokenType) -> Result<Token, DBError> {
+    use domain::schema::tokens::dsl::*;
+
+    diesel::update(tokens
+        .filter(id.eq(id_))
+        .filter(type_.eq(type__))
+        .filter(used.eq(false))
+        .filter(exp.gt(diesel::dsl::now))
+    ).set(used.eq(true))
+        .get_result(&mut conn.connection().await?).await
+}
+
+/// Marks every pending token of the given type of a user as used.
+pub async fn invalidate_user_tokens(conn: &DBPool, user_id_: Uuid, type__: TokenType) -> Result<usize, DBError> {
+    use domain::schema::tokens::dsl::*;
+
+    diesel::update(tokens
+        .filter(user_id.eq(user_id_))
+        .filter(type_.eq(type__))
+        .filter(used.eq(false))
+    ).set(used.eq(true))
+        .execute(&mut get_connection(conn).await?).await
+}
+
+pub async fn delete_expired_tokens(conn: &DBPool) -> Result<usize, DBError> {
+    use domain::schema::tokens::dsl::*;
+
+    diesel::delete(tokens.filter(exp.lt(diesel::dsl::now)))
+        .execute(&mut get_connection(conn).await?).await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use domain::models::tokens::TokenType;
+    use crate::database::users::tests::test_create_user;
+    use chrono::{Duration, Utc};
     use infrastructure::init_pool;
     use uuid::Uuid;
 
@@ -66,53 +103,86 @@ mod tests {
         }
     }
 
-    fn test_create_token() -> (Uuid, String) {
+    async fn test_create_token() -> (Uuid, String) {
+        test_create_user_token(test_create_user().await, Duration::hours(1)).await
+    }
+
+    async fn test_create_user_token(user_id: Uuid, expires_in: Duration) -> (Uuid, String) {
         let context = TestContext::new();
 
         let token = Uuid::new_v4().to_string();
         let new_token = NewToken{
             token: token.clone(),
             type_: TokenType::PassReset,
+            user_id,
+            exp: Utc::now().naive_utc() + expires_in,
         };
 
-        (create_token(&context.conn, new_token).unwrap(), token)
+        (create_token(&context.conn, new_token).await.unwrap(), token)
     }
 
-    #[test]
-    fn test_get_token_by_id() {
+    #[tokio::test]
+    async fn test_get_token_by_id() {
         let context = TestContext::new();
 
-        let (token_id, _) = test_create_token();
+        let (token_id, _) = test_create_token().await;
 
-        let token = get_token_by_id(&context.conn, token_id.clone()).unwrap();
+        let token = get_token_by_id(&context.conn, token_id.clone()).await.unwrap();
 
         assert_eq!(token.id, token_id);
     }
 
-    #[test]
-    fn test_get_token_by_hashed_token() {
+    #[tokio::test]
+    async fn test_get_token_by_hashed_token() {
         let context = TestContext::new();
 
-        let (token_id, token_content) = test_create_token();
+        let (token_id, token_content) = test_create_token().await;
 
-        let token_by_hashed_token = get_token_by_token_string(&context.conn, &token_content).unwrap();
+        let token_by_hashed_token = get_token_by_token_string(&context.conn, &token_content).await.unwrap();
         assert_eq!(token_by_hashed_token.id, token_id)
     }
 
-    #[test]
-    fn test_update_token() {
+    #[tokio::test]
+    async fn test_update_token() {
         let context = TestContext::new();
 
-        let (token_id, _) = test_create_token();
+        let (token_id, _) = test_create_token().await;
 
         let updated_token = UpdatedToken{
             type_: Some(TokenType::PassReset),
             used: Some(true),
         };
 
-        update_token(&context.conn, token_id.clone(), updated_token).unwrap();
+        update_token(&context.conn, token_id.clone(), updated_token).await.unwrap();
 
-        let token = get_token_by_id(&context.conn, token_id).unwrap();
+        let token = get_token_by_id(&context.conn, token_id).await.unwrap();
         assert_eq!(token.type_, TokenType::PassReset)
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_consume_token() {
+        let context = TestContext::new();
+
+        let (token_id, _) = test_create_token().await;
+
+        assert!(consume_token(&context.conn, token_id, TokenType::EmailVerification).await.is_err());
+        consume_token(&context.conn, token_id, TokenType::PassReset).await.unwrap();
+        assert!(consume_token(&context.conn, token_id, TokenType::PassReset).await.is_err());
+
+        let (expired_token_id, _) = test_create_user_token(test_create_user().await, Duration::hours(-1)).await;
+        assert!(consume_token(&context.conn, expired_token_id, TokenType::PassReset).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_user_tokens() {
+        let context = TestContext::new();
+
+        let user_id = test_create_user().await;
+        let (first_token_id, _) = test_create_user_token(user_id, Duration::hours(1)).await;
+        let (second_token_id, _) = test_create_user_token(user_id, Duration::hours(1)).await;
+
+        assert_eq!(invalidate_user_tokens(&context.conn, user_id, TokenType::PassReset).await.unwrap(), 2);
+        assert!(get_token_by_id(&context.conn, first_token_id).await.unwrap().used);
+        assert!(get_token_by_id(&context.conn, second_token_id).await.unwrap().used);
+    }
+}
@@ -0,0 +1,173 @@
+//! Unit of work spanning several queries.
+//!
+//! The functions taking an [`Executor`] run either on a connection of the pool, or on the connection
+//! of a [`transaction`], so the same queries are used alone and as part of a multi-write use case:
+//!
+//! ```ignore
+//! transaction(&conn, |conn| async move {
+//!     let student_id = create_student(&mut *conn, new_student).await?;
+//!     create_promotion_students(&mut *conn, vec![NewPromotionStudent { promotion_id, student_id }]).await
+//! }.scope_boxed()).await?;
+//! ```
+
+use std::future::Future;
+use std::ops::DerefMut;
+
+use diesel::result::Error as DBError;
+use diesel_async::scoped_futures::ScopedBoxFuture;
+pub use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, AsyncPgConnection};
+use infrastructure::{get_connection, DBConnection, DBPool};
+
+/// Where the queries of a database function run.
+pub trait Executor: Send {
+    type Connection: DerefMut<Target = AsyncPgConnection> + Send;
+
+    fn connection(self) -> impl Future<Output = Result<Self::Connection, DBError>> + Send;
+}
+
+impl Executor for &DBPool {
+    type Connection = DBConnection;
+
+    async fn connection(self) -> Result<DBConnection, DBError> {
+        get_connection(self).await
+    }
+}
+
+impl<'c> Executor for &'c mut AsyncPgConnection {
+    type Connection = &'c mut AsyncPgConnection;
+
+    async fn connection(self) -> Result<&'c mut AsyncPgConnection, DBError> {
+        Ok(self)
+    }
+}
+
+/// Runs the operations in a single transaction, committed if they all succeed and rolled back otherwise.
+///
+/// Inside another transaction, the operations run in a savepoint of it.
+pub async fn transaction<'a, X, T, E, F>(conn: X, operations: F) -> Result<T, E>
+where
+    X: Executor,
+    F: for<'r> FnOnce(&'r mut AsyncPgConnection) -> ScopedBoxFuture<'a, 'r, Result<T, E>> + Send + 'a,
+    T: Send + 'a,
+    E: From<DBError> + Send + 'a,
+{
+    let mut connection = conn.connection().await?;
+    connection.deref_mut().transaction(operations).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::organizations::tests::test_create_organization;
+    use crate::database::promotions::tests::test_create_promotion;
+    use crate::database::students::{create_promotion_students, create_student, get_promotions_ids_from_student_id, get_student_by_id};
+    use domain::models::students::{NewPromotionStudent, NewStudent};
+    use infrastructure::{init_pool, init_pool_with_settings, PoolSettings};
+    use shared::error_models::APIError;
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    struct TestContext {
+        conn: DBPool,
+    }
+
+    impl TestContext {
+        fn new() -> Self {
+            TestContext {
+                conn: init_pool(
+                    dotenvy::var("DATABASE_URL")
+                        .expect("DATABASE_URL must be set")
+                        .as_str()
+                ),
+            }
+        }
+    }
+
+    async fn test_new_student() -> NewStudent {
+        let random = Uuid::new_v4();
+        NewStudent {
+            name: format!("test-{}", random),
+            surname: format!("test-{}", random),
+            email: "none".to_string(),
+            organization_id: test_create_organization().await,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transaction_commits_all_the_writes() {
+        let context = TestContext::new();
+        let (promotion_id, _) = test_create_promotion().await;
+        let new_student = test_new_student().await;
+        let organization_id = new_student.organization_id;
+
+        let student_id = transaction(&context.conn, |conn| async move {
+            let student_id = create_student(&mut *conn, new_student).await?;
+            create_promotion_students(&mut *conn, vec![NewPromotionStudent { promotion_id, student_id }]).await?;
+            Ok::<_, DBError>(student_id)
+        }.scope_boxed()).await.unwrap();
+
+        assert!(get_student_by_id(&context.conn, organization_id, student_id).await.is_ok());
+        assert_eq!(get_promotions_ids_from_student_id(&context.conn, student_id).await.unwrap(), vec![promotion_id]);
+    }
+
+    #[tokio::test]
+    async fn test_transaction_rolls_back_all_the_writes() {
+        let context = TestContext::new();
+        let new_student = test_new_student().await;
+        let organization_id = new_student.organization_id;
+
+        let (sender, receiver) = tokio::sync::oneshot::channel();
+        let result = transaction(&context.conn, |conn| async move {
+            let student_id = create_student(&mut *conn, new_student).await?;
+            sender.send(student_id).unwrap();
+            // The promotion doesn't exist
+            create_promotion_students(&mut *conn, vec![NewPromotionStudent { promotion_id: Uuid::new_v4(), student_id }]).await
+        }.scope_boxed()).await;
+
+        assert!(result.is_err());
+        let student_id = receiver.await.unwrap();
+        assert!(matches!(get_student_by_id(&context.conn, organization_id, student_id).await, Err(DBError::NotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_nested_transaction_only_rolls_back_its_writes() {
+        let context = TestContext::new();
+        let kept_student = test_new_student().await;
+        let dropped_student = test_new_student().await;
+        let (kept_organization_id, dropped_organization_id) = (kept_student.organization_id, dropped_student.organization_id);
+
+        let (kept_id, dropped_id) = transaction(&context.conn, |conn| async move {
+            let kept_id = create_student(&mut *conn, kept_student).await?;
+
+            let (sender, receiver) = tokio::sync::oneshot::channel();
+            let nested = transaction(&mut *conn, |conn| async move {
+                let student_id = create_student(&mut *conn, dropped_student).await?;
+                sender.send(student_id).unwrap();
+                Err::<(), _>(DBError::RollbackTransaction)
+            }.scope_boxed()).await;
+            assert!(nested.is_err());
+
+            Ok::<_, DBError>((kept_id, receiver.await.unwrap()))
+        }.scope_boxed()).await.unwrap();
+
+        assert!(get_student_by_id(&context.conn, kept_organization_id, kept_id).await.is_ok());
+        assert!(matches!(get_student_by_id(&context.conn, dropped_organization_id, dropped_id).await, Err(DBError::NotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_database_is_unavailable() {
+        let conn = init_pool_with_settings("postgres://postgres@localhost:1/unreachable", PoolSettings {
+            max_size: 1,
+            wait_timeout: Duration::from_secs(1),
+            connect_timeout: Duration::from_secs(1),
+        });
+
+        let result = get_student_by_id(&conn, Uuid::new_v4(), Uuid::new_v4()).await;
+        let error = APIError::from(result.unwrap_err());
+        assert_eq!(error.problem().status, 503);
+        // The transactions don't panic either
+        let result = transaction(&conn, |_| async move { Ok::<_, DBError>(()) }.scope_boxed()).await;
+        assert!(result.is_err());
+    }
+}
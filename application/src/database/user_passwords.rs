@@ -1,42 +1,44 @@
+use crate::database::transaction::Executor;
 use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
 use diesel::result::Error as DBError;
 use uuid::Uuid;
 
 use domain::models::user_passwords::*;
-use infrastructure::DBPool;
+use infrastructure::{get_connection, DBPool};
 
-pub fn get_user_password_by_user_id(conn: &DBPool, _user_id: Uuid) -> Result<UserPassword, DBError> {
+pub async fn get_user_password_by_user_id(conn: impl Executor, _user_id: Uuid) -> Result<UserPassword, DBError> {
     use domain::schema::user_passwords::dsl::*;
 
     user_passwords.filter(user_id.eq(_user_id))
-        .first(&mut conn.get().unwrap())
+        .first(&mut conn.connection().await?).await
 }
 
-pub fn create_user_password(conn: &DBPool, new_user_password: NewUserPassword) -> Result<(), DBError> {
+pub async fn create_user_password(conn: impl Executor, new_user_password: NewUserPassword) -> Result<(), DBError> {
     use domain::schema::user_passwords::dsl::*;
 
     diesel::insert_into(user_passwords)
         .values(&new_user_password)
-        .execute(&mut conn.get().unwrap())?;
+        .execute(&mut conn.connection().await?).await?;
 
     Ok(())
 }
 
-pub fn update_user_password(conn: &DBPool, _user_id: Uuid, update_user_password: UpdatedUserPassword) -> Result<(), DBError> {
+pub async fn update_user_password(conn: impl Executor, _user_id: Uuid, update_user_password: UpdatedUserPassword) -> Result<(), DBError> {
     use domain::schema::user_passwords::dsl::*;
 
     diesel::update(user_passwords.filter(user_id.eq(_user_id)))
         .set(&update_user_password)
-        .execute(&mut conn.get().unwrap())?;
+        .execute(&mut conn.connection().await?).await?;
 
     Ok(())
 }
 
-pub fn delete_user_password(conn: &DBPool, _user_id: Uuid) -> Result<(), DBError> {
+pub async fn delete_user_password(conn: &DBPool, _user_id: Uuid) -> Result<(), DBError> {
     use domain::schema::user_passwords::dsl::*;
 
     diesel::delete(user_passwords.filter(user_id.eq(_user_id)))
-        .execute(&mut conn.get().unwrap())?;
+        .execute(&mut get_connection(conn).await?).await?;
 
     Ok(())
 }
@@ -64,48 +66,48 @@ mod tests {
         }
     }
 
-    fn test_create_user_password() -> Uuid {
+    async fn test_create_user_password() -> Uuid {
         let context = TestContext::new();
 
-        let user_id = test_create_user();
+        let user_id = test_create_user().await;
         let new_user_password = NewUserPassword {
             user_id,
             password: "password".to_string(),
         };
 
-        create_user_password(&context.conn, new_user_password).unwrap();
+        create_user_password(&context.conn, new_user_password).await.unwrap();
         user_id
     }
 
-    #[test]
-    fn test_get_user_password_by_user_id() {
+    #[tokio::test]
+    async fn test_get_user_password_by_user_id() {
         let context = TestContext::new();
 
-        let user_id = test_create_user_password();
-        let user_password = get_user_password_by_user_id(&context.conn, user_id).unwrap();
+        let user_id = test_create_user_password().await;
+        let user_password = get_user_password_by_user_id(&context.conn, user_id).await.unwrap();
         assert_eq!(user_password.user_id, user_id);
     }
 
-    #[test]
-    fn test_update_user_password() {
+    #[tokio::test]
+    async fn test_update_user_password() {
         let context = TestContext::new();
 
-        let user_id = test_create_user_password();
+        let user_id = test_create_user_password().await;
 
         let updated_user_password = UpdatedUserPassword {
             password: Some("new_password".to_string()),
         };
 
-        update_user_password(&context.conn, user_id, updated_user_password).unwrap();
-        let user_password = get_user_password_by_user_id(&context.conn, user_id).unwrap();
+        update_user_password(&context.conn, user_id, updated_user_password).await.unwrap();
+        let user_password = get_user_password_by_user_id(&context.conn, user_id).await.unwrap();
         assert_eq!(user_password.password, "new_password");
     }
 
-    #[test]
-    fn test_delete_user_password() {
+    #[tokio::test]
+    async fn test_delete_user_password() {
         let context = TestContext::new();
 
-        let user_id = test_create_user_password();
-        delete_user_password(&context.conn, user_id).unwrap();
+        let user_id = test_create_user_password().await;
+        delete_user_password(&context.conn, user_id).await.unwrap();
     }
 }
\ No newline at end of file
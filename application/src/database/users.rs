@@ -1,71 +1,130 @@
+use crate::database::transaction::Executor;
 use diesel::prelude::*;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
 use diesel::result::Error as DBError;
 use uuid::Uuid;
 
 use domain::models::users::*;
-use infrastructure::DBPool;
+use domain::repositories::users::UsersRepository;
+use infrastructure::repositories::PgRepositories;
+use infrastructure::{get_connection, DBPool};
 
-pub fn get_user_by_id(conn: &DBPool, user_id: Uuid) -> Result<User, DBError> {
+pub async fn get_user_by_id(conn: &DBPool, user_id: Uuid) -> Result<User, DBError> {
+    PgRepositories::new(conn.clone()).get_user_by_id(user_id).await
+}
+
+pub async fn get_user_by_username(conn: impl Executor, username_: &str) -> Result<User, DBError> {
     use domain::schema::users::dsl::*;
 
-    users.filter(id.eq(user_id)).first(&mut conn.get().unwrap())
+    users.filter(username.eq(username_)).first(&mut conn.connection().await?).await
 }
 
-pub fn get_user_by_username(conn: &DBPool, username_: &str) -> Result<User, DBError> {
+/// Finds a user by an email address unique across the organizations, only to identify them before they are authenticated.
+pub async fn get_user_by_email_in_any_organization(conn: impl Executor, email_: &str) -> Result<User, DBError> {
     use domain::schema::users::dsl::*;
 
-    users.filter(username.eq(username_)).first(&mut conn.get().unwrap())
+    users.filter(email.eq(email_)).first(&mut conn.connection().await?).await
 }
 
-pub fn get_user_by_email(conn: &DBPool, email_: &str) -> Result<User, DBError> {
+pub async fn get_user_by_email(conn: impl Executor, organization_id_: Uuid, email_: &str) -> Result<User, DBError> {
     use domain::schema::users::dsl::*;
 
-    users.filter(email.eq(email_)).first(&mut conn.get().unwrap())
+    users.filter(organization_id.eq(organization_id_))
+        .filter(email.eq(email_))
+        .first(&mut conn.connection().await?).await
 }
 
-pub fn create_user(conn: &DBPool, new_user: NewUser) -> Result<Uuid, DBError> {
+pub async fn create_user(conn: impl Executor, new_user: NewUser) -> Result<Uuid, DBError> {
     use domain::schema::users::dsl::*;
 
     diesel::insert_into(users)
         .values(&new_user)
         .returning(id)
-        .get_result(&mut conn.get().unwrap())
+        .get_result(&mut conn.connection().await?).await
 }
 
-pub fn update_user(conn: &DBPool, user_id: Uuid, update_user: UpdatedUser) -> Result<(), DBError> {
+pub async fn update_user(conn: impl Executor, user_id: Uuid, update_user: UpdatedUser) -> Result<(), DBError> {
     use domain::schema::users::dsl::*;
 
     diesel::update(users.filter(id.eq(user_id)))
         .set(&update_user)
-        .execute(&mut conn.get().unwrap())?;
+        .execute(&mut conn.connection().await?).await?;
 
     Ok(())
 }
 
-pub fn update_user_info(conn: &DBPool, user_id: Uuid, update_user: UpdatedUserInfo) -> Result<(), DBError> {
+pub async fn update_user_info(conn: &DBPool, user_id: Uuid, update_user: UpdatedUserInfo) -> Result<(), DBError> {
     use domain::schema::users::dsl::*;
 
     diesel::update(users.filter(id.eq(user_id)))
         .set(&update_user)
-        .execute(&mut conn.get().unwrap())?;
+        .execute(&mut get_connection(conn).await?).await?;
 
     Ok(())
 }
 
-pub fn delete_user(conn: &DBPool, user_id: Uuid) -> Result<(), DBError> {
+pub async fn delete_user(conn: &DBPool, user_id: Uuid) -> Result<(), DBError> {
     use domain::schema::users::dsl::*;
 
     diesel::delete(users.filter(id.eq(user_id)))
-        .execute(&mut conn.get().unwrap())?;
+        .execute(&mut get_connection(conn).await?).await?;
 
     Ok(())
 }
 
+/// Deletes a user account.
+///
+/// Each promotion the user owns is handed over to its highest ranking remaining member,
+/// the oldest one first. Promotions without any other member are deleted along with the user.
+pub async fn delete_user_account(conn: &DBPool, user_id: Uuid) -> Result<(), DBError> {
+    use domain::models::promotions_members::{PromotionMember, PromotionRole};
+    use domain::schema::{promotions, promotions_members, users};
+
+    get_connection(conn).await?.transaction::<_, DBError, _>(|conn| async move {
+        let owned_promotion_ids: Vec<Uuid> = promotions_members::table
+            .filter(promotions_members::user_id.eq(user_id))
+            .filter(promotions_members::role.eq(PromotionRole::Owner))
+            .select(promotions_members::promotion_id)
+            .load(conn).await?;
+
+        for owned_promotion_id in owned_promotion_ids {
+            // Roles are ordered from the highest to the lowest in the enum
+            let successor = promotions_members::table
+                .filter(promotions_members::promotion_id.eq(owned_promotion_id))
+                .filter(promotions_members::user_id.ne(user_id))
+                .order((promotions_members::role.asc(), promotions_members::created_at.asc()))
+                .first::<PromotionMember>(conn)
+                .await
+                .optional()?;
+
+            if let Some(successor) = successor {
+                diesel::update(promotions_members::table
+                    .filter(promotions_members::promotion_id.eq(owned_promotion_id))
+                    .filter(promotions_members::user_id.eq(successor.user_id))
+                ).set(promotions_members::role.eq(PromotionRole::Owner))
+                    .execute(conn).await?;
+
+                diesel::update(promotions::table.filter(promotions::id.eq(owned_promotion_id)))
+                    .set(promotions::teacher_id.eq(successor.user_id))
+                    .execute(conn).await?;
+            }
+        }
+
+        diesel::delete(users::table.filter(users::id.eq(user_id)))
+            .execute(conn).await?;
+
+        Ok(())
+    }.scope_boxed()).await
+}
+
 #[cfg(test)]
 pub mod tests {
     use dotenvy;
     use infrastructure::init_pool;
 
+    use crate::database::organizations::tests::test_create_organization;
+
     use super::*;
 
     struct TestContext {
@@ -84,61 +143,78 @@ pub mod tests {
         }
     }
 
-    pub fn test_create_user() -> Uuid {
+    pub async fn test_create_user() -> Uuid {
+        test_create_user_in_organization(test_create_organization().await).await
+    }
+
+    pub async fn test_create_user_in_organization(organization_id: Uuid) -> Uuid {
         let context = TestContext::new();
         let new_user = NewUser {
             username: "test-".to_string() + &Uuid::new_v4().to_string(),
-            email: "test-".to_string() + &Uuid::new_v4().to_string()
+            email: "test-".to_string() + &Uuid::new_v4().to_string(),
+            organization_id,
         };
-        let user_id = create_user(&context.conn, new_user).unwrap();
+        let user_id = create_user(&context.conn, new_user).await.unwrap();
         let updated_user = UpdatedUser {
             username: Some("test-".to_string() + &user_id.to_string()),
             email: Some("test-".to_string() + &user_id.to_string()),
             has_validated_email: None,
             role: None,
             token_version: None,
+            organization_role: None,
         };
-        update_user(&context.conn, user_id, updated_user).unwrap();
+        update_user(&context.conn, user_id, updated_user).await.unwrap();
         user_id
     }
 
-    #[test]
-    fn test_get_user_by_id() {
+    #[tokio::test]
+    async fn test_get_user_by_id() {
         let context = TestContext::new();
 
-        let user_id = test_create_user();
+        let user_id = test_create_user().await;
 
-        let user = get_user_by_id(&context.conn, user_id).unwrap();
+        let user = get_user_by_id(&context.conn, user_id).await.unwrap();
         assert_eq!(user_id, user.id);
     }
 
-    #[test]
-    fn test_get_user_by_username() {
+    #[tokio::test]
+    async fn test_get_user_by_username() {
         let context = TestContext::new();
 
-        let user_id = test_create_user();
+        let user_id = test_create_user().await;
 
         let username = format!("test-{}", user_id);
-        let user = get_user_by_username(&context.conn, &username).unwrap();
+        let user = get_user_by_username(&context.conn, &username).await.unwrap();
         assert_eq!(user.username, username);
     }
 
-    #[test]
-    fn test_get_user_by_email() {
+    #[tokio::test]
+    async fn test_get_user_by_email_in_any_organization() {
         let context = TestContext::new();
 
-        let user_id = test_create_user();
+        let user_id = test_create_user().await;
 
         let mail = format!("test-{}", user_id);
-        let user = get_user_by_email(&context.conn, &mail).unwrap();
+        let user = get_user_by_email_in_any_organization(&context.conn, &mail).await.unwrap();
         assert_eq!(user.email, mail);
     }
 
-    #[test]
-    fn test_update_user() {
+    #[tokio::test]
+    async fn test_get_user_by_email() {
+        let context = TestContext::new();
+
+        let user_id = test_create_user().await;
+        let user = get_user_by_id(&context.conn, user_id).await.unwrap();
+
+        get_user_by_email(&context.conn, user.organization_id, &user.email).await.unwrap();
+        assert!(get_user_by_email(&context.conn, test_create_organization().await, &user.email).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_update_user() {
         let context = TestContext::new();
 
-        let user_id = test_create_user();
+        let user_id = test_create_user().await;
 
         let random = Uuid::new_v4().to_string();
         let updated_user = UpdatedUser {
@@ -147,11 +223,12 @@ pub mod tests {
             has_validated_email: None,
             role: None,
             token_version: None,
+            organization_role: None,
         };
 
-        update_user(&context.conn, user_id, updated_user).unwrap();
+        update_user(&context.conn, user_id, updated_user).await.unwrap();
 
-        let user = get_user_by_id(&context.conn, user_id).unwrap();
+        let user = get_user_by_id(&context.conn, user_id).await.unwrap();
         assert_eq!(user.email, "test".to_string() + &random);
 
         let updated_user_info = UpdatedUserInfo {
@@ -159,17 +236,91 @@ pub mod tests {
             email: None,
         };
 
-        update_user_info(&context.conn, user_id, updated_user_info).unwrap();
+        update_user_info(&context.conn, user_id, updated_user_info).await.unwrap();
 
-        let user = get_user_by_id(&context.conn, user_id).unwrap();
+        let user = get_user_by_id(&context.conn, user_id).await.unwrap();
         assert_eq!(user.username, "test".to_string() + &random);
     }
 
-    #[test]
-    fn test_delete_user() {
+    #[tokio::test]
+    async fn test_delete_user() {
+        let context = TestContext::new();
+
+        let user_id = test_create_user().await;
+        delete_user(&context.conn, user_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_delete_user_account() {
+        use crate::database::promotions::get_promotion_by_id;
+        use crate::database::promotions::tests::test_create_promotion;
+        use crate::database::promotions_members::{create_promotion_member, get_promotion_member};
+        use domain::models::promotions_members::{NewPromotionMember, PromotionRole};
+
+        let context = TestContext::new();
+
+        // A promotion shared with an observer and a co-teacher goes to the co-teacher
+        let (shared_promotion_id, teacher_id) = test_create_promotion().await;
+        let organization_id = get_user_by_id(&context.conn, teacher_id).await.unwrap().organization_id;
+        let observer_id = test_create_user_in_organization(organization_id).await;
+        let co_teacher_id = test_create_user_in_organization(organization_id).await;
+        for (member_id, member_role) in [
+            (teacher_id, PromotionRole::Owner),
+            (observer_id, PromotionRole::Observer),
+            (co_teacher_id, PromotionRole::CoTeacher),
+        ] {
+            create_promotion_member(&context.conn, NewPromotionMember {
+                promotion_id: shared_promotion_id,
+                user_id: member_id,
+                role: member_role,
+            }).await.unwrap();
+        }
+
+        // A promotion without any other member is deleted
+        let (own_promotion_id, own_teacher_id) = test_create_promotion().await;
+        let own_organization_id = get_user_by_id(&context.conn, own_teacher_id).await.unwrap().organization_id;
+        create_promotion_member(&context.conn, NewPromotionMember {
+            promotion_id: own_promotion_id,
+            user_id: own_teacher_id,
+            role: PromotionRole::Owner,
+        }).await.unwrap();
+
+        delete_user_account(&context.conn, teacher_id).await.unwrap();
+        delete_user_account(&context.conn, own_teacher_id).await.unwrap();
+
+        assert!(get_user_by_id(&context.conn, teacher_id).await.is_err());
+        let promotion = get_promotion_by_id(&context.conn, organization_id, shared_promotion_id).await.unwrap();
+        assert_eq!(promotion.teacher_id, co_teacher_id);
+        let member = get_promotion_member(&context.conn, shared_promotion_id, co_teacher_id).await.unwrap();
+        assert_eq!(member.role, PromotionRole::Owner);
+        assert!(get_promotion_by_id(&context.conn, own_organization_id, own_promotion_id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_delete_user_account_after_audited_actions() {
+        use crate::audit::log::{record_audit_log, AuditContext, AuditEvent};
+        use crate::database::audit_logs::{get_audit_logs, AuditLogSearchParams};
+        use domain::models::audit_logs::AuditAction;
+
         let context = TestContext::new();
 
-        let user_id = test_create_user();
-        delete_user(&context.conn, user_id).unwrap();
+        let user_id = test_create_user().await;
+        let audit_context = AuditContext { actor_id: Some(user_id), ip: None };
+        record_audit_log::<()>(&context.conn, &audit_context, AuditEvent {
+            action: AuditAction::PasswordChanged,
+            resource_type: "user",
+            resource_id: Some(user_id.to_string()),
+            before: None,
+            after: None,
+        }).await;
+
+        delete_user_account(&context.conn, user_id).await.unwrap();
+
+        assert!(get_user_by_id(&context.conn, user_id).await.is_err());
+        let params = AuditLogSearchParams { resource_id: Some(user_id.to_string()), ..Default::default() };
+        let audit_logs = get_audit_logs(&context.conn, &params).await.unwrap();
+        assert_eq!(audit_logs.len(), 1);
+        assert_eq!(audit_logs[0].actor_id, None);
+        assert_eq!(audit_logs[0].action, AuditAction::PasswordChanged);
     }
 }
\ No newline at end of file
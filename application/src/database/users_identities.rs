@@ -0,0 +1,92 @@
+use crate::database::transaction::Executor;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use diesel::result::Error as DBError;
+use domain::models::users_identities::*;
+use infrastructure::{get_connection, DBPool};
+use uuid::Uuid;
+
+pub async fn get_user_identity(conn: &DBPool, issuer_: &str, subject_: &str) -> Result<UserIdentity, DBError> {
+    use domain::schema::users_identities::dsl::*;
+
+    users_identities
+        .filter(issuer.eq(issuer_))
+        .filter(subject.eq(subject_))
+        .first(&mut get_connection(conn).await?).await
+}
+
+pub async fn get_user_identities_from_user_id(conn: &DBPool, user_id_: Uuid) -> Result<Vec<UserIdentity>, DBError> {
+    use domain::schema::users_identities::dsl::*;
+
+    users_identities
+        .filter(user_id.eq(user_id_))
+        .load(&mut get_connection(conn).await?).await
+}
+
+pub async fn create_user_identity(conn: impl Executor, new_user_identity: NewUserIdentity) -> Result<Uuid, DBError> {
+    use domain::schema::users_identities::dsl::*;
+
+    diesel::insert_into(users_identities)
+        .values(&new_user_identity)
+        .returning(id)
+        .get_result(&mut conn.connection().await?).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::users::tests::test_create_user;
+    use infrastructure::init_pool;
+
+    struct TestContext {
+        conn: DBPool,
+    }
+
+    impl TestContext {
+        fn new() -> Self {
+            TestContext {
+                conn: init_pool(
+                    dotenvy::var("DATABASE_URL")
+                        .expect("DATABASE_URL must be set")
+                        .as_str()
+                ),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_user_identity() {
+        let context = TestContext::new();
+
+        let user_id = test_create_user().await;
+        let subject = Uuid::new_v4().to_string();
+        create_user_identity(&context.conn, NewUserIdentity {
+            user_id,
+            issuer: "https://idp.example.com".to_string(),
+            subject: subject.clone(),
+        }).await.unwrap();
+
+        let identity = get_user_identity(&context.conn, "https://idp.example.com", &subject).await.unwrap();
+        assert_eq!(identity.user_id, user_id);
+        assert_eq!(get_user_identities_from_user_id(&context.conn, user_id).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_create_duplicated_user_identity() {
+        let context = TestContext::new();
+
+        let subject = Uuid::new_v4().to_string();
+        create_user_identity(&context.conn, NewUserIdentity {
+            user_id: test_create_user().await,
+            issuer: "https://idp.example.com".to_string(),
+            subject: subject.clone(),
+        }).await.unwrap();
+
+        let result = create_user_identity(&context.conn, NewUserIdentity {
+            user_id: test_create_user().await,
+            issuer: "https://idp.example.com".to_string(),
+            subject,
+        }).await;
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,239 @@
+//! Deadlines of the students for the evaluation of a project.
+//!
+//! The students have until the end of the evaluation to evaluate their group, unless a teacher granted one of
+//! them an extension, such as a sick student. The scheduler then renews the token of the student so it
+//! expires with the extension, and tells only them. A project may also accept the evaluations submitted some
+//! hours after the deadline of their students, flagged as late, which lower the marks of their authors.
+
+use chrono::{DateTime, Duration, Utc};
+use domain::models::extensions::{NewProjectLatePolicy, NewStudentExtension, ProjectLatePolicy, StudentExtension};
+use domain::models::projects::{Project, ProjectState};
+use domain::repositories::Repositories;
+use shared::error_models::{APIError, BadRequestError, ConflictError, DBError, NotFoundError, UserError};
+use uuid::Uuid;
+
+use crate::timezone::{get_project_calendar, ProjectCalendar, Tz};
+
+/// Time a student has to evaluate their group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StudentDeadline {
+    pub due_at: DateTime<Utc>,
+    /// Whether the deadline comes from an extension of the student
+    pub is_extended: bool,
+    /// End of the late submissions, if the project accepts them
+    pub late_until: Option<DateTime<Utc>>,
+}
+
+impl StudentDeadline {
+    /// Returns when the token of the student expires, at the last time they can evaluate their group.
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        self.late_until.unwrap_or(self.due_at)
+    }
+
+    /// Returns until when the student can still evaluate their group once the evaluation is closed.
+    pub fn closes_after_evaluation(&self) -> Option<DateTime<Utc>> {
+        match self.is_extended {
+            true => Some(self.expires_at()),
+            false => self.late_until,
+        }
+    }
+
+    /// Checks that a student can evaluate their group at a time, and returns whether it's late.
+    ///
+    /// While the evaluation is open, the students are on time. Once it's closed, only the ones with an extension
+    /// are, and the late submissions are accepted until the end of the late period of the project.
+    pub fn check_submission(&self, state: ProjectState, now: DateTime<Utc>) -> Result<bool, APIError> {
+        match state {
+            ProjectState::EvaluationOpen => Ok(false),
+            ProjectState::EvaluationClosed if self.is_extended && now <= self.due_at => Ok(false),
+            ProjectState::EvaluationClosed if self.late_until.is_some_and(|late_until| now <= late_until) => Ok(true),
+            ProjectState::EvaluationClosed => Err(conflict("The deadline of the evaluation has passed")),
+            _ => Err(conflict("The evaluation of the project is not open")),
+        }
+    }
+}
+
+/// Deadlines of the students of a project.
+#[derive(Debug, Clone)]
+pub struct ProjectDeadlines {
+    /// Timezone of the project, the deadlines being shown in it
+    pub timezone: Tz,
+    pub evaluation_end: DateTime<Utc>,
+    pub extensions: Vec<StudentExtension>,
+    pub late_policy: Option<ProjectLatePolicy>,
+}
+
+impl ProjectDeadlines {
+    pub fn new(calendar: &ProjectCalendar, extensions: Vec<StudentExtension>, late_policy: Option<ProjectLatePolicy>) -> Self {
+        ProjectDeadlines {
+            timezone: calendar.timezone,
+            evaluation_end: calendar.evaluation_end.with_timezone(&Utc),
+            extensions,
+            late_policy,
+        }
+    }
+
+    pub fn extension_of(&self, student_id: Uuid) -> Option<&StudentExtension> {
+        self.extensions.iter().find(|extension| extension.student_id == student_id)
+    }
+
+    /// Returns the deadline of a student, their extension only counting when it ends after the evaluation.
+    pub fn of_student(&self, student_id: Uuid) -> StudentDeadline {
+        let extended_until = self.extension_of(student_id)
+            .map(|extension| extension.extended_until)
+            .filter(|&extended_until| extended_until > self.evaluation_end);
+        let due_at = extended_until.unwrap_or(self.evaluation_end);
+
+        StudentDeadline {
+            due_at,
+            is_extended: extended_until.is_some(),
+            late_until: self.late_policy.as_ref().map(|policy| due_at + Duration::hours(policy.late_hours as i64)),
+        }
+    }
+
+    /// Returns until when some of the students can still evaluate their group once the evaluation is closed.
+    pub fn submissions_end(&self, students_ids: impl IntoIterator<Item = Uuid>) -> Option<DateTime<Utc>> {
+        students_ids.into_iter()
+            .filter_map(|student_id| self.of_student(student_id).closes_after_evaluation())
+            .max()
+    }
+}
+
+/// Returns the deadlines of the students of a project.
+pub async fn get_project_deadlines<R: Repositories>(repos: &R, project: &Project, calendar: &ProjectCalendar) -> Result<ProjectDeadlines, DBError> {
+    let extensions = repos.get_student_extensions(project.id).await?;
+    let late_policy = repos.get_project_late_policy(project.id).await?;
+
+    Ok(ProjectDeadlines::new(calendar, extensions, late_policy))
+}
+
+/// Checks that a student can evaluate their group now, and returns whether it's late.
+pub async fn check_student_submission<R: Repositories>(repos: &R, project: &Project, student_id: Uuid) -> Result<bool, APIError> {
+    let calendar = get_project_calendar(repos, project).await?;
+    let deadlines = get_project_deadlines(repos, project, &calendar).await?;
+
+    deadlines.of_student(student_id).check_submission(project.state, Utc::now())
+}
+
+/// Grants an extension to a student of a project, or replaces theirs.
+///
+/// The extension must end after the evaluation, and can't be granted once the results are published.
+pub async fn grant_extension<R: Repositories>(repos: &R, project: &Project, new_extension: NewStudentExtension) -> Result<StudentExtension, APIError> {
+    if project.state > ProjectState::EvaluationClosed {
+        return Err(conflict("The results of the project are already published"));
+    }
+
+    let is_student_of_project = repos.get_groups_students_from_project_id(project.id).await?.iter()
+        .any(|(group_student, _)| group_student.student_id == new_extension.student_id);
+    if !is_student_of_project {
+        return Err(APIError::UserError(UserError::NotFound(NotFoundError {
+            resource: format!("Student {} in project {}", new_extension.student_id, project.id),
+        })));
+    }
+
+    let calendar = get_project_calendar(repos, project).await?;
+    if new_extension.extended_until <= calendar.evaluation_end || new_extension.extended_until <= Utc::now() {
+        return Err(APIError::UserError(UserError::BadRequest(BadRequestError {
+            request: "The extension must end after the evaluation and in the future".to_string(),
+        })));
+    }
+
+    Ok(repos.set_student_extension(new_extension).await?)
+}
+
+/// Revokes the extension of a student of a project.
+pub async fn revoke_extension<R: Repositories>(repos: &R, project_id: Uuid, student_id: Uuid) -> Result<(), APIError> {
+    match repos.delete_student_extension(project_id, student_id).await? {
+        true => Ok(()),
+        false => Err(APIError::UserError(UserError::NotFound(NotFoundError {
+            resource: format!("Extension of student {}", student_id),
+        }))),
+    }
+}
+
+/// Replaces the late submissions policy of a project, or removes it so the project refuses them, then
+/// returns its new policy.
+pub async fn update_late_policy<R: Repositories>(repos: &R, project: &Project, new_policy: Option<NewProjectLatePolicy>) -> Result<Option<ProjectLatePolicy>, DBError> {
+    match new_policy {
+        Some(new_policy) => Ok(Some(repos.set_project_late_policy(new_policy).await?)),
+        None => {
+            repos.delete_project_late_policy(project.id).await?;
+            Ok(None)
+        }
+    }
+}
+
+/// Lowers the mark of a student who submitted late by the penalty of the project.
+pub fn apply_late_penalty(mark: f64, policy: &ProjectLatePolicy) -> f64 {
+    mark * (1.0 - policy.penalty_percent / 100.0)
+}
+
+fn conflict(reason: &str) -> APIError {
+    APIError::UserError(UserError::Conflict(ConflictError { reason: reason.to_string() }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(date: &str) -> DateTime<Utc> {
+        chrono::NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap().and_utc()
+    }
+
+    fn deadlines(extended_until: Option<&str>, late_hours: Option<i32>) -> (ProjectDeadlines, Uuid) {
+        let (project_id, student_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let extensions = extended_until.map(|extended_until| StudentExtension {
+            project_id,
+            student_id,
+            extended_until: utc(extended_until),
+            reason: Some("Sick".to_string()),
+            granted_by: None,
+            created_at: Utc::now(),
+            notified_at: None,
+        });
+        let late_policy = late_hours.map(|late_hours| ProjectLatePolicy {
+            project_id,
+            late_hours,
+            penalty_percent: 10.0,
+            updated_at: Utc::now(),
+        });
+
+        let deadlines = ProjectDeadlines {
+            timezone: Tz::UTC,
+            evaluation_end: utc("2024-11-20 12:00"),
+            extensions: extensions.into_iter().collect(),
+            late_policy,
+        };
+        (deadlines, student_id)
+    }
+
+    #[test]
+    fn test_extended_students_submit_on_time_after_the_close() {
+        let (deadlines, student_id) = deadlines(Some("2024-11-23 12:00"), None);
+        let deadline = deadlines.of_student(student_id);
+
+        assert_eq!(deadline.expires_at(), utc("2024-11-23 12:00"));
+        assert!(!deadline.check_submission(ProjectState::EvaluationClosed, utc("2024-11-22 09:00")).unwrap());
+        assert!(deadline.check_submission(ProjectState::EvaluationClosed, utc("2024-11-23 13:00")).is_err());
+        assert!(deadline.check_submission(ProjectState::ResultsPublished, utc("2024-11-22 09:00")).is_err());
+        // The other students keep the deadline of the project
+        let other = deadlines.of_student(Uuid::new_v4());
+        assert!(!other.is_extended);
+        assert!(other.check_submission(ProjectState::EvaluationClosed, utc("2024-11-20 11:00")).is_err());
+        assert_eq!(deadlines.submissions_end([student_id, Uuid::new_v4()]), Some(utc("2024-11-23 12:00")));
+    }
+
+    #[test]
+    fn test_late_submissions_are_flagged_until_the_end_of_the_late_period() {
+        let (deadlines, student_id) = deadlines(Some("2024-11-19 12:00"), Some(24));
+        // The extension ends before the evaluation, so it doesn't count
+        let deadline = deadlines.of_student(student_id);
+
+        assert!(!deadline.is_extended);
+        assert_eq!(deadline.expires_at(), utc("2024-11-21 12:00"));
+        assert!(!deadline.check_submission(ProjectState::EvaluationOpen, utc("2024-11-20 09:00")).unwrap());
+        assert!(deadline.check_submission(ProjectState::EvaluationClosed, utc("2024-11-21 09:00")).unwrap());
+        assert!(deadline.check_submission(ProjectState::EvaluationClosed, utc("2024-11-21 13:00")).is_err());
+        assert_eq!(apply_late_penalty(15.0, deadlines.late_policy.as_ref().unwrap()), 13.5);
+    }
+}
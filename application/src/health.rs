@@ -0,0 +1,235 @@
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use diesel_async::RunQueryDsl;
+use infrastructure::{get_connection_with_timeout, pending_migrations};
+use serde::Serialize;
+use shared::app_state_model::{AppState, StartupStatus};
+use shared::cron::CronSchedule;
+use utoipa::ToSchema;
+
+/// Time the readiness probe waits for a database connection
+const DATABASE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Delay after which a missed run of the scheduler is reported
+const SCHEDULER_GRACE_PERIOD: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Up,
+    Down,
+    /// The dependency is not checked
+    Skipped,
+}
+
+/// Result of the check of a dependency.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CheckResult {
+    pub status: CheckStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// Time spent checking the dependency
+    pub duration_ms: u64,
+}
+
+impl CheckResult {
+    fn new(started_at: Instant, result: Result<Option<String>, String>) -> Self {
+        let (status, detail) = match result {
+            Ok(detail) => (CheckStatus::Up, detail),
+            Err(detail) => (CheckStatus::Down, Some(detail)),
+        };
+        CheckResult {
+            status,
+            detail,
+            duration_ms: started_at.elapsed().as_millis() as u64,
+        }
+    }
+
+    fn skipped(detail: &str) -> Self {
+        CheckResult {
+            status: CheckStatus::Skipped,
+            detail: Some(detail.to_string()),
+            duration_ms: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ReadinessStatus {
+    Ready,
+    Degraded,
+}
+
+/// Breakdown of the checks of the readiness probe.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ReadinessReport {
+    pub status: ReadinessStatus,
+    pub startup: CheckResult,
+    pub database: CheckResult,
+    pub migrations: CheckResult,
+    pub smtp: CheckResult,
+    pub scheduler: CheckResult,
+}
+
+/// Checks that the dependencies of the API are available.
+///
+/// The API is degraded until the database is prepared at startup, then when the database is unreachable or
+/// not migrated, the SMTP server is unreachable while its check is enabled, or the scheduler missed a run.
+pub async fn check_readiness(app_state: &AppState) -> ReadinessReport {
+    let startup = check_startup(&app_state.get_startup_status());
+    let (database, migrations) = check_database(app_state).await;
+    let smtp = check_smtp(app_state).await;
+    let schedule = app_state.config.read().scheduler_config.schedule.clone();
+    let scheduler = check_scheduler(app_state.get_scheduler_heartbeat(), &schedule, Utc::now());
+
+    let is_degraded = [&startup, &database, &migrations, &smtp, &scheduler].iter()
+        .any(|check| check.status == CheckStatus::Down);
+
+    ReadinessReport {
+        status: if is_degraded { ReadinessStatus::Degraded } else { ReadinessStatus::Ready },
+        startup,
+        database,
+        migrations,
+        smtp,
+        scheduler,
+    }
+}
+
+async fn check_database(app_state: &AppState) -> (CheckResult, CheckResult) {
+    let started_at = Instant::now();
+    let mut conn = match get_connection_with_timeout(&app_state.database_pool, DATABASE_TIMEOUT).await {
+        Ok(conn) => conn,
+        Err(e) => {
+            let database = CheckResult::new(started_at, Err(e));
+            return (database, CheckResult::skipped("The database is unreachable"));
+        }
+    };
+    let database = CheckResult::new(started_at, diesel::sql_query("SELECT 1")
+        .execute(&mut conn)
+        .await
+        .map(|_| None)
+        .map_err(|e| e.to_string())
+    );
+
+    let started_at = Instant::now();
+    let migrations = CheckResult::new(started_at, pending_migrations(&mut conn).await.and_then(|pending| match pending.is_empty() {
+        true => Ok(None),
+        false => Err(format!("Pending migrations: {}", pending.join(", "))),
+    }));
+
+    (database, migrations)
+}
+
+async fn check_smtp(app_state: &AppState) -> CheckResult {
+    if !app_state.config.read().smtp_config.readiness_check {
+        return CheckResult::skipped("The check of the SMTP server is disabled");
+    }
+
+    let started_at = Instant::now();
+    CheckResult::new(started_at, match app_state.smtp_transport.test_connection().await {
+        Ok(true) => Ok(None),
+        Ok(false) => Err("The SMTP server refused the connection".to_string()),
+        Err(e) => Err(e.to_string()),
+    })
+}
+
+/// Checks that the database was prepared at startup.
+fn check_startup(startup_status: &StartupStatus) -> CheckResult {
+    let started_at = Instant::now();
+
+    CheckResult::new(started_at, match startup_status {
+        StartupStatus::Starting => Err("The database is being prepared".to_string()),
+        StartupStatus::Started => Ok(None),
+        StartupStatus::Failed(e) => Err(format!("The preparation of the database failed: {}", e)),
+    })
+}
+
+/// Checks that the scheduler didn't miss the run following its last one.
+fn check_scheduler(heartbeat: Option<DateTime<Utc>>, schedule: &CronSchedule, now: DateTime<Utc>) -> CheckResult {
+    let started_at = Instant::now();
+
+    CheckResult::new(started_at, match heartbeat {
+        None => Err("The scheduler has not run yet".to_string()),
+        Some(heartbeat) => {
+            let detail = format!("Last run at {}", heartbeat.to_rfc3339());
+            let is_late = schedule.next_after(heartbeat)
+                .and_then(|next_run| (now - next_run).to_std().ok())
+                .is_some_and(|delay| delay > SCHEDULER_GRACE_PERIOD);
+            match is_late {
+                false => Ok(Some(detail)),
+                true => Err(detail),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, NaiveDateTime};
+
+    fn utc(time: &str) -> DateTime<Utc> {
+        NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M").unwrap().and_utc()
+    }
+
+    fn schedule(expression: &str) -> CronSchedule {
+        expression.parse().unwrap()
+    }
+
+    #[test]
+    fn test_check_startup() {
+        assert_eq!(check_startup(&StartupStatus::Starting).status, CheckStatus::Down);
+        assert_eq!(check_startup(&StartupStatus::Started).status, CheckStatus::Up);
+        let failed = check_startup(&StartupStatus::Failed("Error when migrating the database".to_string()));
+        assert_eq!(failed.status, CheckStatus::Down);
+        assert!(failed.detail.unwrap().contains("Error when migrating the database"));
+    }
+
+    #[test]
+    fn test_check_scheduler() {
+        let now = Utc::now();
+        let daily = schedule("@daily");
+
+        assert_eq!(check_scheduler(None, &daily, now).status, CheckStatus::Down);
+        assert_eq!(check_scheduler(Some(now - Duration::hours(1)), &daily, now).status, CheckStatus::Up);
+        assert_eq!(check_scheduler(Some(now - Duration::hours(26)), &daily, now).status, CheckStatus::Down);
+    }
+
+    #[test]
+    fn test_check_scheduler_follows_the_schedule() {
+        let every_quarter = schedule("*/15 * * * *");
+        let heartbeat = Some(utc("2024-11-11 09:00"));
+
+        assert_eq!(check_scheduler(heartbeat, &every_quarter, utc("2024-11-11 10:10")).status, CheckStatus::Up);
+        assert_eq!(check_scheduler(heartbeat, &every_quarter, utc("2024-11-11 10:20")).status, CheckStatus::Down);
+        // The run of the weekend is the next one
+        assert_eq!(check_scheduler(heartbeat, &schedule("0 8 * * 6"), utc("2024-11-15 23:00")).status, CheckStatus::Up);
+    }
+
+    #[test]
+    fn test_cron_schedule() {
+        let next = |expression: &str, after: &str| schedule(expression).next_after(utc(after));
+
+        assert_eq!(next("0 * * * *", "2024-11-11 09:00"), Some(utc("2024-11-11 10:00")));
+        assert_eq!(next("*/15 * * * *", "2024-11-11 09:07"), Some(utc("2024-11-11 09:15")));
+        assert_eq!(next("30 6,18 * * *", "2024-11-11 19:00"), Some(utc("2024-11-12 06:30")));
+        assert_eq!(next("0 0 1 * *", "2024-12-15 00:00"), Some(utc("2025-01-01 00:00")));
+        // Weekdays only, Sunday being both 0 and 7
+        assert_eq!(next("0 8 * * 1-5", "2024-11-15 09:00"), Some(utc("2024-11-18 08:00")));
+        assert_eq!(next("0 8 * * 7", "2024-11-11 09:00"), Some(utc("2024-11-17 08:00")));
+        // Either the day of the month or the day of the week when both are restricted
+        assert_eq!(next("0 0 20 * 1", "2024-11-12 00:00"), Some(utc("2024-11-18 00:00")));
+        assert_eq!(next("0 0 29 2 *", "2025-01-01 00:00"), Some(utc("2028-02-29 00:00")));
+        assert_eq!(next("0 0 30 2 *", "2025-01-01 00:00"), None);
+    }
+
+    #[test]
+    fn test_invalid_cron_schedules_are_refused() {
+        for expression in ["", "* * * *", "60 * * * *", "* 24 * * *", "* * 0 * *", "* * * 13 *", "* * * * 8", "*/0 * * * *", "5-1 * * * *", "a * * * *"] {
+            assert!(expression.parse::<CronSchedule>().is_err(), "{} was accepted", expression);
+        }
+        assert_eq!(schedule("@weekly").next_after(utc("2024-11-11 09:00")), Some(utc("2024-11-17 00:00")));
+    }
+}
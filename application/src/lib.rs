@@ -1,5 +1,14 @@
 pub mod database;
 pub mod mail;
 pub mod authentication;
+pub mod audit;
+pub mod authorization;
 pub mod marks;
-pub mod scheduler;
\ No newline at end of file
+pub mod scheduler;
+pub mod metrics;
+pub mod health;
+pub mod bootstrap;
+pub mod timezone;
+pub mod lifecycle;
+pub mod reminders;
+pub mod extensions;
\ No newline at end of file
@@ -0,0 +1,303 @@
+//! Lifecycle of the projects, from their draft to their archive.
+//!
+//! A project is created as a draft, which the scheduler ignores, or scheduled. The scheduler then moves it as
+//! its dates pass: in progress once it has started, its evaluation open once it has ended and closed at the
+//! end of the notation period, its results being published once the marks of the students are computed.
+//!
+//! The teachers can also open the evaluation before the end of the project, close it before the end of the
+//! notation period or reopen it. The dates of the project are changed along, so they always lead to its
+//! state and the scheduler doesn't undo the change.
+
+use crate::timezone::{get_project_calendar, ProjectCalendar};
+use chrono::{NaiveDate, Utc};
+use domain::models::projects::{Project, ProjectState, UpdatedProject};
+use domain::repositories::projects::ProjectsRepository;
+use domain::repositories::Repositories;
+use shared::error_models::{APIError, BadRequestError, ConflictError, DBError, UserError};
+use uuid::Uuid;
+
+/// Changes of state made by the teachers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProjectAction {
+    /// Schedules a draft, which then follows its dates
+    Schedule,
+    /// Opens the evaluation before the end of the project
+    Open,
+    /// Closes the evaluation before the end of the notation period
+    Close,
+    /// Reopens the evaluation for a number of days, the marks being computed again once it's closed
+    Reopen { days: i32 },
+    Archive,
+}
+
+/// Returns the state the dates of a scheduled project lead to on a day of its timezone.
+pub fn get_dated_state(calendar: &ProjectCalendar, today: NaiveDate) -> ProjectState {
+    if calendar.evaluation_end.date_naive() <= today {
+        ProjectState::EvaluationClosed
+    } else if calendar.end.date_naive() <= today {
+        ProjectState::EvaluationOpen
+    } else if calendar.start.date_naive() <= today {
+        ProjectState::InProgress
+    } else {
+        ProjectState::Scheduled
+    }
+}
+
+/// State following another one when the dates of the project pass.
+fn get_next_dated_state(state: ProjectState) -> Option<ProjectState> {
+    match state {
+        ProjectState::Scheduled => Some(ProjectState::InProgress),
+        ProjectState::InProgress => Some(ProjectState::EvaluationOpen),
+        ProjectState::EvaluationOpen => Some(ProjectState::EvaluationClosed),
+        _ => None,
+    }
+}
+
+/// Moves a project through the states its dates lead to, recording each transition, and returns its new state.
+pub async fn advance_project<R: ProjectsRepository>(repos: &R, project: &Project, calendar: &ProjectCalendar) -> Result<ProjectState, DBError> {
+    let dated_state = get_dated_state(calendar, calendar.today());
+    let mut state = project.state;
+
+    while let Some(next_state) = get_next_dated_state(state).filter(|&next_state| next_state <= dated_state) {
+        if !repos.transition_project(project.id, state, next_state, UpdatedProject::default(), None).await? {
+            // Moved meanwhile, by a teacher or another run of the scheduler
+            return Ok(repos.get_project_by_id(project.id).await?.state);
+        }
+
+        tracing::info!(project_id = %project.id, from = ?state, to = ?next_state, "project state changed");
+        state = next_state;
+    }
+
+    Ok(state)
+}
+
+/// Checks that the dates of a project lead to its state, or to a later one the scheduler will move it to.
+pub fn check_project_dates(state: ProjectState, calendar: &ProjectCalendar) -> Result<(), APIError> {
+    // The dates of a draft are free, the ones of a project whose evaluation is over must stay in the past
+    if state == ProjectState::Draft {
+        return Ok(());
+    }
+    let required_state = state.min(ProjectState::EvaluationClosed);
+
+    let dated_state = get_dated_state(calendar, calendar.today());
+    if dated_state < required_state {
+        return Err(APIError::UserError(UserError::BadRequest(BadRequestError {
+            request: format!("The dates of the project lead to the state {:?}, while it's {:?}", dated_state, state),
+        })));
+    }
+
+    Ok(())
+}
+
+/// Updates a project whose dates agree with its state, moving it forward if they lead further.
+pub async fn check_and_update_project<R: Repositories>(repos: &R, project: &Project, updated_project: UpdatedProject) -> Result<(), APIError> {
+    let mut updated = project.clone();
+    if let Some(start_date) = updated_project.start_date { updated.start_date = start_date; }
+    if let Some(end_date) = updated_project.end_date { updated.end_date = end_date; }
+    if let Some(duration) = updated_project.notation_period_duration { updated.notation_period_duration = duration; }
+    if let Some(timezone) = &updated_project.timezone { updated.timezone = Some(timezone.clone()); }
+
+    let calendar = get_project_calendar(repos, &updated).await?;
+    check_project_dates(project.state, &calendar)?;
+
+    // The state only changes through transitions
+    repos.update_project(project.id, UpdatedProject { state: None, ..updated_project }).await?;
+    if project.state.is_active() {
+        advance_project(repos, &updated, &calendar).await?;
+    }
+
+    Ok(())
+}
+
+/// Applies the change of state of a teacher to a project, and returns its new state.
+pub async fn apply_project_action<R: Repositories>(repos: &R, project: &Project, action: ProjectAction, user_id: Uuid) -> Result<ProjectState, APIError> {
+    let calendar = get_project_calendar(repos, project).await?;
+    // Days of the notation period for the evaluation to end on a given day
+    let days_until = |day: NaiveDate| (day - calendar.end.date_naive()).num_days() as i32;
+
+    let (state, updated_project) = match action {
+        ProjectAction::Schedule => (ProjectState::Scheduled, UpdatedProject::default()),
+        ProjectAction::Open => (ProjectState::EvaluationOpen, UpdatedProject {
+            end_date: Some(Utc::now()),
+            ..Default::default()
+        }),
+        ProjectAction::Close => (ProjectState::EvaluationClosed, UpdatedProject {
+            notation_period_duration: Some(days_until(calendar.today())),
+            ..Default::default()
+        }),
+        ProjectAction::Reopen { days } => (ProjectState::EvaluationOpen, UpdatedProject {
+            notation_period_duration: Some(days_until(calendar.today()) + days),
+            ..Default::default()
+        }),
+        ProjectAction::Archive => (ProjectState::Archived, UpdatedProject::default()),
+    };
+
+    // Opening and reopening the evaluation lead to the same state, from different ones
+    let is_reopening = matches!(project.state, ProjectState::EvaluationClosed | ProjectState::ResultsPublished);
+    let is_allowed = match action {
+        ProjectAction::Open => !is_reopening,
+        ProjectAction::Reopen { .. } => is_reopening,
+        _ => true,
+    };
+    if !is_allowed || !project.state.can_transition_to(state) {
+        return Err(APIError::UserError(UserError::Conflict(ConflictError {
+            reason: format!("A project can't go from the state {:?} to {:?}", project.state, state),
+        })));
+    }
+    if !repos.transition_project(project.id, project.state, state, updated_project, Some(user_id)).await? {
+        return Err(APIError::UserError(UserError::Conflict(ConflictError {
+            reason: "The state of the project changed meanwhile".to_string(),
+        })));
+    }
+    tracing::info!(project_id = %project.id, %user_id, from = ?project.state, to = ?state, "project state changed");
+
+    // A scheduled project may already be due to start
+    if state == ProjectState::Scheduled {
+        let project = repos.get_project_by_id(project.id).await?;
+        return Ok(advance_project(repos, &project, &calendar).await?);
+    }
+
+    Ok(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timezone::Tz;
+    use chrono::{DateTime, Duration};
+    use infrastructure::repositories::{InMemoryData, InMemoryRepositories};
+
+    fn project(state: ProjectState, end_date: DateTime<Utc>, notation_period_duration: i32) -> Project {
+        Project {
+            id: Uuid::new_v4(),
+            name: "Project".to_string(),
+            description: None,
+            start_date: end_date - Duration::days(7),
+            end_date,
+            notation_period_duration,
+            promotion_id: Uuid::new_v4(),
+            state,
+            timezone: Some("UTC".to_string()),
+        }
+    }
+
+    fn repositories(project: &Project) -> InMemoryRepositories {
+        InMemoryRepositories::new(InMemoryData {
+            projects: vec![project.clone()],
+            ..Default::default()
+        })
+    }
+
+    fn transitions(repos: &InMemoryRepositories) -> Vec<(Option<ProjectState>, ProjectState)> {
+        repos.data().project_state_transitions.iter()
+            .map(|transition| (transition.from_state, transition.to_state))
+            .collect()
+    }
+
+    #[test]
+    fn test_transitions_follow_the_lifecycle() {
+        use ProjectState::*;
+
+        assert!(Draft.can_transition_to(Scheduled));
+        assert!(EvaluationOpen.can_transition_to(EvaluationClosed));
+        assert!(EvaluationClosed.can_transition_to(EvaluationOpen));
+        assert!(ResultsPublished.can_transition_to(Archived));
+        assert!(!Draft.can_transition_to(InProgress));
+        assert!(!Scheduled.can_transition_to(EvaluationOpen));
+        assert!(!InProgress.can_transition_to(EvaluationClosed));
+        assert!(!Archived.can_transition_to(EvaluationOpen));
+    }
+
+    #[tokio::test]
+    async fn test_advance_project_records_every_transition_its_dates_lead_to() {
+        // The evaluation ended today
+        let project = project(ProjectState::Scheduled, Utc::now() - Duration::days(3), 3);
+        let repos = repositories(&project);
+        let calendar = ProjectCalendar::new(&project, Tz::UTC);
+
+        let state = advance_project(&repos, &project, &calendar).await.unwrap();
+
+        assert_eq!(state, ProjectState::EvaluationClosed);
+        assert_eq!(transitions(&repos), vec![
+            (Some(ProjectState::Scheduled), ProjectState::InProgress),
+            (Some(ProjectState::InProgress), ProjectState::EvaluationOpen),
+            (Some(ProjectState::EvaluationOpen), ProjectState::EvaluationClosed),
+        ]);
+        assert!(repos.data().project_state_transitions.iter().all(|transition| transition.user_id.is_none()));
+    }
+
+    #[tokio::test]
+    async fn test_advance_project_leaves_drafts_and_future_projects() {
+        let draft = project(ProjectState::Draft, Utc::now() - Duration::days(1), 3);
+        let future = project(ProjectState::InProgress, Utc::now() + Duration::days(2), 3);
+
+        for project in [draft, future] {
+            let repos = repositories(&project);
+            let calendar = ProjectCalendar::new(&project, Tz::UTC);
+
+            assert_eq!(advance_project(&repos, &project, &calendar).await.unwrap(), project.state);
+            assert!(transitions(&repos).is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_closed_evaluation_reopens_for_the_given_days() {
+        let project = project(ProjectState::EvaluationClosed, Utc::now() - Duration::days(5), 3);
+        let repos = repositories(&project);
+        let teacher_id = Uuid::new_v4();
+
+        let state = apply_project_action(&repos, &project, ProjectAction::Reopen { days: 2 }, teacher_id).await.unwrap();
+
+        assert_eq!(state, ProjectState::EvaluationOpen);
+        let reopened = repos.get_project_by_id(project.id).await.unwrap();
+        assert_eq!(reopened.notation_period_duration, 7);
+        // The scheduler keeps it open
+        let calendar = ProjectCalendar::new(&reopened, Tz::UTC);
+        assert_eq!(advance_project(&repos, &reopened, &calendar).await.unwrap(), ProjectState::EvaluationOpen);
+        assert_eq!(repos.data().project_state_transitions[0].user_id, Some(teacher_id));
+    }
+
+    #[tokio::test]
+    async fn test_closing_the_evaluation_ends_the_notation_period_today() {
+        let project = project(ProjectState::EvaluationOpen, Utc::now() - Duration::days(1), 5);
+        let repos = repositories(&project);
+
+        let state = apply_project_action(&repos, &project, ProjectAction::Close, Uuid::new_v4()).await.unwrap();
+
+        assert_eq!(state, ProjectState::EvaluationClosed);
+        let closed = repos.get_project_by_id(project.id).await.unwrap();
+        let calendar = ProjectCalendar::new(&closed, Tz::UTC);
+        assert_eq!(get_dated_state(&calendar, calendar.today()), ProjectState::EvaluationClosed);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_actions_are_refused() {
+        let project = project(ProjectState::InProgress, Utc::now() + Duration::days(2), 3);
+        let repos = repositories(&project);
+
+        for action in [ProjectAction::Schedule, ProjectAction::Close, ProjectAction::Reopen { days: 1 }, ProjectAction::Archive] {
+            let result = apply_project_action(&repos, &project, action, Uuid::new_v4()).await;
+            assert!(matches!(result, Err(APIError::UserError(UserError::Conflict(_)))), "{:?}", action);
+        }
+        assert_eq!(repos.get_project_by_id(project.id).await.unwrap().state, ProjectState::InProgress);
+        assert!(transitions(&repos).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dates_contradicting_the_state_are_refused() {
+        let project = project(ProjectState::EvaluationOpen, Utc::now() - Duration::days(1), 3);
+        let repos = repositories(&project);
+
+        // The project would not be over yet
+        let postponed = UpdatedProject { end_date: Some(Utc::now() + Duration::days(2)), ..Default::default() };
+        let result = check_and_update_project(&repos, &project, postponed).await;
+        assert!(matches!(result, Err(APIError::UserError(UserError::BadRequest(_)))));
+
+        // The notation period can be extended
+        let extended = UpdatedProject { notation_period_duration: Some(6), ..Default::default() };
+        check_and_update_project(&repos, &project, extended).await.unwrap();
+        let updated = repos.get_project_by_id(project.id).await.unwrap();
+        assert_eq!(updated.notation_period_duration, 6);
+        assert_eq!(updated.state, ProjectState::EvaluationOpen);
+    }
+}
@@ -1,12 +1,13 @@
 use lettre::transport::smtp::authentication::Credentials;
-use lettre::SmtpTransport;
+use lettre::{AsyncSmtpTransport, Tokio1Executor};
 
 use shared::app_config::Config;
+use shared::app_state_model::MailTransport;
 use shared::error_models::{APIError, InternalError, ServerError};
 
-pub fn init_smtp_client(config: &Config) -> SmtpTransport {
+pub fn init_smtp_client(config: &Config) -> MailTransport {
     let creds = Credentials::new(config.smtp_config.username.clone(), config.smtp_config.password.clone());
-    SmtpTransport::relay(&config.smtp_config.host)
+    AsyncSmtpTransport::<Tokio1Executor>::relay(&config.smtp_config.host)
         .map_err(|_| APIError::ServerError(ServerError::InternalError(InternalError)))
         .unwrap().credentials(creds)
         .port(config.smtp_config.port)
@@ -1,10 +1,53 @@
 use lettre::message::header::ContentType;
-use lettre::{Message, SmtpTransport, Transport};
+use lettre::{AsyncTransport, Message};
 use serde::Deserialize;
+use tracing::Instrument;
+use uuid::Uuid;
+
+use crate::database::organizations::get_organization_by_id;
+use crate::metrics::observe_email;
+use infrastructure::DBPool;
+use shared::app_state_model::MailTransport;
 
 use shared::error_models::InternalError;
 use shared::error_models::{APIError, ServerError};
 
+pub const DEFAULT_MAIL_SENDER: &str = "Bitbox <no-reply@sigma-bot.fr>";
+
+/// Purpose of an email, used to count the emails sent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MailKind {
+    MfaCode,
+    PasswordReset,
+    EmailVerification,
+    EvaluationStarted,
+    EvaluationReminder,
+    MarksStarted,
+    MarksReminder,
+    /// Students who still haven't evaluated at the last reminder, sent to the teachers
+    EvaluationEscalation,
+    /// New deadline of a student granted an extension, with their renewed link
+    EvaluationExtended,
+    NotationEnded,
+}
+
+impl MailKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MailKind::MfaCode => "mfa_code",
+            MailKind::PasswordReset => "password_reset",
+            MailKind::EmailVerification => "email_verification",
+            MailKind::EvaluationStarted => "evaluation_started",
+            MailKind::EvaluationReminder => "evaluation_reminder",
+            MailKind::MarksStarted => "marks_started",
+            MailKind::MarksReminder => "marks_reminder",
+            MailKind::EvaluationEscalation => "evaluation_escalation",
+            MailKind::EvaluationExtended => "evaluation_extended",
+            MailKind::NotationEnded => "notation_ended",
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct MailProps {
     pub from: String,
@@ -13,11 +56,23 @@ pub struct MailProps {
     pub body: String,
 }
 
-pub fn send_mail(smtp_transport: &SmtpTransport, message: Message) -> Result<(), APIError> {
-    smtp_transport.send(&message)
-        .map_err(|_| APIError::ServerError(ServerError::InternalError(InternalError)))?;
+/// Sends an email, traced with its subject and number of recipients but never its body.
+pub async fn send_mail(smtp_transport: &MailTransport, message: Message, kind: MailKind) -> Result<(), APIError> {
+    let subject = message.headers().get_raw("Subject").unwrap_or_default().to_string();
+    let span = tracing::info_span!("send_mail", kind = kind.as_str(), %subject, recipients = message.envelope().to().len());
 
-    Ok(())
+    match smtp_transport.send(message).instrument(span.clone()).await {
+        Ok(_) => {
+            tracing::info!(parent: &span, "email sent");
+            observe_email(kind.as_str(), true);
+            Ok(())
+        }
+        Err(e) => {
+            tracing::error!(parent: &span, error = %e, "failed to send the email");
+            observe_email(kind.as_str(), false);
+            Err(APIError::ServerError(ServerError::InternalError(InternalError)))
+        }
+    }
 }
 
 pub fn build_mail(mail_props: MailProps) -> Message {
@@ -28,4 +83,11 @@ pub fn build_mail(mail_props: MailProps) -> Message {
         .header(ContentType::TEXT_PLAIN)
         .body(String::from(mail_props.body))
         .unwrap()
-}
\ No newline at end of file
+}
+/// Returns the sender configured by the organization, or the default one if it has none.
+pub async fn get_organization_mail_sender(conn: &DBPool, organization_id: Uuid) -> String {
+    get_organization_by_id(conn, organization_id)
+        .await.ok()
+        .and_then(|organization| organization.mail_sender)
+        .unwrap_or(DEFAULT_MAIL_SENDER.to_string())
+}
@@ -1,45 +1,66 @@
-use crate::database::groups::{get_group_student_mark_details, get_groups_from_project_id, get_students_from_groups};
+use std::collections::{HashMap, HashSet};
+
+use domain::repositories::groups::GroupsRepository;
+use domain::repositories::marks::MarksRepository;
+use domain::repositories::projects::ProjectsRepository;
+use domain::repositories::tokens::TokensRepository;
 use indexmap::IndexMap;
-use infrastructure::DBPool;
 use shared::error_models::DBError;
 use uuid::Uuid;
 
-pub fn calculate_students_marks_from_project(conn: &DBPool, project_id_: Uuid) -> Result<IndexMap<(Uuid, Uuid), Option<f64>>, DBError> {
+use crate::extensions::apply_late_penalty;
+
+/// Computes the marks of the students of a project from the mark of their group and the ones they got from its
+/// members, the students who evaluated their group late losing the penalty of the project.
+pub async fn calculate_students_marks_from_project<R>(repos: &R, project_id_: Uuid) -> Result<IndexMap<(Uuid, Uuid), Option<f64>>, DBError>
+where
+    R: GroupsRepository + MarksRepository + ProjectsRepository + TokensRepository,
+{
     let mut students_marks: IndexMap<(Uuid, Uuid), Option<f64>> = IndexMap::new();
 
-    // Get all students from project that are in a group
-    let groups = get_groups_from_project_id(conn, project_id_)?;
+    // Load the whole project at once, whatever its number of groups and students
+    let groups = repos.get_groups_from_project_id(project_id_).await?;
+    let groups_students = repos.get_groups_students_from_project_id(project_id_).await?;
+    let marks = repos.get_marks_from_project_id(project_id_).await?;
+    let late_policy = repos.get_project_late_policy(project_id_).await?;
+    let late_students: HashSet<Uuid> = repos.get_student_tokens_from_project_id(project_id_).await?.into_iter()
+        .filter(|token| token.submitted_late)
+        .map(|token| token.student_id)
+        .collect();
+
+    let mut received_marks: HashMap<(Uuid, Uuid), Vec<f64>> = HashMap::new();
+    for mark in marks {
+        received_marks.entry((mark.group_id, mark.noted_student_id)).or_default().push(mark.mark);
+    }
+
     for group in groups {
-        let group_avg = calculate_group_average(conn, group.id);
-        let students = get_students_from_groups(conn, vec![group.id])?;
-        for student in students {
-            let details = get_group_student_mark_details(conn, group.id, student.id)?;
-            let marks: Vec<f64> = details.marks.iter().filter_map(|detail| detail.mark).collect();
-            let average = calculate_average(&marks);
+        let students_avg: Vec<(Uuid, f64)> = groups_students.iter()
+            .filter(|(group_student, _)| group_student.group_id == group.id)
+            .map(|(group_student, _)| {
+                let marks = received_marks.get(&(group.id, group_student.student_id)).map(Vec::as_slice).unwrap_or_default();
+                (group_student.student_id, calculate_average(marks))
+            })
+            .collect();
+
+        let averages: Vec<f64> = students_avg.iter().map(|(_, average)| *average).collect();
+        let group_avg = calculate_average(&averages);
+        for (student_id, average) in students_avg {
             let delta = group_avg - average;
             let mark = match group.mark {
                 Some(mark) => if delta > 0.0 { Some(mark - delta) } else { Some(mark) },
                 None => None,
             };
-            students_marks.insert((group.id, student.id), mark);
+            let mark = match &late_policy {
+                Some(policy) if late_students.contains(&student_id) => mark.map(|mark| apply_late_penalty(mark, policy)),
+                _ => mark,
+            };
+            students_marks.insert((group.id, student_id), mark);
         }
     }
 
     Ok(students_marks)
 }
 
-fn calculate_group_average(conn: &DBPool, group_id: Uuid) -> f64 {
-    let students = get_students_from_groups(conn, vec![group_id]).unwrap();
-    let mut students_avg: Vec<f64> = Vec::new();
-    for student in students {
-        let details = get_group_student_mark_details(conn, group_id, student.id).unwrap();
-        let marks: Vec<f64> = details.marks.iter().filter_map(|detail| detail.mark).collect();
-        students_avg.push(calculate_average(&marks));
-    }
-
-    calculate_average(&students_avg)
-}
-
 fn calculate_average(numbers: &[f64]) -> f64 {
     if numbers.is_empty() { return 0.0; }
 
@@ -47,4 +68,169 @@ fn calculate_average(numbers: &[f64]) -> f64 {
     let count = numbers.len() as f64;
 
     sum / count
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use domain::models::extensions::ProjectLatePolicy;
+    use domain::models::groups::{Group, GroupStudent};
+    use domain::models::marks::Mark;
+    use domain::models::students::Student;
+    use domain::models::students_tokens::StudentToken;
+    use infrastructure::repositories::{InMemoryData, InMemoryRepositories};
+
+    fn student(organization_id: Uuid) -> Student {
+        Student {
+            id: Uuid::new_v4(),
+            name: "Student".to_string(),
+            surname: "Test".to_string(),
+            email: "student@example.com".to_string(),
+            organization_id,
+        }
+    }
+
+    fn mark(group: &Group, noted: &Student, grader: &Student, value: f64) -> Mark {
+        Mark {
+            project_id: group.project_id,
+            group_id: group.id,
+            noted_student_id: noted.id,
+            grader_student_id: grader.id,
+            mark: value,
+            max_mark: 20,
+            comment: None,
+        }
+    }
+
+    fn group_with_students(project_id: Uuid, mark: Option<f64>, students: &[&Student]) -> (Group, Vec<GroupStudent>) {
+        let group = Group {
+            id: Uuid::new_v4(),
+            name: "Group".to_string(),
+            mark,
+            max_mark: 20,
+            project_id,
+        };
+        let members = students.iter().map(|student| GroupStudent {
+            group_id: group.id,
+            student_id: student.id,
+            student_mark: None,
+            max_mark: 20,
+        }).collect();
+
+        (group, members)
+    }
+
+    #[test]
+    fn test_calculate_average() {
+        assert_eq!(calculate_average(&[]), 0.0);
+        assert_eq!(calculate_average(&[10.0, 14.0, 18.0]), 14.0);
+    }
+
+    #[tokio::test]
+    async fn test_students_below_the_group_average_lose_the_difference() {
+        let organization_id = Uuid::new_v4();
+        let project_id = Uuid::new_v4();
+        let (alice, bob, carol) = (student(organization_id), student(organization_id), student(organization_id));
+        let (group, members) = group_with_students(project_id, Some(15.0), &[&alice, &bob, &carol]);
+
+        let repos = InMemoryRepositories::new(InMemoryData {
+            marks: vec![
+                mark(&group, &alice, &bob, 16.0),
+                mark(&group, &alice, &carol, 18.0),
+                mark(&group, &bob, &alice, 12.0),
+                mark(&group, &bob, &carol, 12.0),
+                mark(&group, &carol, &alice, 14.0),
+                mark(&group, &carol, &bob, 16.0),
+            ],
+            students: vec![alice.clone(), bob.clone(), carol.clone()],
+            groups: vec![group.clone()],
+            groups_students: members,
+            ..Default::default()
+        });
+
+        let marks = calculate_students_marks_from_project(&repos, project_id).await.unwrap();
+
+        // Averages are 17, 12 and 15, for a group average of 14.67
+        assert_eq!(marks.len(), 3);
+        assert_eq!(marks[&(group.id, alice.id)], Some(15.0));
+        assert_eq!(marks[&(group.id, carol.id)], Some(15.0));
+        let bob_mark = marks[&(group.id, bob.id)].unwrap();
+        assert!((bob_mark - (15.0 - (44.0 / 3.0 - 12.0))).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_groups_without_mark_give_no_mark() {
+        let organization_id = Uuid::new_v4();
+        let project_id = Uuid::new_v4();
+        let (alice, bob) = (student(organization_id), student(organization_id));
+        let (group, members) = group_with_students(project_id, None, &[&alice, &bob]);
+
+        let repos = InMemoryRepositories::new(InMemoryData {
+            marks: vec![mark(&group, &alice, &bob, 10.0)],
+            students: vec![alice.clone(), bob.clone()],
+            groups: vec![group.clone()],
+            groups_students: members,
+            ..Default::default()
+        });
+
+        let marks = calculate_students_marks_from_project(&repos, project_id).await.unwrap();
+
+        assert_eq!(marks.len(), 2);
+        assert!(marks.values().all(Option::is_none));
+    }
+
+    #[tokio::test]
+    async fn test_late_students_lose_the_penalty_of_the_project() {
+        let organization_id = Uuid::new_v4();
+        let project_id = Uuid::new_v4();
+        let (alice, bob) = (student(organization_id), student(organization_id));
+        let (group, members) = group_with_students(project_id, Some(16.0), &[&alice, &bob]);
+        let token = |student: &Student, submitted_late: bool| StudentToken {
+            id: Uuid::new_v4(),
+            token: "token".to_string(),
+            student_id: student.id,
+            project_id,
+            used: true,
+            submitted_late,
+        };
+
+        let repos = InMemoryRepositories::new(InMemoryData {
+            marks: vec![mark(&group, &alice, &bob, 14.0), mark(&group, &bob, &alice, 14.0)],
+            students: vec![alice.clone(), bob.clone()],
+            groups: vec![group.clone()],
+            groups_students: members,
+            students_tokens: vec![token(&alice, false), token(&bob, true)],
+            project_late_policies: vec![ProjectLatePolicy {
+                project_id,
+                late_hours: 24,
+                penalty_percent: 25.0,
+                updated_at: Utc::now(),
+            }],
+            ..Default::default()
+        });
+
+        let marks = calculate_students_marks_from_project(&repos, project_id).await.unwrap();
+
+        assert_eq!(marks[&(group.id, alice.id)], Some(16.0));
+        assert_eq!(marks[&(group.id, bob.id)], Some(12.0));
+    }
+
+    #[tokio::test]
+    async fn test_other_projects_are_ignored() {
+        let organization_id = Uuid::new_v4();
+        let alice = student(organization_id);
+        let (group, members) = group_with_students(Uuid::new_v4(), Some(12.0), &[&alice]);
+
+        let repos = InMemoryRepositories::new(InMemoryData {
+            students: vec![alice],
+            groups: vec![group],
+            groups_students: members,
+            ..Default::default()
+        });
+
+        let marks = calculate_students_marks_from_project(&repos, Uuid::new_v4()).await.unwrap();
+
+        assert!(marks.is_empty());
+    }
+}
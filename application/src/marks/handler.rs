@@ -1,20 +1,21 @@
-use crate::database::groups::update_group_student;
 use crate::marks::calculation::calculate_students_marks_from_project;
-use domain::models::groups::UpdatedGroupStudent;
-use infrastructure::DBPool;
+use domain::repositories::groups::GroupsRepository;
+use domain::repositories::marks::MarksRepository;
+use domain::repositories::projects::ProjectsRepository;
+use domain::repositories::tokens::TokensRepository;
 use shared::error_models::DBError;
 use uuid::Uuid;
 
-pub fn handle_project_rating(conn: &DBPool, project_id_: Uuid) -> Result<(), DBError> {
-    let students_marks = calculate_students_marks_from_project(conn, project_id_)?;
+pub async fn handle_project_rating<R>(repos: &R, project_id_: Uuid) -> Result<(), DBError>
+where
+    R: GroupsRepository + MarksRepository + ProjectsRepository + TokensRepository,
+{
+    let students_marks = calculate_students_marks_from_project(repos, project_id_).await?;
 
-    for ((group_id, student_id), mark) in students_marks {
-        let updated_group_student = UpdatedGroupStudent {
-            student_mark: mark,
-        };
+    // The members of groups without a mark keep theirs
+    let marks = students_marks.into_iter()
+        .filter_map(|((group_id, student_id), mark)| mark.map(|mark| (group_id, student_id, mark)))
+        .collect();
 
-        update_group_student(conn, group_id, student_id, updated_group_student)?;
-    }
-
-    Ok(())
-}
\ No newline at end of file
+    repos.update_groups_students_marks(marks).await
+}
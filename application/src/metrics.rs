@@ -0,0 +1,166 @@
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use shared::app_state_model::AppState;
+
+use crate::database::students_tokens::count_active_student_tokens;
+
+/// Media type of the metrics, in the Prometheus text format
+pub const METRICS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Label of the requests which didn't match any route, to keep the number of series bounded
+pub const UNMATCHED_ROUTE: &str = "unmatched";
+
+static METRICS: Lazy<Metrics> = Lazy::new(Metrics::new);
+
+/// Metrics of the API, exposed to Prometheus.
+///
+/// Counters and histograms are updated as events happen, while gauges are read when the metrics
+/// are scraped.
+struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    db_pool_connections: IntGaugeVec,
+    db_pool_max_connections: IntGauge,
+    scheduler_runs: IntCounterVec,
+    scheduler_run_duration: Histogram,
+    emails_sent: IntCounterVec,
+    emails_failed: IntCounterVec,
+    pending_reminders: IntGauge,
+    active_evaluation_tokens: IntGauge,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("bitbox".to_string()), None).unwrap();
+
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "Number of HTTP requests handled"),
+            &["method", "route", "status"],
+        ).unwrap();
+        let http_request_duration = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "Time spent handling the HTTP requests"),
+            &["method", "route", "status"],
+        ).unwrap();
+        let db_pool_connections = IntGaugeVec::new(
+            Opts::new("db_pool_connections", "Number of connections of the database pool"),
+            &["state"],
+        ).unwrap();
+        let db_pool_max_connections = IntGauge::new(
+            "db_pool_max_connections", "Maximum number of connections of the database pool",
+        ).unwrap();
+        let scheduler_runs = IntCounterVec::new(
+            Opts::new("scheduler_runs_total", "Number of runs of the scheduler"),
+            &["result"],
+        ).unwrap();
+        let scheduler_run_duration = Histogram::with_opts(
+            HistogramOpts::new("scheduler_run_duration_seconds", "Time spent by the runs of the scheduler")
+                .buckets(vec![0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 60.0, 300.0]),
+        ).unwrap();
+        let emails_sent = IntCounterVec::new(
+            Opts::new("emails_sent_total", "Number of emails sent"),
+            &["kind"],
+        ).unwrap();
+        let emails_failed = IntCounterVec::new(
+            Opts::new("emails_failed_total", "Number of emails which couldn't be sent"),
+            &["kind"],
+        ).unwrap();
+        let pending_reminders = IntGauge::new(
+            "scheduler_pending_reminders", "Number of reminders waiting to be sent",
+        ).unwrap();
+        let active_evaluation_tokens = IntGauge::new(
+            "active_evaluation_tokens", "Number of evaluation tokens students can still use",
+        ).unwrap();
+
+        registry.register(Box::new(http_requests.clone())).unwrap();
+        registry.register(Box::new(http_request_duration.clone())).unwrap();
+        registry.register(Box::new(db_pool_connections.clone())).unwrap();
+        registry.register(Box::new(db_pool_max_connections.clone())).unwrap();
+        registry.register(Box::new(scheduler_runs.clone())).unwrap();
+        registry.register(Box::new(scheduler_run_duration.clone())).unwrap();
+        registry.register(Box::new(emails_sent.clone())).unwrap();
+        registry.register(Box::new(emails_failed.clone())).unwrap();
+        registry.register(Box::new(pending_reminders.clone())).unwrap();
+        registry.register(Box::new(active_evaluation_tokens.clone())).unwrap();
+
+        Metrics {
+            registry,
+            http_requests,
+            http_request_duration,
+            db_pool_connections,
+            db_pool_max_connections,
+            scheduler_runs,
+            scheduler_run_duration,
+            emails_sent,
+            emails_failed,
+            pending_reminders,
+            active_evaluation_tokens,
+        }
+    }
+}
+
+/// Records a handled HTTP request, identified by its route pattern rather than its path.
+pub fn observe_http_request(method: &str, route: &str, status: u16, duration: Duration) {
+    let status = status.to_string();
+    let labels = [method, route, status.as_str()];
+    METRICS.http_requests.with_label_values(&labels).inc();
+    METRICS.http_request_duration.with_label_values(&labels).observe(duration.as_secs_f64());
+}
+
+/// Records a run of the scheduler.
+pub fn observe_scheduler_run(succeeded: bool, duration: Duration) {
+    let result = if succeeded { "success" } else { "failure" };
+    METRICS.scheduler_runs.with_label_values(&[result]).inc();
+    METRICS.scheduler_run_duration.observe(duration.as_secs_f64());
+}
+
+/// Records an email sent, or which couldn't be sent.
+pub fn observe_email(kind: &str, sent: bool) {
+    match sent {
+        true => METRICS.emails_sent.with_label_values(&[kind]).inc(),
+        false => METRICS.emails_failed.with_label_values(&[kind]).inc(),
+    }
+}
+
+/// Updates the gauges and returns all the metrics in the Prometheus text format.
+pub async fn render_metrics(app_state: &AppState) -> String {
+    let pool_status = app_state.database_pool.status();
+    METRICS.db_pool_connections.with_label_values(&["idle"]).set(pool_status.available as i64);
+    METRICS.db_pool_connections.with_label_values(&["active"]).set((pool_status.size - pool_status.available) as i64);
+    METRICS.db_pool_connections.with_label_values(&["waiting"]).set(pool_status.waiting as i64);
+    METRICS.db_pool_max_connections.set(pool_status.max_size as i64);
+    METRICS.pending_reminders.set(app_state.count_pending_reminders() as i64);
+    match count_active_student_tokens(&app_state.database_pool).await {
+        Ok(count) => METRICS.active_evaluation_tokens.set(count),
+        Err(e) => tracing::error!(error = %e, "failed to count the active evaluation tokens"),
+    }
+
+    let mut buffer = Vec::new();
+    if let Err(e) = TextEncoder::new().encode(&METRICS.registry.gather(), &mut buffer) {
+        tracing::error!(error = %e, "failed to encode the metrics");
+    }
+    String::from_utf8(buffer).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_observed_events_are_exposed() {
+        let kind = format!("test_{}", uuid::Uuid::new_v4().simple());
+        observe_email(&kind, true);
+        observe_email(&kind, false);
+        observe_email(&kind, false);
+
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&METRICS.registry.gather(), &mut buffer).unwrap();
+        let metrics = String::from_utf8(buffer).unwrap();
+        assert!(metrics.contains(&format!("bitbox_emails_sent_total{{kind=\"{}\"}} 1", kind)));
+        assert!(metrics.contains(&format!("bitbox_emails_failed_total{{kind=\"{}\"}} 2", kind)));
+    }
+}
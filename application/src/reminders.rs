@@ -0,0 +1,255 @@
+//! Reminders sent while the evaluation of a project is open.
+//!
+//! A project follows the alerts of the configuration of its teacher, sent to the students and to the
+//! teachers, unless it has its own reminder policy. The policy chooses the offsets of the reminders from
+//! the opening or the closing of the evaluation, who receives them, and whether the teachers are told which
+//! students still haven't evaluated their group at the last reminder.
+
+use chrono::{DateTime, Duration, Timelike, Utc};
+use domain::models::config::UserConfig;
+use domain::models::done_alerts::{AlertType, DoneAlert};
+use domain::models::projects::Project;
+use domain::models::reminder_policies::{NewProjectReminderPolicy, ProjectReminderPolicy, ReminderAnchor, ReminderOffset, ReminderRecipients};
+use domain::repositories::Repositories;
+use serde::Serialize;
+use shared::error_models::DBError;
+use utoipa::ToSchema;
+
+use crate::timezone::{get_project_calendar, ProjectCalendar};
+
+/// Where the reminder policy of a project comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+pub enum ReminderPolicySource {
+    /// The project has its own policy
+    Project,
+    /// The project follows the alerts of the configuration of its teacher
+    Teacher,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ReminderPolicy {
+    pub source: ReminderPolicySource,
+    pub offsets: Vec<ReminderOffset>,
+    pub recipients: ReminderRecipients,
+    pub escalate_to_teacher: bool,
+    /// When the project's own policy was saved, the reminders due before are not sent
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl ReminderPolicy {
+    /// Policy of the projects without their own, whose reminders are sent to everyone without escalation.
+    pub fn from_teacher_config(teacher_config: &UserConfig) -> Self {
+        ReminderPolicy {
+            source: ReminderPolicySource::Teacher,
+            offsets: teacher_config.alerts.iter().flatten().map(ReminderOffset::from).collect(),
+            recipients: ReminderRecipients::Both,
+            escalate_to_teacher: false,
+            updated_at: None,
+        }
+    }
+
+    /// Returns when the reminders are due, the earliest first.
+    ///
+    /// The offsets leading to the same time give a single reminder, and the last reminder escalates to the
+    /// teachers if the policy says so.
+    pub fn plan(&self, calendar: &ProjectCalendar) -> Vec<PlannedReminder> {
+        let mut reminders: Vec<PlannedReminder> = self.offsets.iter()
+            .map(|offset| {
+                let hours = Duration::hours(offset.hours as i64);
+                let send_at = match offset.anchor {
+                    ReminderAnchor::EvaluationOpen => calendar.end + hours,
+                    ReminderAnchor::EvaluationClose => calendar.evaluation_end - hours,
+                };
+                PlannedReminder {
+                    offset: *offset,
+                    send_at: send_at.with_timezone(&Utc),
+                    recipients: self.recipients,
+                    escalates: false,
+                }
+            })
+            .collect();
+        reminders.sort_by_key(|reminder| reminder.send_at);
+        reminders.dedup_by_key(|reminder| reminder.send_at);
+
+        if let Some(last) = reminders.last_mut() {
+            last.escalates = self.escalate_to_teacher;
+        }
+        reminders
+    }
+}
+
+impl From<ProjectReminderPolicy> for ReminderPolicy {
+    fn from(policy: ProjectReminderPolicy) -> Self {
+        ReminderPolicy {
+            source: ReminderPolicySource::Project,
+            offsets: policy.offsets,
+            recipients: policy.recipients,
+            escalate_to_teacher: policy.escalate_to_teacher,
+            updated_at: Some(policy.updated_at),
+        }
+    }
+}
+
+/// Reminder of a project, due at a given time.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PlannedReminder {
+    /// Offset the reminder comes from
+    pub offset: ReminderOffset,
+    pub send_at: DateTime<Utc>,
+    pub recipients: ReminderRecipients,
+    /// Whether the teachers are told which students still haven't evaluated
+    pub escalates: bool,
+}
+
+/// Returns the reminder policy of a project, its own or else the one of its teacher.
+pub async fn get_reminder_policy<R: Repositories>(repos: &R, project: &Project) -> Result<ReminderPolicy, DBError> {
+    if let Some(policy) = repos.get_project_reminder_policy(project.id).await? {
+        return Ok(policy.into());
+    }
+
+    let promotion = repos.get_promotion_by_id(project.promotion_id).await?;
+    let teacher_config = repos.get_config_by_user_id(promotion.teacher_id).await?;
+    Ok(ReminderPolicy::from_teacher_config(&teacher_config))
+}
+
+/// Returns whether a reminder was already sent, the sent ones being recorded with the time they were due.
+///
+/// The reminders recorded before their due time was kept are recognized by the hour they were sent.
+pub fn is_reminder_sent(done_alerts: &[DoneAlert], send_at: DateTime<Utc>) -> bool {
+    done_alerts.iter().any(|alert| match alert.planned_at {
+        Some(planned_at) => planned_at == send_at.naive_utc(),
+        None => alert.published_at.date() == send_at.date_naive() && alert.published_at.hour() == send_at.hour(),
+    })
+}
+
+/// Returns the reminders of a project which have not been sent yet.
+pub async fn get_pending_reminders<R: Repositories>(repos: &R, project: &Project, calendar: &ProjectCalendar) -> Result<Vec<PlannedReminder>, DBError> {
+    let policy = get_reminder_policy(repos, project).await?;
+    let done_alerts = repos.get_done_alerts_by_project_id_and_type(project.id, AlertType::Pending).await?;
+
+    // The offsets already due when the policy was saved aren't sent at once
+    Ok(policy.plan(calendar).into_iter()
+        .filter(|reminder| policy.updated_at.is_none_or(|updated_at| reminder.send_at >= updated_at))
+        .filter(|reminder| !is_reminder_sent(&done_alerts, reminder.send_at))
+        .collect())
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PreviewedReminder {
+    #[serde(flatten)]
+    pub reminder: PlannedReminder,
+    pub sent: bool,
+}
+
+/// Reminders of a project computed from its dates and its policy, as shown to its teachers.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ReminderSchedule {
+    pub policy: ReminderPolicy,
+    pub reminders: Vec<PreviewedReminder>,
+}
+
+/// Computes the reminders of a project, whether they are sent or not.
+pub async fn preview_project_reminders<R: Repositories>(repos: &R, project: &Project) -> Result<ReminderSchedule, DBError> {
+    let calendar = get_project_calendar(repos, project).await?;
+    let policy = get_reminder_policy(repos, project).await?;
+    let done_alerts = repos.get_done_alerts_by_project_id_and_type(project.id, AlertType::Pending).await?;
+
+    let reminders = policy.plan(&calendar).into_iter()
+        .map(|reminder| PreviewedReminder {
+            sent: is_reminder_sent(&done_alerts, reminder.send_at),
+            reminder,
+        })
+        .collect();
+
+    Ok(ReminderSchedule {
+        policy,
+        reminders,
+    })
+}
+
+/// Replaces the reminder policy of a project, or removes it so the project follows its teacher's alerts
+/// again, then returns its new reminders.
+pub async fn update_reminder_policy<R: Repositories>(repos: &R, project: &Project, new_policy: Option<NewProjectReminderPolicy>) -> Result<ReminderSchedule, DBError> {
+    match new_policy {
+        Some(new_policy) => {
+            repos.set_project_reminder_policy(new_policy).await?;
+        }
+        None => repos.delete_project_reminder_policy(project.id).await?,
+    }
+
+    preview_project_reminders(repos, project).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timezone::Tz;
+    use domain::models::config::Alert;
+    use domain::models::projects::ProjectState;
+    use uuid::Uuid;
+
+    fn utc(date: &str) -> DateTime<Utc> {
+        chrono::NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap().and_utc()
+    }
+
+    fn calendar() -> ProjectCalendar {
+        let project = Project {
+            id: Uuid::new_v4(),
+            name: "Project".to_string(),
+            description: None,
+            start_date: utc("2024-11-01 00:00"),
+            end_date: utc("2024-11-11 00:00"),
+            notation_period_duration: 7,
+            promotion_id: Uuid::new_v4(),
+            state: ProjectState::EvaluationOpen,
+            timezone: None,
+        };
+        ProjectCalendar::new(&project, Tz::UTC)
+    }
+
+    fn offset(anchor: ReminderAnchor, hours: i32) -> ReminderOffset {
+        ReminderOffset { anchor, hours }
+    }
+
+    #[test]
+    fn test_reminders_are_planned_from_the_opening_and_the_closing() {
+        let policy = ReminderPolicy {
+            source: ReminderPolicySource::Project,
+            offsets: vec![
+                offset(ReminderAnchor::EvaluationClose, 24),
+                offset(ReminderAnchor::EvaluationOpen, 48),
+                // Same time as the previous one
+                offset(ReminderAnchor::EvaluationClose, 120),
+            ],
+            recipients: ReminderRecipients::Students,
+            escalate_to_teacher: true,
+            updated_at: None,
+        };
+
+        let reminders = policy.plan(&calendar());
+
+        let times: Vec<DateTime<Utc>> = reminders.iter().map(|reminder| reminder.send_at).collect();
+        assert_eq!(times, vec![utc("2024-11-13 00:00"), utc("2024-11-17 00:00")]);
+        assert!(reminders.iter().all(|reminder| reminder.recipients == ReminderRecipients::Students));
+        // Only the last reminder escalates
+        assert_eq!(reminders.iter().map(|reminder| reminder.escalates).collect::<Vec<_>>(), vec![false, true]);
+    }
+
+    #[test]
+    fn test_teacher_alerts_are_the_default_policy() {
+        let teacher_config = UserConfig {
+            id: 1,
+            user_id: Uuid::new_v4(),
+            alerts: vec![Some(Alert { before_event: true, hours: 12 }), None, Some(Alert { before_event: false, hours: 6 })],
+            updated_at: Utc::now().naive_utc(),
+            timezone: None,
+        };
+
+        let policy = ReminderPolicy::from_teacher_config(&teacher_config);
+
+        assert_eq!(policy.source, ReminderPolicySource::Teacher);
+        assert_eq!(policy.recipients, ReminderRecipients::Both);
+        assert_eq!(policy.offsets, vec![offset(ReminderAnchor::EvaluationClose, 12), offset(ReminderAnchor::EvaluationOpen, 6)]);
+        assert!(policy.plan(&calendar()).iter().all(|reminder| !reminder.escalates));
+    }
+}
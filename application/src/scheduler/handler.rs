@@ -1,476 +1,785 @@
 use crate::authentication::tokens::encode_token;
-use crate::database::config::get_config_by_user_id;
-use crate::database::done_alerts::{create_done_alert, get_done_alerts_by_project_id_and_type};
-use crate::database::groups::{get_group_from_student_and_project_id, get_groups_and_students_from_project_id, get_groups_from_project_id, ProjectGroup};
-use crate::database::marks::get_students_who_didnt_evaluate_group;
-use crate::database::projects::{get_project_by_id, update_project};
-use crate::database::promotions::get_promotion_by_id;
-use crate::database::students_tokens::{create_student_token, get_student_tokens_from_student_and_project_id};
-use crate::database::users::get_user_by_id;
-use crate::mail::send::{build_mail, send_mail, MailProps};
+use crate::extensions::{get_project_deadlines, ProjectDeadlines};
+use crate::lifecycle::advance_project;
+use crate::mail::send::{build_mail, send_mail, MailKind, MailProps, DEFAULT_MAIL_SENDER};
 use crate::marks::handler::handle_project_rating;
-use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Timelike, Utc};
-use domain::models::config::UserConfig;
+use crate::reminders::{get_pending_reminders, PlannedReminder};
+use crate::timezone::{format_local, get_project_calendar, ProjectCalendar, Tz};
+use chrono::{DateTime, Utc};
 use domain::models::done_alerts::{AlertType, NewDoneAlert};
-use domain::models::groups::Group;
+use domain::models::groups::{Group, GroupStudent};
 use domain::models::projects::{Project, ProjectState, UpdatedProject};
+use domain::models::promotions_members::PromotionRole;
 use domain::models::students::Student;
-use domain::models::students_tokens::NewStudentToken;
-use infrastructure::DBPool;
-use lettre::SmtpTransport;
+use domain::models::students_tokens::{NewStudentToken, StudentToken, UpdatedStudentToken};
+use domain::repositories::Repositories;
 use shared::app_config::Config;
-use shared::app_state_model::{AppState, ProjectTimeouts};
+use shared::app_state_model::{AppState, MailTransport, ProjectTimeouts};
 use shared::error_models::{APIError, DBError, InternalError, ServerError};
 use shared::token_models::StudentClaims;
-use std::env;
 use tokio::task::JoinHandle;
-use tokio_js_set_interval::set_timeout;
+use tracing::Instrument;
 use uuid::Uuid;
 
-pub fn handle_projects_alerts(app_state: &AppState, projects: Vec<Project>) {
-    let today = Utc::now().date_naive();
+/// Members of a promotion who are expected to enter the groups' marks
+const GRADER_ROLES: &[PromotionRole] = &[PromotionRole::Owner, PromotionRole::CoTeacher, PromotionRole::Assistant];
+const ALL_ROLES: &[PromotionRole] = &[PromotionRole::Owner, PromotionRole::CoTeacher, PromotionRole::Assistant, PromotionRole::Observer];
 
-    let mut starting_alerts: Vec<Project> = Vec::new();
+/// Moves the projects through the states their dates lead to, then sends the alerts of their new state.
+pub async fn handle_projects_alerts<R: Repositories + Clone + 'static>(app_state: &AppState, repos: &R, projects: Vec<Project>) {
+    let mut starting_alerts: Vec<(Project, ProjectCalendar)> = Vec::new();
     let mut ending_alerts: Vec<Project> = Vec::new();
-    let mut pending_alerts: Vec<Project> = Vec::new();
+    let mut pending_alerts: Vec<(Project, ProjectCalendar)> = Vec::new();
+    let mut extension_alerts: Vec<(Project, ProjectCalendar)> = Vec::new();
 
-    for project in projects {
-        let project_notation_end_date: NaiveDate = project.end_date.date() + Duration::days(project.notation_period_duration as i64);
+    for mut project in projects {
+        // The days are the ones of the timezone of the project
+        let calendar = match get_project_calendar(repos, &project).await {
+            Ok(calendar) => calendar,
+            Err(e) => {
+                tracing::error!(project_id = %project.id, error = %e, "Failed to get the project timezone");
+                continue;
+            }
+        };
+        project.state = match advance_project(repos, &project, &calendar).await {
+            Ok(state) => state,
+            Err(e) => {
+                tracing::error!(project_id = %project.id, error = %e, "Failed to update the project state");
+                continue;
+            }
+        };
+        let today = calendar.today();
+
+        tracing::debug!(project_id = %project.id, state = ?project.state, timezone = %calendar.timezone, "checking project alerts");
 
-        println!("End Date : {:?}", project.end_date.date());
-        println!("Notation End Date : {:?}", project_notation_end_date);
-        println!("Today : {:?}", today);
-        println!("Check : {:?}", project.end_date.date() < today && project_notation_end_date > today);
+        // The reminders are only sent while the evaluation is open
+        if project.state != ProjectState::EvaluationOpen {
+            cancel_project_reminders(app_state, project.id);
+        }
+
+        // The students granted an extension can evaluate their group after the others
+        if matches!(project.state, ProjectState::EvaluationOpen | ProjectState::EvaluationClosed) {
+            extension_alerts.push((project.clone(), calendar));
+        }
 
-        if project.end_date.date() == today {
-            starting_alerts.push(project);
-        } else if project_notation_end_date == today {
-            ending_alerts.push(project);
-        } else if project.end_date.date() < today && project_notation_end_date > today {
-            pending_alerts.push(project);
+        match project.state {
+            ProjectState::EvaluationOpen if are_starting_alerts_due(repos, project.id).await => starting_alerts.push((project, calendar)),
+            ProjectState::EvaluationOpen if calendar.end.date_naive() < today => pending_alerts.push((project, calendar)),
+            ProjectState::EvaluationClosed if are_submissions_over(repos, &project, &calendar).await => ending_alerts.push(project),
+            _ => {}
         }
     }
 
-    if !starting_alerts.is_empty() { handle_starting_alerts(app_state, starting_alerts) }
-    if !ending_alerts.is_empty() { handle_ending_alerts(app_state, ending_alerts) }
-    if !pending_alerts.is_empty() { handle_pending_alerts(app_state, pending_alerts) }
+    for (project, calendar) in starting_alerts {
+        let span = tracing::info_span!("starting_alerts", project_id = %project.id);
+        handle_starting_alerts(app_state, repos, &project, &calendar).instrument(span).await;
+    }
+    for (project, calendar) in extension_alerts {
+        let span = tracing::info_span!("extension_alerts", project_id = %project.id);
+        handle_extension_alerts(app_state, repos, &project, &calendar).instrument(span).await;
+    }
+    for project in ending_alerts {
+        let span = tracing::info_span!("ending_alerts", project_id = %project.id);
+        handle_ending_alerts(app_state, repos, &project).instrument(span).await;
+    }
+    for (project, calendar) in pending_alerts {
+        let span = tracing::info_span!("pending_alerts", project_id = %project.id);
+        handle_pending_alerts(app_state, repos, &project, &calendar).instrument(span).await;
+    }
 }
 
-fn handle_starting_alerts(app_state: &AppState, projects: Vec<Project>) {
-    let conn = app_state.database_pool.clone().as_ref().clone();
-    let smtp_transport = app_state.smtp_transport.clone().as_ref().clone();
+/// Returns whether the evaluation of a project was opened since the students last got their tokens.
+async fn are_starting_alerts_due<R: Repositories>(repos: &R, project_id: Uuid) -> bool {
+    let openings = match repos.get_project_state_transitions(project_id).await {
+        Ok(transitions) => transitions.iter()
+            .filter(|transition| transition.to_state == ProjectState::EvaluationOpen)
+            .count(),
+        Err(e) => {
+            tracing::error!(%project_id, error = %e, "Failed to get the project transitions");
+            return false;
+        }
+    };
 
-    for project in projects {
-        if !should_send_alert(&conn, project.id, AlertType::Started) {
-            continue;
+    match repos.get_done_alerts_by_project_id_and_type(project_id, AlertType::Started).await {
+        Ok(alerts) => alerts.len() < openings,
+        Err(_) => {
+            tracing::error!(%project_id, "Failed to check existing alerts");
+            false
         }
+    }
+}
 
-        match get_groups_and_students_from_project_id(&conn, project.id) {
-            Ok(groups) => {
-                for project_group in groups {
-                    process_group_tokens(app_state, &project, &project_group);
-
-                    // Get teacher email
-                    let teacher_email = match get_teacher_email(&conn, project.promotion_id) {
-                        Ok(email) => email,
-                        Err(_) => {
-                            log_error("Failed to get teacher email");
-                            continue;
-                        }
-                    };
-
-                    // Build email for teacher
-                    let mail = build_mail(MailProps {
-                        from: "Bitbox <no-reply@sigma-bot.fr>".to_string(),
-                        to: teacher_email.to_string(),
-                        subject: "360 Notation has begun".to_string(),
-                        body: format!("The evaluation for the project \"{}\" of the promotion (TODO) has now begun.\
-                            \nThanks to enter all the groups' marks before the end of the timing.\
-                            \nRemaining time : {} days", project.name, project.notation_period_duration.to_string()),
-                    });
-
-                    // Send email to teacher
-                    if let Err(e) = send_mail(&smtp_transport, mail) {
-                        log_error(&format!("Failed to send email: {:?}", e));
-                    }
-                }
-            }
-            Err(_) => {
-                log_error(&format!("Failed to get groups for project {}", project.id));
-                continue;
-            }
+/// Returns whether the students who haven't evaluated their group can no longer do it, their extensions and the
+/// late submissions of the project being over, so its results can be computed.
+async fn are_submissions_over<R: Repositories>(repos: &R, project: &Project, calendar: &ProjectCalendar) -> bool {
+    let deadlines = match get_project_deadlines(repos, project, calendar).await {
+        Ok(deadlines) => deadlines,
+        Err(e) => {
+            tracing::error!(project_id = %project.id, error = %e, "Failed to get the deadlines of the students");
+            return false;
         }
+    };
+    let students = match repos.get_students_who_didnt_evaluate_group(project.id).await {
+        Ok(students) => students,
+        Err(e) => {
+            tracing::error!(project_id = %project.id, error = %e, "Failed to get students who didn't evaluate group");
+            return false;
+        }
+    };
+
+    deadlines.submissions_end(students.iter().map(|student| student.id))
+        .is_none_or(|submissions_end| submissions_end < Utc::now())
+}
 
-        mark_alert_as_done(&conn, project.id, AlertType::Started);
+/// Stops the reminders of a project which are waiting to be sent.
+fn cancel_project_reminders(app_state: &AppState, project_id: Uuid) {
+    if let Some(reminders) = app_state.get_project_reminders(project_id) {
+        reminders.timeouts.iter().for_each(|timeout| timeout.abort());
     }
 }
 
-fn handle_pending_alerts(app_state: &AppState, projects: Vec<Project>) -> () {
-    let conn = app_state.database_pool.clone().as_ref().clone();
-    for project in projects {
-        // Load teacher config
-        let teacher_config =  match get_teacher_config(&conn, project.promotion_id) {
-            Ok(config) => config,
-            Err(_) => {
-                log_error("Failed to get teacher config");
-                continue;
-            }
-        };
-        let alerts_timestamps = calculate_alerts_timestamps(&teacher_config, &project);
-        let done_alerts = match get_done_alerts_by_project_id_and_type(&conn, project.id, AlertType::Pending) {
-            Ok(alerts) => alerts,
-            Err(_) => {
-                log_error("Failed to get done alerts");
-                continue;
-            }
-        };
+async fn handle_starting_alerts<R: Repositories>(app_state: &AppState, repos: &R, project: &Project, calendar: &ProjectCalendar) {
+    let sender = get_project_mail_sender(repos, project).await;
+    let groups = match repos.get_groups_from_project_id(project.id).await {
+        Ok(groups) => groups,
+        Err(_) => {
+            tracing::error!("Failed to get groups");
+            return;
+        }
+    };
 
-        // Filter alerts array by comparing with timestamps of done alerts
-        let alerts_to_send: Vec<DateTime<Utc>> = alerts_timestamps.iter()
-            .filter(|timestamp|
-            done_alerts.iter().all(|alert|
-                alert.published_at.date() != timestamp.date_naive() ||
-                alert.published_at.hour() != timestamp.hour()
-            )
-            )
-            .cloned()
-            .collect();
+    let groups_students = match repos.get_groups_students_from_project_id(project.id).await {
+        Ok(groups_students) => groups_students,
+        Err(_) => {
+            tracing::error!("Failed to get groups students");
+            return;
+        }
+    };
 
-        println!("Alerts to send : {:?}", alerts_to_send);
+    // The tokens issued by a previous opening of the evaluation
+    let tokens = match repos.get_student_tokens_from_project_id(project.id).await {
+        Ok(tokens) => tokens,
+        Err(_) => {
+            tracing::error!("Failed to get tokens");
+            return;
+        }
+    };
 
-        if alerts_to_send.is_empty() { continue; }
+    // The students granted an extension get a token expiring with it
+    let deadlines = match get_project_deadlines(repos, project, calendar).await {
+        Ok(deadlines) => deadlines,
+        Err(_) => {
+            tracing::error!("Failed to get the deadlines of the students");
+            return;
+        }
+    };
 
-        // Generate set_timout functions for each alert
-        let mut timeouts: Vec<JoinHandle<()>> = Vec::new();
-        for alert in alerts_to_send {
-            timeouts.push(match generate_pending_alert_timeout_function(&conn, app_state.smtp_transport.clone().as_ref(), alert, project.id) {
-                Ok(timeout) => timeout,
-                Err(_) => {
-                    log_error("Failed to generate timeout function");
-                    continue;
-                }
-            })
+    // Get the emails of the teachers who can enter marks
+    let teachers_emails = match get_teachers_emails(repos, project.promotion_id, GRADER_ROLES).await {
+        Ok(emails) => emails,
+        Err(_) => {
+            tracing::error!("Failed to get teachers emails");
+            return;
         }
+    };
 
-        println!("Timeouts : {:?}", timeouts);
+    for group in groups {
+        let students: Vec<(Student, Option<StudentToken>)> = groups_students.iter()
+            .filter(|(group_student, _)| group_student.group_id == group.id)
+            .map(|(_, student)| (student.clone(), tokens.iter().find(|token| token.student_id == student.id).cloned()))
+            .collect();
+        process_group_tokens(app_state, repos, project, &deadlines, &group, &students, &sender).await;
 
-        // Modify App State
-        app_state.set_project_reminders(project.id, ProjectTimeouts {
-            timeouts,
-        });
+        for teacher_email in &teachers_emails {
+            // Build email for teacher
+            let mail = build_mail(MailProps {
+                from: sender.clone(),
+                to: teacher_email.to_string(),
+                subject: "360 Notation has begun".to_string(),
+                body: format!("The evaluation for the project \"{}\" of the promotion (TODO) has now begun.\
+                    \nThanks to enter all the groups' marks before the end of the timing.\
+                    \nRemaining time : {} days, until the {}", project.name, project.notation_period_duration.to_string(), format_local(&calendar.evaluation_end)),
+            });
+
+            // Send email to teacher
+            if let Err(e) = send_mail(&app_state.smtp_transport, mail, MailKind::MarksStarted).await {
+                tracing::error!(error = %e, "Failed to send email");
+            }
+        }
     }
+
+    mark_alert_as_done(repos, project.id, AlertType::Started, None).await;
 }
 
-fn handle_ending_alerts(app_state: &AppState, projects: Vec<Project>) {
-    let conn = app_state.database_pool.clone().as_ref().clone();
-    let smtp_transport = app_state.smtp_transport.clone().as_ref().clone();
+async fn handle_pending_alerts<R: Repositories + Clone + 'static>(app_state: &AppState, repos: &R, project: &Project, calendar: &ProjectCalendar) {
+    // Scheduled by a previous run
+    if app_state.get_all_project_ids().contains(&project.id) {
+        return;
+    }
 
-    for project in projects {
-        if !should_send_alert(&conn, project.id, AlertType::Finished) {
-            continue;
+    let reminders = match get_pending_reminders(repos, project, calendar).await {
+        Ok(reminders) => reminders,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to get pending alerts");
+            return;
         }
+    };
 
-        let students = match get_students_for_project(&conn, project.id) {
-            Ok(students) if !students.is_empty() => students,
-            _ => {
-                log_error("No students found for project");
-                continue;
-            }
-        };
+    tracing::debug!(project_id = %project.id, alerts = reminders.len(), "scheduling pending alerts");
 
-        let teacher_email = match get_teacher_email(&conn, project.promotion_id) {
-            Ok(email) => email,
+    if reminders.is_empty() { return; }
+
+    // Spawn a delayed task for each alert
+    let web_url = app_state.config.read().url_config.web_url.clone();
+    let mut timeouts: Vec<JoinHandle<()>> = Vec::new();
+    for reminder in reminders {
+        timeouts.push(match spawn_pending_alert(repos, &app_state.smtp_transport, &web_url, reminder, project.id).await {
+            Ok(timeout) => timeout,
             Err(_) => {
-                log_error("Failed to get teacher email");
+                tracing::error!("Failed to generate timeout function");
                 continue;
             }
-        };
+        })
+    }
 
-        let mut mails_list: Vec<String> = students.iter().map(|student| student.email.clone()).collect();
-        mails_list.push(teacher_email);
+    // Modify App State
+    app_state.set_project_reminders(project.id, ProjectTimeouts {
+        timeouts,
+    });
+}
 
-        for email in mails_list {
-            let mail = build_mail(MailProps {
-                from: "Bitbox <no-reply@sigma-bot.fr>".to_string(),
-                to: email.to_string(),
-                subject: "360 Notation has ended".to_string(),
-                body: format!("The evaluation for the project \"{}\" has now ended.\nThanks for your participation.", project.name),
-            });
+async fn handle_ending_alerts<R: Repositories>(app_state: &AppState, repos: &R, project: &Project) {
+    let students = match get_students_for_project(repos, project.id).await {
+        Ok(students) if !students.is_empty() => students,
+        _ => {
+            tracing::error!("No students found for project");
+            return;
+        }
+    };
 
-            if let Err(e) = send_mail(&smtp_transport, mail) {
-                log_error(&format!("Failed to send email: {:?}", e));
-            }
+    let teachers_emails = match get_teachers_emails(repos, project.promotion_id, ALL_ROLES).await {
+        Ok(emails) => emails,
+        Err(_) => {
+            tracing::error!("Failed to get teachers emails");
+            return;
         }
+    };
 
-        if let Err(e) = handle_project_rating(&conn, project.id) {
-            log_error(&format!("Failed to handle project rating: {:?}", e));
-            continue;
+    let mut mails_list: Vec<String> = students.iter().map(|student| student.email.clone()).collect();
+    mails_list.extend(teachers_emails);
+
+    let sender = get_project_mail_sender(repos, project).await;
+    for email in mails_list {
+        let mail = build_mail(MailProps {
+            from: sender.clone(),
+            to: email.to_string(),
+            subject: "360 Notation has ended".to_string(),
+            body: format!("The evaluation for the project \"{}\" has now ended.\nThanks for your participation.", project.name),
+        });
+
+        if let Err(e) = send_mail(&app_state.smtp_transport, mail, MailKind::NotationEnded).await {
+            tracing::error!(error = %e, "Failed to send email");
         }
+    }
+
+    if let Err(e) = handle_project_rating(repos, project.id).await {
+        tracing::error!(error = %e, "Failed to handle project rating");
+        return;
+    }
 
-        mark_alert_as_done(&conn, project.id, AlertType::Finished);
-        update_project_state(&conn, project.id, ProjectState::NotationFinished);
+    mark_alert_as_done(repos, project.id, AlertType::Finished, None).await;
+    if let Err(e) = repos.transition_project(project.id, ProjectState::EvaluationClosed, ProjectState::ResultsPublished, UpdatedProject::default(), None).await {
+        tracing::error!(error = %e, "Failed to publish the project results");
     }
 }
 
-fn process_group_tokens(app_state: &AppState, project: &Project, project_group: &ProjectGroup) -> () {
-    let conn = app_state.database_pool.clone().as_ref().clone();
-    let config = app_state.config.read();
-    let smtp_transport = app_state.smtp_transport.clone().as_ref().clone();
+/// Issues the tokens of the students of a group, along with the ones they got from a previous opening.
+///
+/// The tokens expire at the deadline of their student, the students granted an extension being told about it.
+async fn process_group_tokens<R: Repositories>(app_state: &AppState, repos: &R, project: &Project, deadlines: &ProjectDeadlines, group: &Group, students: &[(Student, Option<StudentToken>)], sender: &str) -> () {
+    let config = app_state.config.read().clone();
 
-    let stop_date_time = project.end_date + Duration::days(project.notation_period_duration as i64);
-    let stop_date = DateTime::<Utc>::from_naive_utc_and_offset(stop_date_time, Utc);
+    for (student_info, existing_token) in students {
+        // The students who already evaluated their group can't do it again
+        if existing_token.as_ref().is_some_and(|token| token.used) {
+            continue;
+        }
 
-    for student_group in &project_group.students {
-        let student_info = &student_group.student;
-        let token = match generate_student_token(&config, student_info.id, project_group.group.id, stop_date) {
+        let deadline = deadlines.of_student(student_info.id);
+        let token = match generate_student_token(&config, student_info.id, group.id, deadline.expires_at()) {
             Ok(token) => token,
             Err(e) => {
-                log_error(&format!("Failed to generate token: {:?}", e));
+                tracing::error!(error = %e, "Failed to generate token");
                 return;
             }
         };
 
-        let new_student_token = NewStudentToken {
-            token: token.to_string(),
-            student_id: student_info.id,
-            project_id: project.id,
+        // A token issued by a previous opening is renewed, so its link stays the same
+        let token_id = match existing_token {
+            Some(existing_token) => repos.update_student_token(existing_token.id, UpdatedStudentToken {
+                token: Some(token),
+                used: None,
+                submitted_late: None,
+            }).await.map(|_| existing_token.id),
+            None => repos.create_student_token(NewStudentToken {
+                token,
+                student_id: student_info.id,
+                project_id: project.id,
+            }).await,
         };
-        let token_id = match create_student_token(&conn, new_student_token) {
+        let token_id = match token_id {
             Ok(id) => id,
             Err(e) => {
-                log_error(&format!("Failed to create token: {:?}", e));
+                tracing::error!(error = %e, "Failed to create token");
                 return;
             }
         };
 
-        let web_url = env::var("WEB_URL").unwrap_or("http://localhost:3000".to_string());
-        let url = format!("{}/evaluate/{}", web_url, token_id);
+        let url = format!("{}/evaluate/{}", config.url_config.web_url, token_id);
         let mail = build_mail(MailProps {
-            from: "Bitbox <no-reply@sigma-bot.fr>".to_string(),
+            from: sender.to_string(),
             to: student_info.email.clone(),
             subject: "360 Notation has begun".to_string(),
             body: format!(
                 "Now's the time to evaluate your peers from group \"{}\" on project \"{}\".\nHere's the link : {}\
-                \nRemaining time : {} days",
-                project_group.group.name, project.name, url, project.notation_period_duration.to_string()
+                \nRemaining time : {} days, until the {}",
+                group.name, project.name, url, project.notation_period_duration.to_string(), format_local(&deadline.due_at.with_timezone(&deadlines.timezone))
             ),
         });
 
-        if let Err(e) = send_mail(&smtp_transport, mail) {
-            log_error(&format!("Failed to send email: {:?}", e));
+        if let Err(e) = send_mail(&app_state.smtp_transport, mail, MailKind::EvaluationStarted).await {
+            tracing::error!(error = %e, "Failed to send email");
+        }
+
+        // The mail already gives the deadline of the extension
+        if deadline.is_extended {
+            set_extension_notified(repos, project.id, student_info.id).await;
+        }
+    }
+}
+
+/// Renews the tokens of the students granted an extension since the last run, so they expire with it, and tells
+/// only them about their new deadline.
+async fn handle_extension_alerts<R: Repositories>(app_state: &AppState, repos: &R, project: &Project, calendar: &ProjectCalendar) {
+    let deadlines = match get_project_deadlines(repos, project, calendar).await {
+        Ok(deadlines) => deadlines,
+        Err(_) => {
+            tracing::error!("Failed to get the deadlines of the students");
+            return;
+        }
+    };
+    let now = Utc::now();
+    let extensions: Vec<Uuid> = deadlines.extensions.iter()
+        .filter(|extension| extension.notified_at.is_none() && extension.extended_until > now)
+        .map(|extension| extension.student_id)
+        .collect();
+    if extensions.is_empty() { return; }
+
+    let tokens = match repos.get_student_tokens_from_project_id(project.id).await {
+        Ok(tokens) => tokens,
+        Err(_) => {
+            tracing::error!("Failed to get tokens");
+            return;
+        }
+    };
+    let groups = match repos.get_groups_from_project_id(project.id).await {
+        Ok(groups) => groups,
+        Err(_) => {
+            tracing::error!("Failed to get groups");
+            return;
+        }
+    };
+    let groups_students = match repos.get_groups_students_from_project_id(project.id).await {
+        Ok(groups_students) => groups_students,
+        Err(_) => {
+            tracing::error!("Failed to get groups students");
+            return;
+        }
+    };
+
+    let config = app_state.config.read().clone();
+    let sender = get_project_mail_sender(repos, project).await;
+    for student_id in extensions {
+        // The students get their token when the evaluation opens
+        let Some(token) = tokens.iter().find(|token| token.student_id == student_id) else { continue };
+        let Some((_, student)) = groups_students.iter().find(|(_, student)| student.id == student_id) else { continue };
+        let Some(group) = find_student_group(&groups, &groups_students, student) else { continue };
+
+        // The students who already evaluated their group have nothing left to do
+        if !token.used {
+            let deadline = deadlines.of_student(student_id);
+            if let Err(e) = renew_student_token(&config, repos, token, group.id, deadline.expires_at()).await {
+                tracing::error!(%student_id, error = %e, "Failed to renew the token of the student");
+                continue;
+            }
+
+            let url = format!("{}/evaluate/{}", config.url_config.web_url, token.id);
+            let due_at = deadline.due_at.with_timezone(&deadlines.timezone);
+            if let Err(e) = send_extension_to_student(&app_state.smtp_transport, &sender, student, group, project, &due_at, &url).await {
+                tracing::error!(%student_id, error = %e, "Failed to send email");
+            }
+        }
+
+        set_extension_notified(repos, project.id, student_id).await;
+    }
+}
+
+/// Renews the unused tokens of the students of a project so they expire at their deadline, after its late
+/// submissions policy changed. Their links stay the same, so the students are not told.
+pub async fn renew_project_tokens<R: Repositories>(app_state: &AppState, repos: &R, project: &Project) {
+    if !matches!(project.state, ProjectState::EvaluationOpen | ProjectState::EvaluationClosed) {
+        return;
+    }
+
+    let deadlines = match get_project_calendar(repos, project).await {
+        Ok(calendar) => get_project_deadlines(repos, project, &calendar).await,
+        Err(e) => Err(e),
+    };
+    let (deadlines, tokens, groups_students) = match (
+        deadlines,
+        repos.get_student_tokens_from_project_id(project.id).await,
+        repos.get_groups_students_from_project_id(project.id).await,
+    ) {
+        (Ok(deadlines), Ok(tokens), Ok(groups_students)) => (deadlines, tokens, groups_students),
+        _ => {
+            tracing::error!(project_id = %project.id, "Failed to get the tokens of the students");
+            return;
+        }
+    };
+
+    let config = app_state.config.read().clone();
+    for token in tokens.iter().filter(|token| !token.used) {
+        let Some((group_student, _)) = groups_students.iter().find(|(group_student, _)| group_student.student_id == token.student_id) else { continue };
+        let expires_at = deadlines.of_student(token.student_id).expires_at();
+        if let Err(e) = renew_student_token(&config, repos, token, group_student.group_id, expires_at).await {
+            tracing::error!(project_id = %project.id, student_id = %token.student_id, error = %e, "Failed to renew the token of the student");
         }
     }
 }
 
-fn generate_pending_alert_timeout_function(conn: &DBPool, smtp_transport: &SmtpTransport, alert_datetime: DateTime<Utc>, project_id: Uuid) -> Result<JoinHandle<()>, APIError> {
+/// Replaces the token of a student by one expiring at another time, its link staying the same.
+async fn renew_student_token<R: Repositories>(config: &Config, repos: &R, token: &StudentToken, group_id: Uuid, expires_at: DateTime<Utc>) -> Result<(), APIError> {
+    let renewed_token = generate_student_token(config, token.student_id, group_id, expires_at)?;
+
+    Ok(repos.update_student_token(token.id, UpdatedStudentToken {
+        token: Some(renewed_token),
+        used: None,
+        submitted_late: None,
+    }).await?)
+}
+
+async fn set_extension_notified<R: Repositories>(repos: &R, project_id: Uuid, student_id: Uuid) {
+    if let Err(e) = repos.set_student_extension_notified(project_id, student_id, Utc::now()).await {
+        tracing::error!(%project_id, %student_id, error = %e, "Failed to record the extension as notified");
+    }
+}
+
+/// Replaces the reminders of a project waiting to be sent, after its reminder policy changed.
+pub async fn reschedule_project_reminders<R: Repositories + Clone + 'static>(app_state: &AppState, repos: &R, project: &Project) {
+    cancel_project_reminders(app_state, project.id);
+    if project.state != ProjectState::EvaluationOpen {
+        return;
+    }
+
+    match get_project_calendar(repos, project).await {
+        Ok(calendar) if calendar.end.date_naive() < calendar.today() => handle_pending_alerts(app_state, repos, project, &calendar).await,
+        Ok(_) => {}
+        Err(e) => tracing::error!(project_id = %project.id, error = %e, "Failed to get the project timezone"),
+    }
+}
+
+/// Spawns a task sending a reminder of a project once it's due.
+async fn spawn_pending_alert<R: Repositories + Clone + 'static>(repos: &R, smtp_transport: &MailTransport, web_url: &str, reminder: PlannedReminder, project_id: Uuid) -> Result<JoinHandle<()>, APIError> {
+    let alert_datetime = reminder.send_at;
     // Calculate ms between now and alert_datetime
     let ms = alert_datetime.timestamp_millis() - Utc::now().timestamp_millis();
-    let interval = ms as u64;
+    let delay = std::time::Duration::from_millis(ms.max(0) as u64);
 
     // Get project info
-    let project = match get_project_by_id(&conn, project_id) {
+    let project = match repos.get_project_by_id(project_id).await {
         Ok(project) => project,
         Err(_) => {
-            log_error("Failed to get project");
+            tracing::error!("Failed to get project");
             return Err(APIError::ServerError(ServerError::InternalError(InternalError)));
         }
     };
-    let end_date = project.end_date + Duration::days(project.notation_period_duration as i64);
+    let end_date = match get_project_calendar(repos, &project).await {
+        Ok(calendar) => calendar.evaluation_end,
+        Err(_) => {
+            tracing::error!("Failed to get project timezone");
+            return Err(APIError::ServerError(ServerError::InternalError(InternalError)));
+        }
+    };
+    let sender = get_project_mail_sender(repos, &project).await;
 
     // Clone the necessary data
-    let conn = conn.clone();
+    let repos = repos.clone();
     let smtp_transport = smtp_transport.clone();
+    let web_url = web_url.to_string();
+    let span = tracing::info_span!("project_reminder", %project_id, %alert_datetime);
 
-    let timeout = set_timeout!(move || {
-        // Get all students from project who hasn't evaluated their group
-        let students = match get_students_who_didnt_evaluate_group(&conn, project_id) {
-            Ok(students) => students,
-            Err(_) => {
-                log_error("Failed to get students who didn't evaluate group");
+    Ok(tokio::spawn(async move {
+        tokio::time::sleep(delay).await;
+        send_pending_alert(&repos, &smtp_transport, &sender, &web_url, &project, end_date, &reminder).await;
+    }.instrument(span)))
+}
+
+async fn send_pending_alert<R: Repositories>(repos: &R, smtp_transport: &MailTransport, sender: &str, web_url: &str, project: &Project, end_date: DateTime<Tz>, reminder: &PlannedReminder) {
+    let project_id = project.id;
+
+    // The evaluation may have been closed since the reminder was scheduled
+    match repos.get_project_by_id(project_id).await {
+        Ok(project) if project.state == ProjectState::EvaluationOpen => {}
+        Ok(_) => return,
+        Err(_) => {
+            tracing::error!("Failed to get project");
+            return;
+        }
+    }
+
+    // Get all students from project who hasn't evaluated their group
+    let students = match repos.get_students_who_didnt_evaluate_group(project_id).await {
+        Ok(students) => students,
+        Err(_) => {
+            tracing::error!("Failed to get students who didn't evaluate group");
+            return;
+        }
+    };
+
+    // Get the tokens and groups of the whole project at once
+    let tokens = match repos.get_student_tokens_from_project_id(project_id).await {
+        Ok(tokens) => tokens,
+        Err(_) => {
+            tracing::error!("Failed to get tokens");
+            return;
+        }
+    };
+    let groups = match repos.get_groups_from_project_id(project_id).await {
+        Ok(groups) => groups,
+        Err(_) => {
+            tracing::error!("Failed to get groups");
+            return;
+        }
+    };
+    let groups_students = match repos.get_groups_students_from_project_id(project_id).await {
+        Ok(groups_students) => groups_students,
+        Err(_) => {
+            tracing::error!("Failed to get groups students");
+            return;
+        }
+    };
+
+    // Send email to each student
+    for student in students.iter().filter(|_| reminder.recipients.includes_students()) {
+        let token_id = match tokens.iter().find(|token| token.student_id == student.id) {
+            Some(token) => token.id,
+            None => {
+                tracing::error!("Failed to get token");
                 return;
             }
         };
 
-        // Send email to each student
-        for student in students {
-            // Get token
-            let token_id = match get_student_tokens_from_student_and_project_id(&conn, student.id, project_id)
-                .map(|token| token.id) {
-                Ok(id) => id,
-                Err(_) => {
-                    log_error("Failed to get token");
-                    return;
-                }
-            };
-
-            // Get group
-            let group = match get_group_from_student_and_project_id(&conn, student.id, project_id) {
-                Ok(group) => match group {
-                    Some(group) => Some(group),
-                    None => {
-                        log_error("Student is not in a group");
-                        return;
-                    },
-                },
-                Err(_) => {
-                    log_error("Failed to get group");
-                    return;
-                }
-            };
+        let group = match find_student_group(&groups, &groups_students, student) {
+            Some(group) => group,
+            None => {
+                tracing::error!("Student is not in a group");
+                return;
+            }
+        };
 
-            // Send reminder email to student
-            match send_reminder_to_student(&smtp_transport, &student, &group.unwrap(), &project, end_date, token_id) {
-                Ok(_) => (),
-                Err(_) => {
-                    log_error("Failed to send reminder to student");
-                    return;
-                }
-            };
+        // Send reminder email to student
+        let url = format!("{}/evaluate/{}", web_url, token_id);
+        if send_reminder_to_student(smtp_transport, sender, student, group, project, &end_date, &url).await.is_err() {
+            tracing::error!("Failed to send reminder to student");
+            return;
         }
+    }
 
-        // Check if teacher has given a note to each group
-        let check = match are_all_groups_from_project_evaluated(&conn, project_id) {
-            Ok(check) => check,
+    // Check if teacher has given a note to each group
+    let are_groups_marked = are_all_groups_evaluated(&groups);
+    let is_escalated = reminder.escalates && !students.is_empty();
+    let students_list: Vec<String> = students.iter()
+        .filter(|_| is_escalated)
+        .map(|student| match find_student_group(&groups, &groups_students, student) {
+            Some(group) => format!("- {} {} (group \"{}\")", student.name, student.surname, group.name),
+            None => format!("- {} {}", student.name, student.surname),
+        })
+        .collect();
+
+    if (reminder.recipients.includes_teacher() && !are_groups_marked) || is_escalated {
+        // Get the emails of the teachers who can enter marks
+        let teachers_emails = match get_teachers_emails(repos, project.promotion_id, GRADER_ROLES).await {
+            Ok(emails) => emails,
             Err(_) => {
-                log_error("Failed to check if all groups are evaluated");
+                tracing::error!("Failed to get teachers emails");
                 return;
             }
         };
 
-        // Send email to teacher if not
-        if !check {
-            // Get teacher email
-            let teacher_email = match get_teacher_email(&conn, project.promotion_id) {
-                Ok(email) => email,
-                Err(_) => {
-                    log_error("Failed to get teacher email");
-                    return;
-                }
-            };
-
-            // Send email
-            match send_reminder_to_teacher(&smtp_transport, &teacher_email, &project, &end_date) {
-                Ok(_) => (),
-                Err(_) => {
-                    log_error("Failed to send reminder to teacher");
-                    return;
-                }
-            };
+        // Send email
+        for teacher_email in teachers_emails {
+            if reminder.recipients.includes_teacher() && !are_groups_marked
+                && send_reminder_to_teacher(smtp_transport, sender, &teacher_email, project, &end_date).await.is_err() {
+                tracing::error!("Failed to send reminder to teacher");
+            }
+            if is_escalated
+                && send_escalation_to_teacher(smtp_transport, sender, &teacher_email, project, &end_date, &students_list).await.is_err() {
+                tracing::error!("Failed to send escalation to teacher");
+            }
         }
+    }
 
-        // Mark alert as done
-        mark_alert_as_done(&conn, project_id, AlertType::Pending);
-    }, interval);
+    // Mark alert as done, with the time it was due even if it's sent late
+    mark_alert_as_done(repos, project_id, AlertType::Pending, Some(reminder.send_at)).await;
+}
 
-    Ok(timeout)
+fn find_student_group<'a>(groups: &'a [Group], groups_students: &[(GroupStudent, Student)], student: &Student) -> Option<&'a Group> {
+    groups_students.iter()
+        .find(|(group_student, _)| group_student.student_id == student.id)
+        .and_then(|(group_student, _)| groups.iter().find(|group| group.id == group_student.group_id))
 }
 
-fn are_all_groups_from_project_evaluated(conn: &DBPool, project_id: Uuid) -> Result<bool, DBError> {
-    let groups = get_groups_from_project_id(conn, project_id)?;
-    let mut flag: bool = false;
-    for group in groups {
-        if group.mark.is_none() {
-            flag = true;
-            break;
-        }
-    }
+fn are_all_groups_evaluated(groups: &[Group]) -> bool {
+    groups.iter().all(|group| group.mark.is_some())
+}
 
-    Ok(!flag)
+async fn get_students_for_project<R: Repositories>(repos: &R, project_id: Uuid) -> Result<Vec<Student>, DBError> {
+    let groups = repos.get_groups_from_project_id(project_id).await?;
+    repos.get_students_from_groups(groups.into_iter().map(|group| group.id).collect()).await
 }
 
-fn get_students_for_project(conn: &DBPool, project_id: Uuid) -> Result<Vec<Student>, DBError> {
-    get_groups_and_students_from_project_id(conn, project_id)
-        .map(|groups| groups.into_iter()
-            .flat_map(|group| group.students)
-            .map(|student_group| student_group.student)
-            .collect())
+async fn get_teachers_emails<R: Repositories>(repos: &R, promotion_id: Uuid, roles: &[PromotionRole]) -> Result<Vec<String>, DBError> {
+    repos.get_members_emails_from_promotion_id(promotion_id, roles).await
 }
 
-fn get_teacher_email(conn: &DBPool, promotion_id: Uuid) -> Result<String, DBError> {
-    let promotion = get_promotion_by_id(conn, promotion_id)?;
-    let teacher = get_user_by_id(conn, promotion.teacher_id)?;
-    Ok(teacher.email)
+async fn get_project_mail_sender<R: Repositories>(repos: &R, project: &Project) -> String {
+    match repos.get_promotion_by_id(project.promotion_id).await {
+        Ok(promotion) => repos.get_organization_by_id(promotion.organization_id)
+            .await.ok()
+            .and_then(|organization| organization.mail_sender)
+            .unwrap_or(DEFAULT_MAIL_SENDER.to_string()),
+        Err(_) => DEFAULT_MAIL_SENDER.to_string(),
+    }
+}
+
+/// Reminder the scheduler will send during the notation period of a project.
+#[derive(Debug, Clone)]
+pub struct ScheduledReminder {
+    pub project_id: Uuid,
+    pub project_name: String,
+    pub send_at: DateTime<Utc>,
 }
 
-fn get_teacher_config(conn: &DBPool, promotion_id: Uuid) -> Result<UserConfig, DBError> {
-    let promotion = get_promotion_by_id(conn, promotion_id)?;
-    let teacher = get_user_by_id(conn, promotion.teacher_id)?;
-    get_config_by_user_id(conn, teacher.id)
+/// Lists the reminders of the projects in their notation period which are still to be sent, the earliest first.
+pub async fn get_scheduled_reminders<R: Repositories>(repos: &R) -> Result<Vec<ScheduledReminder>, DBError> {
+    let now = Utc::now();
+    let mut reminders = Vec::new();
+
+    for project in repos.get_current_projects().await? {
+        let calendar = get_project_calendar(repos, &project).await?;
+        let today = calendar.today();
+        if project.state != ProjectState::EvaluationOpen || calendar.end.date_naive() >= today {
+            continue;
+        }
+
+        for reminder in get_pending_reminders(repos, &project, &calendar).await? {
+            if reminder.send_at > now {
+                reminders.push(ScheduledReminder {
+                    project_id: project.id,
+                    project_name: project.name.clone(),
+                    send_at: reminder.send_at,
+                });
+            }
+        }
+    }
+
+    reminders.sort_by_key(|reminder| reminder.send_at);
+    Ok(reminders)
 }
 
-fn send_reminder_to_student(
-    smtp_transport: &SmtpTransport,
+async fn send_reminder_to_student(
+    smtp_transport: &MailTransport,
+    sender: &str,
     student: &Student,
     group: &Group,
     project: &Project,
-    end_date: NaiveDateTime,
-    token_id: Uuid
+    end_date: &DateTime<Tz>,
+    url: &str
 ) -> Result<(), APIError> {
     // Build email
-    let web_url = env::var("WEB_URL").unwrap_or("http://localhost:3000".to_string());
-    let url = format!("{}/evaluate/{}", web_url, token_id);
     let mail = build_mail(MailProps {
-        from: "Bitbox <no-reply@sigma-bot.fr>".to_string(),
+        from: sender.to_string(),
         to: student.email.clone(),
         subject: "360 Notation Reminder".to_string(),
         body: format!(
             "Please evaluate your colleagues from group \"{}\" on project \"{}\".\
                     \nHere's the link : {}\
                     \nYou have till the {} to complete the evaluation.",
-            group.name, project.name, url, end_date.date().to_string()
+            group.name, project.name, url, format_local(end_date)
         ),
     });
 
     // Send mail
-    send_mail(smtp_transport, mail)
+    send_mail(smtp_transport, mail, MailKind::EvaluationReminder).await
+}
+
+/// Tells a student granted an extension about their new deadline.
+async fn send_extension_to_student(
+    smtp_transport: &MailTransport,
+    sender: &str,
+    student: &Student,
+    group: &Group,
+    project: &Project,
+    due_at: &DateTime<Tz>,
+    url: &str
+) -> Result<(), APIError> {
+    let mail = build_mail(MailProps {
+        from: sender.to_string(),
+        to: student.email.clone(),
+        subject: "360 Notation deadline extended".to_string(),
+        body: format!(
+            "Your deadline to evaluate your colleagues from group \"{}\" on project \"{}\" has been extended.\
+                    \nHere's the link : {}\
+                    \nYou have till the {} to complete the evaluation.",
+            group.name, project.name, url, format_local(due_at)
+        ),
+    });
+
+    send_mail(smtp_transport, mail, MailKind::EvaluationExtended).await
 }
 
-fn send_reminder_to_teacher(smtp_transport: &SmtpTransport, teacher_email: &String, project: &Project, end_date: &NaiveDateTime) -> Result<(), APIError> {
+async fn send_reminder_to_teacher(smtp_transport: &MailTransport, sender: &str, teacher_email: &String, project: &Project, end_date: &DateTime<Tz>) -> Result<(), APIError> {
     // Build email
     let mail = build_mail(MailProps {
-        from: "Bitbox <no-reply@sigma-bot.fr>".to_string(),
+        from: sender.to_string(),
         to: teacher_email.to_string(),
         subject: "Marks Reminder".to_string(),
         body: format!(
             "Please enter all the notes on the platform for the groups of project \"{}\".\
                     \nYou have till the {} to complete the evaluation.",
-            project.name, end_date.date().to_string()
+            project.name, format_local(end_date)
         ),
     });
 
     // Send mail
-    send_mail(smtp_transport, mail)
+    send_mail(smtp_transport, mail, MailKind::MarksReminder).await
 }
 
-fn calculate_alerts_timestamps(teacher_config: &UserConfig, project: &Project) -> Vec<DateTime<Utc>> {
-    let mut timestamps: Vec<DateTime<Utc>> = Vec::new();
-
-    for alert in &teacher_config.alerts {
-        if let Some(alert) = alert {
-            let alert_time = Duration::hours(alert.hours as i64);
-            let project_evaluation_end_date = project.end_date + Duration::days(project.notation_period_duration as i64);
-            let alert_date = if alert.before_event {
-                project_evaluation_end_date - alert_time
-            } else {
-                project.end_date + alert_time
-            };
-
-            let alert_timestamp = DateTime::from_naive_utc_and_offset(alert_date, Utc);
-            timestamps.push(alert_timestamp);
-        }
-    }
-
-    timestamps
-}
+/// Tells a teacher which students still haven't evaluated their group at the last reminder.
+async fn send_escalation_to_teacher(smtp_transport: &MailTransport, sender: &str, teacher_email: &str, project: &Project, end_date: &DateTime<Tz>, students_list: &[String]) -> Result<(), APIError> {
+    let mail = build_mail(MailProps {
+        from: sender.to_string(),
+        to: teacher_email.to_string(),
+        subject: "Students who haven't evaluated".to_string(),
+        body: format!(
+            "The following students still haven't evaluated their group on project \"{}\", after their last reminder :\
+                    \n{}\
+                    \nThe evaluation ends the {}.",
+            project.name, students_list.join("\n"), format_local(end_date)
+        ),
+    });
 
-fn should_send_alert(conn: &DBPool, project_id: Uuid, alert_type: AlertType) -> bool {
-    match get_done_alerts_by_project_id_and_type(conn, project_id, alert_type) {
-        Ok(alerts) => alerts.is_empty(),
-        Err(_) => {
-            log_error(&format!("Failed to check existing alerts for project {}", project_id));
-            false
-        }
-    }
+    send_mail(smtp_transport, mail, MailKind::EvaluationEscalation).await
 }
 
 fn generate_student_token(config: &Config, student_id: Uuid, group_id: Uuid, stop_date: DateTime<Utc>) -> Result<String, APIError> {
@@ -483,33 +792,450 @@ fn generate_student_token(config: &Config, student_id: Uuid, group_id: Uuid, sto
     encode_token::<StudentClaims>(&claims, config)
 }
 
-fn mark_alert_as_done(conn: &DBPool, project_id: Uuid, alert_type: AlertType) {
+async fn mark_alert_as_done<R: Repositories>(repos: &R, project_id: Uuid, alert_type: AlertType, planned_at: Option<DateTime<Utc>>) {
     let new_done_alert = NewDoneAlert {
         description: None,
         project_id,
         type_: alert_type,
+        planned_at: planned_at.map(|planned_at| planned_at.naive_utc()),
     };
 
-    if let Err(e) = create_done_alert(conn, new_done_alert) {
-        log_error(&format!("Failed to create done alert for project {}: {:?}", project_id, e));
+    if let Err(e) = repos.create_done_alert(new_done_alert).await {
+        tracing::error!(%project_id, error = %e, "Failed to create done alert");
     }
 }
 
-fn update_project_state(conn: &DBPool, project_id: Uuid, state: ProjectState) {
-    let updated_project = UpdatedProject {
-        name: None,
-        description: None,
-        start_date: None,
-        end_date: None,
-        notation_period_duration: None,
-        state: Some(state),
-    };
-    if let Err(e) = update_project(&conn, project_id, updated_project) {
-        log_error(&format!("Failed to update project status: {:?}", e));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extensions::{grant_extension, revoke_extension};
+    use crate::lifecycle::{apply_project_action, ProjectAction};
+    use crate::mail::init::init_smtp_client;
+    use crate::reminders::{update_reminder_policy, ReminderPolicySource};
+    use crate::timezone::from_local;
+    use domain::repositories::projects::ProjectsRepository;
+    use chrono::Duration;
+    use domain::models::config::{Alert, MainConfig, UserConfig};
+    use domain::models::done_alerts::DoneAlert;
+    use domain::models::extensions::{NewStudentExtension, StudentExtension};
+    use domain::models::marks::Mark;
+    use domain::models::organizations::Organization;
+    use domain::models::promotions::Promotion;
+    use domain::models::promotions_members::PromotionMember;
+    use domain::models::reminder_policies::{NewProjectReminderPolicy, ReminderAnchor, ReminderOffset, ReminderRecipients};
+    use domain::models::users::{OrganizationRole, User, UserRole};
+    use infrastructure::repositories::{InMemoryData, InMemoryRepositories};
+    use infrastructure::{init_pool, PoolSettings};
+    use shared::app_config::{JwtConfig, MetricsConfig, SchedulerConfig, ServerConfig, SmtpConfig, UrlConfig};
+
+    struct TestContext {
+        app_state: AppState,
+        repos: InMemoryRepositories,
+        project: Project,
+        group: Group,
+        students: Vec<Student>,
     }
-}
 
-fn log_error(message: &str) {
-    eprintln!("Error: {}", message);
-    // TODO: Implement proper logging
-}
\ No newline at end of file
+    impl TestContext {
+        /// A project of two students graded by each other, the mails being sent to a server which refuses them.
+        fn new(end_date: DateTime<Utc>, notation_period_duration: i32, alerts: Vec<Option<Alert>>) -> Self {
+            let now = Utc::now().naive_utc();
+            let organization = Organization {
+                id: Uuid::new_v4(),
+                name: "Organization".to_string(),
+                slug: "organization".to_string(),
+                register: false,
+                authorized_domains: vec![],
+                logo_url: None,
+                primary_color: None,
+                mail_sender: None,
+                created_at: now,
+                updated_at: now,
+                timezone: "UTC".to_string(),
+            };
+            let teacher = User {
+                id: Uuid::new_v4(),
+                username: "teacher".to_string(),
+                email: "teacher@example.com".to_string(),
+                has_validated_email: true,
+                created_at: now,
+                updated_at: now,
+                role: UserRole::User,
+                token_version: 0,
+                organization_id: organization.id,
+                organization_role: OrganizationRole::Member,
+            };
+            let promotion = Promotion {
+                id: Uuid::new_v4(),
+                title: "Promotion".to_string(),
+                start_year: now.date(),
+                end_year: now.date(),
+                teacher_id: teacher.id,
+                organization_id: organization.id,
+            };
+            let project = Project {
+                id: Uuid::new_v4(),
+                name: "Project".to_string(),
+                description: None,
+                start_date: end_date - Duration::days(7),
+                end_date,
+                notation_period_duration,
+                promotion_id: promotion.id,
+                state: ProjectState::InProgress,
+                timezone: None,
+            };
+            let group = Group {
+                id: Uuid::new_v4(),
+                name: "Group".to_string(),
+                mark: Some(14.0),
+                max_mark: 20,
+                project_id: project.id,
+            };
+            let students: Vec<Student> = ["alice", "bob"].iter().map(|name| Student {
+                id: Uuid::new_v4(),
+                name: name.to_string(),
+                surname: "Test".to_string(),
+                email: format!("{}@example.com", name),
+                organization_id: organization.id,
+            }).collect();
+            let mark = |noted: &Student, grader: &Student, value: f64| Mark {
+                project_id: project.id,
+                group_id: group.id,
+                noted_student_id: noted.id,
+                grader_student_id: grader.id,
+                mark: value,
+                max_mark: 20,
+                comment: None,
+            };
+
+            let repos = InMemoryRepositories::new(InMemoryData {
+                user_configs: vec![UserConfig {
+                    id: 1,
+                    user_id: teacher.id,
+                    alerts,
+                    updated_at: now,
+                    timezone: None,
+                }],
+                promotions_members: vec![PromotionMember {
+                    promotion_id: promotion.id,
+                    user_id: teacher.id,
+                    role: PromotionRole::Owner,
+                    created_at: now,
+                }],
+                groups_students: students.iter().map(|student| GroupStudent {
+                    group_id: group.id,
+                    student_id: student.id,
+                    student_mark: None,
+                    max_mark: 20,
+                }).collect(),
+                marks: vec![mark(&students[0], &students[1], 16.0), mark(&students[1], &students[0], 10.0)],
+                users: vec![teacher],
+                organizations: vec![organization],
+                promotions: vec![promotion],
+                projects: vec![project.clone()],
+                students: students.clone(),
+                groups: vec![group.clone()],
+                ..Default::default()
+            });
+
+            let config = test_config();
+            let app_state = AppState::init(init_pool("postgres://localhost/unused"), init_smtp_client(&config), config);
+
+            TestContext { app_state, repos, project, group, students }
+        }
+
+        fn done_alerts(&self, alert_type: AlertType) -> Vec<DoneAlert> {
+            self.repos.data().done_alerts.iter()
+                .filter(|alert| alert.project_id == self.project.id && alert.type_ == alert_type)
+                .cloned()
+                .collect()
+        }
+    }
+
+    fn test_config() -> Config {
+        Config {
+            database_url: String::new(),
+            database_pool: PoolSettings::default(),
+            jwt_config: JwtConfig {
+                secret: "test-secret".to_string(),
+                expires_in: Duration::hours(1),
+                password_reset_expires_in: Duration::hours(1),
+            },
+            smtp_config: SmtpConfig {
+                host: "localhost".to_string(),
+                port: 1,
+                username: String::new(),
+                password: String::new(),
+                readiness_check: false,
+            },
+            url_config: UrlConfig {
+                web_url: "http://localhost".to_string(),
+                api_url: String::new(),
+            },
+            oidc_config: None,
+            metrics_config: MetricsConfig::default(),
+            server_config: ServerConfig::default(),
+            scheduler_config: SchedulerConfig::default(),
+            main_config: MainConfig {
+                id: 1,
+                register: false,
+                authorized_domains: vec![],
+                updated_at: Utc::now().naive_utc(),
+                audit_log_retention_days: 0,
+                password_min_length: 0,
+                password_max_length: 0,
+                password_min_lower_case: 0,
+                password_min_upper_case: 0,
+                password_min_digits: 0,
+                password_min_special_chars: 0,
+                password_forbid_common: false,
+                password_forbid_username: false,
+                password_history_size: 0,
+                password_expiry_days: 0,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_starting_alerts_issue_the_students_tokens_once() {
+        let context = TestContext::new(Utc::now(), 3, vec![]);
+
+        handle_projects_alerts(&context.app_state, &context.repos, vec![context.project.clone()]).await;
+        handle_projects_alerts(&context.app_state, &context.repos, vec![context.project.clone()]).await;
+
+        let tokens = context.repos.data().students_tokens.clone();
+        assert_eq!(tokens.len(), 2);
+        for student in &context.students {
+            assert!(tokens.iter().any(|token| token.student_id == student.id && token.project_id == context.project.id));
+        }
+        assert_eq!(context.done_alerts(AlertType::Started).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_ending_alerts_rate_the_students_and_finish_the_project() {
+        let context = TestContext::new(Utc::now() - Duration::days(3), 3, vec![]);
+
+        handle_projects_alerts(&context.app_state, &context.repos, vec![context.project.clone()]).await;
+
+        let data = context.repos.data();
+        let mark_of = |student: &Student| data.groups_students.iter()
+            .find(|group_student| group_student.group_id == context.group.id && group_student.student_id == student.id)
+            .and_then(|group_student| group_student.student_mark);
+        // Averages are 16 and 10, the student below the group average of 13 loses the difference
+        assert_eq!(mark_of(&context.students[0]), Some(14.0));
+        assert_eq!(mark_of(&context.students[1]), Some(11.0));
+        assert_eq!(data.projects[0].state, ProjectState::ResultsPublished);
+        drop(data);
+
+        assert_eq!(context.done_alerts(AlertType::Finished).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_reopened_evaluation_renews_the_tokens_of_the_students_who_did_not_evaluate() {
+        let context = TestContext::new(Utc::now(), 3, vec![]);
+        handle_projects_alerts(&context.app_state, &context.repos, vec![context.project.clone()]).await;
+        let tokens = context.repos.data().students_tokens.clone();
+        let token_of = |student: &Student| tokens.iter().find(|token| token.student_id == student.id).unwrap().clone();
+        let (used_token, unused_token) = (token_of(&context.students[0]), token_of(&context.students[1]));
+        context.repos.data().students_tokens.iter_mut()
+            .filter(|token| token.id == used_token.id)
+            .for_each(|token| token.used = true);
+
+        let teacher_id = context.repos.data().users[0].id;
+        for action in [ProjectAction::Close, ProjectAction::Reopen { days: 2 }] {
+            let project = context.repos.get_project_by_id(context.project.id).await.unwrap();
+            apply_project_action(&context.repos, &project, action, teacher_id).await.unwrap();
+        }
+        let project = context.repos.get_project_by_id(context.project.id).await.unwrap();
+        handle_projects_alerts(&context.app_state, &context.repos, vec![project]).await;
+
+        let data = context.repos.data();
+        assert_eq!(data.projects[0].state, ProjectState::EvaluationOpen);
+        assert_eq!(data.students_tokens.len(), 2);
+        let renewed_token = data.students_tokens.iter().find(|token| token.id == unused_token.id).unwrap();
+        assert_ne!(renewed_token.token, unused_token.token);
+        let kept_token = data.students_tokens.iter().find(|token| token.id == used_token.id).unwrap();
+        assert_eq!(kept_token.token, used_token.token);
+        drop(data);
+
+        assert_eq!(context.done_alerts(AlertType::Started).len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_projects_out_of_their_notation_period_are_ignored() {
+        let context = TestContext::new(Utc::now() + Duration::days(2), 3, vec![]);
+
+        handle_projects_alerts(&context.app_state, &context.repos, vec![context.project.clone()]).await;
+
+        let data = context.repos.data();
+        assert!(data.students_tokens.is_empty());
+        assert!(data.done_alerts.is_empty());
+        assert_eq!(data.projects[0].state, ProjectState::InProgress);
+    }
+
+    #[tokio::test]
+    async fn test_deadlines_are_evaluated_in_the_project_timezone() {
+        // Far east and far west of UTC, the local day differs from the UTC one for most of the day
+        for timezone in [Tz::Pacific__Kiritimati, Tz::Pacific__Pago_Pago] {
+            let local_midnight = Utc::now().with_timezone(&timezone).date_naive().and_hms_opt(0, 0, 0).unwrap();
+            let mut context = TestContext::new(from_local(timezone, local_midnight), 3, vec![]);
+            context.project.timezone = Some(timezone.name().to_string());
+
+            handle_projects_alerts(&context.app_state, &context.repos, vec![context.project.clone()]).await;
+
+            assert_eq!(context.done_alerts(AlertType::Started).len(), 1, "{}", timezone);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_reminders_skip_the_sent_ones() {
+        let alerts = vec![
+            Some(Alert { before_event: true, hours: 2 }),
+            Some(Alert { before_event: true, hours: 4 }),
+        ];
+        let context = TestContext::new(Utc::now() - Duration::days(1), 3, alerts);
+        context.repos.data().projects[0].state = ProjectState::EvaluationOpen;
+
+        let reminders = get_scheduled_reminders(&context.repos).await.unwrap();
+        assert_eq!(reminders.len(), 2);
+        assert!(reminders[0].send_at < reminders[1].send_at);
+        assert!(reminders.iter().all(|reminder| reminder.project_id == context.project.id));
+
+        context.repos.data().done_alerts.push(DoneAlert {
+            id: 1,
+            description: None,
+            project_id: context.project.id,
+            type_: AlertType::Pending,
+            published_at: reminders[0].send_at.naive_utc(),
+            planned_at: None,
+        });
+
+        let reminders_left = get_scheduled_reminders(&context.repos).await.unwrap();
+        assert_eq!(reminders_left.len(), 1);
+        assert_eq!(reminders_left[0].send_at, reminders[1].send_at);
+
+        // A reminder sent late is still recognized by the time it was due
+        context.repos.data().done_alerts.push(DoneAlert {
+            id: 2,
+            description: None,
+            project_id: context.project.id,
+            type_: AlertType::Pending,
+            published_at: (reminders[1].send_at + Duration::hours(3)).naive_utc(),
+            planned_at: Some(reminders[1].send_at.naive_utc()),
+        });
+        assert!(get_scheduled_reminders(&context.repos).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reminders_due_before_the_policy_are_skipped() {
+        let context = TestContext::new(Utc::now() - Duration::days(1), 3, vec![]);
+        context.repos.data().projects[0].state = ProjectState::EvaluationOpen;
+        let calendar = get_project_calendar(&context.repos, &context.project).await.unwrap();
+
+        update_reminder_policy(&context.repos, &context.project, Some(NewProjectReminderPolicy {
+            project_id: context.project.id,
+            offsets: vec![
+                // Due since the evaluation opened the day before
+                ReminderOffset { anchor: ReminderAnchor::EvaluationOpen, hours: 1 },
+                ReminderOffset { anchor: ReminderAnchor::EvaluationClose, hours: 6 },
+            ],
+            recipients: ReminderRecipients::Both,
+            escalate_to_teacher: false,
+        })).await.unwrap();
+
+        let reminders = get_pending_reminders(&context.repos, &context.project, &calendar).await.unwrap();
+        let send_times: Vec<DateTime<Utc>> = reminders.iter().map(|reminder| reminder.send_at).collect();
+        assert_eq!(send_times, vec![(calendar.evaluation_end - Duration::hours(6)).with_timezone(&Utc)]);
+    }
+
+    #[tokio::test]
+    async fn test_project_reminder_policy_overrides_the_teacher_alerts() {
+        let alerts = vec![Some(Alert { before_event: true, hours: 2 })];
+        let context = TestContext::new(Utc::now() - Duration::days(1), 3, alerts);
+        context.repos.data().projects[0].state = ProjectState::EvaluationOpen;
+        let calendar = get_project_calendar(&context.repos, &context.project).await.unwrap();
+
+        let schedule = update_reminder_policy(&context.repos, &context.project, Some(NewProjectReminderPolicy {
+            project_id: context.project.id,
+            offsets: vec![
+                ReminderOffset { anchor: ReminderAnchor::EvaluationClose, hours: 24 },
+                ReminderOffset { anchor: ReminderAnchor::EvaluationClose, hours: 6 },
+            ],
+            recipients: ReminderRecipients::Students,
+            escalate_to_teacher: true,
+        })).await.unwrap();
+
+        assert_eq!(schedule.policy.source, ReminderPolicySource::Project);
+        let send_times: Vec<DateTime<Utc>> = schedule.reminders.iter().map(|reminder| reminder.reminder.send_at).collect();
+        assert_eq!(send_times, vec![
+            (calendar.evaluation_end - Duration::hours(24)).with_timezone(&Utc),
+            (calendar.evaluation_end - Duration::hours(6)).with_timezone(&Utc),
+        ]);
+        assert!(schedule.reminders[1].reminder.escalates);
+        assert_eq!(get_scheduled_reminders(&context.repos).await.unwrap().len(), 2);
+
+        // Without its policy, the project follows the alerts of its teacher again
+        let schedule = update_reminder_policy(&context.repos, &context.project, None).await.unwrap();
+        assert_eq!(schedule.policy.source, ReminderPolicySource::Teacher);
+        assert_eq!(schedule.reminders.len(), 1);
+        assert!(!schedule.reminders[0].reminder.escalates);
+    }
+
+    #[tokio::test]
+    async fn test_extended_students_get_a_renewed_token_once() {
+        let context = TestContext::new(Utc::now(), 3, vec![]);
+        handle_projects_alerts(&context.app_state, &context.repos, vec![context.project.clone()]).await;
+        let tokens = context.repos.data().students_tokens.clone();
+        let token_of = |tokens: &[StudentToken], student: &Student| tokens.iter().find(|token| token.student_id == student.id).unwrap().clone();
+
+        let project = context.repos.get_project_by_id(context.project.id).await.unwrap();
+        grant_extension(&context.repos, &project, NewStudentExtension {
+            project_id: project.id,
+            student_id: context.students[1].id,
+            extended_until: Utc::now() + Duration::days(5),
+            reason: Some("Sick".to_string()),
+            granted_by: None,
+        }).await.unwrap();
+        handle_projects_alerts(&context.app_state, &context.repos, vec![project.clone()]).await;
+
+        let renewed_tokens = context.repos.data().students_tokens.clone();
+        assert_eq!(token_of(&renewed_tokens, &context.students[0]).token, token_of(&tokens, &context.students[0]).token);
+        let renewed_token = token_of(&renewed_tokens, &context.students[1]);
+        assert_eq!(renewed_token.id, token_of(&tokens, &context.students[1]).id);
+        assert_ne!(renewed_token.token, token_of(&tokens, &context.students[1]).token);
+        assert!(context.repos.data().student_extensions[0].notified_at.is_some());
+
+        // The next runs leave the token alone
+        handle_projects_alerts(&context.app_state, &context.repos, vec![project]).await;
+        assert_eq!(token_of(&context.repos.data().students_tokens, &context.students[1]).token, renewed_token.token);
+    }
+
+    #[tokio::test]
+    async fn test_results_wait_for_the_extensions() {
+        let context = TestContext::new(Utc::now() - Duration::days(3), 3, vec![]);
+        let late_student = context.students[1].clone();
+        context.repos.data().marks.retain(|mark| mark.grader_student_id != late_student.id);
+        context.repos.data().student_extensions.push(StudentExtension {
+            project_id: context.project.id,
+            student_id: late_student.id,
+            extended_until: Utc::now() + Duration::days(2),
+            reason: None,
+            granted_by: None,
+            created_at: Utc::now(),
+            notified_at: None,
+        });
+
+        handle_projects_alerts(&context.app_state, &context.repos, vec![context.project.clone()]).await;
+
+        assert_eq!(context.repos.data().projects[0].state, ProjectState::EvaluationClosed);
+        assert!(context.done_alerts(AlertType::Finished).is_empty());
+
+        // Without the extension, the results are computed at the next run
+        revoke_extension(&context.repos, context.project.id, late_student.id).await.unwrap();
+        let project = context.repos.get_project_by_id(context.project.id).await.unwrap();
+        handle_projects_alerts(&context.app_state, &context.repos, vec![project]).await;
+
+        assert_eq!(context.repos.data().projects[0].state, ProjectState::ResultsPublished);
+        assert_eq!(context.done_alerts(AlertType::Finished).len(), 1);
+    }
+}
@@ -1,98 +1,96 @@
-use crate::database::projects::{get_current_projects, update_project};
+use crate::audit::log::purge_expired_audit_logs;
+use crate::database::oidc_login_states::delete_expired_oidc_login_states;
+use crate::database::projects::get_current_projects;
+use crate::database::tokens::delete_expired_tokens;
+use crate::metrics::observe_scheduler_run;
 use crate::scheduler::handler::handle_projects_alerts;
-use domain::models::projects::{Project, ProjectState, UpdatedProject};
+use chrono::Utc;
+use domain::repositories::projects::ProjectsRepository;
 use shared::app_state_model::AppState;
 use shared::error_models::DBError;
 use std::sync::Arc;
-use tokio_js_set_interval;
-use tokio_js_set_interval::set_interval;
+use std::time::Instant;
+use tokio::task::JoinHandle;
+use tracing::Instrument;
+use uuid::Uuid;
 
-pub fn init_projects_check(app_state: &AppState) -> u64 {
-    let database_pool = Arc::clone(&app_state.database_pool);
-    let smtp_transport = Arc::clone(&app_state.smtp_transport);
-    let config = Arc::clone(&app_state.config);
-    let runtime_values = Arc::clone(&app_state.runtime_values);
-    let interval = 60*60*24*1000;
+/// Runs the checks once, then at each time of the schedule of the configuration.
+pub async fn init_projects_check(app_state: &AppState) -> JoinHandle<()> {
+    run_scheduled_checks(app_state).await;
 
-    let projects = check_projects(&app_state);
-    match projects {
-        Ok(projects) => handle_projects_alerts(&app_state, projects),
-        Err(e) => eprintln!("Error while checking projects: {:?}", e),
-    }
+    let app_state = AppState {
+        database_pool: Arc::clone(&app_state.database_pool),
+        smtp_transport: Arc::clone(&app_state.smtp_transport),
+        config: Arc::clone(&app_state.config),
+        runtime_values: Arc::clone(&app_state.runtime_values),
+    };
 
-    // Create a new interval that runs every 24 hours
-    set_interval!(move || {
-        let database_pool = Arc::clone(&database_pool);
-        let smtp_transport = Arc::clone(&smtp_transport);
-        let config = Arc::clone(&config);
-        let runtime_values = Arc::clone(&runtime_values);
+    tokio::spawn(async move {
+        loop {
+            let schedule = app_state.config.read().scheduler_config.schedule.clone();
+            let next_run = schedule.next_after(Utc::now());
+            app_state.set_scheduler_next_run(next_run);
 
-        tokio::spawn(async move {
-            let app_state = AppState {
-                database_pool,
-                smtp_transport,
-                config,
-                runtime_values,
+            let Some(next_run) = next_run else {
+                tracing::warn!(schedule = %schedule, "the schedule of the scheduler never runs");
+                return;
             };
-
-            let projects = check_projects(&app_state);
-            match projects {
-                Ok(projects) => handle_projects_alerts(&app_state, projects),
-                Err(e) => eprintln!("Error while checking projects: {:?}", e),
-            }
-        });
-    }, interval)
+            tokio::time::sleep((next_run - Utc::now()).to_std().unwrap_or_default()).await;
+            run_scheduled_checks(&app_state).await;
+        }
+    })
 }
 
-fn check_projects(app_state: &AppState) -> Result<Vec<Project>, DBError> {
-    let conn = app_state.database_pool.clone().as_ref().clone();
-    let current_projects_lists = app_state.get_all_project_ids();
-    let projects = get_current_projects(&conn)?;
-    let mut sorted_projects: Vec<Project> = Vec::new();
+/// Runs the checks of the scheduler, traced in a span identifying the run.
+pub async fn run_scheduled_checks(app_state: &AppState) {
+    let span = tracing::info_span!("scheduler_run", run_id = %Uuid::new_v4());
+    async {
+        let _guard = app_state.lock_scheduler().await;
+        tracing::info!("scheduler run started");
+        let start = Instant::now();
 
-    // Update project state if needed
-    for project in projects.iter().clone() {
-        let now = chrono::Utc::now().date_naive();
-        let mut state: ProjectState = project.state.clone();
+        let succeeded = match get_current_projects(&app_state.database_pool).await {
+            Ok(projects) => {
+                handle_projects_alerts(app_state, &app_state.repositories(), projects).await;
+                true
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "failed to get the current projects");
+                false
+            }
+        };
+        purge_expired_records(app_state).await;
 
-        // Check dates
-        if project.start_date.date() <= now && project.end_date.date() > now {
-            state = ProjectState::InProgress;
-        } else if project.end_date.date() == now {
-            state = ProjectState::Finished;
-        }
+        observe_scheduler_run(succeeded, start.elapsed());
+        app_state.record_scheduler_heartbeat();
+        tracing::info!("scheduler run completed");
+    }.instrument(span).await
+}
 
-        // Update project in DB
-        let updated_project = UpdatedProject {
-            name: None,
-            description: None,
-            start_date: None,
-            end_date: None,
-            notation_period_duration: None,
-            state: Some(state),
-        };
-        let _ = update_project(&conn, project.id, updated_project);
-        // TODO : Add logging
+/// Runs the checks of the scheduler on a single project, whether it's current or not.
+pub async fn run_project_check(app_state: &AppState, project_id: Uuid) -> Result<(), DBError> {
+    let span = tracing::info_span!("scheduler_run", run_id = %Uuid::new_v4(), project_id = %project_id);
+    async {
+        let _guard = app_state.lock_scheduler().await;
+        let repos = app_state.repositories();
+        let project = repos.get_project_by_id(project_id).await?;
 
-        // Add modified value
-        if state == ProjectState::NotStarted || state == ProjectState::InProgress { continue; }
-        sorted_projects.push(Project {
-            id: project.id,
-            name: project.name.clone(),
-            description: project.description.clone(),
-            start_date: project.start_date.clone(),
-            end_date: project.end_date.clone(),
-            notation_period_duration: project.notation_period_duration.clone(),
-            promotion_id: project.promotion_id,
-            state,
-        });
-    }
+        tracing::info!("project check started");
+        handle_projects_alerts(app_state, &repos, vec![project]).await;
+        tracing::info!("project check completed");
+        Ok(())
+    }.instrument(span).await
+}
 
-    // Return all projects that are not in the current_projects_list
-    let next_projects = projects.into_iter().filter(|project| {
-        !current_projects_lists.contains(&project.id)
-    })
-        .collect();
+async fn purge_expired_records(app_state: &AppState) {
+    let retention_days = app_state.config.read().main_config.audit_log_retention_days;
+    purge_expired_audit_logs(&app_state.database_pool, retention_days).await;
 
-    Ok(next_projects)
-}
\ No newline at end of file
+    // Abandoned OIDC logins are never consumed
+    if let Err(e) = delete_expired_oidc_login_states(&app_state.database_pool).await {
+        tracing::error!(error = %e, "failed to purge the OIDC login states");
+    }
+    if let Err(e) = delete_expired_tokens(&app_state.database_pool).await {
+        tracing::error!(error = %e, "failed to purge the tokens");
+    }
+}
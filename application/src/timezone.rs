@@ -0,0 +1,151 @@
+//! Timezones the dates of the projects are entered, evaluated and displayed in.
+//!
+//! A project uses its own timezone, else the one of the teacher of its promotion, else the one of its
+//! organization. The dates are stored as instants, and turned into days and times of that timezone to
+//! decide when the evaluation begins and ends, so a project ending at midnight ends at midnight on the
+//! clocks of its students whatever the daylight saving time.
+
+use chrono::{DateTime, Duration, LocalResult, NaiveDate, NaiveDateTime, TimeZone, Utc};
+pub use chrono_tz::Tz;
+use domain::models::projects::Project;
+use domain::repositories::promotions::PromotionsRepository;
+use shared::error_models::DBError;
+use uuid::Uuid;
+
+/// Timezone of the organizations which haven't chosen one
+pub const DEFAULT_TIMEZONE: Tz = Tz::UTC;
+
+/// Parses the IANA name of a timezone, such as `Europe/Paris`.
+pub fn parse_timezone(name: &str) -> Option<Tz> {
+    name.parse().ok()
+}
+
+/// Parses a stored timezone, falling back to the default one if it's unknown.
+pub fn resolve_timezone(name: &str) -> Tz {
+    parse_timezone(name).unwrap_or_else(|| {
+        tracing::warn!(timezone = name, "unknown timezone, using the default one");
+        DEFAULT_TIMEZONE
+    })
+}
+
+/// Returns the timezone of the projects of a promotion which don't have their own.
+pub async fn get_promotion_timezone<R: PromotionsRepository>(repos: &R, promotion_id: Uuid) -> Result<Tz, DBError> {
+    Ok(resolve_timezone(&repos.get_promotion_timezone(promotion_id).await?))
+}
+
+pub async fn get_project_timezone<R: PromotionsRepository>(repos: &R, project: &Project) -> Result<Tz, DBError> {
+    match &project.timezone {
+        Some(timezone) => Ok(resolve_timezone(timezone)),
+        None => get_promotion_timezone(repos, project.promotion_id).await,
+    }
+}
+
+pub async fn get_project_calendar<R: PromotionsRepository>(repos: &R, project: &Project) -> Result<ProjectCalendar, DBError> {
+    Ok(ProjectCalendar::new(project, get_project_timezone(repos, project).await?))
+}
+
+/// Returns the instant a date and time of the timezone's clocks refer to.
+///
+/// The times skipped when the clocks go forward are moved after the gap, the repeated ones when they go
+/// back are the first.
+pub fn from_local(timezone: Tz, local: NaiveDateTime) -> DateTime<Utc> {
+    let zoned = match timezone.from_local_datetime(&local) {
+        LocalResult::Single(date) | LocalResult::Ambiguous(date, _) => date,
+        LocalResult::None => timezone.from_local_datetime(&(local + Duration::hours(1))).earliest()
+            .unwrap_or_else(|| timezone.from_utc_datetime(&local)),
+    };
+
+    zoned.with_timezone(&Utc)
+}
+
+/// Formats a date for the emails, e.g. `2024-06-01 00:00 CEST`.
+pub fn format_local(date: &DateTime<Tz>) -> String {
+    date.format("%Y-%m-%d %H:%M %Z").to_string()
+}
+
+/// Dates of a project, in its timezone.
+#[derive(Debug, Clone, Copy)]
+pub struct ProjectCalendar {
+    pub timezone: Tz,
+    pub start: DateTime<Tz>,
+    /// End of the project, when its evaluation begins
+    pub end: DateTime<Tz>,
+    /// End of the evaluation, at the same time of day as the end of the project
+    pub evaluation_end: DateTime<Tz>,
+}
+
+impl ProjectCalendar {
+    pub fn new(project: &Project, timezone: Tz) -> Self {
+        let end = project.end_date.with_timezone(&timezone);
+        let evaluation_end = from_local(timezone, end.naive_local() + Duration::days(project.notation_period_duration as i64));
+
+        ProjectCalendar {
+            timezone,
+            start: project.start_date.with_timezone(&timezone),
+            end,
+            evaluation_end: evaluation_end.with_timezone(&timezone),
+        }
+    }
+
+    /// Returns the current day in the timezone of the project.
+    pub fn today(&self) -> NaiveDate {
+        Utc::now().with_timezone(&self.timezone).date_naive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain::models::projects::ProjectState;
+
+    fn local(date: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn utc(date: &str) -> DateTime<Utc> {
+        local(date).and_utc()
+    }
+
+    fn project(end_date: DateTime<Utc>, notation_period_duration: i32) -> Project {
+        Project {
+            id: Uuid::new_v4(),
+            name: "Project".to_string(),
+            description: None,
+            start_date: end_date - Duration::days(7),
+            end_date,
+            notation_period_duration,
+            promotion_id: Uuid::new_v4(),
+            state: ProjectState::InProgress,
+            timezone: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_timezone() {
+        assert_eq!(parse_timezone("Europe/Paris"), Some(Tz::Europe__Paris));
+        assert_eq!(parse_timezone("Mars/Olympus_Mons"), None);
+        assert_eq!(resolve_timezone("Mars/Olympus_Mons"), DEFAULT_TIMEZONE);
+    }
+
+    #[test]
+    fn test_from_local() {
+        let paris = Tz::Europe__Paris;
+        assert_eq!(from_local(paris, local("2024-01-15 00:00")), utc("2024-01-14 23:00"));
+        assert_eq!(from_local(paris, local("2024-07-15 00:00")), utc("2024-07-14 22:00"));
+        // 02:30 doesn't exist on the day the clocks go forward, and exists twice when they go back
+        assert_eq!(from_local(paris, local("2024-03-31 02:30")), utc("2024-03-31 01:30"));
+        assert_eq!(from_local(paris, local("2024-10-27 02:30")), utc("2024-10-27 00:30"));
+    }
+
+    #[test]
+    fn test_the_evaluation_ends_at_the_same_local_time_across_daylight_saving_time() {
+        // Midnight in Paris, the night before the clocks go forward
+        let project = project(utc("2024-03-29 23:00"), 3);
+
+        let calendar = ProjectCalendar::new(&project, Tz::Europe__Paris);
+
+        assert_eq!(calendar.end.date_naive(), NaiveDate::from_ymd_opt(2024, 3, 30).unwrap());
+        assert_eq!(calendar.evaluation_end.with_timezone(&Utc), utc("2024-04-01 22:00"));
+        assert_eq!(format_local(&calendar.evaluation_end), "2024-04-02 00:00 CEST");
+    }
+}
@@ -1,2 +1,3 @@
 pub mod models;
+pub mod repositories;
 pub mod schema;
\ No newline at end of file
@@ -0,0 +1,58 @@
+use chrono::NaiveDateTime;
+use diesel::{Identifiable, Insertable, Queryable, Selectable};
+use diesel_derive_enum::DbEnum;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, DbEnum, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[ExistingTypePath = "crate::schema::sql_types::AuditAction"]
+pub enum AuditAction {
+    #[db_rename = "group_updated"]
+    GroupUpdated,
+    #[db_rename = "student_deleted"]
+    StudentDeleted,
+    #[db_rename = "main_config_updated"]
+    MainConfigUpdated,
+    #[db_rename = "password_reset_requested"]
+    PasswordResetRequested,
+    #[db_rename = "password_reset"]
+    PasswordReset,
+    #[db_rename = "password_changed"]
+    PasswordChanged,
+    #[db_rename = "email_changed"]
+    EmailChanged,
+    #[db_rename = "account_deleted"]
+    AccountDeleted,
+}
+
+#[derive(Debug, Serialize, Queryable, Identifiable, Selectable, ToSchema)]
+#[diesel(table_name = crate::schema::audit_logs)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct AuditLog {
+    pub id: Uuid,
+    pub actor_id: Option<Uuid>,
+    pub action: AuditAction,
+    pub resource_type: String,
+    pub resource_id: Option<String>,
+    #[schema(value_type = Option<Object>)]
+    pub before: Option<Value>,
+    #[schema(value_type = Option<Object>)]
+    pub after: Option<Value>,
+    pub ip: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::audit_logs)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewAuditLog {
+    pub actor_id: Option<Uuid>,
+    pub action: AuditAction,
+    pub resource_type: String,
+    pub resource_id: Option<String>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    pub ip: Option<String>,
+}
@@ -10,9 +10,47 @@ use uuid::Uuid;
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct MainConfig {
     pub id: i32,
+    /// Only used to seed the default organization, each organization allows its own registration
     pub register: bool,
     pub authorized_domains: Vec<Option<String>>,
-    pub updated_at: NaiveDateTime
+    pub updated_at: NaiveDateTime,
+    pub audit_log_retention_days: i32,
+    pub password_min_length: i32,
+    pub password_max_length: i32,
+    pub password_min_lower_case: i32,
+    pub password_min_upper_case: i32,
+    pub password_min_digits: i32,
+    pub password_min_special_chars: i32,
+    /// Reject the passwords of the bundled common passwords list
+    pub password_forbid_common: bool,
+    pub password_forbid_username: bool,
+    /// Number of previous passwords that can't be reused, 0 to allow any
+    pub password_history_size: i32,
+    /// Days before a password has to be changed, 0 to never expire
+    pub password_expiry_days: i32,
+}
+
+/// Defaults of the table, used until the configuration is read from the database.
+impl Default for MainConfig {
+    fn default() -> Self {
+        MainConfig {
+            id: 1,
+            register: true,
+            authorized_domains: vec![],
+            updated_at: chrono::Utc::now().naive_utc(),
+            audit_log_retention_days: 365,
+            password_min_length: 8,
+            password_max_length: 64,
+            password_min_lower_case: 1,
+            password_min_upper_case: 1,
+            password_min_digits: 1,
+            password_min_special_chars: 1,
+            password_forbid_common: true,
+            password_forbid_username: true,
+            password_history_size: 5,
+            password_expiry_days: 0,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Insertable)]
@@ -29,6 +67,17 @@ pub struct NewMainConfig {
 pub struct UpdatedMainConfig {
     pub register: Option<bool>,
     pub authorized_domains: Option<Vec<String>>,
+    pub audit_log_retention_days: Option<i32>,
+    pub password_min_length: Option<i32>,
+    pub password_max_length: Option<i32>,
+    pub password_min_lower_case: Option<i32>,
+    pub password_min_upper_case: Option<i32>,
+    pub password_min_digits: Option<i32>,
+    pub password_min_special_chars: Option<i32>,
+    pub password_forbid_common: Option<bool>,
+    pub password_forbid_username: Option<bool>,
+    pub password_history_size: Option<i32>,
+    pub password_expiry_days: Option<i32>,
 }
 
 // #[sql_type(crate::schema::sql_types::alert)]
@@ -38,14 +87,16 @@ pub struct Alert {
     pub hours: i8,
 }
 
-#[derive(Serialize, Queryable, Identifiable, Selectable, ToSchema)]
+#[derive(Clone, Serialize, Queryable, Identifiable, Selectable, ToSchema)]
 #[diesel(table_name = crate::schema::user_config)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct UserConfig {
     pub id: i32,
     pub user_id: Uuid,
     pub alerts: Vec<Option<Alert>>,
-    pub updated_at: NaiveDateTime
+    pub updated_at: NaiveDateTime,
+    /// Timezone of the projects of the user, unless they have their own
+    pub timezone: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Insertable, ToSchema)]
@@ -61,4 +112,6 @@ pub struct NewUserConfig {
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct UpdatedUserConfig {
     pub alerts: Option<Vec<Alert>>,
+    /// IANA name of the timezone, such as `Europe/Paris`
+    pub timezone: Option<String>,
 }
\ No newline at end of file
@@ -24,6 +24,8 @@ pub struct DoneAlert {
     #[serde(rename = "type")]
     pub type_: AlertType,
     pub published_at: chrono::NaiveDateTime,
+    /// When the reminder was due, for the pending alerts
+    pub planned_at: Option<chrono::NaiveDateTime>,
 }
 
 #[derive(Debug, Deserialize, Insertable)]
@@ -34,4 +36,5 @@ pub struct NewDoneAlert {
     pub project_id: Uuid,
     #[serde(rename = "type")]
     pub type_: AlertType,
+    pub planned_at: Option<chrono::NaiveDateTime>,
 }
\ No newline at end of file
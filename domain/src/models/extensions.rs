@@ -0,0 +1,57 @@
+use chrono::{DateTime, Utc};
+use diesel::{AsChangeset, Identifiable, Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Later deadline granted to a student of a project, such as a sick one.
+#[derive(Debug, Clone, Serialize, Queryable, Identifiable, Selectable, ToSchema)]
+#[diesel(table_name = crate::schema::student_extensions)]
+#[diesel(primary_key(project_id, student_id))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct StudentExtension {
+    pub project_id: Uuid,
+    pub student_id: Uuid,
+    pub extended_until: DateTime<Utc>,
+    pub reason: Option<String>,
+    /// Teacher who granted the extension
+    pub granted_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    /// When the student got their renewed token, none until the scheduler sends it
+    pub notified_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Insertable, AsChangeset)]
+#[diesel(table_name = crate::schema::student_extensions)]
+#[diesel(treat_none_as_null = true)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewStudentExtension {
+    pub project_id: Uuid,
+    pub student_id: Uuid,
+    pub extended_until: DateTime<Utc>,
+    pub reason: Option<String>,
+    pub granted_by: Option<Uuid>,
+}
+
+/// Submissions a project accepts after the deadline of its students, flagged as late.
+#[derive(Debug, Clone, Serialize, Queryable, Identifiable, Selectable, ToSchema)]
+#[diesel(table_name = crate::schema::project_late_policies)]
+#[diesel(primary_key(project_id))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ProjectLatePolicy {
+    pub project_id: Uuid,
+    /// Hours the submissions are accepted after the deadline
+    pub late_hours: i32,
+    /// Percentage removed from the marks of the students who submitted late
+    pub penalty_percent: f64,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize, Insertable, AsChangeset)]
+#[diesel(table_name = crate::schema::project_late_policies)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewProjectLatePolicy {
+    pub project_id: Uuid,
+    pub late_hours: i32,
+    pub penalty_percent: f64,
+}
@@ -32,7 +32,7 @@ pub struct UpdatedGroup {
     pub max_mark: Option<i32>,
 }
 
-#[derive(Debug, Serialize, Queryable, Selectable, Identifiable, ToSchema)]
+#[derive(Debug, Clone, Serialize, Queryable, Selectable, Identifiable, ToSchema)]
 #[diesel(table_name = crate::schema::groups_students)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[diesel(primary_key(group_id, student_id))]
@@ -2,7 +2,7 @@ use diesel::{Identifiable, Insertable, Queryable, Selectable};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[derive(Debug, Serialize, Queryable, Identifiable, Selectable)]
+#[derive(Debug, Clone, Serialize, Queryable, Identifiable, Selectable)]
 #[diesel(table_name = crate::schema::marks)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[diesel(primary_key(group_id, noted_student_id, grader_student_id))]
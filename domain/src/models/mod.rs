@@ -1,12 +1,21 @@
+pub mod organizations;
 pub mod users;
+pub mod users_identities;
+pub mod api_keys;
 pub mod user_passwords;
+pub mod password_history;
 pub mod promotions;
+pub mod promotions_members;
 pub mod tokens;
+pub mod audit_logs;
 pub mod config;
 pub mod students;
 pub mod projects;
+pub mod reminder_policies;
+pub mod extensions;
 pub mod groups;
 pub mod marks;
 pub mod students_tokens;
 pub mod done_alerts;
-pub mod mfa_codes;
\ No newline at end of file
+pub mod mfa_codes;
+pub mod oidc_login_states;
\ No newline at end of file
@@ -0,0 +1,25 @@
+use chrono::NaiveDateTime;
+use diesel::{Identifiable, Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Pending OIDC login, kept between the redirection to the identity provider and its callback.
+#[derive(Debug, Serialize, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = crate::schema::oidc_login_states)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct OidcLoginState {
+    pub id: Uuid,
+    pub state: String,
+    pub code_verifier: String,
+    pub organization_id: Uuid,
+    pub exp: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize, Insertable)]
+#[diesel(table_name = crate::schema::oidc_login_states)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewOidcLoginState {
+    pub state: String,
+    pub code_verifier: String,
+    pub organization_id: Uuid,
+}
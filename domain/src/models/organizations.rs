@@ -0,0 +1,60 @@
+use chrono::NaiveDateTime;
+use diesel::{AsChangeset, Identifiable, Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Queryable, Identifiable, Selectable, ToSchema, Clone)]
+#[diesel(table_name = crate::schema::organizations)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Organization {
+    pub id: Uuid,
+    pub name: String,
+    pub slug: String,
+    pub register: bool,
+    pub authorized_domains: Vec<Option<String>>,
+    pub logo_url: Option<String>,
+    pub primary_color: Option<String>,
+    pub mail_sender: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    /// Timezone of the projects of the organization, unless their teacher or themselves have one
+    pub timezone: String,
+}
+
+#[derive(Debug, Deserialize, Insertable)]
+#[diesel(table_name = crate::schema::organizations)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewOrganization {
+    pub name: String,
+    pub slug: String,
+    pub register: Option<bool>,
+    pub authorized_domains: Option<Vec<String>>,
+    pub logo_url: Option<String>,
+    pub primary_color: Option<String>,
+    pub mail_sender: Option<String>,
+}
+
+#[derive(Debug, Deserialize, AsChangeset, ToSchema)]
+#[diesel(table_name = crate::schema::organizations)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct UpdatedOrganization {
+    pub name: Option<String>,
+    pub register: Option<bool>,
+    pub authorized_domains: Option<Vec<String>>,
+    pub logo_url: Option<String>,
+    pub primary_color: Option<String>,
+    pub mail_sender: Option<String>,
+    pub timezone: Option<String>,
+}
+
+/// Public part of an organization, displayed on the login and register pages.
+#[derive(Debug, Serialize, Queryable, Selectable, ToSchema)]
+#[diesel(table_name = crate::schema::organizations)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct OrganizationBranding {
+    pub name: String,
+    pub slug: String,
+    pub logo_url: Option<String>,
+    pub primary_color: Option<String>,
+}
@@ -0,0 +1,23 @@
+use chrono::NaiveDateTime;
+use diesel::{Identifiable, Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Hash of a previous password of a user.
+#[derive(Debug, Deserialize, Queryable, Selectable, Identifiable)]
+#[diesel(table_name = crate::schema::password_history)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct PasswordHistory {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub password: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Insertable)]
+#[diesel(table_name = crate::schema::password_history)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewPasswordHistory {
+    pub user_id: Uuid,
+    pub password: String,
+}
@@ -1,35 +1,76 @@
-use chrono::NaiveDateTime;
+use chrono::{DateTime, Utc};
 use diesel::{AsChangeset, Identifiable, Insertable, Queryable, Selectable};
 use diesel_derive_enum::DbEnum;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use uuid::Uuid;
 
+/// Step of the lifecycle of a project, the steps being ordered as they follow each other.
 #[derive(Debug, Serialize, Deserialize, DbEnum, Clone, Copy, PartialEq, Eq, Ord, PartialOrd, ToSchema)]
 #[ExistingTypePath = "crate::schema::sql_types::ProjectState"]
 pub enum ProjectState {
-    #[db_rename = "not-started"]
-    NotStarted,
+    /// Being prepared, ignored by the scheduler until it's scheduled
+    #[db_rename = "draft"]
+    Draft,
+    #[db_rename = "scheduled"]
+    Scheduled,
     #[db_rename = "in-progress"]
     InProgress,
-    #[db_rename = "finished"]
-    Finished,
-    #[db_rename = "notation-finished"]
-    NotationFinished,
+    /// The students evaluate each other
+    #[db_rename = "evaluation-open"]
+    EvaluationOpen,
+    /// The evaluation is over, the marks of the students are not computed yet
+    #[db_rename = "evaluation-closed"]
+    EvaluationClosed,
+    #[db_rename = "results-published"]
+    ResultsPublished,
+    #[db_rename = "archived"]
+    Archived,
 }
 
-#[derive(Debug, Serialize, Queryable, Identifiable, Selectable, ToSchema)]
+impl ProjectState {
+    /// States of the projects handled by the scheduler
+    pub const ACTIVE: [ProjectState; 4] = [ProjectState::Scheduled, ProjectState::InProgress, ProjectState::EvaluationOpen, ProjectState::EvaluationClosed];
+
+    /// Returns whether a project can go from this state to another one.
+    ///
+    /// A project follows its lifecycle one step at a time, except that its evaluation can be reopened once closed.
+    pub fn can_transition_to(self, state: ProjectState) -> bool {
+        use ProjectState::*;
+
+        matches!(
+            (self, state),
+            (Draft, Scheduled)
+                | (Scheduled, InProgress)
+                | (InProgress, EvaluationOpen)
+                | (EvaluationOpen, EvaluationClosed)
+                | (EvaluationClosed, EvaluationOpen)
+                | (EvaluationClosed, ResultsPublished)
+                | (ResultsPublished, EvaluationOpen)
+                | (ResultsPublished, Archived)
+        )
+    }
+
+    /// Returns whether the scheduler handles the projects in this state.
+    pub fn is_active(self) -> bool {
+        ProjectState::ACTIVE.contains(&self)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Queryable, Identifiable, Selectable, ToSchema)]
 #[diesel(table_name = crate::schema::projects)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct Project {
     pub id: Uuid,
     pub name: String,
     pub description: Option<String>,
-    pub start_date: NaiveDateTime,
-    pub end_date: NaiveDateTime,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
     pub notation_period_duration: i32,
     pub promotion_id: Uuid,
     pub state: ProjectState,
+    /// Timezone of the project, the one of its teacher or organization being used if not set
+    pub timezone: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Insertable)]
@@ -38,21 +79,47 @@ pub struct Project {
 pub struct NewProject {
     pub name: String,
     pub description: Option<String>,
-    pub start_date: Option<NaiveDateTime>,
-    pub end_date: NaiveDateTime,
+    pub start_date: Option<DateTime<Utc>>,
+    pub end_date: DateTime<Utc>,
     pub notation_period_duration: Option<i32>,
     pub promotion_id: Uuid,
     pub state: Option<ProjectState>,
+    pub timezone: Option<String>,
 }
 
-#[derive(Debug, Deserialize, AsChangeset)]
+#[derive(Debug, Default, Deserialize, AsChangeset)]
 #[diesel(table_name = crate::schema::projects)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct UpdatedProject {
     pub name: Option<String>,
     pub description: Option<String>,
-    pub start_date: Option<NaiveDateTime>,
-    pub end_date: Option<NaiveDateTime>,
+    pub start_date: Option<DateTime<Utc>>,
+    pub end_date: Option<DateTime<Utc>>,
     pub notation_period_duration: Option<i32>,
     pub state: Option<ProjectState>,
-}
\ No newline at end of file
+    pub timezone: Option<String>,
+}
+/// Change of state of a project.
+#[derive(Debug, Clone, Serialize, Queryable, Identifiable, Selectable, ToSchema)]
+#[diesel(table_name = crate::schema::project_state_transitions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ProjectStateTransition {
+    pub id: i32,
+    pub project_id: Uuid,
+    /// State before the transition, not set for the creation of the project
+    pub from_state: Option<ProjectState>,
+    pub to_state: ProjectState,
+    /// User who made the transition, not set for the ones made by the scheduler
+    pub user_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::project_state_transitions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewProjectStateTransition {
+    pub project_id: Uuid,
+    pub from_state: Option<ProjectState>,
+    pub to_state: ProjectState,
+    pub user_id: Option<Uuid>,
+}
@@ -4,7 +4,7 @@ use utoipa::ToSchema;
 
 use uuid::Uuid;
 
-#[derive(Debug, Serialize, Queryable, Identifiable, Selectable, ToSchema)]
+#[derive(Debug, Clone, Serialize, Queryable, Identifiable, Selectable, ToSchema)]
 #[diesel(table_name = crate::schema::promotions)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct Promotion {
@@ -13,6 +13,7 @@ pub struct Promotion {
     pub start_year: chrono::NaiveDate,
     pub end_year: chrono::NaiveDate,
     pub teacher_id: Uuid,
+    pub organization_id: Uuid,
 }
 
 #[derive(Debug, Deserialize, Insertable)]
@@ -23,6 +24,7 @@ pub struct NewPromotion {
     pub start_year: chrono::NaiveDate,
     pub end_year: chrono::NaiveDate,
     pub teacher_id: Uuid,
+    pub organization_id: Uuid,
 }
 
 #[derive(Debug, Deserialize, AsChangeset)]
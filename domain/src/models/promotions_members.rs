@@ -0,0 +1,68 @@
+use chrono::NaiveDateTime;
+use diesel::{AsChangeset, Identifiable, Insertable, Queryable, Selectable};
+use diesel_derive_enum::DbEnum;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, DbEnum, Clone, Copy, PartialEq, Eq, Ord, PartialOrd, ToSchema)]
+#[ExistingTypePath = "crate::schema::sql_types::PromotionRole"]
+pub enum PromotionRole {
+    #[db_rename = "owner"]
+    Owner,
+    #[db_rename = "co-teacher"]
+    CoTeacher,
+    #[db_rename = "assistant"]
+    Assistant,
+    #[db_rename = "observer"]
+    Observer,
+}
+
+/// Actions a member can perform on a promotion and on everything it contains
+/// (projects, students, groups).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromotionPermission {
+    Read,
+    Grade,
+    Edit,
+    Delete,
+    Manage,
+}
+
+impl PromotionRole {
+    pub fn has_permission(&self, permission: PromotionPermission) -> bool {
+        match self {
+            PromotionRole::Owner => true,
+            PromotionRole::CoTeacher => permission != PromotionPermission::Manage,
+            PromotionRole::Assistant => matches!(permission, PromotionPermission::Read | PromotionPermission::Grade),
+            PromotionRole::Observer => permission == PromotionPermission::Read,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Queryable, Selectable, Identifiable, ToSchema)]
+#[diesel(table_name = crate::schema::promotions_members)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(primary_key(promotion_id, user_id))]
+pub struct PromotionMember {
+    pub promotion_id: Uuid,
+    pub user_id: Uuid,
+    pub role: PromotionRole,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize, Insertable)]
+#[diesel(table_name = crate::schema::promotions_members)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewPromotionMember {
+    pub promotion_id: Uuid,
+    pub user_id: Uuid,
+    pub role: PromotionRole,
+}
+
+#[derive(Debug, Deserialize, AsChangeset)]
+#[diesel(table_name = crate::schema::promotions_members)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct UpdatedPromotionMember {
+    pub role: Option<PromotionRole>,
+}
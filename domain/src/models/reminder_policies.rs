@@ -0,0 +1,80 @@
+use chrono::{DateTime, Utc};
+use diesel::{AsChangeset, Identifiable, Insertable, Queryable, Selectable};
+use diesel_as_jsonb::AsJsonb;
+use diesel_derive_enum::DbEnum;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::models::config::Alert;
+
+/// Event of the evaluation a reminder is sent relative to.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+pub enum ReminderAnchor {
+    /// The reminder is sent the given hours after the evaluation opens
+    EvaluationOpen,
+    /// The reminder is sent the given hours before the evaluation closes
+    EvaluationClose,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema, AsJsonb)]
+pub struct ReminderOffset {
+    pub anchor: ReminderAnchor,
+    pub hours: i32,
+}
+
+impl From<&Alert> for ReminderOffset {
+    fn from(alert: &Alert) -> Self {
+        ReminderOffset {
+            anchor: if alert.before_event { ReminderAnchor::EvaluationClose } else { ReminderAnchor::EvaluationOpen },
+            hours: alert.hours as i32,
+        }
+    }
+}
+
+/// Who receives the reminders: the students who haven't evaluated their group, the teachers who haven't
+/// marked every group, or both.
+#[derive(Debug, Serialize, Deserialize, DbEnum, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[ExistingTypePath = "crate::schema::sql_types::ReminderRecipients"]
+pub enum ReminderRecipients {
+    #[db_rename = "students"]
+    Students,
+    #[db_rename = "teacher"]
+    Teacher,
+    #[db_rename = "both"]
+    Both,
+}
+
+impl ReminderRecipients {
+    pub fn includes_students(self) -> bool {
+        matches!(self, ReminderRecipients::Students | ReminderRecipients::Both)
+    }
+
+    pub fn includes_teacher(self) -> bool {
+        matches!(self, ReminderRecipients::Teacher | ReminderRecipients::Both)
+    }
+}
+
+/// Reminders of a project, overriding the alerts of the configuration of its teacher.
+#[derive(Debug, Clone, Serialize, Queryable, Identifiable, Selectable, ToSchema)]
+#[diesel(table_name = crate::schema::project_reminder_policies)]
+#[diesel(primary_key(project_id))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ProjectReminderPolicy {
+    pub project_id: Uuid,
+    pub offsets: Vec<ReminderOffset>,
+    pub recipients: ReminderRecipients,
+    /// Whether the teachers are told which students still haven't evaluated at the last reminder
+    pub escalate_to_teacher: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize, Insertable, AsChangeset)]
+#[diesel(table_name = crate::schema::project_reminder_policies)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewProjectReminderPolicy {
+    pub project_id: Uuid,
+    pub offsets: Vec<ReminderOffset>,
+    pub recipients: ReminderRecipients,
+    pub escalate_to_teacher: bool,
+}
@@ -11,6 +11,7 @@ pub struct Student {
     pub name: String,
     pub surname: String,
     pub email: String,
+    pub organization_id: Uuid,
 }
 
 #[derive(Debug, Deserialize, Insertable)]
@@ -20,6 +21,7 @@ pub struct NewStudent {
     pub name: String,
     pub surname: String,
     pub email: String,
+    pub organization_id: Uuid,
 }
 
 #[derive(Debug, Deserialize, AsChangeset, ToSchema)]
@@ -2,7 +2,7 @@ use diesel::{AsChangeset, Identifiable, Insertable, Queryable, Selectable};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[derive(Debug, Serialize, Queryable, Identifiable, Selectable)]
+#[derive(Debug, Clone, Serialize, Queryable, Identifiable, Selectable)]
 #[diesel(table_name = crate::schema::students_tokens)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct StudentToken {
@@ -11,6 +11,8 @@ pub struct StudentToken {
     pub student_id: Uuid,
     pub project_id: Uuid,
     pub used: bool,
+    /// Whether the student evaluated their group after their deadline
+    pub submitted_late: bool,
 }
 
 #[derive(Debug, Deserialize, Insertable)]
@@ -26,6 +28,9 @@ pub struct NewStudentToken {
 #[diesel(table_name = crate::schema::students_tokens)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct UpdatedStudentToken {
+    /// New token replacing the expired one, the link sent to the student staying the same
+    pub token: Option<String>,
     #[serde(rename = "type")]
-    pub used: Option<bool>
+    pub used: Option<bool>,
+    pub submitted_late: Option<bool>,
 }
\ No newline at end of file
@@ -1,3 +1,4 @@
+use chrono::NaiveDateTime;
 use diesel::{AsChangeset, Identifiable, Insertable, Queryable, Selectable};
 use diesel_derive_enum::DbEnum;
 use serde::{Deserialize, Serialize};
@@ -23,6 +24,8 @@ pub struct Token {
     #[serde(rename = "type")]
     pub type_: TokenType,
     pub used: bool,
+    pub user_id: Uuid,
+    pub exp: NaiveDateTime,
 }
 
 #[derive(Debug, Deserialize, Insertable)]
@@ -32,6 +35,8 @@ pub struct NewToken {
     pub token: String,
     #[serde(rename = "type")]
     pub type_: TokenType,
+    pub user_id: Uuid,
+    pub exp: NaiveDateTime,
 }
 
 #[derive(Debug, Deserialize, AsChangeset)]
@@ -1,9 +1,10 @@
 use diesel::{AsChangeset, Identifiable, Insertable, Queryable, Selectable};
 use diesel_derive_enum::DbEnum;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
-#[derive(Debug, Serialize, Deserialize, DbEnum, Clone, Copy, PartialEq, Eq, Ord, PartialOrd)]
+#[derive(Debug, Serialize, Deserialize, DbEnum, Clone, Copy, PartialEq, Eq, Ord, PartialOrd, ToSchema)]
 #[ExistingTypePath = "crate::schema::sql_types::UserRole"]
 pub enum UserRole {
     #[db_rename = "admin"]
@@ -12,6 +13,16 @@ pub enum UserRole {
     User,
 }
 
+/// Role of a user inside its organization, independent of the global [UserRole].
+#[derive(Debug, Serialize, Deserialize, DbEnum, Clone, Copy, PartialEq, Eq, Ord, PartialOrd, ToSchema)]
+#[ExistingTypePath = "crate::schema::sql_types::OrganizationRole"]
+pub enum OrganizationRole {
+    #[db_rename = "admin"]
+    Admin,
+    #[db_rename = "member"]
+    Member,
+}
+
 #[derive(Debug, Clone, Deserialize, Queryable, Identifiable, Selectable)]
 #[diesel(table_name = crate::schema::users)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
@@ -24,6 +35,8 @@ pub struct User {
     pub updated_at: chrono::NaiveDateTime,
     pub role: UserRole,
     pub token_version: i32,
+    pub organization_id: Uuid,
+    pub organization_role: OrganizationRole,
 }
 
 #[derive(Debug, Serialize, Insertable)]
@@ -32,6 +45,7 @@ pub struct User {
 pub struct NewUser {
     pub username: String,
     pub email: String,
+    pub organization_id: Uuid,
 }
 
 #[derive(Debug, Serialize, AsChangeset)]
@@ -43,6 +57,7 @@ pub struct UpdatedUser {
     pub has_validated_email: Option<bool>,
     pub role: Option<UserRole>,
     pub token_version: Option<i32>,
+    pub organization_role: Option<OrganizationRole>,
 }
 
 #[derive(Debug, Serialize, AsChangeset)]
@@ -0,0 +1,25 @@
+use chrono::NaiveDateTime;
+use diesel::{Identifiable, Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Link between a user and an account of an external identity provider.
+#[derive(Debug, Serialize, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = crate::schema::users_identities)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct UserIdentity {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub issuer: String,
+    pub subject: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize, Insertable)]
+#[diesel(table_name = crate::schema::users_identities)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewUserIdentity {
+    pub user_id: Uuid,
+    pub issuer: String,
+    pub subject: String,
+}
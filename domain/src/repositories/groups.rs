@@ -0,0 +1,24 @@
+use std::future::Future;
+
+use uuid::Uuid;
+
+use crate::models::groups::{Group, GroupStudent, UpdatedGroupStudent};
+use crate::models::students::Student;
+use crate::repositories::RepositoryResult;
+
+pub trait GroupsRepository: Send + Sync {
+    fn get_groups_from_project_id(&self, project_id: Uuid) -> impl Future<Output = RepositoryResult<Vec<Group>>> + Send;
+
+    /// Returns the students of the groups, each student being returned once.
+    fn get_students_from_groups(&self, group_ids: Vec<Uuid>) -> impl Future<Output = RepositoryResult<Vec<Student>>> + Send;
+
+    /// Returns the members of all the groups of a project, with their student.
+    fn get_groups_students_from_project_id(&self, project_id: Uuid) -> impl Future<Output = RepositoryResult<Vec<(GroupStudent, Student)>>> + Send;
+
+    fn get_group_from_student_and_project_id(&self, student_id: Uuid, project_id: Uuid) -> impl Future<Output = RepositoryResult<Option<Group>>> + Send;
+
+    fn update_group_student(&self, group_id: Uuid, student_id: Uuid, updated_group_student: UpdatedGroupStudent) -> impl Future<Output = RepositoryResult<()>> + Send;
+
+    /// Sets the marks of several group members at once, given as `(group_id, student_id, mark)`.
+    fn update_groups_students_marks(&self, marks: Vec<(Uuid, Uuid, f64)>) -> impl Future<Output = RepositoryResult<()>> + Send;
+}
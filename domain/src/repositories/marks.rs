@@ -0,0 +1,18 @@
+use std::future::Future;
+
+use uuid::Uuid;
+
+use crate::models::marks::Mark;
+use crate::models::students::Student;
+use crate::repositories::RepositoryResult;
+
+pub trait MarksRepository: Send + Sync {
+    /// Returns the marks a student received from the other students of a group.
+    fn get_marks_given_to_student_id_and_group_id(&self, student_id: Uuid, group_id: Uuid) -> impl Future<Output = RepositoryResult<Vec<Mark>>> + Send;
+
+    /// Returns all the marks the students gave in a project.
+    fn get_marks_from_project_id(&self, project_id: Uuid) -> impl Future<Output = RepositoryResult<Vec<Mark>>> + Send;
+
+    /// Returns the students of the groups of a project who haven't evaluated anyone yet.
+    fn get_students_who_didnt_evaluate_group(&self, project_id: Uuid) -> impl Future<Output = RepositoryResult<Vec<Student>>> + Send;
+}
@@ -0,0 +1,44 @@
+//! Access to the data needed by the business logic, independently of where it is stored.
+//!
+//! The repositories are implemented on PostgreSQL and in memory by `infrastructure::repositories`.
+
+pub mod groups;
+pub mod marks;
+pub mod organizations;
+pub mod projects;
+pub mod promotions;
+pub mod tokens;
+pub mod users;
+
+use groups::GroupsRepository;
+use marks::MarksRepository;
+use organizations::OrganizationsRepository;
+use projects::ProjectsRepository;
+use promotions::PromotionsRepository;
+use tokens::TokensRepository;
+use users::UsersRepository;
+
+/// The errors are the ones of Diesel whatever the storage, so they are handled the same way
+pub type RepositoryResult<T> = Result<T, diesel::result::Error>;
+
+/// All the repositories, implemented by a single type for each storage.
+pub trait Repositories:
+    UsersRepository
+    + OrganizationsRepository
+    + PromotionsRepository
+    + ProjectsRepository
+    + GroupsRepository
+    + MarksRepository
+    + TokensRepository
+{}
+
+impl<T> Repositories for T
+    where
+        T: UsersRepository
+        + OrganizationsRepository
+        + PromotionsRepository
+        + ProjectsRepository
+        + GroupsRepository
+        + MarksRepository
+        + TokensRepository,
+{}
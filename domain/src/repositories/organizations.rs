@@ -0,0 +1,10 @@
+use std::future::Future;
+
+use uuid::Uuid;
+
+use crate::models::organizations::Organization;
+use crate::repositories::RepositoryResult;
+
+pub trait OrganizationsRepository: Send + Sync {
+    fn get_organization_by_id(&self, id: Uuid) -> impl Future<Output = RepositoryResult<Organization>> + Send;
+}
@@ -0,0 +1,62 @@
+use std::future::Future;
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::models::done_alerts::{AlertType, DoneAlert, NewDoneAlert};
+use crate::models::extensions::{NewProjectLatePolicy, NewStudentExtension, ProjectLatePolicy, StudentExtension};
+use crate::models::projects::{Project, ProjectState, ProjectStateTransition, UpdatedProject};
+use crate::models::reminder_policies::{NewProjectReminderPolicy, ProjectReminderPolicy};
+use crate::repositories::RepositoryResult;
+
+pub trait ProjectsRepository: Send + Sync {
+    fn get_project_by_id(&self, id: Uuid) -> impl Future<Output = RepositoryResult<Project>> + Send;
+
+    /// Returns the projects handled by the scheduler, whose state is active.
+    fn get_current_projects(&self) -> impl Future<Output = RepositoryResult<Vec<Project>>> + Send;
+
+    fn update_project(&self, id: Uuid, updated_project: UpdatedProject) -> impl Future<Output = RepositoryResult<()>> + Send;
+
+    /// Moves a project from a state to another one, updating it and recording the transition at once.
+    ///
+    /// Returns false, without any change, if the project is no longer in the state it's moved from.
+    fn transition_project(&self, id: Uuid, from: ProjectState, to: ProjectState, updated_project: UpdatedProject, user_id: Option<Uuid>) -> impl Future<Output = RepositoryResult<bool>> + Send;
+
+    /// Returns the changes of state of a project, the oldest first.
+    fn get_project_state_transitions(&self, project_id: Uuid) -> impl Future<Output = RepositoryResult<Vec<ProjectStateTransition>>> + Send;
+
+    /// Returns the reminder policy of a project, if it overrides the alerts of its teacher.
+    fn get_project_reminder_policy(&self, project_id: Uuid) -> impl Future<Output = RepositoryResult<Option<ProjectReminderPolicy>>> + Send;
+
+    /// Creates the reminder policy of a project, or replaces it.
+    fn set_project_reminder_policy(&self, new_policy: NewProjectReminderPolicy) -> impl Future<Output = RepositoryResult<ProjectReminderPolicy>> + Send;
+
+    /// Removes the reminder policy of a project, which then follows the alerts of its teacher.
+    fn delete_project_reminder_policy(&self, project_id: Uuid) -> impl Future<Output = RepositoryResult<()>> + Send;
+
+    /// Returns the late submissions policy of a project, if it accepts them.
+    fn get_project_late_policy(&self, project_id: Uuid) -> impl Future<Output = RepositoryResult<Option<ProjectLatePolicy>>> + Send;
+
+    /// Creates the late submissions policy of a project, or replaces it.
+    fn set_project_late_policy(&self, new_policy: NewProjectLatePolicy) -> impl Future<Output = RepositoryResult<ProjectLatePolicy>> + Send;
+
+    /// Removes the late submissions policy of a project, which then refuses them.
+    fn delete_project_late_policy(&self, project_id: Uuid) -> impl Future<Output = RepositoryResult<()>> + Send;
+
+    /// Returns the extensions granted to the students of a project.
+    fn get_student_extensions(&self, project_id: Uuid) -> impl Future<Output = RepositoryResult<Vec<StudentExtension>>> + Send;
+
+    /// Grants an extension to a student, or replaces theirs, the student being notified again.
+    fn set_student_extension(&self, new_extension: NewStudentExtension) -> impl Future<Output = RepositoryResult<StudentExtension>> + Send;
+
+    /// Revokes the extension of a student, returning whether they had one.
+    fn delete_student_extension(&self, project_id: Uuid, student_id: Uuid) -> impl Future<Output = RepositoryResult<bool>> + Send;
+
+    /// Records that a student got the token following their extension.
+    fn set_student_extension_notified(&self, project_id: Uuid, student_id: Uuid, notified_at: DateTime<Utc>) -> impl Future<Output = RepositoryResult<()>> + Send;
+
+    /// Returns the alerts of a type already sent for a project.
+    fn get_done_alerts_by_project_id_and_type(&self, project_id: Uuid, alert_type: AlertType) -> impl Future<Output = RepositoryResult<Vec<DoneAlert>>> + Send;
+
+    fn create_done_alert(&self, new_done_alert: NewDoneAlert) -> impl Future<Output = RepositoryResult<()>> + Send;
+}
@@ -0,0 +1,17 @@
+use std::future::Future;
+
+use uuid::Uuid;
+
+use crate::models::promotions::Promotion;
+use crate::models::promotions_members::PromotionRole;
+use crate::repositories::RepositoryResult;
+
+pub trait PromotionsRepository: Send + Sync {
+    fn get_promotion_by_id(&self, id: Uuid) -> impl Future<Output = RepositoryResult<Promotion>> + Send;
+
+    /// Returns the emails of the members of a promotion having one of the roles.
+    fn get_members_emails_from_promotion_id(&self, promotion_id: Uuid, roles: &[PromotionRole]) -> impl Future<Output = RepositoryResult<Vec<String>>> + Send;
+
+    /// Returns the timezone of the teacher of a promotion, or the one of its organization if the teacher has none.
+    fn get_promotion_timezone(&self, id: Uuid) -> impl Future<Output = RepositoryResult<String>> + Send;
+}
@@ -0,0 +1,16 @@
+use std::future::Future;
+
+use uuid::Uuid;
+
+use crate::models::students_tokens::{NewStudentToken, StudentToken, UpdatedStudentToken};
+use crate::repositories::RepositoryResult;
+
+pub trait TokensRepository: Send + Sync {
+    fn create_student_token(&self, new_student_token: NewStudentToken) -> impl Future<Output = RepositoryResult<Uuid>> + Send;
+
+    fn update_student_token(&self, id: Uuid, updated_student_token: UpdatedStudentToken) -> impl Future<Output = RepositoryResult<()>> + Send;
+
+    fn get_student_tokens_from_student_and_project_id(&self, student_id: Uuid, project_id: Uuid) -> impl Future<Output = RepositoryResult<StudentToken>> + Send;
+
+    fn get_student_tokens_from_project_id(&self, project_id: Uuid) -> impl Future<Output = RepositoryResult<Vec<StudentToken>>> + Send;
+}
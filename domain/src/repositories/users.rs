@@ -0,0 +1,13 @@
+use std::future::Future;
+
+use uuid::Uuid;
+
+use crate::models::config::UserConfig;
+use crate::models::users::User;
+use crate::repositories::RepositoryResult;
+
+pub trait UsersRepository: Send + Sync {
+    fn get_user_by_id(&self, user_id: Uuid) -> impl Future<Output = RepositoryResult<User>> + Send;
+
+    fn get_config_by_user_id(&self, user_id: Uuid) -> impl Future<Output = RepositoryResult<UserConfig>> + Send;
+}
@@ -5,10 +5,30 @@ pub mod sql_types {
     #[diesel(postgres_type(name = "alert_type"))]
     pub struct AlertType;
 
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "api_key_scope"))]
+    pub struct ApiKeyScope;
+
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "audit_action"))]
+    pub struct AuditAction;
+
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "organization_role"))]
+    pub struct OrganizationRole;
+
     #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
     #[diesel(postgres_type(name = "project_state"))]
     pub struct ProjectState;
 
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "promotion_role"))]
+    pub struct PromotionRole;
+
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "reminder_recipients"))]
+    pub struct ReminderRecipients;
+
     #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
     #[diesel(postgres_type(name = "token_type"))]
     pub struct TokenType;
@@ -18,6 +38,47 @@ pub mod sql_types {
     pub struct UserRole;
 }
 
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::ApiKeyScope;
+
+    api_keys (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        #[max_length = 64]
+        name -> Varchar,
+        #[max_length = 16]
+        prefix -> Varchar,
+        #[max_length = 64]
+        key_hash -> Varchar,
+        scopes -> Array<ApiKeyScope>,
+        expires_at -> Nullable<Timestamp>,
+        last_used_at -> Nullable<Timestamp>,
+        revoked_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::AuditAction;
+
+    audit_logs (id) {
+        id -> Uuid,
+        actor_id -> Nullable<Uuid>,
+        action -> AuditAction,
+        #[max_length = 64]
+        resource_type -> Varchar,
+        #[max_length = 64]
+        resource_id -> Nullable<Varchar>,
+        before -> Nullable<Jsonb>,
+        after -> Nullable<Jsonb>,
+        #[max_length = 45]
+        ip -> Nullable<Varchar>,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     use diesel::sql_types::*;
     use super::sql_types::AlertType;
@@ -30,6 +91,7 @@ diesel::table! {
         #[sql_name = "type"]
         type_ -> AlertType,
         published_at -> Timestamp,
+        planned_at -> Nullable<Timestamp>,
     }
 }
 
@@ -59,6 +121,17 @@ diesel::table! {
         register -> Bool,
         authorized_domains -> Array<Nullable<Text>>,
         updated_at -> Timestamp,
+        audit_log_retention_days -> Int4,
+        password_min_length -> Int4,
+        password_max_length -> Int4,
+        password_min_lower_case -> Int4,
+        password_min_upper_case -> Int4,
+        password_min_digits -> Int4,
+        password_min_special_chars -> Int4,
+        password_forbid_common -> Bool,
+        password_forbid_username -> Bool,
+        password_history_size -> Int4,
+        password_expiry_days -> Int4,
     }
 }
 
@@ -85,6 +158,85 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    oidc_login_states (id) {
+        id -> Uuid,
+        #[max_length = 128]
+        state -> Varchar,
+        #[max_length = 128]
+        code_verifier -> Varchar,
+        organization_id -> Uuid,
+        exp -> Timestamp,
+    }
+}
+
+diesel::table! {
+    organizations (id) {
+        id -> Uuid,
+        #[max_length = 255]
+        name -> Varchar,
+        #[max_length = 64]
+        slug -> Varchar,
+        register -> Bool,
+        authorized_domains -> Array<Nullable<Text>>,
+        logo_url -> Nullable<Text>,
+        #[max_length = 16]
+        primary_color -> Nullable<Varchar>,
+        #[max_length = 255]
+        mail_sender -> Nullable<Varchar>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        #[max_length = 64]
+        timezone -> Varchar,
+    }
+}
+
+diesel::table! {
+    password_history (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        #[max_length = 255]
+        password -> Varchar,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    project_late_policies (project_id) {
+        project_id -> Uuid,
+        late_hours -> Int4,
+        penalty_percent -> Float8,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::ReminderRecipients;
+
+    project_reminder_policies (project_id) {
+        project_id -> Uuid,
+        offsets -> Array<Jsonb>,
+        recipients -> ReminderRecipients,
+        escalate_to_teacher -> Bool,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::ProjectState;
+
+    project_state_transitions (id) {
+        id -> Int4,
+        project_id -> Uuid,
+        from_state -> Nullable<ProjectState>,
+        to_state -> ProjectState,
+        user_id -> Nullable<Uuid>,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     use diesel::sql_types::*;
     use super::sql_types::ProjectState;
@@ -94,11 +246,13 @@ diesel::table! {
         #[max_length = 64]
         name -> Varchar,
         description -> Nullable<Text>,
-        start_date -> Timestamp,
-        end_date -> Timestamp,
+        start_date -> Timestamptz,
+        end_date -> Timestamptz,
         notation_period_duration -> Int4,
         promotion_id -> Uuid,
         state -> ProjectState,
+        #[max_length = 64]
+        timezone -> Nullable<Varchar>,
     }
 }
 
@@ -110,6 +264,19 @@ diesel::table! {
         start_year -> Date,
         end_year -> Date,
         teacher_id -> Uuid,
+        organization_id -> Uuid,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::PromotionRole;
+
+    promotions_members (promotion_id, user_id) {
+        promotion_id -> Uuid,
+        user_id -> Uuid,
+        role -> PromotionRole,
+        created_at -> Timestamp,
     }
 }
 
@@ -120,6 +287,18 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    student_extensions (project_id, student_id) {
+        project_id -> Uuid,
+        student_id -> Uuid,
+        extended_until -> Timestamptz,
+        reason -> Nullable<Text>,
+        granted_by -> Nullable<Uuid>,
+        created_at -> Timestamptz,
+        notified_at -> Nullable<Timestamptz>,
+    }
+}
+
 diesel::table! {
     students (id) {
         id -> Uuid,
@@ -129,6 +308,7 @@ diesel::table! {
         surname -> Varchar,
         #[max_length = 128]
         email -> Varchar,
+        organization_id -> Uuid,
     }
 }
 
@@ -139,6 +319,7 @@ diesel::table! {
         student_id -> Uuid,
         project_id -> Uuid,
         used -> Bool,
+        submitted_late -> Bool,
     }
 }
 
@@ -152,6 +333,8 @@ diesel::table! {
         #[sql_name = "type"]
         type_ -> TokenType,
         used -> Bool,
+        user_id -> Uuid,
+        exp -> Timestamp,
     }
 }
 
@@ -161,6 +344,8 @@ diesel::table! {
         user_id -> Uuid,
         alerts -> Array<Nullable<Jsonb>>,
         updated_at -> Timestamp,
+        #[max_length = 64]
+        timezone -> Nullable<Varchar>,
     }
 }
 
@@ -176,6 +361,7 @@ diesel::table! {
 diesel::table! {
     use diesel::sql_types::*;
     use super::sql_types::UserRole;
+    use super::sql_types::OrganizationRole;
 
     users (id) {
         id -> Uuid,
@@ -188,9 +374,25 @@ diesel::table! {
         updated_at -> Timestamp,
         role -> UserRole,
         token_version -> Int4,
+        organization_id -> Uuid,
+        organization_role -> OrganizationRole,
+    }
+}
+
+diesel::table! {
+    users_identities (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        #[max_length = 255]
+        issuer -> Varchar,
+        #[max_length = 255]
+        subject -> Varchar,
+        created_at -> Timestamp,
     }
 }
 
+diesel::joinable!(api_keys -> users (user_id));
+diesel::joinable!(audit_logs -> users (actor_id));
 diesel::joinable!(done_alerts -> projects (project_id));
 diesel::joinable!(groups -> projects (project_id));
 diesel::joinable!(groups_students -> groups (group_id));
@@ -198,29 +400,56 @@ diesel::joinable!(groups_students -> students (student_id));
 diesel::joinable!(marks -> groups (group_id));
 diesel::joinable!(marks -> projects (project_id));
 diesel::joinable!(mfa_codes -> users (user_id));
+diesel::joinable!(oidc_login_states -> organizations (organization_id));
+diesel::joinable!(password_history -> users (user_id));
+diesel::joinable!(project_late_policies -> projects (project_id));
+diesel::joinable!(project_reminder_policies -> projects (project_id));
+diesel::joinable!(project_state_transitions -> projects (project_id));
+diesel::joinable!(project_state_transitions -> users (user_id));
 diesel::joinable!(projects -> promotions (promotion_id));
+diesel::joinable!(promotions -> organizations (organization_id));
 diesel::joinable!(promotions -> users (teacher_id));
+diesel::joinable!(promotions_members -> promotions (promotion_id));
+diesel::joinable!(promotions_members -> users (user_id));
 diesel::joinable!(promotions_students -> promotions (promotion_id));
 diesel::joinable!(promotions_students -> students (student_id));
+diesel::joinable!(students -> organizations (organization_id));
 diesel::joinable!(students_tokens -> projects (project_id));
+diesel::joinable!(student_extensions -> projects (project_id));
+diesel::joinable!(student_extensions -> students (student_id));
+diesel::joinable!(student_extensions -> users (granted_by));
 diesel::joinable!(students_tokens -> students (student_id));
+diesel::joinable!(tokens -> users (user_id));
 diesel::joinable!(user_config -> users (user_id));
 diesel::joinable!(user_passwords -> users (user_id));
+diesel::joinable!(users -> organizations (organization_id));
+diesel::joinable!(users_identities -> users (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    api_keys,
+    audit_logs,
     done_alerts,
     groups,
     groups_students,
     main_config,
     marks,
     mfa_codes,
+    oidc_login_states,
+    organizations,
+    password_history,
+    project_late_policies,
+    project_reminder_policies,
+    project_state_transitions,
     projects,
     promotions,
+    promotions_members,
     promotions_students,
+    student_extensions,
     students,
     students_tokens,
     tokens,
     user_config,
     user_passwords,
     users,
+    users_identities,
 );
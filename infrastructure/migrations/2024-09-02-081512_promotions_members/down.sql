@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS promotions_members;
+DROP TYPE IF EXISTS promotion_role;
@@ -0,0 +1,28 @@
+-- Your SQL goes here
+DO
+$$
+    BEGIN
+        IF NOT EXISTS (SELECT 1
+                       FROM pg_type
+                       WHERE typname = 'promotion_role') THEN CREATE TYPE promotion_role AS ENUM
+            ('owner', 'co-teacher', 'assistant', 'observer');
+        END IF;
+    END
+$$;
+
+CREATE TABLE IF NOT EXISTS promotions_members
+(
+    promotion_id UUID           NOT NULL REFERENCES promotions (id) ON DELETE CASCADE,
+    user_id      UUID           NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    role         promotion_role NOT NULL DEFAULT 'observer',
+    created_at   TIMESTAMP      NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (promotion_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS promotions_members_user_id_idx ON promotions_members (user_id);
+
+-- Existing promotions are owned by their teacher
+INSERT INTO promotions_members (promotion_id, user_id, role)
+SELECT id, teacher_id, 'owner'
+FROM promotions
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,12 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS students_organization_id_idx;
+DROP INDEX IF EXISTS promotions_organization_id_idx;
+DROP INDEX IF EXISTS users_organization_id_idx;
+
+ALTER TABLE students DROP COLUMN IF EXISTS organization_id;
+ALTER TABLE promotions DROP COLUMN IF EXISTS organization_id;
+ALTER TABLE users DROP COLUMN IF EXISTS organization_role;
+ALTER TABLE users DROP COLUMN IF EXISTS organization_id;
+
+DROP TABLE IF EXISTS organizations;
+DROP TYPE IF EXISTS organization_role;
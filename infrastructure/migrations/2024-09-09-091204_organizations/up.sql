@@ -0,0 +1,56 @@
+-- Your SQL goes here
+DO $$ BEGIN IF NOT EXISTS (
+  SELECT
+    1
+  FROM
+    pg_type
+  WHERE
+    typname = 'organization_role'
+) THEN CREATE TYPE organization_role AS ENUM ('member', 'admin');
+END IF;
+END $$;
+
+CREATE TABLE IF NOT EXISTS organizations
+(
+    id                 UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    name               VARCHAR(255) NOT NULL,
+    slug               VARCHAR(64)  NOT NULL UNIQUE,
+    register           BOOLEAN      NOT NULL DEFAULT TRUE,
+    authorized_domains TEXT[]       NOT NULL DEFAULT ARRAY[]::TEXT[],
+    logo_url           TEXT,
+    primary_color      VARCHAR(16),
+    mail_sender        VARCHAR(255),
+    created_at         TIMESTAMP    NOT NULL DEFAULT now(),
+    updated_at         TIMESTAMP    NOT NULL DEFAULT now()
+);
+
+CREATE TRIGGER update_organizations_timestamp
+    BEFORE UPDATE
+    ON organizations
+    FOR EACH ROW
+EXECUTE FUNCTION update_timestamp();
+
+-- Existing data is moved into a default organization using the current main configuration
+INSERT INTO organizations (name, slug, register, authorized_domains)
+SELECT 'Default',
+       'default',
+       COALESCE((SELECT register FROM main_config ORDER BY id LIMIT 1), TRUE),
+       COALESCE((SELECT authorized_domains FROM main_config ORDER BY id LIMIT 1), ARRAY[]::TEXT[])
+ON CONFLICT (slug) DO NOTHING;
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS organization_id UUID REFERENCES organizations (id) ON DELETE CASCADE;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS organization_role organization_role NOT NULL DEFAULT 'member';
+UPDATE users SET organization_id = (SELECT id FROM organizations WHERE slug = 'default') WHERE organization_id IS NULL;
+ALTER TABLE users ALTER COLUMN organization_id SET NOT NULL;
+
+ALTER TABLE promotions ADD COLUMN IF NOT EXISTS organization_id UUID REFERENCES organizations (id) ON DELETE CASCADE;
+UPDATE promotions SET organization_id = (SELECT id FROM organizations WHERE slug = 'default') WHERE organization_id IS NULL;
+ALTER TABLE promotions ALTER COLUMN organization_id SET NOT NULL;
+
+ALTER TABLE students ADD COLUMN IF NOT EXISTS organization_id UUID REFERENCES organizations (id) ON DELETE CASCADE;
+UPDATE students SET organization_id = (SELECT id FROM organizations WHERE slug = 'default') WHERE organization_id IS NULL;
+ALTER TABLE students ALTER COLUMN organization_id SET NOT NULL;
+
+CREATE INDEX IF NOT EXISTS users_organization_id_idx ON users (organization_id);
+CREATE INDEX IF NOT EXISTS promotions_organization_id_idx ON promotions (organization_id);
+CREATE INDEX IF NOT EXISTS students_organization_id_idx ON students (organization_id);
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE main_config DROP COLUMN IF EXISTS audit_log_retention_days;
+
+DROP TABLE IF EXISTS audit_logs;
+DROP FUNCTION IF EXISTS prevent_audit_logs_update();
+DROP TYPE IF EXISTS audit_action;
@@ -0,0 +1,56 @@
+-- Your SQL goes here
+DO $$ BEGIN IF NOT EXISTS (
+  SELECT
+    1
+  FROM
+    pg_type
+  WHERE
+    typname = 'audit_action'
+) THEN CREATE TYPE audit_action AS ENUM (
+    'group_updated',
+    'student_deleted',
+    'main_config_updated',
+    'password_reset_requested',
+    'password_reset'
+);
+END IF;
+END $$;
+
+CREATE TABLE IF NOT EXISTS audit_logs
+(
+    id            UUID PRIMARY KEY      DEFAULT uuid_generate_v4(),
+    actor_id      UUID         REFERENCES users (id) ON DELETE SET NULL,
+    action        audit_action NOT NULL,
+    resource_type VARCHAR(64)  NOT NULL,
+    resource_id   VARCHAR(64),
+    before        JSONB,
+    after         JSONB,
+    ip            VARCHAR(45),
+    created_at    TIMESTAMP    NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS audit_logs_actor_id_idx ON audit_logs (actor_id);
+CREATE INDEX IF NOT EXISTS audit_logs_resource_idx ON audit_logs (resource_type, resource_id);
+CREATE INDEX IF NOT EXISTS audit_logs_created_at_idx ON audit_logs (created_at);
+
+-- The audit log is append-only, rows can only be removed by the retention cleanup.
+-- Deleting a user only clears the actor of their entries, through the ON DELETE SET NULL of the foreign key.
+CREATE OR REPLACE FUNCTION prevent_audit_logs_update()
+    RETURNS TRIGGER AS
+$$
+BEGIN
+    IF OLD.actor_id IS NOT NULL AND NEW.actor_id IS NULL
+        AND to_jsonb(NEW) - 'actor_id' = to_jsonb(OLD) - 'actor_id' THEN
+        RETURN NEW;
+    END IF;
+    RAISE EXCEPTION 'audit_logs is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER prevent_audit_logs_update
+    BEFORE UPDATE
+    ON audit_logs
+    FOR EACH ROW
+EXECUTE FUNCTION prevent_audit_logs_update();
+
+ALTER TABLE main_config ADD COLUMN IF NOT EXISTS audit_log_retention_days INT NOT NULL DEFAULT 365;
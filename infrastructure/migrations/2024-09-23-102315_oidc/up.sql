@@ -0,0 +1,19 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS oidc_login_states
+(
+    id              UUID PRIMARY KEY      DEFAULT uuid_generate_v4(),
+    state           VARCHAR(128) NOT NULL UNIQUE,
+    code_verifier   VARCHAR(128) NOT NULL,
+    organization_id UUID         NOT NULL REFERENCES organizations (id) ON DELETE CASCADE,
+    exp             TIMESTAMP    NOT NULL DEFAULT CURRENT_TIMESTAMP + INTERVAL '10 minutes'
+);
+
+CREATE TABLE IF NOT EXISTS users_identities
+(
+    id         UUID PRIMARY KEY      DEFAULT uuid_generate_v4(),
+    user_id    UUID         NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    issuer     VARCHAR(255) NOT NULL,
+    subject    VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP    NOT NULL DEFAULT now(),
+    UNIQUE (issuer, subject)
+);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS api_keys;
+DROP TYPE IF EXISTS api_key_scope;
@@ -0,0 +1,27 @@
+-- Your SQL goes here
+DO $$ BEGIN IF NOT EXISTS (
+  SELECT
+    1
+  FROM
+    pg_type
+  WHERE
+    typname = 'api_key_scope'
+) THEN CREATE TYPE api_key_scope AS ENUM ('read', 'write');
+END IF;
+END $$;
+
+CREATE TABLE IF NOT EXISTS api_keys
+(
+    id           UUID PRIMARY KEY         DEFAULT uuid_generate_v4(),
+    user_id      UUID            NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    name         VARCHAR(64)     NOT NULL,
+    prefix       VARCHAR(16)     NOT NULL,
+    key_hash     VARCHAR(64)     NOT NULL UNIQUE,
+    scopes       api_key_scope[] NOT NULL,
+    expires_at   TIMESTAMP,
+    last_used_at TIMESTAMP,
+    revoked_at   TIMESTAMP,
+    created_at   TIMESTAMP       NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS api_keys_user_id_idx ON api_keys (user_id);
@@ -0,0 +1,16 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER IF EXISTS set_updated_at ON user_passwords;
+
+DROP TABLE IF EXISTS password_history;
+
+ALTER TABLE main_config
+    DROP COLUMN IF EXISTS password_min_length,
+    DROP COLUMN IF EXISTS password_max_length,
+    DROP COLUMN IF EXISTS password_min_lower_case,
+    DROP COLUMN IF EXISTS password_min_upper_case,
+    DROP COLUMN IF EXISTS password_min_digits,
+    DROP COLUMN IF EXISTS password_min_special_chars,
+    DROP COLUMN IF EXISTS password_forbid_common,
+    DROP COLUMN IF EXISTS password_forbid_username,
+    DROP COLUMN IF EXISTS password_history_size,
+    DROP COLUMN IF EXISTS password_expiry_days;
@@ -0,0 +1,25 @@
+-- Your SQL goes here
+ALTER TABLE main_config
+    ADD COLUMN IF NOT EXISTS password_min_length        INT     NOT NULL DEFAULT 8,
+    ADD COLUMN IF NOT EXISTS password_max_length        INT     NOT NULL DEFAULT 64,
+    ADD COLUMN IF NOT EXISTS password_min_lower_case    INT     NOT NULL DEFAULT 1,
+    ADD COLUMN IF NOT EXISTS password_min_upper_case    INT     NOT NULL DEFAULT 1,
+    ADD COLUMN IF NOT EXISTS password_min_digits        INT     NOT NULL DEFAULT 1,
+    ADD COLUMN IF NOT EXISTS password_min_special_chars INT     NOT NULL DEFAULT 1,
+    ADD COLUMN IF NOT EXISTS password_forbid_common     BOOLEAN NOT NULL DEFAULT TRUE,
+    ADD COLUMN IF NOT EXISTS password_forbid_username   BOOLEAN NOT NULL DEFAULT TRUE,
+    ADD COLUMN IF NOT EXISTS password_history_size      INT     NOT NULL DEFAULT 5,
+    ADD COLUMN IF NOT EXISTS password_expiry_days       INT     NOT NULL DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS password_history
+(
+    id         UUID PRIMARY KEY      DEFAULT uuid_generate_v4(),
+    user_id    UUID         NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    password   VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP    NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS password_history_user_id_idx ON password_history (user_id, created_at);
+
+-- The last update of a password is used to know when it expires
+SELECT diesel_manage_updated_at('user_passwords');
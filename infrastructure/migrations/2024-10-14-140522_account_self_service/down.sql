@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+-- PostgreSQL can't remove values from an enum, the unused values are kept
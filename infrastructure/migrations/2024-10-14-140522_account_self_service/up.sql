@@ -0,0 +1,4 @@
+-- Your SQL goes here
+ALTER TYPE audit_action ADD VALUE IF NOT EXISTS 'password_changed';
+ALTER TYPE audit_action ADD VALUE IF NOT EXISTS 'email_changed';
+ALTER TYPE audit_action ADD VALUE IF NOT EXISTS 'account_deleted';
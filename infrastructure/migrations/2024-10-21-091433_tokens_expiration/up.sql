@@ -0,0 +1,6 @@
+-- Your SQL goes here
+-- Tokens issued before this migration belong to no one and are dropped
+DELETE FROM tokens;
+
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE;
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS exp TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP + INTERVAL '1 hour';
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE organizations DROP COLUMN IF EXISTS timezone;
+ALTER TABLE user_config DROP COLUMN IF EXISTS timezone;
+ALTER TABLE projects DROP COLUMN IF EXISTS timezone;
+
+ALTER TABLE projects ALTER COLUMN end_date TYPE TIMESTAMP USING end_date AT TIME ZONE 'UTC';
+ALTER TABLE projects ALTER COLUMN start_date TYPE TIMESTAMP USING start_date AT TIME ZONE 'UTC';
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+-- The dates of the projects were stored in UTC
+ALTER TABLE projects ALTER COLUMN start_date TYPE TIMESTAMPTZ USING start_date AT TIME ZONE 'UTC';
+ALTER TABLE projects ALTER COLUMN end_date TYPE TIMESTAMPTZ USING end_date AT TIME ZONE 'UTC';
+
+-- IANA names of the timezones the dates are evaluated in, the one of the project prevailing over the one of its teacher,
+-- which prevails over the one of its organization
+ALTER TABLE projects ADD COLUMN IF NOT EXISTS timezone VARCHAR(64);
+ALTER TABLE user_config ADD COLUMN IF NOT EXISTS timezone VARCHAR(64);
+ALTER TABLE organizations ADD COLUMN IF NOT EXISTS timezone VARCHAR(64) NOT NULL DEFAULT 'UTC';
@@ -0,0 +1,20 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS project_state_transitions;
+
+ALTER TYPE project_state RENAME TO project_state_new;
+CREATE TYPE project_state AS ENUM
+    ('not-started', 'in-progress', 'finished', 'notation-finished');
+
+ALTER TABLE projects ALTER COLUMN state DROP DEFAULT;
+ALTER TABLE projects ALTER COLUMN state TYPE project_state USING (
+    CASE state
+        WHEN 'draft' THEN 'not-started'
+        WHEN 'scheduled' THEN 'not-started'
+        WHEN 'in-progress' THEN 'in-progress'
+        WHEN 'evaluation-open' THEN 'finished'
+        WHEN 'evaluation-closed' THEN 'finished'
+        ELSE 'notation-finished'
+    END
+)::project_state;
+ALTER TABLE projects ALTER COLUMN state SET DEFAULT 'not-started';
+DROP TYPE project_state_new;
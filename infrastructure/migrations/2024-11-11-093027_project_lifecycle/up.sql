@@ -0,0 +1,34 @@
+-- Your SQL goes here
+-- The states of the lifecycle of a project, replacing the ones deduced from its dates
+ALTER TYPE project_state RENAME TO project_state_old;
+CREATE TYPE project_state AS ENUM
+    ('draft', 'scheduled', 'in-progress', 'evaluation-open', 'evaluation-closed', 'results-published', 'archived');
+
+ALTER TABLE projects ALTER COLUMN state DROP DEFAULT;
+ALTER TABLE projects ALTER COLUMN state TYPE project_state USING (
+    CASE state
+        WHEN 'not-started' THEN 'scheduled'
+        WHEN 'in-progress' THEN 'in-progress'
+        WHEN 'finished' THEN 'evaluation-open'
+        WHEN 'notation-finished' THEN 'results-published'
+    END
+)::project_state;
+ALTER TABLE projects ALTER COLUMN state SET DEFAULT 'scheduled';
+DROP TYPE project_state_old;
+
+-- The changes of state of the projects, made by a user or by the scheduler when the user is not set
+CREATE TABLE IF NOT EXISTS project_state_transitions
+(
+    id INT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    from_state project_state,
+    to_state project_state NOT NULL,
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS project_state_transitions_project_id_idx ON project_state_transitions (project_id);
+
+-- The history of the existing projects begins with their current state
+INSERT INTO project_state_transitions (project_id, to_state)
+SELECT id, state FROM projects;
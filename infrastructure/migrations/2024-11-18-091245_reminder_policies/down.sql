@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS project_reminder_policies;
+DROP TYPE IF EXISTS reminder_recipients;
@@ -0,0 +1,23 @@
+-- Your SQL goes here
+DO $$ BEGIN IF NOT EXISTS (
+  SELECT
+    1
+  FROM
+    pg_type
+  WHERE
+    typname = 'reminder_recipients'
+) THEN CREATE TYPE reminder_recipients AS ENUM ('students', 'teacher', 'both');
+END IF;
+END $$;
+
+-- The reminders of a project, replacing the alerts of the configuration of its teacher
+CREATE TABLE IF NOT EXISTS project_reminder_policies
+(
+    project_id UUID PRIMARY KEY REFERENCES projects(id) ON DELETE CASCADE,
+    -- Offsets such as {"anchor": "EvaluationClose", "hours": 24}
+    offsets JSONB[] NOT NULL DEFAULT '{}',
+    recipients reminder_recipients NOT NULL DEFAULT 'both',
+    -- Whether the teachers are told which students still haven't evaluated at the last reminder
+    escalate_to_teacher BOOLEAN NOT NULL DEFAULT FALSE,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
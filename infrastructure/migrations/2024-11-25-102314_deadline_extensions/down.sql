@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE students_tokens DROP COLUMN IF EXISTS submitted_late;
+DROP TABLE IF EXISTS project_late_policies;
+DROP TABLE IF EXISTS student_extensions;
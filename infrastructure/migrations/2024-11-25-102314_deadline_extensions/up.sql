@@ -0,0 +1,27 @@
+-- Your SQL goes here
+
+-- Extensions of the evaluation granted to single students, such as sick ones
+CREATE TABLE IF NOT EXISTS student_extensions
+(
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    student_id UUID NOT NULL REFERENCES students(id) ON DELETE CASCADE,
+    extended_until TIMESTAMPTZ NOT NULL,
+    reason TEXT,
+    granted_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    -- When the scheduler renewed the token of the student and told them, NULL until then
+    notified_at TIMESTAMPTZ,
+    PRIMARY KEY (project_id, student_id)
+);
+
+-- Submissions accepted after the deadline of the students, their marks being lowered by a penalty
+CREATE TABLE IF NOT EXISTS project_late_policies
+(
+    project_id UUID PRIMARY KEY REFERENCES projects(id) ON DELETE CASCADE,
+    late_hours INTEGER NOT NULL CHECK (late_hours > 0),
+    -- Percentage removed from the marks of the students who submitted late
+    penalty_percent DOUBLE PRECISION NOT NULL CHECK (penalty_percent BETWEEN 0 AND 100),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+ALTER TABLE students_tokens ADD COLUMN IF NOT EXISTS submitted_late BOOLEAN NOT NULL DEFAULT FALSE;
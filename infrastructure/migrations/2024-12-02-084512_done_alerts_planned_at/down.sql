@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE done_alerts DROP COLUMN IF EXISTS planned_at;
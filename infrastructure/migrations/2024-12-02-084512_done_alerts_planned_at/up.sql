@@ -0,0 +1,4 @@
+-- Your SQL goes here
+
+-- When a sent reminder was due, which it's recognized by even if it was sent late
+ALTER TABLE done_alerts ADD COLUMN IF NOT EXISTS planned_at TIMESTAMP;
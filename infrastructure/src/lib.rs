@@ -1,10 +1,115 @@
-use diesel::{pg::PgConnection, r2d2::{Pool, ConnectionManager}};
+use std::collections::HashSet;
+use std::time::Duration;
 
-pub type DBPool = Pool<ConnectionManager<PgConnection>>;
+use deadpool::managed::Timeouts;
+use deadpool::Runtime;
+use diesel::migration::MigrationSource;
+use diesel::pg::{Pg, PgConnection};
+use diesel::result::{DatabaseErrorKind, Error as DBError};
+use diesel::sql_types::Text;
+use diesel::{sql_query, Connection, QueryableByName};
+use diesel_async::pooled_connection::deadpool::{Object, Pool};
+use diesel_async::pooled_connection::{AsyncDieselConnectionManager, ManagerConfig};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+
+pub mod repositories;
+
+pub type DBPool = Pool<AsyncPgConnection>;
+pub type DBConnection = Object<AsyncPgConnection>;
+
+/// Migrations of the database, embedded in the binaries
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
+
+/// Size and timeouts of the pool of database connections.
+#[derive(Debug, Clone, Copy)]
+pub struct PoolSettings {
+    pub max_size: usize,
+    /// Time to wait for a connection of the pool to be available
+    pub wait_timeout: Duration,
+    /// Time to wait for a new connection to be established
+    pub connect_timeout: Duration,
+}
+
+impl Default for PoolSettings {
+    fn default() -> Self {
+        PoolSettings {
+            max_size: 10,
+            wait_timeout: Duration::from_secs(30),
+            connect_timeout: Duration::from_secs(10),
+        }
+    }
+}
 
 pub fn init_pool(database_url: &str) -> DBPool {
-    let manager = ConnectionManager::<PgConnection>::new(database_url);
-    Pool::builder()
-        .build(manager)
+    init_pool_with_settings(database_url, PoolSettings::default())
+}
+
+/// Creates a pool which doesn't connect to the database until a connection is requested.
+pub fn init_pool_with_settings(database_url: &str, settings: PoolSettings) -> DBPool {
+    let manager = AsyncDieselConnectionManager::<AsyncPgConnection>::new_with_config(database_url, ManagerConfig::default());
+    Pool::builder(manager)
+        .max_size(settings.max_size)
+        .wait_timeout(Some(settings.wait_timeout))
+        .create_timeout(Some(settings.connect_timeout))
+        .runtime(Runtime::Tokio1)
+        .build()
         .expect("Failed to create pool.")
-}
\ No newline at end of file
+}
+
+/// Returns a connection of the pool, the pool failing to provide one being reported as a closed connection.
+pub async fn get_connection(pool: &DBPool) -> Result<DBConnection, DBError> {
+    pool.get().await.map_err(|e| DBError::DatabaseError(DatabaseErrorKind::ClosedConnection, Box::new(e.to_string())))
+}
+
+/// Returns a connection of the pool, waiting at most `timeout` for it to be available or established.
+pub async fn get_connection_with_timeout(pool: &DBPool, timeout: Duration) -> Result<DBConnection, String> {
+    let timeouts = Timeouts {
+        wait: Some(timeout),
+        create: Some(timeout),
+        recycle: Some(timeout),
+    };
+    pool.timeout_get(&timeouts).await.map_err(|e| e.to_string())
+}
+
+#[derive(QueryableByName)]
+struct AppliedMigration {
+    #[diesel(sql_type = Text)]
+    version: String,
+}
+
+/// Returns the versions of the migrations which have not been applied to the database.
+pub async fn pending_migrations(conn: &mut AsyncPgConnection) -> Result<Vec<String>, String> {
+    let applied = sql_query("SELECT version FROM __diesel_schema_migrations")
+        .load::<AppliedMigration>(conn)
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|migration| migration.version)
+        .collect::<HashSet<_>>();
+
+    Ok(embedded_versions()?.into_iter()
+        .filter(|version| !applied.contains(version))
+        .collect())
+}
+
+/// Applies the migrations which have not been applied yet, and returns their versions.
+///
+/// The migrations run on a blocking connection of their own, outside of the pool.
+pub fn run_pending_migrations(database_url: &str) -> Result<Vec<String>, String> {
+    let mut conn = PgConnection::establish(database_url).map_err(|e| e.to_string())?;
+    conn.run_pending_migrations(MIGRATIONS)
+        .map(|versions| versions.iter().map(|version| version.to_string()).collect())
+        .map_err(|e| e.to_string())
+}
+
+/// Returns the version of the latest migration, which identifies the schema of the database.
+pub fn schema_version() -> Option<String> {
+    embedded_versions().ok()?.into_iter().max()
+}
+
+fn embedded_versions() -> Result<Vec<String>, String> {
+    MigrationSource::<Pg>::migrations(&MIGRATIONS)
+        .map(|migrations| migrations.iter().map(|migration| migration.name().version().to_string()).collect())
+        .map_err(|e| e.to_string())
+}
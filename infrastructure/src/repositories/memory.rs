@@ -0,0 +1,427 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use chrono::{DateTime, Utc};
+use diesel::result::Error as DBError;
+use domain::models::config::UserConfig;
+use domain::models::done_alerts::{AlertType, DoneAlert, NewDoneAlert};
+use domain::models::extensions::{NewProjectLatePolicy, NewStudentExtension, ProjectLatePolicy, StudentExtension};
+use domain::models::groups::{Group, GroupStudent, UpdatedGroupStudent};
+use domain::models::marks::Mark;
+use domain::models::organizations::Organization;
+use domain::models::projects::{Project, ProjectState, ProjectStateTransition, UpdatedProject};
+use domain::models::promotions::Promotion;
+use domain::models::reminder_policies::{NewProjectReminderPolicy, ProjectReminderPolicy};
+use domain::models::promotions_members::{PromotionMember, PromotionRole};
+use domain::models::students::Student;
+use domain::models::students_tokens::{NewStudentToken, StudentToken, UpdatedStudentToken};
+use domain::models::users::User;
+use domain::repositories::groups::GroupsRepository;
+use domain::repositories::marks::MarksRepository;
+use domain::repositories::organizations::OrganizationsRepository;
+use domain::repositories::projects::ProjectsRepository;
+use domain::repositories::promotions::PromotionsRepository;
+use domain::repositories::tokens::TokensRepository;
+use domain::repositories::users::UsersRepository;
+use domain::repositories::RepositoryResult;
+use uuid::Uuid;
+
+/// Rows of the tables the repositories read and write, filled by the tests.
+#[derive(Default)]
+pub struct InMemoryData {
+    pub users: Vec<User>,
+    pub user_configs: Vec<UserConfig>,
+    pub organizations: Vec<Organization>,
+    pub promotions: Vec<Promotion>,
+    pub promotions_members: Vec<PromotionMember>,
+    pub projects: Vec<Project>,
+    pub project_state_transitions: Vec<ProjectStateTransition>,
+    pub project_reminder_policies: Vec<ProjectReminderPolicy>,
+    pub project_late_policies: Vec<ProjectLatePolicy>,
+    pub student_extensions: Vec<StudentExtension>,
+    pub done_alerts: Vec<DoneAlert>,
+    pub students: Vec<Student>,
+    pub groups: Vec<Group>,
+    pub groups_students: Vec<GroupStudent>,
+    pub marks: Vec<Mark>,
+    pub students_tokens: Vec<StudentToken>,
+}
+
+/// Repositories kept in memory, to test the business logic without a database.
+///
+/// The clones share the same data.
+#[derive(Clone, Default)]
+pub struct InMemoryRepositories {
+    data: Arc<Mutex<InMemoryData>>,
+}
+
+impl InMemoryRepositories {
+    pub fn new(data: InMemoryData) -> Self {
+        InMemoryRepositories {
+            data: Arc::new(Mutex::new(data)),
+        }
+    }
+
+    pub fn data(&self) -> MutexGuard<'_, InMemoryData> {
+        self.data.lock().unwrap()
+    }
+}
+
+fn find<T: Clone>(rows: &[T], predicate: impl Fn(&T) -> bool) -> RepositoryResult<T> {
+    rows.iter().find(|row| predicate(row)).cloned().ok_or(DBError::NotFound)
+}
+
+/// Sets the fields of a project which are set, as with the changesets of Diesel.
+fn apply_updated_project(project: &mut Project, updated_project: UpdatedProject) {
+    if let Some(name) = updated_project.name { project.name = name; }
+    if let Some(description) = updated_project.description { project.description = Some(description); }
+    if let Some(start_date) = updated_project.start_date { project.start_date = start_date; }
+    if let Some(end_date) = updated_project.end_date { project.end_date = end_date; }
+    if let Some(duration) = updated_project.notation_period_duration { project.notation_period_duration = duration; }
+    if let Some(state) = updated_project.state { project.state = state; }
+    if let Some(timezone) = updated_project.timezone { project.timezone = Some(timezone); }
+}
+
+impl UsersRepository for InMemoryRepositories {
+    async fn get_user_by_id(&self, user_id: Uuid) -> RepositoryResult<User> {
+        find(&self.data().users, |user| user.id == user_id)
+    }
+
+    async fn get_config_by_user_id(&self, user_id: Uuid) -> RepositoryResult<UserConfig> {
+        find(&self.data().user_configs, |config| config.user_id == user_id)
+    }
+}
+
+impl OrganizationsRepository for InMemoryRepositories {
+    async fn get_organization_by_id(&self, id: Uuid) -> RepositoryResult<Organization> {
+        find(&self.data().organizations, |organization| organization.id == id)
+    }
+}
+
+impl PromotionsRepository for InMemoryRepositories {
+    async fn get_promotion_by_id(&self, id: Uuid) -> RepositoryResult<Promotion> {
+        find(&self.data().promotions, |promotion| promotion.id == id)
+    }
+
+    async fn get_members_emails_from_promotion_id(&self, promotion_id: Uuid, roles: &[PromotionRole]) -> RepositoryResult<Vec<String>> {
+        let data = self.data();
+        Ok(data.promotions_members.iter()
+            .filter(|member| member.promotion_id == promotion_id && roles.contains(&member.role))
+            .filter_map(|member| data.users.iter().find(|user| user.id == member.user_id))
+            .map(|user| user.email.clone())
+            .collect())
+    }
+
+    async fn get_promotion_timezone(&self, id: Uuid) -> RepositoryResult<String> {
+        let data = self.data();
+        let promotion = find(&data.promotions, |promotion| promotion.id == id)?;
+        let organization = find(&data.organizations, |organization| organization.id == promotion.organization_id)?;
+
+        Ok(data.user_configs.iter()
+            .find(|config| config.user_id == promotion.teacher_id)
+            .and_then(|config| config.timezone.clone())
+            .unwrap_or(organization.timezone))
+    }
+}
+
+impl ProjectsRepository for InMemoryRepositories {
+    async fn get_project_by_id(&self, id: Uuid) -> RepositoryResult<Project> {
+        find(&self.data().projects, |project| project.id == id)
+    }
+
+    async fn get_current_projects(&self) -> RepositoryResult<Vec<Project>> {
+        Ok(self.data().projects.iter()
+            .filter(|project| project.state.is_active())
+            .cloned()
+            .collect())
+    }
+
+    async fn update_project(&self, id: Uuid, updated_project: UpdatedProject) -> RepositoryResult<()> {
+        let mut data = self.data();
+        let project = data.projects.iter_mut().find(|project| project.id == id).ok_or(DBError::NotFound)?;
+        apply_updated_project(project, updated_project);
+
+        Ok(())
+    }
+
+    async fn transition_project(&self, id: Uuid, from: ProjectState, to: ProjectState, updated_project: UpdatedProject, user_id: Option<Uuid>) -> RepositoryResult<bool> {
+        let mut data = self.data();
+        let project = data.projects.iter_mut().find(|project| project.id == id).ok_or(DBError::NotFound)?;
+        if project.state != from {
+            return Ok(false);
+        }
+        apply_updated_project(project, UpdatedProject { state: Some(to), ..updated_project });
+
+        let transition_id = data.project_state_transitions.len() as i32 + 1;
+        data.project_state_transitions.push(ProjectStateTransition {
+            id: transition_id,
+            project_id: id,
+            from_state: Some(from),
+            to_state: to,
+            user_id,
+            created_at: chrono::Utc::now(),
+        });
+
+        Ok(true)
+    }
+
+    async fn get_project_state_transitions(&self, project_id: Uuid) -> RepositoryResult<Vec<ProjectStateTransition>> {
+        Ok(self.data().project_state_transitions.iter()
+            .filter(|transition| transition.project_id == project_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_project_reminder_policy(&self, project_id: Uuid) -> RepositoryResult<Option<ProjectReminderPolicy>> {
+        Ok(self.data().project_reminder_policies.iter()
+            .find(|policy| policy.project_id == project_id)
+            .cloned())
+    }
+
+    async fn set_project_reminder_policy(&self, new_policy: NewProjectReminderPolicy) -> RepositoryResult<ProjectReminderPolicy> {
+        let policy = ProjectReminderPolicy {
+            project_id: new_policy.project_id,
+            offsets: new_policy.offsets,
+            recipients: new_policy.recipients,
+            escalate_to_teacher: new_policy.escalate_to_teacher,
+            updated_at: chrono::Utc::now(),
+        };
+
+        let mut data = self.data();
+        data.project_reminder_policies.retain(|existing| existing.project_id != policy.project_id);
+        data.project_reminder_policies.push(policy.clone());
+
+        Ok(policy)
+    }
+
+    async fn delete_project_reminder_policy(&self, project_id: Uuid) -> RepositoryResult<()> {
+        self.data().project_reminder_policies.retain(|policy| policy.project_id != project_id);
+        Ok(())
+    }
+
+    async fn get_project_late_policy(&self, project_id: Uuid) -> RepositoryResult<Option<ProjectLatePolicy>> {
+        Ok(self.data().project_late_policies.iter()
+            .find(|policy| policy.project_id == project_id)
+            .cloned())
+    }
+
+    async fn set_project_late_policy(&self, new_policy: NewProjectLatePolicy) -> RepositoryResult<ProjectLatePolicy> {
+        let policy = ProjectLatePolicy {
+            project_id: new_policy.project_id,
+            late_hours: new_policy.late_hours,
+            penalty_percent: new_policy.penalty_percent,
+            updated_at: Utc::now(),
+        };
+
+        let mut data = self.data();
+        data.project_late_policies.retain(|existing| existing.project_id != policy.project_id);
+        data.project_late_policies.push(policy.clone());
+
+        Ok(policy)
+    }
+
+    async fn delete_project_late_policy(&self, project_id: Uuid) -> RepositoryResult<()> {
+        self.data().project_late_policies.retain(|policy| policy.project_id != project_id);
+        Ok(())
+    }
+
+    async fn get_student_extensions(&self, project_id: Uuid) -> RepositoryResult<Vec<StudentExtension>> {
+        let mut extensions: Vec<StudentExtension> = self.data().student_extensions.iter()
+            .filter(|extension| extension.project_id == project_id)
+            .cloned()
+            .collect();
+        extensions.sort_by_key(|extension| extension.extended_until);
+
+        Ok(extensions)
+    }
+
+    async fn set_student_extension(&self, new_extension: NewStudentExtension) -> RepositoryResult<StudentExtension> {
+        let mut data = self.data();
+        let created_at = data.student_extensions.iter()
+            .find(|existing| existing.project_id == new_extension.project_id && existing.student_id == new_extension.student_id)
+            .map_or_else(Utc::now, |existing| existing.created_at);
+        let extension = StudentExtension {
+            project_id: new_extension.project_id,
+            student_id: new_extension.student_id,
+            extended_until: new_extension.extended_until,
+            reason: new_extension.reason,
+            granted_by: new_extension.granted_by,
+            created_at,
+            notified_at: None,
+        };
+
+        data.student_extensions.retain(|existing| existing.project_id != extension.project_id || existing.student_id != extension.student_id);
+        data.student_extensions.push(extension.clone());
+
+        Ok(extension)
+    }
+
+    async fn delete_student_extension(&self, project_id: Uuid, student_id: Uuid) -> RepositoryResult<bool> {
+        let mut data = self.data();
+        let count = data.student_extensions.len();
+        data.student_extensions.retain(|extension| extension.project_id != project_id || extension.student_id != student_id);
+
+        Ok(data.student_extensions.len() < count)
+    }
+
+    async fn set_student_extension_notified(&self, project_id: Uuid, student_id: Uuid, notified_at: DateTime<Utc>) -> RepositoryResult<()> {
+        self.data().student_extensions.iter_mut()
+            .filter(|extension| extension.project_id == project_id && extension.student_id == student_id)
+            .for_each(|extension| extension.notified_at = Some(notified_at));
+
+        Ok(())
+    }
+
+    async fn get_done_alerts_by_project_id_and_type(&self, project_id: Uuid, alert_type: AlertType) -> RepositoryResult<Vec<DoneAlert>> {
+        Ok(self.data().done_alerts.iter()
+            .filter(|alert| alert.project_id == project_id && alert.type_ == alert_type)
+            .cloned()
+            .collect())
+    }
+
+    async fn create_done_alert(&self, new_done_alert: NewDoneAlert) -> RepositoryResult<()> {
+        let mut data = self.data();
+        let id = data.done_alerts.len() as i32 + 1;
+        data.done_alerts.push(DoneAlert {
+            id,
+            description: new_done_alert.description,
+            project_id: new_done_alert.project_id,
+            type_: new_done_alert.type_,
+            published_at: chrono::Utc::now().naive_utc(),
+            planned_at: new_done_alert.planned_at,
+        });
+
+        Ok(())
+    }
+}
+
+impl GroupsRepository for InMemoryRepositories {
+    async fn get_groups_from_project_id(&self, project_id: Uuid) -> RepositoryResult<Vec<Group>> {
+        Ok(self.data().groups.iter()
+            .filter(|group| group.project_id == project_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_students_from_groups(&self, group_ids: Vec<Uuid>) -> RepositoryResult<Vec<Student>> {
+        let data = self.data();
+        Ok(data.students.iter()
+            .filter(|student| data.groups_students.iter()
+                .any(|group_student| group_student.student_id == student.id && group_ids.contains(&group_student.group_id)))
+            .cloned()
+            .collect())
+    }
+
+    async fn get_groups_students_from_project_id(&self, project_id: Uuid) -> RepositoryResult<Vec<(GroupStudent, Student)>> {
+        let data = self.data();
+        Ok(data.groups_students.iter()
+            .filter(|group_student| data.groups.iter()
+                .any(|group| group.id == group_student.group_id && group.project_id == project_id))
+            .filter_map(|group_student| data.students.iter()
+                .find(|student| student.id == group_student.student_id)
+                .map(|student| (group_student.clone(), student.clone())))
+            .collect())
+    }
+
+    async fn get_group_from_student_and_project_id(&self, student_id: Uuid, project_id: Uuid) -> RepositoryResult<Option<Group>> {
+        let data = self.data();
+        Ok(data.groups.iter()
+            .find(|group| group.project_id == project_id && data.groups_students.iter()
+                .any(|group_student| group_student.group_id == group.id && group_student.student_id == student_id))
+            .cloned())
+    }
+
+    async fn update_group_student(&self, group_id: Uuid, student_id: Uuid, updated_group_student: UpdatedGroupStudent) -> RepositoryResult<()> {
+        let mut data = self.data();
+        let group_student = data.groups_students.iter_mut()
+            .find(|group_student| group_student.group_id == group_id && group_student.student_id == student_id)
+            .ok_or(DBError::NotFound)?;
+
+        if let Some(student_mark) = updated_group_student.student_mark {
+            group_student.student_mark = Some(student_mark);
+        }
+
+        Ok(())
+    }
+
+    async fn update_groups_students_marks(&self, marks: Vec<(Uuid, Uuid, f64)>) -> RepositoryResult<()> {
+        let mut data = self.data();
+        for (group_id, student_id, mark) in marks {
+            // Like the UPDATE, the marks of students who aren't in the group are ignored
+            if let Some(group_student) = data.groups_students.iter_mut()
+                .find(|group_student| group_student.group_id == group_id && group_student.student_id == student_id) {
+                group_student.student_mark = Some(mark);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl MarksRepository for InMemoryRepositories {
+    async fn get_marks_given_to_student_id_and_group_id(&self, student_id: Uuid, group_id: Uuid) -> RepositoryResult<Vec<Mark>> {
+        Ok(self.data().marks.iter()
+            .filter(|mark| mark.noted_student_id == student_id && mark.group_id == group_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_marks_from_project_id(&self, project_id: Uuid) -> RepositoryResult<Vec<Mark>> {
+        Ok(self.data().marks.iter()
+            .filter(|mark| mark.project_id == project_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_students_who_didnt_evaluate_group(&self, project_id: Uuid) -> RepositoryResult<Vec<Student>> {
+        let data = self.data();
+        let groups_ids: Vec<Uuid> = data.groups.iter()
+            .filter(|group| group.project_id == project_id)
+            .map(|group| group.id)
+            .collect();
+
+        Ok(data.students.iter()
+            .filter(|student| data.groups_students.iter()
+                .any(|group_student| group_student.student_id == student.id && groups_ids.contains(&group_student.group_id)))
+            .filter(|student| !data.marks.iter()
+                .any(|mark| mark.project_id == project_id && mark.grader_student_id == student.id))
+            .cloned()
+            .collect())
+    }
+}
+
+impl TokensRepository for InMemoryRepositories {
+    async fn create_student_token(&self, new_student_token: NewStudentToken) -> RepositoryResult<Uuid> {
+        let id = Uuid::new_v4();
+        self.data().students_tokens.push(StudentToken {
+            id,
+            token: new_student_token.token,
+            student_id: new_student_token.student_id,
+            project_id: new_student_token.project_id,
+            used: false,
+            submitted_late: false,
+        });
+
+        Ok(id)
+    }
+
+    async fn update_student_token(&self, id: Uuid, updated_student_token: UpdatedStudentToken) -> RepositoryResult<()> {
+        let mut data = self.data();
+        let student_token = data.students_tokens.iter_mut().find(|token| token.id == id).ok_or(DBError::NotFound)?;
+        if let Some(token) = updated_student_token.token { student_token.token = token; }
+        if let Some(used) = updated_student_token.used { student_token.used = used; }
+        if let Some(submitted_late) = updated_student_token.submitted_late { student_token.submitted_late = submitted_late; }
+
+        Ok(())
+    }
+
+    async fn get_student_tokens_from_student_and_project_id(&self, student_id: Uuid, project_id: Uuid) -> RepositoryResult<StudentToken> {
+        find(&self.data().students_tokens, |token| token.student_id == student_id && token.project_id == project_id)
+    }
+
+    async fn get_student_tokens_from_project_id(&self, project_id: Uuid) -> RepositoryResult<Vec<StudentToken>> {
+        Ok(self.data().students_tokens.iter()
+            .filter(|token| token.project_id == project_id)
+            .cloned()
+            .collect())
+    }
+}
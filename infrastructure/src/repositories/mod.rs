@@ -0,0 +1,7 @@
+//! Implementations of the repositories of `domain::repositories`.
+
+mod memory;
+mod postgres;
+
+pub use memory::{InMemoryData, InMemoryRepositories};
+pub use postgres::PgRepositories;
@@ -0,0 +1,99 @@
+use diesel::prelude::*;
+use diesel::sql_types::{Array, Double};
+use diesel_async::RunQueryDsl;
+use domain::models::groups::{Group, GroupStudent, UpdatedGroupStudent};
+use domain::models::students::Student;
+use domain::repositories::groups::GroupsRepository;
+use domain::repositories::RepositoryResult;
+use uuid::Uuid;
+
+use super::PgRepositories;
+
+impl GroupsRepository for PgRepositories {
+    async fn get_groups_from_project_id(&self, project_id_: Uuid) -> RepositoryResult<Vec<Group>> {
+        use domain::schema::groups::dsl::*;
+
+        groups.filter(project_id.eq(project_id_))
+            .load::<Group>(&mut self.connection().await?).await
+    }
+
+    async fn get_students_from_groups(&self, group_ids: Vec<Uuid>) -> RepositoryResult<Vec<Student>> {
+        use domain::schema::groups_students::dsl::*;
+        use domain::schema::students::dsl::*;
+
+        let mut conn = self.connection().await?;
+        let students_ids = groups_students
+            .filter(group_id.eq_any(group_ids))
+            .select(student_id)
+            .load::<Uuid>(&mut conn).await?;
+
+        students.filter(id.eq_any(students_ids))
+            .load::<Student>(&mut conn).await
+    }
+
+    async fn get_groups_students_from_project_id(&self, project_id_: Uuid) -> RepositoryResult<Vec<(GroupStudent, Student)>> {
+        use domain::schema::{groups, groups_students, students};
+
+        groups_students::table
+            .inner_join(groups::table)
+            .inner_join(students::table)
+            .filter(groups::project_id.eq(project_id_))
+            .order((groups_students::group_id, students::surname, students::name))
+            .select((GroupStudent::as_select(), Student::as_select()))
+            .load::<(GroupStudent, Student)>(&mut self.connection().await?).await
+    }
+
+    async fn get_group_from_student_and_project_id(&self, student_id_: Uuid, project_id_: Uuid) -> RepositoryResult<Option<Group>> {
+        use domain::schema::groups::dsl::*;
+        use domain::schema::groups_students::dsl::*;
+
+        groups_students
+            .filter(student_id.eq(student_id_))
+            .inner_join(groups)
+            .filter(project_id.eq(project_id_))
+            .select(Group::as_select())
+            .first::<Group>(&mut self.connection().await?).await
+            .optional()
+    }
+
+    async fn update_group_student(&self, group_id_: Uuid, student_id_: Uuid, updated_group_student: UpdatedGroupStudent) -> RepositoryResult<()> {
+        use domain::schema::groups_students::dsl::*;
+
+        let mut conn = self.connection().await?;
+
+        // Check if the student is in the group
+        groups_students
+            .filter(group_id.eq(group_id_))
+            .filter(student_id.eq(student_id_))
+            .first::<GroupStudent>(&mut conn).await?;
+
+        diesel::update(groups_students
+            .filter(group_id.eq(group_id_))
+            .filter(student_id.eq(student_id_))
+        ).set(&updated_group_student)
+            .execute(&mut conn).await?;
+
+        Ok(())
+    }
+
+    async fn update_groups_students_marks(&self, marks: Vec<(Uuid, Uuid, f64)>) -> RepositoryResult<()> {
+        if marks.is_empty() {
+            return Ok(());
+        }
+
+        let (groups_ids, (students_ids, students_marks)): (Vec<Uuid>, (Vec<Uuid>, Vec<f64>)) = marks.into_iter()
+            .map(|(group_id_, student_id_, mark)| (group_id_, (student_id_, mark)))
+            .unzip();
+
+        diesel::sql_query(
+            "UPDATE groups_students SET student_mark = new_marks.student_mark \
+            FROM unnest($1, $2, $3) AS new_marks(group_id, student_id, student_mark) \
+            WHERE groups_students.group_id = new_marks.group_id AND groups_students.student_id = new_marks.student_id"
+        ).bind::<Array<diesel::sql_types::Uuid>, _>(groups_ids)
+            .bind::<Array<diesel::sql_types::Uuid>, _>(students_ids)
+            .bind::<Array<Double>, _>(students_marks)
+            .execute(&mut self.connection().await?).await?;
+
+        Ok(())
+    }
+}
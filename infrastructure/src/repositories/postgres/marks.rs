@@ -0,0 +1,44 @@
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use domain::models::marks::Mark;
+use domain::models::students::Student;
+use domain::repositories::marks::MarksRepository;
+use domain::repositories::RepositoryResult;
+use uuid::Uuid;
+
+use super::PgRepositories;
+
+impl MarksRepository for PgRepositories {
+    async fn get_marks_given_to_student_id_and_group_id(&self, student_id_: Uuid, group_id_: Uuid) -> RepositoryResult<Vec<Mark>> {
+        use domain::schema::marks::dsl::*;
+
+        marks.filter(noted_student_id.eq(student_id_))
+            .filter(group_id.eq(group_id_))
+            .get_results(&mut self.connection().await?).await
+    }
+
+    async fn get_marks_from_project_id(&self, project_id_: Uuid) -> RepositoryResult<Vec<Mark>> {
+        use domain::schema::marks::dsl::*;
+
+        marks.filter(project_id.eq(project_id_))
+            .get_results(&mut self.connection().await?).await
+    }
+
+    async fn get_students_who_didnt_evaluate_group(&self, project_id_: Uuid) -> RepositoryResult<Vec<Student>> {
+        use domain::schema::{groups, groups_students, marks, students};
+
+        let mut conn = self.connection().await?;
+        let graders_ids = marks::table
+            .filter(marks::project_id.eq(project_id_))
+            .select(marks::grader_student_id)
+            .load::<Uuid>(&mut conn).await?;
+
+        students::table
+            .inner_join(groups_students::table.inner_join(groups::table))
+            .filter(groups::project_id.eq(project_id_))
+            .filter(students::id.ne_all(graders_ids))
+            .select(Student::as_select())
+            .distinct()
+            .load::<Student>(&mut conn).await
+    }
+}
@@ -0,0 +1,27 @@
+mod groups;
+mod marks;
+mod organizations;
+mod projects;
+mod promotions;
+mod tokens;
+mod users;
+
+use diesel::result::Error as DBError;
+
+use crate::{get_connection, DBConnection, DBPool};
+
+/// Repositories stored in PostgreSQL, each query using a connection of the pool.
+#[derive(Clone)]
+pub struct PgRepositories {
+    pool: DBPool,
+}
+
+impl PgRepositories {
+    pub fn new(pool: DBPool) -> Self {
+        PgRepositories { pool }
+    }
+
+    async fn connection(&self) -> Result<DBConnection, DBError> {
+        get_connection(&self.pool).await
+    }
+}
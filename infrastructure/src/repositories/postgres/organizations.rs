@@ -0,0 +1,17 @@
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use domain::models::organizations::Organization;
+use domain::repositories::organizations::OrganizationsRepository;
+use domain::repositories::RepositoryResult;
+use uuid::Uuid;
+
+use super::PgRepositories;
+
+impl OrganizationsRepository for PgRepositories {
+    async fn get_organization_by_id(&self, id_: Uuid) -> RepositoryResult<Organization> {
+        use domain::schema::organizations::dsl::*;
+
+        organizations.filter(id.eq(id_))
+            .first(&mut self.connection().await?).await
+    }
+}
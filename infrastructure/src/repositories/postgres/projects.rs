@@ -0,0 +1,194 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use domain::models::done_alerts::{AlertType, DoneAlert, NewDoneAlert};
+use domain::models::extensions::{NewProjectLatePolicy, NewStudentExtension, ProjectLatePolicy, StudentExtension};
+use domain::models::projects::{NewProjectStateTransition, Project, ProjectState, ProjectStateTransition, UpdatedProject};
+use domain::models::reminder_policies::{NewProjectReminderPolicy, ProjectReminderPolicy};
+use domain::repositories::projects::ProjectsRepository;
+use domain::repositories::RepositoryResult;
+use uuid::Uuid;
+
+use super::PgRepositories;
+
+impl ProjectsRepository for PgRepositories {
+    async fn get_project_by_id(&self, id_: Uuid) -> RepositoryResult<Project> {
+        use domain::schema::projects::dsl::*;
+
+        projects.filter(id.eq(id_))
+            .first(&mut self.connection().await?).await
+    }
+
+    async fn get_current_projects(&self) -> RepositoryResult<Vec<Project>> {
+        use domain::schema::projects::dsl::*;
+
+        projects.filter(state.eq_any(ProjectState::ACTIVE))
+            .get_results(&mut self.connection().await?).await
+    }
+
+    async fn update_project(&self, id_: Uuid, updated_project: UpdatedProject) -> RepositoryResult<()> {
+        use domain::schema::projects::dsl::*;
+
+        let mut conn = self.connection().await?;
+
+        // Check if the project exists
+        projects.filter(id.eq(id_))
+            .first::<Project>(&mut conn).await?;
+
+        diesel::update(projects.filter(id.eq(id_)))
+            .set(&updated_project)
+            .execute(&mut conn).await?;
+
+        Ok(())
+    }
+
+    async fn transition_project(&self, id_: Uuid, from: ProjectState, to: ProjectState, updated_project: UpdatedProject, user_id: Option<Uuid>) -> RepositoryResult<bool> {
+        use domain::schema::project_state_transitions;
+        use domain::schema::projects::dsl::*;
+
+        let updated_project = UpdatedProject { state: Some(to), ..updated_project };
+        let new_transition = NewProjectStateTransition {
+            project_id: id_,
+            from_state: Some(from),
+            to_state: to,
+            user_id,
+        };
+
+        let mut conn = self.connection().await?;
+        conn.transaction(|conn| async move {
+            // Only moves the project if another transition didn't happen meanwhile
+            let updated = diesel::update(projects.filter(id.eq(id_)).filter(state.eq(from)))
+                .set(&updated_project)
+                .execute(conn).await?;
+            if updated == 0 {
+                return Ok(false);
+            }
+
+            diesel::insert_into(project_state_transitions::table)
+                .values(&new_transition)
+                .execute(conn).await?;
+
+            Ok(true)
+        }.scope_boxed()).await
+    }
+
+    async fn get_project_state_transitions(&self, project_id_: Uuid) -> RepositoryResult<Vec<ProjectStateTransition>> {
+        use domain::schema::project_state_transitions::dsl::*;
+
+        project_state_transitions.filter(project_id.eq(project_id_))
+            .order(id.asc())
+            .get_results(&mut self.connection().await?).await
+    }
+
+    async fn get_project_reminder_policy(&self, project_id_: Uuid) -> RepositoryResult<Option<ProjectReminderPolicy>> {
+        use domain::schema::project_reminder_policies::dsl::*;
+
+        project_reminder_policies.filter(project_id.eq(project_id_))
+            .first(&mut self.connection().await?).await
+            .optional()
+    }
+
+    async fn set_project_reminder_policy(&self, new_policy: NewProjectReminderPolicy) -> RepositoryResult<ProjectReminderPolicy> {
+        use domain::schema::project_reminder_policies::dsl::*;
+
+        diesel::insert_into(project_reminder_policies)
+            .values(&new_policy)
+            .on_conflict(project_id)
+            .do_update()
+            .set((&new_policy, updated_at.eq(diesel::dsl::now)))
+            .get_result(&mut self.connection().await?).await
+    }
+
+    async fn delete_project_reminder_policy(&self, project_id_: Uuid) -> RepositoryResult<()> {
+        use domain::schema::project_reminder_policies::dsl::*;
+
+        diesel::delete(project_reminder_policies.filter(project_id.eq(project_id_)))
+            .execute(&mut self.connection().await?).await?;
+
+        Ok(())
+    }
+
+    async fn get_project_late_policy(&self, project_id_: Uuid) -> RepositoryResult<Option<ProjectLatePolicy>> {
+        use domain::schema::project_late_policies::dsl::*;
+
+        project_late_policies.filter(project_id.eq(project_id_))
+            .first(&mut self.connection().await?).await
+            .optional()
+    }
+
+    async fn set_project_late_policy(&self, new_policy: NewProjectLatePolicy) -> RepositoryResult<ProjectLatePolicy> {
+        use domain::schema::project_late_policies::dsl::*;
+
+        diesel::insert_into(project_late_policies)
+            .values(&new_policy)
+            .on_conflict(project_id)
+            .do_update()
+            .set((&new_policy, updated_at.eq(diesel::dsl::now)))
+            .get_result(&mut self.connection().await?).await
+    }
+
+    async fn delete_project_late_policy(&self, project_id_: Uuid) -> RepositoryResult<()> {
+        use domain::schema::project_late_policies::dsl::*;
+
+        diesel::delete(project_late_policies.filter(project_id.eq(project_id_)))
+            .execute(&mut self.connection().await?).await?;
+
+        Ok(())
+    }
+
+    async fn get_student_extensions(&self, project_id_: Uuid) -> RepositoryResult<Vec<StudentExtension>> {
+        use domain::schema::student_extensions::dsl::*;
+
+        student_extensions.filter(project_id.eq(project_id_))
+            .order(extended_until.asc())
+            .get_results(&mut self.connection().await?).await
+    }
+
+    async fn set_student_extension(&self, new_extension: NewStudentExtension) -> RepositoryResult<StudentExtension> {
+        use domain::schema::student_extensions::dsl::*;
+
+        diesel::insert_into(student_extensions)
+            .values(&new_extension)
+            .on_conflict((project_id, student_id))
+            .do_update()
+            .set((&new_extension, notified_at.eq(None::<DateTime<Utc>>)))
+            .get_result(&mut self.connection().await?).await
+    }
+
+    async fn delete_student_extension(&self, project_id_: Uuid, student_id_: Uuid) -> RepositoryResult<bool> {
+        use domain::schema::student_extensions::dsl::*;
+
+        diesel::delete(student_extensions.filter(project_id.eq(project_id_)).filter(student_id.eq(student_id_)))
+            .execute(&mut self.connection().await?).await
+            .map(|deleted| deleted > 0)
+    }
+
+    async fn set_student_extension_notified(&self, project_id_: Uuid, student_id_: Uuid, notified_at_: DateTime<Utc>) -> RepositoryResult<()> {
+        use domain::schema::student_extensions::dsl::*;
+
+        diesel::update(student_extensions.filter(project_id.eq(project_id_)).filter(student_id.eq(student_id_)))
+            .set(notified_at.eq(notified_at_))
+            .execute(&mut self.connection().await?).await?;
+
+        Ok(())
+    }
+
+    async fn get_done_alerts_by_project_id_and_type(&self, project_id_: Uuid, alert_type: AlertType) -> RepositoryResult<Vec<DoneAlert>> {
+        use domain::schema::done_alerts::dsl::*;
+
+        done_alerts.filter(project_id.eq(project_id_))
+            .filter(type_.eq(alert_type))
+            .get_results(&mut self.connection().await?).await
+    }
+
+    async fn create_done_alert(&self, new_done_alert: NewDoneAlert) -> RepositoryResult<()> {
+        use domain::schema::done_alerts::dsl::*;
+
+        diesel::insert_into(done_alerts)
+            .values(&new_done_alert)
+            .execute(&mut self.connection().await?).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,43 @@
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use domain::models::promotions::Promotion;
+use domain::models::promotions_members::PromotionRole;
+use domain::repositories::promotions::PromotionsRepository;
+use domain::repositories::RepositoryResult;
+use uuid::Uuid;
+
+use super::PgRepositories;
+
+impl PromotionsRepository for PgRepositories {
+    async fn get_promotion_by_id(&self, id_: Uuid) -> RepositoryResult<Promotion> {
+        use domain::schema::promotions::dsl::*;
+
+        promotions.filter(id.eq(id_))
+            .first(&mut self.connection().await?).await
+    }
+
+    async fn get_members_emails_from_promotion_id(&self, promotion_id_: Uuid, roles: &[PromotionRole]) -> RepositoryResult<Vec<String>> {
+        use domain::schema::promotions_members::dsl::*;
+        use domain::schema::users;
+
+        promotions_members
+            .inner_join(users::table)
+            .filter(promotion_id.eq(promotion_id_))
+            .filter(role.eq_any(roles))
+            .select(users::email)
+            .load::<String>(&mut self.connection().await?).await
+    }
+
+    async fn get_promotion_timezone(&self, id_: Uuid) -> RepositoryResult<String> {
+        use domain::schema::{organizations, promotions, user_config};
+
+        let (teacher_timezone, organization_timezone) = promotions::table
+            .inner_join(organizations::table)
+            .left_join(user_config::table.on(user_config::user_id.eq(promotions::teacher_id)))
+            .filter(promotions::id.eq(id_))
+            .select((user_config::timezone.nullable(), organizations::timezone))
+            .first::<(Option<String>, String)>(&mut self.connection().await?).await?;
+
+        Ok(teacher_timezone.unwrap_or(organization_timezone))
+    }
+}
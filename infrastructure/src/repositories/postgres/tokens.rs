@@ -0,0 +1,47 @@
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use domain::models::students_tokens::{NewStudentToken, StudentToken, UpdatedStudentToken};
+use domain::repositories::tokens::TokensRepository;
+use domain::repositories::RepositoryResult;
+use uuid::Uuid;
+
+use super::PgRepositories;
+
+impl TokensRepository for PgRepositories {
+    async fn create_student_token(&self, new_student_token: NewStudentToken) -> RepositoryResult<Uuid> {
+        use domain::schema::students_tokens::dsl::*;
+
+        diesel::insert_into(students_tokens)
+            .values(&new_student_token)
+            .returning(id)
+            .get_result(&mut self.connection().await?).await
+    }
+
+    async fn update_student_token(&self, id_: Uuid, updated_student_token: UpdatedStudentToken) -> RepositoryResult<()> {
+        use domain::schema::students_tokens::dsl::*;
+
+        let updated = diesel::update(students_tokens.filter(id.eq(id_)))
+            .set(&updated_student_token)
+            .execute(&mut self.connection().await?).await?;
+
+        match updated {
+            0 => Err(diesel::result::Error::NotFound),
+            _ => Ok(()),
+        }
+    }
+
+    async fn get_student_tokens_from_student_and_project_id(&self, student_id_: Uuid, project_id_: Uuid) -> RepositoryResult<StudentToken> {
+        use domain::schema::students_tokens::dsl::*;
+
+        students_tokens.filter(student_id.eq(student_id_))
+            .filter(project_id.eq(project_id_))
+            .first(&mut self.connection().await?).await
+    }
+
+    async fn get_student_tokens_from_project_id(&self, project_id_: Uuid) -> RepositoryResult<Vec<StudentToken>> {
+        use domain::schema::students_tokens::dsl::*;
+
+        students_tokens.filter(project_id.eq(project_id_))
+            .load(&mut self.connection().await?).await
+    }
+}
@@ -0,0 +1,25 @@
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use domain::models::config::UserConfig;
+use domain::models::users::User;
+use domain::repositories::users::UsersRepository;
+use domain::repositories::RepositoryResult;
+use uuid::Uuid;
+
+use super::PgRepositories;
+
+impl UsersRepository for PgRepositories {
+    async fn get_user_by_id(&self, user_id: Uuid) -> RepositoryResult<User> {
+        use domain::schema::users::dsl::*;
+
+        users.filter(id.eq(user_id))
+            .first(&mut self.connection().await?).await
+    }
+
+    async fn get_config_by_user_id(&self, user_id_: Uuid) -> RepositoryResult<UserConfig> {
+        use domain::schema::user_config::dsl::*;
+
+        user_config.filter(user_id.eq(user_id_))
+            .first(&mut self.connection().await?).await
+    }
+}
@@ -1,13 +1,19 @@
 use actix_web::rt::task::JoinHandle;
+use chrono::{DateTime, Utc};
+use infrastructure::repositories::PgRepositories;
 use infrastructure::DBPool;
-use lettre::SmtpTransport;
+use lettre::{AsyncSmtpTransport, Tokio1Executor};
 use parking_lot::{Mutex, RwLock};
 use std::collections::HashMap;
 use std::sync::Arc;
+use tokio::sync::OwnedMutexGuard;
 use uuid::Uuid;
 
 use crate::app_config::Config;
 
+/// Client of the SMTP server, sending the emails without blocking the runtime
+pub type MailTransport = AsyncSmtpTransport<Tokio1Executor>;
+
 // type TimeoutFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
 
 pub struct ProjectTimeouts {
@@ -15,32 +21,58 @@ pub struct ProjectTimeouts {
     pub timeouts: Vec<JoinHandle<()>>,
 }
 
+/// Progress of the preparation of the database when the server starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StartupStatus {
+    Starting,
+    Started,
+    /// The preparation stopped on an error, the API staying unavailable
+    Failed(String),
+}
+
 pub struct RuntimeValues {
-    pub projects_checker: Arc<Mutex<Option<u64>>>,
+    /// Whether the database is ready, the API being unavailable until it is
+    pub startup_status: Arc<Mutex<StartupStatus>>,
+    /// Task running the checks of the projects at the times of the schedule
+    pub projects_checker: Arc<Mutex<Option<JoinHandle<()>>>>,
     pub project_reminders: Arc<Mutex<HashMap<Uuid, ProjectTimeouts>>>,
+    /// When the scheduler last completed a run
+    pub scheduler_heartbeat: Arc<Mutex<Option<DateTime<Utc>>>>,
+    /// When the scheduler runs next
+    pub scheduler_next_run: Arc<Mutex<Option<DateTime<Utc>>>>,
+    /// Held during a run of the scheduler, so the scheduled and manual runs don't overlap
+    pub scheduler_lock: Arc<tokio::sync::Mutex<()>>,
 }
 
 impl RuntimeValues {
     pub fn init() -> RuntimeValues {
+        let startup_status = Arc::new(Mutex::new(StartupStatus::Starting));
         let projects_checker = Arc::new(Mutex::new(None));
         let project_reminders = Arc::new(Mutex::new(HashMap::new()));
+        let scheduler_heartbeat = Arc::new(Mutex::new(None));
+        let scheduler_next_run = Arc::new(Mutex::new(None));
+        let scheduler_lock = Arc::new(tokio::sync::Mutex::new(()));
 
         RuntimeValues {
+            startup_status,
             projects_checker,
             project_reminders,
+            scheduler_heartbeat,
+            scheduler_next_run,
+            scheduler_lock,
         }
     }
 }
 
 pub struct AppState {
     pub database_pool: Arc<DBPool>,
-    pub smtp_transport: Arc<SmtpTransport>,
+    pub smtp_transport: Arc<MailTransport>,
     pub config: Arc<RwLock<Config>>,
     pub runtime_values: Arc<RwLock<RuntimeValues>>,
 }
 
 impl AppState {
-    pub fn init(database_pool: DBPool, smtp_transport: SmtpTransport, config: Config) -> AppState {
+    pub fn init(database_pool: DBPool, smtp_transport: MailTransport, config: Config) -> AppState {
         let database_pool = Arc::new(database_pool);
         let smtp_transport = Arc::new(smtp_transport);
         let config = Arc::new(RwLock::new(config));
@@ -54,12 +86,66 @@ impl AppState {
         }
     }
 
+    /// Repositories sharing the pool of connections of the application.
+    pub fn repositories(&self) -> PgRepositories {
+        PgRepositories::new(self.database_pool.as_ref().clone())
+    }
+
     pub fn get_all_project_ids(&self) -> Vec<Uuid> {
         let runtime_values = self.runtime_values.read();
         let project_reminders = runtime_values.project_reminders.lock();
         project_reminders.keys().cloned().collect()
     }
 
+    pub fn set_startup_status(&self, status: StartupStatus) {
+        let runtime_values = self.runtime_values.read();
+        *runtime_values.startup_status.lock() = status;
+    }
+
+    pub fn get_startup_status(&self) -> StartupStatus {
+        let runtime_values = self.runtime_values.read();
+        let startup_status = runtime_values.startup_status.lock();
+        startup_status.clone()
+    }
+
+    pub fn record_scheduler_heartbeat(&self) {
+        let runtime_values = self.runtime_values.read();
+        *runtime_values.scheduler_heartbeat.lock() = Some(Utc::now());
+    }
+
+    pub fn get_scheduler_heartbeat(&self) -> Option<DateTime<Utc>> {
+        let runtime_values = self.runtime_values.read();
+        let scheduler_heartbeat = runtime_values.scheduler_heartbeat.lock();
+        *scheduler_heartbeat
+    }
+
+    pub fn set_scheduler_next_run(&self, next_run: Option<DateTime<Utc>>) {
+        let runtime_values = self.runtime_values.read();
+        *runtime_values.scheduler_next_run.lock() = next_run;
+    }
+
+    pub fn get_scheduler_next_run(&self) -> Option<DateTime<Utc>> {
+        let runtime_values = self.runtime_values.read();
+        let scheduler_next_run = runtime_values.scheduler_next_run.lock();
+        *scheduler_next_run
+    }
+
+    /// Waits for the current run of the scheduler to end, the returned guard preventing another one until it's dropped.
+    pub async fn lock_scheduler(&self) -> OwnedMutexGuard<()> {
+        let scheduler_lock = Arc::clone(&self.runtime_values.read().scheduler_lock);
+        scheduler_lock.lock_owned().await
+    }
+
+    /// Counts the reminders which have not been sent yet.
+    pub fn count_pending_reminders(&self) -> usize {
+        let runtime_values = self.runtime_values.read();
+        let project_reminders = runtime_values.project_reminders.lock();
+        project_reminders.values()
+            .flat_map(|project_timeouts| project_timeouts.timeouts.iter())
+            .filter(|timeout| !timeout.is_finished())
+            .count()
+    }
+
     pub fn get_project_reminders(&self, project_id: Uuid) -> Option<ProjectTimeouts> {
         let mut runtime_values = self.runtime_values.write();
         let mut project_reminders = runtime_values.project_reminders.lock();
@@ -0,0 +1,166 @@
+//! Schedules written as cron expressions, such as `0 * * * *` for every hour.
+//!
+//! The five fields are the minute, the hour, the day of the month, the month and the day of the week, 0 and 7
+//! being Sunday, evaluated in UTC. Each field is `*`, a value, a range such as `1-5`, a step such as `*/15`
+//! or `1-30/2`, or a list of them separated by commas. As with cron, when both the day of the month and the
+//! day of the week are restricted, a day matching either of them matches. The `@hourly`, `@daily`, `@weekly`
+//! and `@monthly` shortcuts are also accepted.
+
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Timelike, Utc};
+
+/// Years searched for the next run, a schedule such as `0 0 30 2 *` never running
+const SEARCH_YEARS: i32 = 5;
+
+/// Values of a field, as a set of bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Field {
+    values: u64,
+    /// Whether the field is `*`, possibly with a step
+    is_wildcard: bool,
+}
+
+impl Field {
+    fn contains(&self, value: u32) -> bool {
+        self.values & (1 << value) != 0
+    }
+
+    fn parse(field: &str, name: &str, min: u32, max: u32) -> Result<Field, String> {
+        let invalid = |part: &str| format!("invalid {} `{}`, expected values between {} and {}", name, part, min, max);
+        let parse_value = |value: &str, part: &str| value.parse::<u32>().ok()
+            .filter(|value| (min..=max).contains(value))
+            .ok_or_else(|| invalid(part));
+
+        let mut values = 0;
+        for part in field.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => (range, step.parse::<u32>().ok().filter(|&step| step > 0).ok_or_else(|| invalid(part))?),
+                None => (part, 1),
+            };
+            let (start, end) = match range {
+                "*" => (min, max),
+                _ => match range.split_once('-') {
+                    Some((start, end)) => (parse_value(start, part)?, parse_value(end, part)?),
+                    // A single value with a step runs from it to the maximum
+                    None if step > 1 => (parse_value(range, part)?, max),
+                    None => (parse_value(range, part)?, parse_value(range, part)?),
+                },
+            };
+            if start > end {
+                return Err(invalid(part));
+            }
+            for value in (start..=end).step_by(step as usize) {
+                values |= 1 << value;
+            }
+        }
+
+        Ok(Field {
+            values,
+            is_wildcard: field.starts_with('*'),
+        })
+    }
+}
+
+/// When a task runs, parsed from a cron expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    expression: String,
+    minutes: Field,
+    hours: Field,
+    days: Field,
+    months: Field,
+    weekdays: Field,
+}
+
+impl CronSchedule {
+    /// Every hour, on the hour
+    pub const HOURLY: &'static str = "0 * * * *";
+
+    /// Returns the first time the schedule runs strictly after a given time.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let after = after.naive_utc();
+        let mut time = after.date().and_hms_opt(after.hour(), after.minute(), 0)? + Duration::minutes(1);
+        let last_year = time.year() + SEARCH_YEARS;
+
+        // Skips the months, days and hours which don't match, then the minutes
+        while time.year() <= last_year {
+            if !self.months.contains(time.month()) {
+                let (year, month) = match time.month() {
+                    12 => (time.year() + 1, 1),
+                    month => (time.year(), month + 1),
+                };
+                time = NaiveDate::from_ymd_opt(year, month, 1)?.and_time(NaiveTime::MIN);
+            } else if !self.matches_day(time.date()) {
+                time = (time.date() + Duration::days(1)).and_time(NaiveTime::MIN);
+            } else if !self.hours.contains(time.hour()) {
+                time = time.date().and_hms_opt(time.hour(), 0, 0)? + Duration::hours(1);
+            } else if !self.minutes.contains(time.minute()) {
+                time += Duration::minutes(1);
+            } else {
+                return Some(time.and_utc());
+            }
+        }
+
+        None
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day = self.days.contains(date.day());
+        let weekday = self.weekdays.contains(date.weekday().num_days_from_sunday());
+
+        match (self.days.is_wildcard, self.weekdays.is_wildcard) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+}
+
+impl FromStr for CronSchedule {
+    type Err = String;
+
+    fn from_str(expression: &str) -> Result<CronSchedule, String> {
+        let expression = expression.trim();
+        let fields = match expression {
+            "@hourly" => CronSchedule::HOURLY,
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            _ => expression,
+        };
+
+        let fields: Vec<&str> = fields.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(format!("expected 5 fields (minute, hour, day of month, month, day of week), got {}", fields.len()));
+        };
+
+        let mut weekdays = Field::parse(weekdays, "day of week", 0, 7)?;
+        // Sunday is both 0 and 7
+        if weekdays.contains(7) {
+            weekdays.values |= 1;
+        }
+
+        Ok(CronSchedule {
+            expression: expression.to_string(),
+            minutes: Field::parse(minutes, "minute", 0, 59)?,
+            hours: Field::parse(hours, "hour", 0, 23)?,
+            days: Field::parse(days, "day of month", 1, 31)?,
+            months: Field::parse(months, "month", 1, 12)?,
+            weekdays,
+        })
+    }
+}
+
+impl Default for CronSchedule {
+    fn default() -> Self {
+        CronSchedule::HOURLY.parse().expect("The default schedule is valid")
+    }
+}
+
+impl fmt::Display for CronSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expression)
+    }
+}
+
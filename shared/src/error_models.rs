@@ -1,4 +1,4 @@
-use std::fmt::Debug;
+use std::fmt::{Debug, Display, Formatter};
 
 use actix_web::{
     error,
@@ -6,12 +6,109 @@ use actix_web::{
     HttpResponse,
 };
 use derive_more::{Display, Error};
+use diesel::result::DatabaseErrorKind;
 pub use diesel::result::Error as DBError;
+use serde::{Deserialize, Serialize};
 use utoipa::{ToResponse, ToSchema};
 
-#[derive(Debug, Error, ToSchema, ToResponse, Display)]
+/// Media type of the error responses
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// Prefix of the `type` of the error responses
+pub const PROBLEM_TYPE_PREFIX: &str = "urn:bitbox:problem:";
+
+/// Reason why a field of the request was rejected.
+///
+/// An empty field refers to the request as a whole.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// Body of all the error responses, as described by RFC 7807.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, ToResponse)]
+pub struct ProblemDetails {
+    /// Identifier of the kind of error, such as `urn:bitbox:problem:not-found`
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub title: String,
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// The rejected fields of the request
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+    /// Identifier of the request, also sent in the `X-Request-Id` header
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+impl ProblemDetails {
+    pub fn new(status: StatusCode, kind: &str, title: &str) -> Self {
+        ProblemDetails {
+            type_: format!("{}{}", PROBLEM_TYPE_PREFIX, kind),
+            title: title.to_string(),
+            status: status.as_u16(),
+            detail: None,
+            errors: Vec::new(),
+            request_id: None,
+        }
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
+
+impl Display for ProblemDetails {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.detail {
+            Some(detail) => write!(f, "{}: {}", self.title, detail),
+            None => write!(f, "{}", self.title),
+        }
+    }
+}
+
+impl error::ResponseError for ProblemDetails {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code())
+            .insert_header(ContentType(PROBLEM_JSON.parse().unwrap()))
+            .json(self)
+    }
+}
+
+#[derive(Debug, Error, ToSchema, ToResponse)]
 pub struct ValidationError {
-    field: String,
+    pub errors: Vec<FieldError>,
+}
+
+impl ValidationError {
+    pub fn new(field: &str, message: &str) -> Self {
+        ValidationError {
+            errors: vec![FieldError {
+                field: field.to_string(),
+                message: message.to_string(),
+            }],
+        }
+    }
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let errors = self.errors.iter()
+            .map(|error| match error.field.is_empty() {
+                true => error.message.clone(),
+                false => format!("{}: {}", error.field, error.message),
+            })
+            .collect::<Vec<String>>();
+        write!(f, "{}", errors.join(", "))
+    }
 }
 
 #[derive(Debug, Error, ToSchema, ToResponse, Display)]
@@ -27,15 +124,30 @@ pub struct ForbiddenError;
 
 #[derive(Debug, Error, ToSchema, ToResponse, Display)]
 pub struct BadRequestError {
-    request: String,
+    pub request: String,
+}
+
+#[derive(Debug, Error, ToSchema, ToResponse, Display)]
+pub struct ConflictError {
+    pub reason: String,
+}
+
+#[derive(Debug, Error, ToSchema, ToResponse, Display)]
+pub struct UnprocessableError {
+    pub reason: String,
 }
 
 #[derive(Debug, Error, ToSchema, ToResponse, Display)]
 pub struct InternalError;
 
+#[derive(Debug, Error, ToSchema, ToResponse, Display)]
+pub struct UnavailableError {
+    pub reason: String,
+}
+
 #[derive(Debug, Display, Error)]
 pub enum UserError {
-    #[display(fmt = "Validation error : {}", ValidationError.field)]
+    #[display(fmt = "Validation error : {}", _0)]
     ValidationError (ValidationError),
     #[display(fmt = "Resource not found: {}", NotFoundError.resource)]
     NotFound (NotFoundError),
@@ -45,12 +157,18 @@ pub enum UserError {
     Forbidden (ForbiddenError),
     #[display(fmt = "Bad request: {}", BadRequestError.request)]
     BadRequest (BadRequestError),
+    #[display(fmt = "Conflict: {}", ConflictError.reason)]
+    Conflict (ConflictError),
+    #[display(fmt = "Unprocessable entity: {}", UnprocessableError.reason)]
+    Unprocessable (UnprocessableError),
 }
 
 #[derive(Debug, Display, Error)]
 pub enum ServerError {
     #[display(fmt = "An internal error occurred. Please try again later.")]
     InternalError (InternalError),
+    #[display(fmt = "Service unavailable: {}", UnavailableError.reason)]
+    Unavailable (UnavailableError),
 }
 
 #[derive(Debug, Display, Error)]
@@ -66,29 +184,75 @@ impl From<serde_json::Error> for APIError {
     }
 }
 
+/// Maps the database errors caused by the request to user errors, the other ones are internal errors.
+fn user_error_from_db_error(err: &DBError) -> Option<UserError> {
+    match err {
+        DBError::NotFound => Some(UserError::NotFound(NotFoundError {
+            resource: "Resource".to_string(),
+        })),
+        DBError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => Some(UserError::Conflict(ConflictError {
+            reason: "A resource with the same unique values already exists".to_string(),
+        })),
+        // PostgreSQL reports the deletion of a referenced row this way
+        DBError::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, info) if info.message().starts_with("update or delete") => {
+            Some(UserError::Conflict(ConflictError {
+                reason: "The resource is still referenced by other resources".to_string(),
+            }))
+        }
+        DBError::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, _) => Some(UserError::Unprocessable(UnprocessableError {
+            reason: "The request references a resource which doesn't exist".to_string(),
+        })),
+        DBError::DatabaseError(DatabaseErrorKind::NotNullViolation, info) => Some(UserError::Unprocessable(UnprocessableError {
+            reason: match info.column_name() {
+                Some(column) => format!("A value is required for {}", column),
+                None => "A required value is missing".to_string(),
+            },
+        })),
+        DBError::DatabaseError(DatabaseErrorKind::CheckViolation, _) => Some(UserError::Unprocessable(UnprocessableError {
+            reason: "A value doesn't satisfy the constraints of the resource".to_string(),
+        })),
+        _ => None,
+    }
+}
+
+/// Maps a database which can't be reached to a temporary unavailability, the other errors are internal errors.
+fn server_error_from_db_error(err: &DBError) -> ServerError {
+    match err {
+        DBError::DatabaseError(DatabaseErrorKind::ClosedConnection, _) => ServerError::Unavailable(UnavailableError {
+            reason: "The database is unavailable, please try again later".to_string(),
+        }),
+        _ => ServerError::InternalError(InternalError),
+    }
+}
+
 impl From<DBError> for APIError {
+    /// The internal errors are logged here, as their response doesn't tell what happened.
     fn from(err: DBError) -> Self {
-        match err {
-            DBError::NotFound => APIError::UserError(UserError::NotFound(NotFoundError {
-                resource: "Database record".to_string(),
-            })),
-            _ => APIError::ServerError(ServerError::InternalError(InternalError)),
+        match user_error_from_db_error(&err) {
+            Some(user_error) => APIError::UserError(user_error),
+            None => {
+                tracing::error!(error = %err, "database error");
+                APIError::ServerError(server_error_from_db_error(&err))
+            }
         }
     }
 }
 
 impl From<garde::Error> for APIError {
     fn from(err: garde::Error) -> Self {
-        APIError::UserError(UserError::ValidationError(ValidationError {
-            field: err.message().to_string()
-        }))
+        APIError::UserError(UserError::ValidationError(ValidationError::new("", err.message())))
     }
 }
 
 impl From<garde::Report> for APIError {
     fn from(err: garde::Report) -> Self {
         APIError::UserError(UserError::ValidationError(ValidationError {
-            field: err.into_inner().iter().map(|(_, e)| e.message().to_string()).collect::<Vec<String>>().join(", ")
+            errors: err.into_inner().into_iter()
+                .map(|(path, error)| FieldError {
+                    field: path.to_string(),
+                    message: error.message().to_string(),
+                })
+                .collect(),
         }))
     }
 }
@@ -99,6 +263,54 @@ impl From<bcrypt::BcryptError> for APIError {
     }
 }
 
+impl UserError {
+    pub fn problem(&self) -> ProblemDetails {
+        let status = error::ResponseError::status_code(self);
+        match self {
+            UserError::ValidationError(validation_error) => ProblemDetails {
+                errors: validation_error.errors.clone(),
+                ..ProblemDetails::new(status, "validation-error", "Validation failed")
+                    .with_detail("Some fields of the request are invalid")
+            },
+            UserError::NotFound(not_found) => ProblemDetails::new(status, "not-found", "Resource not found")
+                .with_detail(format!("{} not found", not_found.resource)),
+            UserError::Unauthorized(_) => ProblemDetails::new(status, "unauthorized", "Unauthorized"),
+            UserError::Forbidden(_) => ProblemDetails::new(status, "forbidden", "Forbidden"),
+            UserError::BadRequest(bad_request) => ProblemDetails::new(status, "bad-request", "Bad request")
+                .with_detail(&bad_request.request),
+            UserError::Conflict(conflict) => ProblemDetails::new(status, "conflict", "Conflict")
+                .with_detail(&conflict.reason),
+            UserError::Unprocessable(unprocessable) => ProblemDetails::new(status, "unprocessable-entity", "Unprocessable entity")
+                .with_detail(&unprocessable.reason),
+        }
+    }
+}
+
+impl ServerError {
+    pub fn problem(&self) -> ProblemDetails {
+        let status = error::ResponseError::status_code(self);
+        match self {
+            ServerError::InternalError(_) => ProblemDetails::new(status, "internal-error", "Internal server error")
+                .with_detail(self.to_string()),
+            ServerError::Unavailable(unavailable) => ProblemDetails::new(status, "service-unavailable", "Service unavailable")
+                .with_detail(&unavailable.reason),
+        }
+    }
+}
+
+impl APIError {
+    pub fn problem(&self) -> ProblemDetails {
+        match self {
+            APIError::UserError(user_error) => user_error.problem(),
+            APIError::ServerError(server_error) => server_error.problem(),
+            APIError::DBError(db_error) => match user_error_from_db_error(db_error) {
+                Some(user_error) => user_error.problem(),
+                None => server_error_from_db_error(db_error).problem(),
+            },
+        }
+    }
+}
+
 impl error::ResponseError for UserError {
     fn status_code(&self) -> StatusCode {
         match *self {
@@ -107,12 +319,13 @@ impl error::ResponseError for UserError {
             UserError::Unauthorized { .. } => StatusCode::UNAUTHORIZED,
             UserError::Forbidden { .. } => StatusCode::FORBIDDEN,
             UserError::BadRequest { .. } => StatusCode::BAD_REQUEST,
+            UserError::Conflict { .. } => StatusCode::CONFLICT,
+            UserError::Unprocessable { .. } => StatusCode::UNPROCESSABLE_ENTITY,
         }
     }
+
     fn error_response(&self) -> HttpResponse {
-        HttpResponse::build(self.status_code())
-            .insert_header(ContentType::html())
-            .body(self.to_string())
+        self.problem().error_response()
     }
 }
 
@@ -120,30 +333,21 @@ impl error::ResponseError for ServerError {
     fn status_code(&self) -> StatusCode {
         match *self {
             ServerError::InternalError { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            ServerError::Unavailable { .. } => StatusCode::SERVICE_UNAVAILABLE,
         }
     }
 
     fn error_response(&self) -> HttpResponse {
-        HttpResponse::build(self.status_code())
-            .insert_header(ContentType::html())
-            .body(self.to_string())
+        self.problem().error_response()
     }
 }
 
 impl error::ResponseError for APIError {
     fn status_code(&self) -> StatusCode {
-        match self {
-            APIError::UserError(user_error) => user_error.status_code(),
-            APIError::ServerError(server_error) => server_error.status_code(),
-            APIError::DBError(_) => StatusCode::INTERNAL_SERVER_ERROR,
-        }
+        self.problem().status_code()
     }
 
     fn error_response(&self) -> HttpResponse {
-        match self {
-            APIError::UserError(user_error) => user_error.error_response(),
-            APIError::ServerError(server_error) => server_error.error_response(),
-            _ => { HttpResponse::InternalServerError().finish() }
-        }
+        self.problem().error_response()
     }
-}
\ No newline at end of file
+}
@@ -1,4 +1,5 @@
 pub mod app_state_model;
 pub mod error_models;
 pub mod token_models;
-pub mod app_config;
\ No newline at end of file
+pub mod app_config;
+pub mod cron;
@@ -19,6 +19,17 @@ pub struct SpecificClaims {
     pub exp: usize,
 }
 
+/// Claims of the link sent to a new email address, which is only applied once verified.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailVerificationClaims {
+    pub sub: Uuid,
+    #[serde(rename = "type")]
+    pub type_: TokenType,
+    pub email: String,
+    pub iat: usize,
+    pub exp: usize,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StudentClaims {
     pub sub: Uuid,
@@ -1,8 +1,11 @@
 /* tslint:disable */
 /* eslint-disable */
 export enum ProjectState {
-  NotStarted = 'NotStarted',
+  Draft = 'Draft',
+  Scheduled = 'Scheduled',
   InProgress = 'InProgress',
-  Finished = 'Finished',
-  NotationFinished = 'NotationFinished'
+  EvaluationOpen = 'EvaluationOpen',
+  EvaluationClosed = 'EvaluationClosed',
+  ResultsPublished = 'ResultsPublished',
+  Archived = 'Archived'
 }
@@ -1,7 +1,14 @@
 /* tslint:disable */
 /* eslint-disable */
+import {ProjectState} from '../models/project-state';
+
 export interface PromotionSearchParams {
-  end_year?: string | null;
-  start_year?: string | null;
   title?: string | null;
+  start_year_from?: number | null;
+  start_year_to?: number | null;
+  end_year_from?: number | null;
+  end_year_to?: number | null;
+  min_projects?: number | null;
+  max_projects?: number | null;
+  project_state?: ProjectState | null;
 }
@@ -59,7 +59,7 @@ const GroupComponent: React.FC<{
                 ))}
             </div>
 
-            {projectState === ProjectState.Finished && (
+            {projectState === ProjectState.EvaluationOpen && (
                 <div className="mt-4">
                     {editMode ? (
                         <form onSubmit={handleMarkSubmit} className="flex items-center space-x-4">
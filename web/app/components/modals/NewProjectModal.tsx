@@ -65,7 +65,7 @@ const NewProjectModal: React.FC<NewProjectModalProps> = ({
                     start_date: formData.start_date,
                     notation_period_duration: formData.notation_period_duration as unknown as number,
                     promotion_id,
-                    state: ProjectState.NotStarted,
+                    state: ProjectState.Scheduled,
                 };
                 setProjects([...projects, newProject]);
                 hideModal("new_project_modal");
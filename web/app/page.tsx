@@ -177,7 +177,7 @@ const PromotionsClientPage: React.FC = () => {
                     credentials: "include"
                 });
                 const data = await response.json();
-                setPromotions(data);
+                setPromotions(data.items);
             } catch (err) {
                 setError('Error fetching promotions');
             } finally {
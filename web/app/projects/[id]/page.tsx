@@ -28,7 +28,7 @@ async function getGroupsFromProject(project_id: string): Promise<ProjectGroup[]>
         credentials: "include",
     });
     const data = await response.json();
-    return data.groups as ProjectGroup[];
+    return data.items as ProjectGroup[];
 }
 
 const ProjectDetails = ({ project }: { project: Project }) => {
@@ -39,7 +39,7 @@ const ProjectDetails = ({ project }: { project: Project }) => {
             <p className="text-gray-600"><strong>Start Date:</strong> {project.start_date}</p>
             <p className="text-gray-600"><strong>End Date:</strong> {project.end_date}</p>
             <p className="text-gray-600"><strong>Duration:</strong> {project.notation_period_duration} days</p>
-            <p className={`text-sm rounded-full px-2 py-1 ${project.state === ProjectState.Finished ? 'bg-green-200 text-green-800' : 'bg-yellow-200 text-yellow-800'}`}>
+            <p className={`text-sm rounded-full px-2 py-1 ${project.state === ProjectState.EvaluationOpen ? 'bg-green-200 text-green-800' : 'bg-yellow-200 text-yellow-800'}`}>
                 {project.state}
             </p>
         </div>
@@ -93,7 +93,7 @@ const ProjectPage: React.FC = () => {
         notation_period_duration: 0,
         promotion_id: "",
         start_date: "",
-        state: ProjectState.NotStarted
+        state: ProjectState.Scheduled
     });
 
     useEffect(() => {
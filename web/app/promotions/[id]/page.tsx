@@ -21,7 +21,7 @@ async function getStudentsFromPromotion(promotion_id: string): Promise<Student[]
         credentials: "include",
     });
     const data = await response.json();
-    return data as Student[];
+    return data.items as Student[];
 }
 
 async function getProjectsFromPromotion(promotion_id: string): Promise<Project[]> {
@@ -31,7 +31,7 @@ async function getProjectsFromPromotion(promotion_id: string): Promise<Project[]
         credentials: "include",
     });
     const data = await response.json();
-    return data as Project[];
+    return data.items as Project[];
 }
 
 async function getPromotion(promotion_id: string): Promise<Promotion> {
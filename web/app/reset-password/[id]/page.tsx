@@ -0,0 +1,107 @@
+'use client'
+
+import React, {useEffect, useState} from "react";
+import {useParams, useRouter} from "next/navigation";
+import {deleteCookie} from "cookies-next";
+import {ResetPasswordPostModel} from "@/app/api/models/reset-password-post-model";
+
+const ResetPasswordPage: React.FC = () => {
+    const {id: token_id} = useParams<{ id: string }>();
+    const router = useRouter();
+    const [formData, setFormData] = useState<ResetPasswordPostModel>({
+        password: "",
+    });
+    const [error, setError] = useState<string | null>(null);
+    const [isLoading, setIsLoading] = useState<boolean>(true);
+
+    const api_url = process.env.NEXT_PUBLIC_API_URL;
+
+    useEffect(() => {
+        const fetchToken = async () => {
+            if (!token_id) {
+                return;
+            }
+
+            deleteCookie('token');
+
+            const response = await fetch(`${api_url}/token/reset?id=${token_id}`, {
+                headers: {'Content-Type': 'application/json',},
+                credentials: "include",
+            });
+
+            if (response.status === 200) {
+                setIsLoading(false);
+            } else {
+                setError("This link is invalid or has expired.");
+            }
+        }
+
+        fetchToken();
+    }, [token_id]);
+
+    const handleSubmit = async (e: React.FormEvent<HTMLFormElement>) => {
+        e.preventDefault();
+        setError(null);
+        setIsLoading(true);
+        try {
+            const response = await fetch(`${api_url}/reset/password`, {
+                method: "POST",
+                headers: {"Content-Type": "application/json"},
+                body: JSON.stringify(formData),
+                credentials: "include",
+            });
+
+            if (response.ok) {
+                deleteCookie('token');
+                router.push(`/login`);
+            } else if (response.status === 400) {
+                setError("This password doesn't follow the password policy.");
+                setIsLoading(false);
+            } else {
+                setError("This link is invalid or has expired.");
+            }
+        } catch (err) {
+            setError("An error occurred. Please try again later.");
+            setIsLoading(false);
+        }
+    };
+
+    return (
+        <div className="flex font-poppins items-center justify-center">
+            <div className="h-screen w-screen flex justify-center items-center dark:bg-gray-900">
+                <div className="border-[20px] border-transparent rounded-[20px] dark:bg-gray-900 bg-white shadow-lg p-10 m-2">
+                    <h1 className="pt-8 pb-6 font-bold dark:text-gray-400 text-5xl text-center cursor-default">
+                        Reset password
+                    </h1>
+                    <form onSubmit={handleSubmit} className="space-y-4">
+                        <div>
+                            <label htmlFor="password" className="mb-2 dark:text-gray-400 text-lg">
+                                New password
+                            </label>
+                            <input
+                                id="password"
+                                name="password"
+                                className="border p-3 shadow-md dark:bg-indigo-700 dark:text-gray-300 dark:border-gray-700 placeholder:text-base border-gray-300 rounded-lg w-full"
+                                type="password"
+                                placeholder="New password"
+                                value={formData.password}
+                                onChange={(e) => setFormData({password: e.target.value})}
+                                required
+                            />
+                        </div>
+                        {error && <p style={{color: "red"}}>{error}</p>}
+                        <button
+                            className="bg-gradient-to-r dark:text-gray-300 from-blue-500 to-purple-500 shadow-lg mt-6 p-2 text-white rounded-lg w-full"
+                            type="submit"
+                            disabled={isLoading}
+                        >
+                            RESET PASSWORD
+                        </button>
+                    </form>
+                </div>
+            </div>
+        </div>
+    );
+}
+
+export default ResetPasswordPage;